@@ -0,0 +1,71 @@
+//! Compares allocations and time of parsing many similar documents with and
+//! without a [DocumentPool].
+//!
+//! Run with `cargo bench --bench pool`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use nestac::{Document, DocumentPool};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const DOCUMENTS: usize = 10_000;
+
+/// Runs `f` returning the allocations, allocated bytes and time it took.
+fn measure(f: impl FnOnce()) -> (usize, usize, Duration) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        ALLOCATED.load(Ordering::Relaxed) - allocated,
+        start.elapsed(),
+    )
+}
+
+fn main() {
+    let sources: Vec<String> = (0..DOCUMENTS)
+        .map(|i| format!(r#"{{"id": {}, "server": {{"host": "localhost", "port": 80, "tags": ["a", "b"]}}}}"#, i))
+        .collect();
+
+    let plain = measure(|| {
+        for source in &sources {
+            let doc = Document::parse(source).unwrap();
+            assert!(doc.read("server.port", None).is_some());
+        }
+    });
+    let pool = DocumentPool::new();
+    let pooled = measure(|| {
+        for source in &sources {
+            let doc = pool.parse(source).unwrap();
+            assert!(doc.read("server.port", None).is_some());
+            pool.recycle(doc);
+        }
+    });
+
+    for (name, (allocations, allocated, elapsed)) in [("plain", plain), ("pooled", pooled)] {
+        println!(
+            "{:>6}: {:>8} allocations, {:>10} bytes, {:?} ({} documents)",
+            name, allocations, allocated, elapsed, DOCUMENTS,
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Derive macro for [nestac](https://docs.rs/nestac), see [Nestac].
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, LitStr, Type};
+
+/// Maps the fields of a struct to paths of a `nestac::Document`,
+/// generating:
+///
+/// - `from_document(&doc) -> Result<Self, NestacError>`, reading every
+///   field with `Document::read_as`;
+/// - `apply_to(&self, &mut doc) -> Result<(), NestacError>`, writing every
+///   field back with `Document::upsert_serializable`, which creates the
+///   missing objects along the way;
+/// - `view(&mut doc) -> Result<{Struct}View, NestacError>`, a typed facade
+///   over the document with a getter and a `set_{field}` setter per field.
+///   `flush()` writes the fields set since the last flush back through
+///   their paths (an `Option` field set to [None] is deleted) and leaves
+///   every other key of the document untouched.
+///
+/// Each field is read from `#[nestac(path = "...")]`, or from its own name
+/// when the attribute is left out. `Option` fields are [None] when their
+/// path does not exist and are left untouched by `apply_to` when [None].
+/// Paths use `.` as separator unless the struct sets another one with
+/// `#[nestac(separator = "...")]`.
+///
+/// Field types must implement `serde::Deserialize` and `serde::Serialize`.
+///
+/// # Examples:
+/// ```rust,ignore
+/// use nestac::{Document, Nestac};
+///
+/// #[derive(Nestac)]
+/// struct Http {
+///     #[nestac(path = "server.http.port")]
+///     port: u16,
+///     #[nestac(path = "server.http.tls")]
+///     tls: Option<bool>,
+///     name: String,
+/// }
+///
+/// fn main() {
+///     let mut doc = Document::parse(r#"{"name": "api", "server": {"http": {"port": 80}}}"#).unwrap();
+///     let mut http = Http::from_document(&doc).unwrap();
+///     assert_eq!(http.port, 80);
+///     assert_eq!(http.tls, None);
+///
+///     http.tls = Some(true);
+///     http.apply_to(&mut doc).unwrap();
+///     assert_eq!(doc.read("server.http.tls", None), Some(&true.into()));
+///
+///     let mut view = Http::view(&mut doc).unwrap();
+///     view.set_port(8080);
+///     assert_eq!(*view.port(), 8080);
+///     assert_eq!(view.flush().unwrap(), 1);
+///     assert_eq!(doc.read("server.http.port", None), Some(&8080.into()));
+/// }
+/// ```
+#[proc_macro_derive(Nestac, attributes(nestac))]
+pub fn derive_nestac(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(input, "`Nestac` needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(input, "`Nestac` can only be derived for structs")),
+    };
+    let separator = match attribute(&input.attrs, "separator")? {
+        Some(separator) => quote!(::core::option::Option::Some(#separator)),
+        None => quote!(::core::option::Option::None),
+    };
+
+    let mut reads = vec![];
+    let mut writes = vec![];
+    let mut accessors = vec![];
+    let mut flushes = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field.ident.as_ref().expect("named fields have a name");
+        let path = match attribute(&field.attrs, "path")? {
+            Some(path) => path.value(),
+            None => ident.to_string(),
+        };
+        let ty = &field.ty;
+        let setter = format_ident!("set_{}", ident);
+        accessors.push(quote! {
+            pub fn #ident(&self) -> &#ty {
+                &self.value.#ident
+            }
+
+            pub fn #setter(&mut self, #ident: #ty) {
+                self.value.#ident = #ident;
+                self.dirty[#i] = true;
+            }
+        });
+        let flush = match is_option(ty) {
+            true => quote! {
+                match &self.value.#ident {
+                    ::core::option::Option::Some(value) => {
+                        self.doc.upsert_serializable(#path, #separator, value)?;
+                    }
+                    ::core::option::Option::None => {
+                        self.doc.delete(#path, #separator);
+                    }
+                }
+            },
+            false => quote! {
+                self.doc.upsert_serializable(#path, #separator, &self.value.#ident)?;
+            },
+        };
+        flushes.push(quote! {
+            if self.dirty[#i] {
+                #flush
+                self.dirty[#i] = false;
+                flushed += 1;
+            }
+        });
+        if is_option(ty) {
+            reads.push(quote! {
+                #ident: match doc.read(#path, #separator) {
+                    ::core::option::Option::Some(_) => doc.read_as(#path, #separator)?,
+                    ::core::option::Option::None => ::core::option::Option::None,
+                }
+            });
+            writes.push(quote! {
+                if let ::core::option::Option::Some(value) = &self.#ident {
+                    doc.upsert_serializable(#path, #separator, value)?;
+                }
+            });
+        } else {
+            reads.push(quote! {
+                #ident: doc.read_as(#path, #separator)?
+            });
+            writes.push(quote! {
+                doc.upsert_serializable(#path, #separator, &self.#ident)?;
+            });
+        }
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let view = format_ident!("{}View", name);
+    let view_doc = format!("Typed view over a [::nestac::Document] through the paths of [{}].", name);
+    let len = fields.len();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut view_generics = input.generics.clone();
+    view_generics.params.insert(0, GenericParam::Lifetime(parse_quote!('nestac)));
+    let (view_impl_generics, view_ty_generics, _) = view_generics.split_for_impl();
+    Ok(quote! {
+        #[doc = #view_doc]
+        #vis struct #view #view_impl_generics #where_clause {
+            doc: &'nestac mut ::nestac::Document,
+            value: #name #ty_generics,
+            dirty: [bool; #len],
+        }
+
+        impl #view_impl_generics #view #view_ty_generics #where_clause {
+            #(#accessors)*
+
+            /// Writes the fields set since the last flush to the document,
+            /// returning how many were written.
+            pub fn flush(&mut self) -> ::core::result::Result<usize, ::nestac::NestacError> {
+                let mut flushed = 0;
+                #(#flushes)*
+                ::core::result::Result::Ok(flushed)
+            }
+
+            /// Whether fields were set since the last flush.
+            pub fn is_dirty(&self) -> bool {
+                self.dirty.contains(&true)
+            }
+
+            /// The struct behind the view, dropping fields not flushed.
+            pub fn into_inner(self) -> #name #ty_generics {
+                self.value
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Reads every field from its path on `doc`.
+            pub fn from_document<M>(doc: &::nestac::Document<M>) -> ::core::result::Result<Self, ::nestac::NestacError> {
+                ::core::result::Result::Ok(Self {
+                    #(#reads,)*
+                })
+            }
+
+            /// Reads every field from its path on `doc` into a view writing
+            /// back to it.
+            pub fn view<'nestac>(doc: &'nestac mut ::nestac::Document) -> ::core::result::Result<#view #view_ty_generics, ::nestac::NestacError> {
+                ::core::result::Result::Ok(#view {
+                    value: Self::from_document(doc)?,
+                    doc,
+                    dirty: [false; #len],
+                })
+            }
+
+            /// Writes every field to its path on `doc`.
+            pub fn apply_to(&self, doc: &mut ::nestac::Document) -> ::core::result::Result<(), ::nestac::NestacError> {
+                #(#writes)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+/// Value of `#[nestac(name = "...")]` among `attrs`.
+fn attribute(attrs: &[syn::Attribute], name: &str) -> syn::Result<Option<LitStr>> {
+    let mut found = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("nestac")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(name) {
+                found = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown `nestac` attribute"))
+            }
+        })?;
+    }
+    Ok(found)
+}
+
+/// Whether `ty` is spelled as an `Option`.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty.qself.is_none() && ty.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
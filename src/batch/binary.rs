@@ -0,0 +1,469 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use serde_json::Value;
+use crate::nested::{Change, ChangeKind};
+use super::{FileResult, FileStatus, Plan, PlannedFile, RunReport};
+
+// Layout shared by plans and reports: the magic bytes, a kind byte and the
+// format version, then one record per file, each preceded by `RECORD`, and
+// `END`. Integers are LEB128 varints, strings are length-prefixed UTF-8.
+// Reports write their ops before the records and the total elapsed time
+// after `END`.
+const MAGIC: &[u8; 4] = b"NSTC";
+const VERSION: u8 = 1;
+const PLAN: u8 = b'P';
+const REPORT: u8 = b'R';
+const RECORD: u8 = 1;
+const END: u8 = 0;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_varint<W: Write>(out: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(input)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint is too long"))
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(out, bytes.len() as u64)?;
+    out.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(input: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_varint(input)?;
+    let mut bytes = vec![];
+    input.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn write_str<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+    write_bytes(out, text.as_bytes())
+}
+
+fn read_string<R: Read>(input: &mut R) -> io::Result<String> {
+    String::from_utf8(read_bytes(input)?).map_err(|err| invalid(err.to_string()))
+}
+
+fn write_value<W: Write>(out: &mut W, value: Option<&Value>) -> io::Result<()> {
+    match value {
+        Some(value) => {
+            out.write_all(&[1])?;
+            write_str(out, &value.to_string())
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+fn read_value<R: Read>(input: &mut R) -> io::Result<Option<Value>> {
+    match read_byte(input)? {
+        0 => Ok(None),
+        1 => serde_json::from_str(&read_string(input)?).map(Some).map_err(|err| invalid(err.to_string())),
+        tag => Err(invalid(format!("unknown value tag {}", tag))),
+    }
+}
+
+fn write_duration<W: Write>(out: &mut W, duration: Duration) -> io::Result<()> {
+    write_varint(out, duration.as_nanos().min(u64::MAX as u128) as u64)
+}
+
+fn read_duration<R: Read>(input: &mut R) -> io::Result<Duration> {
+    read_varint(input).map(Duration::from_nanos)
+}
+
+fn write_status<W: Write>(out: &mut W, status: &FileStatus) -> io::Result<()> {
+    match status {
+        FileStatus::Updated => out.write_all(&[0]),
+        FileStatus::Unchanged => out.write_all(&[1]),
+        FileStatus::Failed(err) => {
+            out.write_all(&[2])?;
+            write_str(out, err)
+        }
+        FileStatus::Skipped(err) => {
+            out.write_all(&[3])?;
+            write_str(out, err)
+        }
+    }
+}
+
+fn read_status<R: Read>(input: &mut R) -> io::Result<FileStatus> {
+    match read_byte(input)? {
+        0 => Ok(FileStatus::Updated),
+        1 => Ok(FileStatus::Unchanged),
+        2 => Ok(FileStatus::Failed(read_string(input)?)),
+        3 => Ok(FileStatus::Skipped(read_string(input)?)),
+        tag => Err(invalid(format!("unknown status tag {}", tag))),
+    }
+}
+
+fn write_header<W: Write>(out: &mut W, kind: u8) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&[kind, VERSION])
+}
+
+fn read_header<R: Read>(input: &mut R, kind: u8) -> io::Result<()> {
+    let mut header = [0; 6];
+    input.read_exact(&mut header)?;
+    if &header[..4] != MAGIC || header[4] != kind {
+        return Err(invalid(match kind {
+            PLAN => "not a binary plan",
+            _ => "not a binary report",
+        }));
+    }
+    if header[5] != VERSION {
+        return Err(invalid(format!("unsupported format version {}", header[5])));
+    }
+    Ok(())
+}
+
+/// Whether another record follows.
+fn read_tag<R: Read>(input: &mut R) -> io::Result<bool> {
+    match read_byte(input)? {
+        RECORD => Ok(true),
+        END => Ok(false),
+        tag => Err(invalid(format!("unknown record tag {}", tag))),
+    }
+}
+
+/// SHA-256 hex digest as raw bytes.
+fn hex_to_bytes(hex: &str) -> io::Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(invalid(format!("`{}` is not a hex digest", hex)));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid(format!("`{}` is not a hex digest", hex))))
+        .collect()
+}
+
+fn write_planned<W: Write>(out: &mut W, file: &PlannedFile) -> io::Result<()> {
+    write_str(out, &file.path.to_string_lossy())?;
+    write_status(out, &file.status)?;
+    write_bytes(out, &hex_to_bytes(&file.sha256)?)?;
+    write_varint(out, file.changes.len() as u64)?;
+    for change in &file.changes {
+        write_str(out, &change.path)?;
+        out.write_all(&[match change.kind {
+            ChangeKind::Added => 0,
+            ChangeKind::Removed => 1,
+            ChangeKind::Modified => 2,
+        }])?;
+        write_value(out, change.old.as_ref())?;
+        write_value(out, change.new.as_ref())?;
+    }
+    match &file.contents {
+        Some(contents) => {
+            out.write_all(&[1])?;
+            write_str(out, contents)
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+fn read_planned<R: Read>(input: &mut R) -> io::Result<PlannedFile> {
+    let path = PathBuf::from(read_string(input)?);
+    let status = read_status(input)?;
+    let sha256 = read_bytes(input)?.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let mut changes = vec![];
+    for _ in 0..read_varint(input)? {
+        let path = read_string(input)?;
+        let kind = match read_byte(input)? {
+            0 => ChangeKind::Added,
+            1 => ChangeKind::Removed,
+            2 => ChangeKind::Modified,
+            tag => return Err(invalid(format!("unknown change kind {}", tag))),
+        };
+        let old = read_value(input)?;
+        let new = read_value(input)?;
+        changes.push(Change { path, kind, old, new });
+    }
+    let contents = match read_byte(input)? {
+        0 => None,
+        1 => Some(read_string(input)?),
+        tag => return Err(invalid(format!("unknown contents tag {}", tag))),
+    };
+    if status == FileStatus::Updated && contents.is_none() {
+        return Err(invalid(format!("updated file `{}` has no contents", path.display())));
+    }
+    Ok(PlannedFile { path, status, sha256, changes, contents })
+}
+
+/// Writes a [Plan] in the compact binary format one file at a time, so
+/// huge plans never have to be held in memory. See [PlanReader].
+pub struct PlanWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PlanWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        write_header(&mut out, PLAN)?;
+        Ok(PlanWriter { out })
+    }
+
+    pub fn write(&mut self, file: &PlannedFile) -> io::Result<()> {
+        self.out.write_all(&[RECORD])?;
+        write_planned(&mut self.out, file)
+    }
+
+    /// Ends the plan, returning the underlying writer. A plan not finished
+    /// is refused by [PlanReader] as truncated.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[END])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads the files of a plan written by [PlanWriter] or
+/// [Plan::write_binary] one at a time.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::batch::{plan, BatchOptions, Plan, PlanReader};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("config.json");
+///     std::fs::write(&file, r#"{"version": 1}"#).unwrap();
+///
+///     let planned = plan(&[&file], &[("version", Value::from(2))], None, &BatchOptions::new());
+///     let mut encoded = vec![];
+///     planned.write_binary(&mut encoded).unwrap();
+///     assert!(encoded.len() < planned.to_json().to_string().len());
+///
+///     for file in PlanReader::new(encoded.as_slice()).unwrap() {
+///         assert_eq!(file.unwrap().changes[0].path, "version");
+///     }
+///     assert_eq!(Plan::read_binary(encoded.as_slice()).unwrap(), planned);
+/// }
+/// ```
+pub struct PlanReader<R: Read> {
+    input: R,
+    done: bool,
+}
+
+impl<R: Read> PlanReader<R> {
+    /// Fails with [io::ErrorKind::InvalidData] when `input` does not start
+    /// with a binary plan.
+    pub fn new(mut input: R) -> io::Result<Self> {
+        read_header(&mut input, PLAN)?;
+        Ok(PlanReader { input, done: false })
+    }
+}
+
+impl<R: Read> Iterator for PlanReader<R> {
+    type Item = io::Result<PlannedFile>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = read_tag(&mut self.input).and_then(|more| match more {
+            true => read_planned(&mut self.input).map(Some),
+            false => Ok(None),
+        });
+        if !matches!(record, Ok(Some(_))) {
+            self.done = true;
+        }
+        record.transpose()
+    }
+}
+
+impl Plan {
+    /// Writes the plan in the compact binary format read back by
+    /// [Plan::read_binary] and [PlanReader].
+    pub fn write_binary<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = PlanWriter::new(out)?;
+        for file in &self.files {
+            writer.write(file)?;
+        }
+        writer.finish().map(|_| ())
+    }
+
+    /// Reads a whole plan written by [Plan::write_binary] or [PlanWriter].
+    pub fn read_binary<R: Read>(input: R) -> io::Result<Self> {
+        let files = PlanReader::new(input)?.collect::<io::Result<Vec<PlannedFile>>>()?;
+        Ok(Plan { files })
+    }
+}
+
+/// Writes a [RunReport] in the compact binary format one file at a time,
+/// e.g. while the run is going on. See [ReportReader].
+pub struct ReportWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> ReportWriter<W> {
+    pub fn new(mut out: W, ops: &[String]) -> io::Result<Self> {
+        write_header(&mut out, REPORT)?;
+        write_varint(&mut out, ops.len() as u64)?;
+        for op in ops {
+            write_str(&mut out, op)?;
+        }
+        Ok(ReportWriter { out })
+    }
+
+    pub fn write(&mut self, file: &FileResult) -> io::Result<()> {
+        self.out.write_all(&[RECORD])?;
+        write_str(&mut self.out, &file.path.to_string_lossy())?;
+        write_status(&mut self.out, &file.status)?;
+        write_duration(&mut self.out, file.elapsed)
+    }
+
+    /// Ends the report with the time the whole run took, returning the
+    /// underlying writer.
+    pub fn finish(mut self, elapsed: Duration) -> io::Result<W> {
+        self.out.write_all(&[END])?;
+        write_duration(&mut self.out, elapsed)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads the files of a report written by [ReportWriter] or
+/// [RunReport::write_binary] one at a time.
+pub struct ReportReader<R: Read> {
+    input: R,
+    ops: Vec<String>,
+    elapsed: Option<Duration>,
+    done: bool,
+}
+
+impl<R: Read> ReportReader<R> {
+    /// Fails with [io::ErrorKind::InvalidData] when `input` does not start
+    /// with a binary report.
+    pub fn new(mut input: R) -> io::Result<Self> {
+        read_header(&mut input, REPORT)?;
+        let ops = (0..read_varint(&mut input)?)
+            .map(|_| read_string(&mut input))
+            .collect::<io::Result<Vec<String>>>()?;
+        Ok(ReportReader { input, ops, elapsed: None, done: false })
+    }
+
+    pub fn ops(&self) -> &[String] {
+        &self.ops
+    }
+
+    /// Time the whole run took, known once every file was read.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<FileResult>> {
+        if !read_tag(&mut self.input)? {
+            self.elapsed = Some(read_duration(&mut self.input)?);
+            return Ok(None);
+        }
+        Ok(Some(FileResult {
+            path: PathBuf::from(read_string(&mut self.input)?),
+            status: read_status(&mut self.input)?,
+            elapsed: read_duration(&mut self.input)?,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for ReportReader<R> {
+    type Item = io::Result<FileResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record();
+        if !matches!(record, Ok(Some(_))) {
+            self.done = true;
+        }
+        record.transpose()
+    }
+}
+
+impl RunReport {
+    /// Writes the report in the compact binary format read back by
+    /// [RunReport::read_binary] and [ReportReader].
+    pub fn write_binary<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = ReportWriter::new(out, &self.ops)?;
+        for file in &self.files {
+            writer.write(file)?;
+        }
+        writer.finish(self.elapsed).map(|_| ())
+    }
+
+    /// Reads a whole report written by [RunReport::write_binary] or
+    /// [ReportWriter].
+    pub fn read_binary<R: Read>(input: R) -> io::Result<Self> {
+        let mut reader = ReportReader::new(input)?;
+        let files = reader.by_ref().collect::<io::Result<Vec<FileResult>>>()?;
+        let elapsed = reader.elapsed().expect("the report was read to its end");
+        Ok(RunReport { ops: reader.ops, files, elapsed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_round_trip_and_truncation_is_detected() {
+        let report = RunReport::new(
+            vec!["set version".to_string()],
+            vec![
+                FileResult { path: PathBuf::from("a.json"), status: FileStatus::Updated, elapsed: Duration::from_micros(1500) },
+                FileResult { path: PathBuf::from("b.json"), status: FileStatus::Skipped("locked".to_string()), elapsed: Duration::ZERO },
+            ],
+            Duration::from_millis(3),
+        );
+        let mut encoded = vec![];
+        report.write_binary(&mut encoded).unwrap();
+        assert_eq!(RunReport::read_binary(encoded.as_slice()).unwrap(), report);
+
+        let truncated = &encoded[..encoded.len() - 2];
+        let err = RunReport::read_binary(truncated).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let mut reader = ReportReader::new(truncated).unwrap();
+        assert_eq!(reader.ops(), ["set version"]);
+        assert_eq!(reader.by_ref().filter(Result::is_ok).count(), 2);
+        assert_eq!(reader.elapsed(), None);
+
+        let err = Plan::read_binary(encoded.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, u64::MAX] {
+            let mut encoded = vec![];
+            write_varint(&mut encoded, value).unwrap();
+            assert_eq!(read_varint(&mut encoded.as_slice()).unwrap(), value);
+        }
+        assert!(read_varint(&mut [0xff; 11].as_slice()).is_err());
+    }
+}
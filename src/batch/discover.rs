@@ -0,0 +1,663 @@
+//! Expanding glob patterns into the files a batch runs on.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use glob::{MatchOptions, Pattern};
+use crate::write::long_path;
+
+/// How symbolic links met while walking directories are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Ignore symlinks to files and directories.
+    Skip,
+    /// Resolve symlinks, descending into linked directories unless they
+    /// link back to one of their ancestors.
+    Follow,
+}
+
+/// How a discovered path was resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// A regular file.
+    File,
+    /// A symlink to a file, or a file reached through a symlinked
+    /// directory; `target` is the canonical path of the file.
+    Symlink { target: PathBuf },
+    /// A symlink ignored because of [SymlinkPolicy::Skip].
+    SkippedSymlink,
+    /// A symlinked directory not descended into because it links back to
+    /// `target`, one of its ancestors.
+    Cycle { target: PathBuf },
+    /// A symlink whose target does not exist.
+    Broken,
+    /// A directory (or entry) that could not be read, e.g. for lack of
+    /// permissions; the rest of the walk goes on.
+    Unreadable { error: String },
+}
+
+/// Path met by [discover_report] and how it was resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    pub path: PathBuf,
+    pub resolution: Resolution,
+}
+
+/// Options for [discover].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::{DiscoverOptions, SymlinkPolicy};
+///
+/// fn main() {
+///     let options = DiscoverOptions::new()
+///         .case_sensitive(false)
+///         .symlinks(SymlinkPolicy::Follow);
+///     assert_eq!(options.get_case_sensitive(), false);
+///     assert_eq!(options.get_symlinks(), SymlinkPolicy::Follow);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoverOptions {
+    case_sensitive: bool,
+    symlinks: SymlinkPolicy,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    ignore_files: bool,
+}
+
+impl Default for DiscoverOptions {
+    fn default() -> Self {
+        DiscoverOptions {
+            case_sensitive: true,
+            symlinks: SymlinkPolicy::Skip,
+            include: vec![],
+            exclude: vec![],
+            ignore_files: false,
+        }
+    }
+}
+
+impl DiscoverOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether patterns match file names case-sensitively (default: `true`).
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// See [SymlinkPolicy] (default: [SymlinkPolicy::Skip]).
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Adds a glob a file must match, relative to the directory the walk
+    /// starts from. With no include glob every file is included.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    /// Adds a glob excluding the files it matches, relative to the
+    /// directory the walk starts from (e.g. `target/**`).
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Whether `.gitignore` and `.nestacignore` files met while walking are
+    /// honored (default: `false`). Their usual syntax is supported: `#`
+    /// comments, `!` negations, trailing `/` for directories only and
+    /// patterns holding a `/` being relative to the ignore file.
+    pub fn ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    pub fn get_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    pub fn get_symlinks(&self) -> SymlinkPolicy {
+        self.symlinks
+    }
+
+    pub fn get_include(&self) -> &[String] {
+        &self.include
+    }
+
+    pub fn get_exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    pub fn get_ignore_files(&self) -> bool {
+        self.ignore_files
+    }
+}
+
+/// Removes the Windows verbatim prefix (`\\?\C:\...` or
+/// `\\?\UNC\server\share\...`) and turns every `\` into `/`, giving the
+/// form patterns are matched against.
+pub(crate) fn normalize(path: &str) -> String {
+    let path = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    };
+    path.replace('\\', "/")
+}
+
+/// Number of leading pattern components that hold no glob metacharacter,
+/// the last component always being left out.
+fn literal_len(components: &[&str]) -> usize {
+    components
+        .iter()
+        .take(components.len() - 1)
+        .take_while(|component| !component.contains(['*', '?', '[']))
+        .count()
+}
+
+/// Directory to start walking from: every leading pattern component that
+/// holds no glob metacharacter.
+fn walk_root(pattern: &str) -> PathBuf {
+    let mut root = PathBuf::new();
+    let components: Vec<&str> = pattern.split('/').collect();
+    for component in &components[..literal_len(&components)] {
+        root.push(if component.is_empty() { "/" } else { component });
+    }
+    if root.as_os_str().is_empty() {
+        root.push(".");
+    }
+    root
+}
+
+/// How many directory levels below [walk_root] a pattern can match, [None]
+/// when it holds a `**` component matching any depth.
+fn walk_depth(pattern: &str) -> Option<usize> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    if components.contains(&"**") {
+        return None;
+    }
+    Some(components.len() - literal_len(&components))
+}
+
+/// A single line of a `.gitignore`/`.nestacignore` file.
+struct IgnoreRule {
+    /// Directory holding the ignore file.
+    base: PathBuf,
+    pattern: Pattern,
+    negate: bool,
+    dir_only: bool,
+    /// Whether the pattern is matched against the path relative to `base`
+    /// rather than against the file name.
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    fn parse(base: &Path, line: &str) -> Option<IgnoreRule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+        Some(IgnoreRule {
+            base: base.to_path_buf(),
+            pattern,
+            negate,
+            dir_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        if self.anchored {
+            match path.strip_prefix(&self.base) {
+                Ok(relative) => self.pattern.matches_with(&normalize(&relative.to_string_lossy()), options),
+                Err(_) => false,
+            }
+        } else {
+            let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            self.pattern.matches_with(&name, options)
+        }
+    }
+}
+
+/// Name of the ignore files honored when [DiscoverOptions::ignore_files] is
+/// enabled.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".nestacignore"];
+
+/// Walks directories collecting files and the symlink decisions taken on
+/// the way.
+struct Walker<'a> {
+    options: &'a DiscoverOptions,
+    /// Canonical path of every directory being walked.
+    ancestors: Vec<PathBuf>,
+    /// Rules of the ignore files found on the directories being walked.
+    rules: Vec<IgnoreRule>,
+    /// Directory levels left to descend into, see [walk_depth].
+    max_depth: Option<usize>,
+    found: Vec<Discovered>,
+}
+
+impl Walker<'_> {
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negate)
+    }
+
+    /// Walks `dir`, `depth` levels below the root, recording whatever
+    /// cannot be read as [Resolution::Unreadable].
+    fn walk(&mut self, dir: &Path, depth: usize, through_symlink: bool) {
+        if let Err(err) = self.walk_dir(dir, depth, through_symlink) {
+            self.found.push(Discovered {
+                path: dir.to_path_buf(),
+                resolution: Resolution::Unreadable { error: err.to_string() },
+            });
+        }
+    }
+
+    /// Whether the directories of `depth` can hold files the pattern
+    /// matches.
+    fn descends(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth < max_depth)
+    }
+
+    fn walk_dir(&mut self, dir: &Path, depth: usize, through_symlink: bool) -> io::Result<()> {
+        let rules_len = self.rules.len();
+        if self.options.ignore_files {
+            for name in IGNORE_FILES {
+                if let Ok(content) = fs::read_to_string(long_path(&dir.join(name))) {
+                    self.rules.extend(content.lines().filter_map(|line| IgnoreRule::parse(dir, line)));
+                }
+            }
+        }
+        let result = self.walk_entries(dir, depth, through_symlink);
+        self.rules.truncate(rules_len);
+        result
+    }
+
+    fn walk_entries(&mut self, dir: &Path, depth: usize, through_symlink: bool) -> io::Result<()> {
+        for entry in fs::read_dir(long_path(dir))? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.found.push(Discovered {
+                        path: dir.to_path_buf(),
+                        resolution: Resolution::Unreadable { error: err.to_string() },
+                    });
+                    continue;
+                }
+            };
+            let path = dir.join(entry.file_name());
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(err) => {
+                    self.found.push(Discovered { path, resolution: Resolution::Unreadable { error: err.to_string() } });
+                    continue;
+                }
+            };
+            if file_type.is_symlink() {
+                if self.options.symlinks == SymlinkPolicy::Skip {
+                    if !self.is_ignored(&path, false) {
+                        self.found.push(Discovered { path, resolution: Resolution::SkippedSymlink });
+                    }
+                    continue;
+                }
+                let target = match fs::canonicalize(long_path(&path)) {
+                    Ok(target) => target,
+                    Err(_) => {
+                        if !self.is_ignored(&path, false) {
+                            self.found.push(Discovered { path, resolution: Resolution::Broken });
+                        }
+                        continue;
+                    }
+                };
+                if self.is_ignored(&path, target.is_dir()) {
+                    continue;
+                }
+                if target.is_dir() {
+                    if !self.descends(depth + 1) {
+                        continue;
+                    }
+                    if self.ancestors.contains(&target) {
+                        self.found.push(Discovered { path, resolution: Resolution::Cycle { target } });
+                        continue;
+                    }
+                    self.ancestors.push(target);
+                    self.walk(&path, depth + 1, true);
+                    self.ancestors.pop();
+                } else {
+                    self.found.push(Discovered { path, resolution: Resolution::Symlink { target } });
+                }
+            } else if file_type.is_dir() {
+                if !self.descends(depth + 1) || self.is_ignored(&path, true) {
+                    continue;
+                }
+                match fs::canonicalize(long_path(&path)) {
+                    Ok(canonical) => self.ancestors.push(canonical),
+                    Err(err) => {
+                        self.found.push(Discovered { path, resolution: Resolution::Unreadable { error: err.to_string() } });
+                        continue;
+                    }
+                }
+                self.walk(&path, depth + 1, through_symlink);
+                self.ancestors.pop();
+            } else if self.is_ignored(&path, false) {
+                continue;
+            } else if through_symlink {
+                let resolution = match fs::canonicalize(long_path(&path)) {
+                    Ok(target) => Resolution::Symlink { target },
+                    Err(err) => Resolution::Unreadable { error: err.to_string() },
+                };
+                self.found.push(Discovered { path, resolution });
+            } else {
+                self.found.push(Discovered { path, resolution: Resolution::File });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compile(patterns: &[String]) -> io::Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(&normalize(pattern))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string())))
+        .collect()
+}
+
+/// Returns the files matching a glob `pattern` (e.g. `configs/**/*.json`),
+/// sorted by path.
+///
+/// `\` and `/` are both accepted as directory separators, in the pattern
+/// and on disk, and Windows verbatim (`\\?\`) paths are matched like their
+/// regular form; long paths are opened through their verbatim form on
+/// Windows. Symlinks are handled according to [SymlinkPolicy]; see
+/// [discover_report] to know how each file was reached.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::{discover, DiscoverOptions};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+///     std::fs::write(dir.path().join("a/b/Config.JSON"), "{}").unwrap();
+///     std::fs::write(dir.path().join("a/notes.txt"), "").unwrap();
+///
+///     let pattern = format!("{}/**/*.json", dir.path().display());
+///     let options = DiscoverOptions::new().case_sensitive(false);
+///     let files = discover(&pattern, &options).unwrap();
+///     assert_eq!(files, vec![dir.path().join("a/b/Config.JSON")]);
+/// }
+/// ```
+pub fn discover(pattern: &str, options: &DiscoverOptions) -> io::Result<Vec<PathBuf>> {
+    Ok(discover_report(pattern, options)?
+        .into_iter()
+        .filter(|found| matches!(
+            found.resolution,
+            Resolution::File | Resolution::Symlink { .. },
+        ))
+        .map(|found| found.path)
+        .collect())
+}
+
+/// Same as [discover] but also reports how each matching path was
+/// resolved, including the symlinks that were skipped, broken or not
+/// followed because of a cycle, and the directories that could not be
+/// read.
+///
+/// Patterns without a `**` component only walk as many directory levels
+/// as they have components, so `configs/*.json` never enters
+/// `configs/node_modules`.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::{discover_report, DiscoverOptions, Resolution, SymlinkPolicy};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     std::fs::create_dir_all(dir.path().join("shared")).unwrap();
+///     std::fs::write(dir.path().join("shared/base.json"), "{}").unwrap();
+///     # #[cfg(unix)]
+///     std::os::unix::fs::symlink(dir.path(), dir.path().join("shared/loop")).unwrap();
+///
+///     let pattern = format!("{}/**/*", dir.path().display());
+///     let options = DiscoverOptions::new().symlinks(SymlinkPolicy::Follow);
+///     let report = discover_report(&pattern, &options).unwrap();
+///     assert_eq!(report[0].path, dir.path().join("shared/base.json"));
+///     assert_eq!(report[0].resolution, Resolution::File);
+///     # #[cfg(unix)]
+///     assert!(matches!(report[1].resolution, Resolution::Cycle { .. }));
+/// }
+/// ```
+pub fn discover_report(pattern: &str, options: &DiscoverOptions) -> io::Result<Vec<Discovered>> {
+    let pattern = normalize(pattern);
+    let matcher = Pattern::new(&pattern)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+    let match_options = MatchOptions {
+        case_sensitive: options.case_sensitive,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    let include = compile(&options.include)?;
+    let exclude = compile(&options.exclude)?;
+    let root = walk_root(&pattern);
+    let mut walker = Walker {
+        options,
+        ancestors: vec![fs::canonicalize(long_path(&root))?],
+        rules: vec![],
+        max_depth: walk_depth(&pattern),
+        found: vec![],
+    };
+    // only the root failing fails the whole discovery
+    fs::read_dir(long_path(&root))?;
+    walker.walk(&root, 0, false);
+    let mut found: Vec<Discovered> = walker.found
+        .into_iter()
+        .filter(|found| {
+            let display = normalize(&found.path.to_string_lossy());
+            let display = display.strip_prefix("./").unwrap_or(&display);
+            let relative = match found.path.strip_prefix(&root) {
+                Ok(relative) => normalize(&relative.to_string_lossy()),
+                Err(_) => display.to_string(),
+            };
+            let unreadable = matches!(found.resolution, Resolution::Unreadable { .. });
+            (unreadable || matcher.matches_with(display, match_options))
+                && (unreadable || include.is_empty() || include.iter().any(|p| p.matches_with(&relative, match_options)))
+                && !exclude.iter().any(|p| p.matches_with(&relative, match_options))
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_separators_and_verbatim_prefix() {
+        assert_eq!(normalize(r"\\?\C:\configs\a.json"), "C:/configs/a.json");
+        assert_eq!(normalize(r"\\?\UNC\server\share\a.json"), "//server/share/a.json");
+        assert_eq!(normalize(r"configs\nested/a.json"), "configs/nested/a.json");
+    }
+
+    #[test]
+    fn walk_root_stops_at_first_glob() {
+        assert_eq!(walk_root("configs/**/*.json"), PathBuf::from("configs"));
+        assert_eq!(walk_root("*.json"), PathBuf::from("."));
+        assert_eq!(walk_root("/etc/app/*.json"), PathBuf::from("/etc/app"));
+        assert_eq!(walk_depth("configs/*/*.json"), Some(2));
+        assert_eq!(walk_depth("*.json"), Some(1));
+        assert_eq!(walk_depth("configs/**/*.json"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shallow_patterns_and_unreadable_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(root.join("locked")).unwrap();
+        fs::write(root.join("a.json"), "{}").unwrap();
+        fs::write(root.join("node_modules/pkg/b.json"), "{}").unwrap();
+        fs::write(root.join("locked/c.json"), "{}").unwrap();
+        fs::set_permissions(root.join("node_modules"), fs::Permissions::from_mode(0o000)).unwrap();
+        fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+        // privileged users read the directories anyway
+        let enforced = fs::read_dir(root.join("locked")).is_err();
+
+        let report = discover_report(&format!("{}/*.json", root.display()), &DiscoverOptions::new()).unwrap();
+        assert_eq!(report, vec![Discovered { path: root.join("a.json"), resolution: Resolution::File }]);
+        let options = DiscoverOptions::new();
+        let mut walker = Walker {
+            options: &options,
+            ancestors: vec![],
+            rules: vec![],
+            max_depth: walk_depth("*.json"),
+            found: vec![],
+        };
+        walker.walk(root, 0, false);
+        assert_eq!(walker.found, vec![Discovered { path: root.join("a.json"), resolution: Resolution::File }]);
+
+        let pattern = format!("{}/**/*.json", root.display());
+        let options = DiscoverOptions::new().exclude("node_modules");
+        let report = discover_report(&pattern, &options).unwrap();
+        fs::set_permissions(root.join("node_modules"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(report[0], Discovered { path: root.join("a.json"), resolution: Resolution::File });
+        if enforced {
+            assert_eq!(report.len(), 2);
+            assert_eq!(report[1].path, root.join("locked"));
+            assert!(matches!(report[1].resolution, Resolution::Unreadable { .. }));
+        }
+    }
+
+    #[test]
+    fn discover_with_mixed_separators() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("x/y")).unwrap();
+        fs::write(dir.path().join("x/y/a.json"), "{}").unwrap();
+        fs::write(dir.path().join("x/b.json"), "{}").unwrap();
+        fs::write(dir.path().join("x/C.JSON"), "{}").unwrap();
+        let pattern = format!(r"{}\x\*.json", dir.path().display());
+        let files = discover(&pattern, &DiscoverOptions::new()).unwrap();
+        assert_eq!(files, vec![dir.path().join("x/b.json")]);
+        let files = discover(
+            &pattern,
+            &DiscoverOptions::new().case_sensitive(false),
+        ).unwrap();
+        assert_eq!(files, vec![dir.path().join("x/C.JSON"), dir.path().join("x/b.json")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_policies() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("configs");
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::write(root.join("shared/base.json"), "{}").unwrap();
+        symlink(root.join("shared"), root.join("app/shared")).unwrap();
+        symlink(root.join("shared/base.json"), root.join("app/base.json")).unwrap();
+        symlink(root.join("app"), root.join("app/self")).unwrap();
+        symlink(root.join("missing.json"), root.join("app/broken.json")).unwrap();
+        let pattern = format!("{}/app/**/*", root.display());
+
+        let report = discover_report(&pattern, &DiscoverOptions::new()).unwrap();
+        assert_eq!(report.len(), 4);
+        assert!(report.iter().all(|f| f.resolution == Resolution::SkippedSymlink));
+
+        let options = DiscoverOptions::new().symlinks(SymlinkPolicy::Follow);
+        let report = discover_report(&pattern, &options).unwrap();
+        let target = fs::canonicalize(root.join("shared/base.json")).unwrap();
+        assert_eq!(report, vec![
+            Discovered {
+                path: root.join("app/base.json"),
+                resolution: Resolution::Symlink { target: target.clone() },
+            },
+            Discovered {
+                path: root.join("app/broken.json"),
+                resolution: Resolution::Broken,
+            },
+            Discovered {
+                path: root.join("app/self"),
+                resolution: Resolution::Cycle { target: fs::canonicalize(root.join("app")).unwrap() },
+            },
+            Discovered {
+                path: root.join("app/shared/base.json"),
+                resolution: Resolution::Symlink { target },
+            },
+        ]);
+        let files = discover(&pattern, &options).unwrap();
+        assert_eq!(files, vec![root.join("app/base.json"), root.join("app/shared/base.json")]);
+    }
+
+    #[test]
+    fn include_exclude_and_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "app/config.json",
+            "app/local.json",
+            "app/build/out.json",
+            "target/debug/x.json",
+            "node_modules/pkg/package.json",
+            "vendor/lib.json",
+            "vendor/keep.json",
+        ] {
+            fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            fs::write(root.join(file), "{}").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "# build outputs\ntarget/\nnode_modules/\n").unwrap();
+        fs::write(root.join("app/.nestacignore"), "/build\nlocal.json\n").unwrap();
+        fs::write(root.join("vendor/.gitignore"), "*.json\n!keep.json\n").unwrap();
+        let pattern = format!("{}/**/*.json", root.display());
+
+        let options = DiscoverOptions::new().ignore_files(true);
+        let files = discover(&pattern, &options).unwrap();
+        assert_eq!(files, vec![root.join("app/config.json"), root.join("vendor/keep.json")]);
+
+        let options = DiscoverOptions::new()
+            .include("app/**")
+            .include("vendor/**")
+            .exclude("**/build/**")
+            .exclude("*/local.json");
+        let files = discover(&pattern, &options).unwrap();
+        assert_eq!(files, vec![
+            root.join("app/config.json"),
+            root.join("vendor/keep.json"),
+            root.join("vendor/lib.json"),
+        ]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::nested::escape_key;
+use super::load_file;
+
+/// Spelling of a key found by [key_clusters], with every place it is used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyVariant {
+    pub key: String,
+    /// File and path (usable with [crate::json_read]) of every occurrence.
+    pub occurrences: Vec<(PathBuf, String)>,
+}
+
+/// Keys spelled differently but likely meant to be the same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyCluster {
+    /// Variants sorted by key, at least two of them.
+    pub variants: Vec<KeyVariant>,
+}
+
+/// Groups the keys used across the JSON files whose spellings are close:
+/// keys equal once lowercased and stripped of `_`, `-` and spaces
+/// (`timeout`, `time_out`, `timeOut`), or within `max_distance` edits
+/// (Levenshtein distance) of each other once normalized that way.
+///
+/// Only groups of two or more spellings are returned, sorted by their
+/// first key. Files that cannot be read or parsed are left out (see
+/// [super::probe] to find them). Keep `max_distance` low: short keys such
+/// as `id` and `ip` are already one edit apart.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::key_clusters;
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file_a = dir.path().join("a.json");
+///     let file_b = dir.path().join("b.json");
+///     std::fs::write(&file_a, r#"{"http": {"timeout": 5, "retries": 1}}"#).unwrap();
+///     std::fs::write(&file_b, r#"{"http": {"timeOut": 5, "retry": 1}}"#).unwrap();
+///
+///     let clusters = key_clusters(&[&file_a, &file_b], 0);
+///     assert_eq!(clusters.len(), 1);
+///     let keys: Vec<&str> = clusters[0].variants.iter().map(|variant| variant.key.as_str()).collect();
+///     assert_eq!(keys, vec!["timeOut", "timeout"]);
+///     assert_eq!(clusters[0].variants[0].occurrences, vec![(file_b.clone(), "http.timeOut".to_string())]);
+///
+///     assert_eq!(key_clusters(&[&file_a, &file_b], 3).len(), 2);
+/// }
+/// ```
+pub fn key_clusters<P: AsRef<Path>>(files: &[P], max_distance: usize) -> Vec<KeyCluster> {
+    let mut keys: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+    for file in files {
+        if let Ok(doc) = load_file(file.as_ref()) {
+            collect_keys(doc.value(), "", file.as_ref(), &mut keys);
+        }
+    }
+    let variants: Vec<(String, Vec<(PathBuf, String)>)> = keys.into_iter().collect();
+    let normalized: Vec<Vec<char>> = variants.iter().map(|(key, _)| normalize(key)).collect();
+    // union-find over the distinct keys
+    let mut parents: Vec<usize> = (0..variants.len()).collect();
+    for i in 0..variants.len() {
+        for j in i + 1..variants.len() {
+            if levenshtein(&normalized[i], &normalized[j]) <= max_distance {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let mut clusters: BTreeMap<usize, Vec<KeyVariant>> = BTreeMap::new();
+    for (i, (key, occurrences)) in variants.into_iter().enumerate() {
+        let cluster = root(&mut parents, i);
+        clusters.entry(cluster).or_default().push(KeyVariant { key, occurrences });
+    }
+    clusters
+        .into_values()
+        .filter(|variants| variants.len() > 1)
+        .map(|variants| KeyCluster { variants })
+        .collect()
+}
+
+fn collect_keys(node: &Value, path: &str, file: &Path, keys: &mut BTreeMap<String, Vec<(PathBuf, String)>>) {
+    let join = |token: &str| match path.is_empty() {
+        true => token.to_string(),
+        false => [path, token].join("."),
+    };
+    match node {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_path = join(&escape_key(key, "."));
+                keys.entry(key.clone()).or_default().push((file.to_path_buf(), child_path.clone()));
+                collect_keys(child, &child_path, file, keys);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_keys(item, &join(&format!("[{}]", i)), file, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Lowercased key without word separators.
+fn normalize(key: &str) -> Vec<char> {
+    key.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Number of single character insertions, deletions or substitutions
+/// turning `a` into `b`.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_distance() {
+        let distance = |a: &str, b: &str| levenshtein(&normalize(a), &normalize(b));
+        assert_eq!(distance("time_out", "timeOut"), 0);
+        assert_eq!(distance("retry", "retries"), 3);
+        assert_eq!(distance("color", "colour"), 1);
+        assert_eq!(distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,1350 @@
+//! Applying the same path updates to many JSON files.
+//!
+//! Enabled by the `batch` feature.
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use serde_json::Value;
+use crate::{json_read, json_read_all, Document, NestacError};
+use crate::error::{value_kind, value_preview};
+use crate::nested::check_update;
+use crate::write::{long_path, sync_parent, write_file};
+
+mod binary;
+mod discover;
+mod keys;
+mod plan;
+mod report;
+#[cfg(feature = "rhai")]
+mod script;
+pub use binary::{PlanReader, PlanWriter, ReportReader, ReportWriter};
+pub use discover::{discover, discover_report, Discovered, DiscoverOptions, Resolution, SymlinkPolicy};
+pub use keys::{key_clusters, KeyCluster, KeyVariant};
+pub use plan::{apply, plan, Plan, PlannedFile};
+pub use report::{RunReport, Totals};
+#[cfg(feature = "rhai")]
+pub use script::ScriptOp;
+
+/// Outcome of processing a single file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileStatus {
+    /// At least one update changed the document and the file was rewritten.
+    Updated,
+    /// Every update left the document as it was, the file was not written.
+    Unchanged,
+    /// The file could not be read, parsed, updated or written.
+    Failed(String),
+    /// The file was not processed, e.g. its lock could not be acquired and
+    /// [LockFailure::Skip] was requested.
+    Skipped(String),
+}
+
+/// Result of processing a single file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    /// Time spent on the file, including waiting for its lock.
+    pub elapsed: Duration,
+}
+
+/// When written files are flushed to disk with `fsync`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Leave flushing to the operating system.
+    Never,
+    /// Flush every file right after writing it, and its directory after an
+    /// atomic rename.
+    PerFile,
+    /// Flush every written file once all files were processed, and with
+    /// atomic writes the directories holding them.
+    AtEnd,
+}
+
+/// What to do with a file whose advisory lock could not be acquired in
+/// time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockFailure {
+    /// Report the file as [FileStatus::Failed].
+    Fail,
+    /// Report the file as [FileStatus::Skipped].
+    Skip,
+}
+
+/// Options for the batch writers ([update_files_with], [run_ops_with]).
+///
+/// Results are always returned in the same order as the input files,
+/// regardless of the concurrency.
+///
+/// Files are written in place unless atomic writes are enabled, in which
+/// case they are written to a temporary file renamed over the original one,
+/// keeping its permissions and (when allowed) ownership. An explicit unix
+/// `mode` can be given for the written files instead.
+///
+/// When locking is enabled every file is exclusively locked (advisory lock,
+/// `flock` on unix) for the whole read-modify-write cycle, waiting at most
+/// the lock timeout for other processes to release it. The lock is taken on
+/// a `<file>.lock` sidecar, created next to the file and left in place, so
+/// it keeps guarding the file when atomic writes rename a new one over it.
+///
+/// When a checkpoint file is given, the result of every file is appended to
+/// it as soon as the file is processed, so an interrupted run can be picked
+/// up again with [resume].
+///
+/// Protected path patterns guard critical keys against overly broad edits:
+/// a file fails as a whole, and is left untouched, when its updates or
+/// operations modified, deleted or created any path matched by them.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::{BatchOptions, FsyncPolicy};
+///
+/// fn main() {
+///     let options = BatchOptions::new()
+///         .concurrency(4)
+///         .fsync(FsyncPolicy::PerFile)
+///         .lock(true)
+///         .protect("license.**");
+///     assert_eq!(options.get_concurrency(), 4);
+///     assert_eq!(options.get_fsync(), FsyncPolicy::PerFile);
+///     assert_eq!(options.get_lock(), true);
+///     assert_eq!(options.get_protected(), ["license.**"]);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOptions {
+    concurrency: usize,
+    fsync: FsyncPolicy,
+    lock: bool,
+    lock_timeout: Duration,
+    lock_failure: LockFailure,
+    atomic: bool,
+    mode: Option<u32>,
+    checkpoint: Option<PathBuf>,
+    protected: Vec<String>,
+    deterministic: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            concurrency: 0,
+            fsync: FsyncPolicy::Never,
+            lock: false,
+            lock_timeout: Duration::from_secs(10),
+            lock_failure: LockFailure::Fail,
+            atomic: false,
+            mode: None,
+            checkpoint: None,
+            protected: vec![],
+            deterministic: false,
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of files processed at the same time (default: `0`,
+    /// one per available CPU).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// See [FsyncPolicy] (default: [FsyncPolicy::Never]).
+    pub fn fsync(mut self, fsync: FsyncPolicy) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Whether files are locked while being edited (default: `false`).
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// How long to wait for a lock (default: 10 seconds).
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// See [LockFailure] (default: [LockFailure::Fail]).
+    pub fn lock_failure(mut self, lock_failure: LockFailure) -> Self {
+        self.lock_failure = lock_failure;
+        self
+    }
+
+    /// Whether files are written through a temporary file renamed over the
+    /// original one (default: `false`).
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Explicit unix permissions for written files (default: keep the
+    /// original ones).
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// File the result of every processed file is appended to, one JSON
+    /// object per line (default: none). See [RunReport::parse].
+    pub fn checkpoint<P: AsRef<Path>>(mut self, checkpoint: P) -> Self {
+        self.checkpoint = Some(checkpoint.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds a path pattern that must never be modified or deleted, using
+    /// `.` as separator and the wildcard tokens of [crate::json_read_all]
+    /// (default: none).
+    pub fn protect(mut self, pattern: &str) -> Self {
+        self.protected.push(pattern.to_string());
+        self
+    }
+
+    /// Makes results byte-for-byte reproducible (default: `false`): every
+    /// elapsed time is recorded as zero, and files are processed one at a
+    /// time in input order when a checkpoint is written so that its lines
+    /// come in that order too. Results are always returned in input order.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn get_fsync(&self) -> FsyncPolicy {
+        self.fsync
+    }
+
+    pub fn get_lock(&self) -> bool {
+        self.lock
+    }
+
+    pub fn get_lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    pub fn get_lock_failure(&self) -> LockFailure {
+        self.lock_failure
+    }
+
+    pub fn get_atomic(&self) -> bool {
+        self.atomic
+    }
+
+    pub fn get_mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    pub fn get_checkpoint(&self) -> Option<&Path> {
+        self.checkpoint.as_deref()
+    }
+
+    pub fn get_protected(&self) -> &[String] {
+        &self.protected
+    }
+
+    pub fn get_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Time elapsed since `start`, or zero in deterministic mode.
+    fn elapsed_since(&self, start: Instant) -> Duration {
+        match self.deterministic {
+            true => Duration::ZERO,
+            false => start.elapsed(),
+        }
+    }
+}
+
+/// Outcome of probing a single file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProbeStatus {
+    /// The path exists; `kind` is the JSON type of the value and `preview`
+    /// a short single-line rendering of it.
+    Found { kind: String, preview: String },
+    /// The path does not exist on the document.
+    Missing,
+    /// The file could not be read or parsed.
+    Failed(String),
+}
+
+/// Result of probing a single file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Probe {
+    pub path: PathBuf,
+    pub status: ProbeStatus,
+}
+
+/// Distinct values found at a path across many files.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram {
+    /// Number of files holding each distinct value, keyed by the value
+    /// serialized as compact JSON.
+    pub counts: BTreeMap<String, usize>,
+    /// Number of files where the path does not exist.
+    pub missing: usize,
+    /// Number of files that could not be read or parsed.
+    pub failed: usize,
+}
+
+/// A named operation that can be applied to every document of a batch.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::batch::{run_ops, FileStatus, Op, OpCall, OpRegistry};
+///
+/// struct Increment;
+///
+/// impl Op for Increment {
+///     fn name(&self) -> &str {
+///         "increment"
+///     }
+///
+///     fn apply(&self, data: &mut Value, args: &Value) -> Result<(), String> {
+///         let path = args["path"].as_str().ok_or("missing `path`")?;
+///         let current = nestac::json_read(path, data, None)
+///             .and_then(|v| v.as_i64())
+///             .ok_or("not a number")?;
+///         nestac::json_update(data, path, None, Value::from(current + 1));
+///         Ok(())
+///     }
+/// }
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("counter.json");
+///     std::fs::write(&file, r#"{"count": 41}"#).unwrap();
+///
+///     let mut registry = OpRegistry::new();
+///     registry.register(Box::new(Increment));
+///     let calls = [OpCall::new("increment", json!({"path": "count"}))];
+///     let results = run_ops(&[&file], &registry, &calls);
+///     assert_eq!(results[0].status, FileStatus::Updated);
+///     assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"{"count":42}"#);
+/// }
+/// ```
+pub trait Op: Send + Sync {
+    /// Name used to invoke the operation from an [OpCall].
+    fn name(&self) -> &str;
+
+    /// Applies the operation to a document.
+    fn apply(&self, data: &mut Value, args: &Value) -> Result<(), String>;
+}
+
+/// Built-in `set` operation: `{"path": "a.b", "value": 1}` with an optional
+/// `"separator"`. Fails when the parent of the path does not exist.
+struct SetOp;
+
+impl Op for SetOp {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn apply(&self, data: &mut Value, args: &Value) -> Result<(), String> {
+        let path = args["path"].as_str().ok_or("`set` expects a string `path`")?;
+        let separator = args["separator"].as_str();
+        let new_value = args.get("value").ok_or("`set` expects a `value`")?;
+        check_update(data, path, separator, new_value).map_err(|err| err.render(data, separator))?;
+        if json_read(path, data, separator) != Some(new_value) {
+            crate::nested::update(data, path, separator, new_value.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Operations available to [run_ops] by name.
+pub struct OpRegistry {
+    ops: BTreeMap<String, Box<dyn Op>>,
+}
+
+impl Default for OpRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpRegistry {
+    /// Registry holding the built-in operations (`set`, plus `script` with
+    /// the `rhai` feature).
+    pub fn new() -> Self {
+        let mut registry = OpRegistry {
+            ops: BTreeMap::new(),
+        };
+        registry.register(Box::new(SetOp));
+        #[cfg(feature = "rhai")]
+        registry.register(Box::new(ScriptOp));
+        registry
+    }
+
+    /// Registers an operation, replacing any operation with the same name.
+    pub fn register(&mut self, op: Box<dyn Op>) {
+        self.ops.insert(op.name().to_string(), op);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Op> {
+        self.ops.get(name).map(|op| op.as_ref())
+    }
+
+    /// Names of the registered operations.
+    pub fn names(&self) -> Vec<&str> {
+        self.ops.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+/// Invocation of a registered [Op] with its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpCall {
+    pub name: String,
+    pub args: Value,
+}
+
+impl OpCall {
+    pub fn new(name: &str, args: Value) -> Self {
+        OpCall {
+            name: name.to_string(),
+            args,
+        }
+    }
+}
+
+/// Parses a batch manifest: a JSON array of objects each holding the
+/// operation name under `"op"`, every other key being its arguments.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::batch::{parse_manifest, OpCall};
+///
+/// fn main() {
+///     let manifest = json!([{"op": "set", "path": "a.b", "value": 1}]);
+///     let calls = parse_manifest(&manifest).unwrap();
+///     assert_eq!(calls, vec![OpCall::new("set", json!({"path": "a.b", "value": 1}))]);
+/// }
+/// ```
+pub fn parse_manifest(manifest: &Value) -> Result<Vec<OpCall>, String> {
+    let entries = manifest.as_array().ok_or("manifest must be an array")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut args = entry.as_object()
+                .ok_or(format!("manifest entry {} must be an object", i))?
+                .clone();
+            match args.remove("op") {
+                Some(Value::String(name)) => Ok(OpCall { name, args: Value::Object(args) }),
+                _ => Err(format!("manifest entry {} must have a string `op`", i)),
+            }
+        })
+        .collect()
+}
+
+fn load_file(file: &Path) -> Result<Document, String> {
+    let source = fs::read_to_string(long_path(file)).map_err(|err| err.to_string())?;
+    Document::parse(&source).map_err(|err| err.to_string())
+}
+
+/// Applies a single update, failing when the path cannot be updated (e.g.
+/// its parent does not exist). `new_value` is only cloned when it differs from the
+/// current one, so re-running a batch over up-to-date files allocates
+/// nothing.
+fn apply_update(doc: &mut Document, path: &str, separator: Option<&str>, new_value: &Value) -> Result<(), NestacError> {
+    check_update(doc.value(), path, separator, new_value)?;
+    if doc.read(path, separator) != Some(new_value) {
+        doc.update(path, separator, new_value.clone());
+    }
+    Ok(())
+}
+
+/// Copy of the loaded data the protected paths are checked against, only
+/// taken when there are protected paths.
+fn snapshot(doc: &Document, options: &BatchOptions) -> Option<Value> {
+    match options.protected.is_empty() {
+        true => None,
+        false => Some(doc.value().clone()),
+    }
+}
+
+/// Fails when a path matched by a protected pattern differs between
+/// `original` and `data`.
+fn check_protected(original: &Value, data: &Value, options: &BatchOptions) -> Result<(), String> {
+    for pattern in &options.protected {
+        let before: BTreeMap<String, &Value> = json_read_all(pattern, original, None).into_iter().collect();
+        let after: BTreeMap<String, &Value> = json_read_all(pattern, data, None).into_iter().collect();
+        for (path, old_value) in &before {
+            match after.get(path) {
+                None => return Err(format!("protected path `{}` was deleted", path)),
+                Some(new_value) if new_value != old_value => {
+                    return Err(format!("protected path `{}` was modified", path));
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(path) = after.keys().find(|path| !before.contains_key(*path)) {
+            return Err(format!("protected path `{}` was created", path));
+        }
+    }
+    Ok(())
+}
+
+fn save_file(file: &Path, doc: &Document, original: Option<Value>, options: &BatchOptions) -> FileStatus {
+    if !doc.is_dirty() {
+        return FileStatus::Unchanged;
+    }
+    if let Some(original) = original {
+        if let Err(err) = check_protected(&original, doc.value(), options) {
+            return FileStatus::Failed(err);
+        }
+    }
+    let written = write_file(
+        file,
+        &doc.to_string(),
+        options.atomic,
+        options.mode,
+        options.fsync == FsyncPolicy::PerFile,
+    );
+    match written {
+        Ok(_) => FileStatus::Updated,
+        Err(err) => FileStatus::Failed(err.to_string()),
+    }
+}
+
+/// Sidecar holding the advisory lock of `file`. Locking `file` itself
+/// would not do with atomic writes, which replace its inode.
+fn lock_path(file: &Path) -> PathBuf {
+    let name = file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    file.with_file_name(format!("{}.lock", name))
+}
+
+/// Acquires the advisory lock of a file when locking is enabled. The lock
+/// is released when the returned handle is dropped.
+fn lock_file(file: &Path, options: &BatchOptions) -> Result<Option<File>, FileStatus> {
+    if !options.lock {
+        return Ok(None);
+    }
+    let handle = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(long_path(&lock_path(file)))
+        .map_err(|err| FileStatus::Failed(err.to_string()))?;
+    let deadline = Instant::now() + options.lock_timeout;
+    loop {
+        match handle.try_lock() {
+            Ok(()) => return Ok(Some(handle)),
+            Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(fs::TryLockError::WouldBlock) => {
+                let reason = format!("lock not acquired within {:?}", options.lock_timeout);
+                return Err(match options.lock_failure {
+                    LockFailure::Fail => FileStatus::Failed(reason),
+                    LockFailure::Skip => FileStatus::Skipped(reason),
+                });
+            }
+            Err(fs::TryLockError::Error(err)) => {
+                return Err(FileStatus::Failed(err.to_string()));
+            }
+        }
+    }
+}
+
+/// Processes every file with `f` honoring the concurrency and fsync
+/// options, returning the results in input order.
+fn process<P, F>(files: &[P], options: &BatchOptions, f: F) -> Vec<FileResult>
+where
+    P: AsRef<Path> + Sync,
+    F: Fn(&Path) -> FileStatus + Sync,
+{
+    let checkpoint = match &options.checkpoint {
+        Some(checkpoint) => match File::options().create(true).append(true).open(checkpoint) {
+            Ok(handle) => Some(Mutex::new(handle)),
+            Err(err) => {
+                let status = FileStatus::Failed(format!("cannot open checkpoint: {}", err));
+                return files
+                    .iter()
+                    .map(|file| FileResult {
+                        path: file.as_ref().to_path_buf(),
+                        status: status.clone(),
+                        elapsed: Duration::ZERO,
+                    })
+                    .collect();
+            }
+        },
+        None => None,
+    };
+    let process_file = |file: &P| {
+        let start = Instant::now();
+        let status = match lock_file(file.as_ref(), options) {
+            Ok(_lock) => f(file.as_ref()),
+            Err(status) => status,
+        };
+        let result = FileResult {
+            path: file.as_ref().to_path_buf(),
+            status,
+            elapsed: options.elapsed_since(start),
+        };
+        if let Some(checkpoint) = &checkpoint {
+            let line = format!("{}\n", report::file_to_json(&result));
+            let mut handle = checkpoint.lock().unwrap_or_else(|err| err.into_inner());
+            // a lost checkpoint line only means the file is retried
+            let _ = handle.write_all(line.as_bytes()).and_then(|_| handle.flush());
+        }
+        result
+    };
+    let run = || -> Vec<FileResult> {
+        match options.deterministic && checkpoint.is_some() {
+            true => files.iter().map(process_file).collect(),
+            false => files.par_iter().map(process_file).collect(),
+        }
+    };
+    let mut results = match rayon::ThreadPoolBuilder::new()
+        .num_threads(options.concurrency)
+        .build()
+    {
+        Ok(pool) => pool.install(run),
+        Err(_) => run(),
+    };
+    if options.fsync == FsyncPolicy::AtEnd {
+        for result in results.iter_mut() {
+            if result.status != FileStatus::Updated {
+                continue;
+            }
+            if let Err(err) = File::open(long_path(&result.path)).and_then(|handle| handle.sync_all()) {
+                result.status = FileStatus::Failed(err.to_string());
+            }
+        }
+        if options.atomic {
+            // the renames are only durable once their directories are flushed
+            let mut synced: HashMap<PathBuf, io::Result<()>> = HashMap::new();
+            for result in results.iter_mut() {
+                if result.status != FileStatus::Updated {
+                    continue;
+                }
+                let dir = result.path.parent().unwrap_or(Path::new("")).to_path_buf();
+                let synced = synced.entry(dir).or_insert_with(|| sync_parent(&long_path(&result.path)));
+                if let Err(err) = synced {
+                    result.status = FileStatus::Failed(err.to_string());
+                }
+            }
+        }
+    }
+    results
+}
+
+fn update_file(file: &Path, updates: &[(&str, Value)], separator: Option<&str>, options: &BatchOptions) -> FileStatus {
+    let mut doc = match load_file(file) {
+        Ok(doc) => doc,
+        Err(err) => return FileStatus::Failed(err),
+    };
+    let original = snapshot(&doc, options);
+    for (path, new_value) in updates {
+        if let Err(err) = apply_update(&mut doc, path, separator, new_value) {
+            return FileStatus::Failed(err.render(doc.value(), separator));
+        }
+    }
+    save_file(file, &doc, original, options)
+}
+
+fn run_ops_file(file: &Path, registry: &OpRegistry, calls: &[OpCall], options: &BatchOptions) -> FileStatus {
+    let mut doc = match load_file(file) {
+        Ok(doc) => doc,
+        Err(err) => return FileStatus::Failed(err),
+    };
+    let mut value = doc.value().clone();
+    for call in calls {
+        let op = match registry.get(&call.name) {
+            Some(op) => op,
+            None => return FileStatus::Failed(format!("unknown operation `{}`", call.name)),
+        };
+        if let Err(err) = op.apply(&mut value, &call.args) {
+            return FileStatus::Failed(format!("`{}` failed: {}", call.name, err));
+        }
+    }
+    let original = snapshot(&doc, options);
+    if &value != doc.value() {
+        *doc.value_mut() = value;
+    }
+    save_file(file, &doc, original, options)
+}
+
+fn for_each_file<S, F>(file: &Path, state: &S, f: &F, options: &BatchOptions) -> FileStatus
+where
+    F: Fn(&mut Document, &S) -> Result<(), String>,
+{
+    let mut doc = match load_file(file) {
+        Ok(doc) => doc,
+        Err(err) => return FileStatus::Failed(err),
+    };
+    let original = snapshot(&doc, options);
+    if let Err(err) = f(&mut doc, state) {
+        return FileStatus::Failed(err);
+    }
+    save_file(file, &doc, original, options)
+}
+
+/// Applies every `(path, new value)` update to each JSON file, in order.
+///
+/// Files are only written back when an update effectively changed the
+/// document; the others are reported as [FileStatus::Unchanged]. A file
+/// fails as a whole (and is left untouched) when any of its updates targets
+/// a path whose parent does not exist.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::batch::{update_files, FileStatus};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("config.json");
+///     std::fs::write(&file, r#"{"foo": {"bar": "bingo!"}}"#).unwrap();
+///
+///     let updates = [("foo.bar", Value::String("updated!".into()))];
+///     let results = update_files(&[&file], &updates, None);
+///     assert_eq!(results[0].status, FileStatus::Updated);
+///
+///     let results = update_files(&[&file], &updates, None);
+///     assert_eq!(results[0].status, FileStatus::Unchanged);
+/// }
+/// ```
+pub fn update_files<P: AsRef<Path> + Sync>(files: &[P], updates: &[(&str, Value)], separator: Option<&str>) -> Vec<FileResult> {
+    update_files_with(files, updates, separator, &BatchOptions::default())
+}
+
+/// Same as [update_files] but configured through [BatchOptions].
+pub fn update_files_with<P: AsRef<Path> + Sync>(files: &[P], updates: &[(&str, Value)], separator: Option<&str>, options: &BatchOptions) -> Vec<FileResult> {
+    process(files, options, |file| update_file(file, updates, separator, options))
+}
+
+/// Applies every `(path, new value)` update to each JSON file matching the
+/// glob `pattern` (e.g. `configs/**/*.json`), in parallel.
+///
+/// Shorthand for [discover] followed by [update_files]; fails only when the
+/// pattern is invalid or a directory cannot be walked, per-file failures
+/// are reported on the results.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::batch::{update_glob, FileStatus};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     for name in ["a.json", "b.json"] {
+///         std::fs::write(dir.path().join(name), r#"{"version": 1}"#).unwrap();
+///     }
+///
+///     let pattern = format!("{}/*.json", dir.path().display());
+///     let results = update_glob(&pattern, &[("version", Value::from(2))], None).unwrap();
+///     assert_eq!(results.len(), 2);
+///     assert!(results.iter().all(|result| result.status == FileStatus::Updated));
+/// }
+/// ```
+pub fn update_glob(pattern: &str, updates: &[(&str, Value)], separator: Option<&str>) -> io::Result<Vec<FileResult>> {
+    update_glob_with(pattern, updates, separator, &DiscoverOptions::default(), &BatchOptions::default())
+}
+
+/// Same as [update_glob] but configured through [DiscoverOptions] and
+/// [BatchOptions].
+pub fn update_glob_with(pattern: &str, updates: &[(&str, Value)], separator: Option<&str>, discover_options: &DiscoverOptions, options: &BatchOptions) -> io::Result<Vec<FileResult>> {
+    let files = discover(pattern, discover_options)?;
+    Ok(update_files_with(&files, updates, separator, options))
+}
+
+/// Runs the registered operations named by `calls`, in order, against each
+/// JSON file.
+///
+/// Like [update_files], files are only written back when the operations
+/// effectively changed the document and a file fails as a whole when any of
+/// its operations fails or is not registered.
+pub fn run_ops<P: AsRef<Path> + Sync>(files: &[P], registry: &OpRegistry, calls: &[OpCall]) -> Vec<FileResult> {
+    run_ops_with(files, registry, calls, &BatchOptions::default())
+}
+
+/// Same as [run_ops] but configured through [BatchOptions].
+pub fn run_ops_with<P: AsRef<Path> + Sync>(files: &[P], registry: &OpRegistry, calls: &[OpCall], options: &BatchOptions) -> Vec<FileResult> {
+    process(files, options, |file| run_ops_file(file, registry, calls, options))
+}
+
+/// Calls `f` with each JSON file, parsed as a [Document], and the shared
+/// `state`, for jobs the fixed operations of [run_ops] cannot express.
+///
+/// Files are loaded, locked, saved and reported the same way as on
+/// [update_files]: a file is only written back when `f` modified the
+/// document, and fails as a whole (being left untouched) when `f` returns an
+/// error. Files are processed in parallel, so `state` is shared by
+/// reference; use atomics or a [Mutex] to collect results.
+///
+/// # Examples:
+/// ```rust
+/// use std::sync::Mutex;
+/// use serde_json::Value;
+/// use nestac::batch::{for_each_document, FileStatus};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("config.json");
+///     std::fs::write(&file, r#"{"version": 1}"#).unwrap();
+///
+///     let seen = Mutex::new(vec![]);
+///     let results = for_each_document(&[&file], &seen, |doc, seen| {
+///         let version = doc.read("version", None).and_then(Value::as_i64).ok_or("no version")?;
+///         seen.lock().unwrap().push(version);
+///         doc.update("version", None, Value::from(version + 1));
+///         Ok(())
+///     });
+///     assert_eq!(results[0].status, FileStatus::Updated);
+///     assert_eq!(*seen.lock().unwrap(), vec![1]);
+/// }
+/// ```
+pub fn for_each_document<P, S, F>(files: &[P], state: &S, f: F) -> Vec<FileResult>
+where
+    P: AsRef<Path> + Sync,
+    S: Sync,
+    F: Fn(&mut Document, &S) -> Result<(), String> + Sync,
+{
+    for_each_document_with(files, state, &BatchOptions::default(), f)
+}
+
+/// Same as [for_each_document] but configured through [BatchOptions].
+pub fn for_each_document_with<P, S, F>(files: &[P], state: &S, options: &BatchOptions, f: F) -> Vec<FileResult>
+where
+    P: AsRef<Path> + Sync,
+    S: Sync,
+    F: Fn(&mut Document, &S) -> Result<(), String> + Sync,
+{
+    process(files, options, |file| for_each_file(file, state, &f, options))
+}
+
+/// Same as [run_ops_with] but returns a [RunReport] of the run, timings
+/// included.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::batch::{run_ops_report, BatchOptions, OpCall, OpRegistry};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("config.json");
+///     std::fs::write(&file, r#"{"foo": {"bar": "bingo!"}}"#).unwrap();
+///
+///     let calls = [OpCall::new("set", json!({"path": "foo.bar", "value": 1}))];
+///     let report = run_ops_report(&[&file], &OpRegistry::new(), &calls, &BatchOptions::new());
+///     assert_eq!(report.totals().updated, 1);
+///     assert_eq!(report.to_json()["files"][0]["status"], "updated");
+/// }
+/// ```
+pub fn run_ops_report<P: AsRef<Path> + Sync>(files: &[P], registry: &OpRegistry, calls: &[OpCall], options: &BatchOptions) -> RunReport {
+    let start = Instant::now();
+    let results = run_ops_with(files, registry, calls, options);
+    let ops = calls.iter().map(|call| call.name.clone()).collect();
+    RunReport::new(ops, results, options.elapsed_since(start))
+}
+
+/// Resumes an interrupted or partially failed run: files reported as
+/// [FileStatus::Updated] or [FileStatus::Unchanged] by `report` keep their
+/// result, every other file (failed, skipped or never reached) is processed
+/// again like [run_ops_with] does.
+///
+/// `report` is usually loaded with [RunReport::parse] from a saved
+/// [RunReport::to_json] or from the checkpoint file of the interrupted run.
+/// The returned report covers every file of `files`, in order.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::batch::{resume, run_ops_report, BatchOptions, OpCall, OpRegistry, RunReport};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file_a = dir.path().join("a.json");
+///     let file_b = dir.path().join("b.json");
+///     std::fs::write(&file_a, r#"{"foo": {}}"#).unwrap();
+///     std::fs::write(&file_b, r#"{}"#).unwrap();
+///
+///     let files = [&file_a, &file_b];
+///     let calls = [OpCall::new("set", json!({"path": "foo.bar", "value": 1}))];
+///     let registry = OpRegistry::new();
+///     let report = run_ops_report(&files, &registry, &calls, &BatchOptions::new());
+///     assert_eq!(report.totals().failed, 1);
+///
+///     std::fs::write(&file_b, r#"{"foo": {}}"#).unwrap();
+///     let saved = RunReport::parse(&report.to_json().to_string()).unwrap();
+///     let report = resume(&saved, &files, &registry, &calls, &BatchOptions::new());
+///     assert_eq!(report.totals().updated, 2);
+/// }
+/// ```
+pub fn resume<P: AsRef<Path> + Sync>(report: &RunReport, files: &[P], registry: &OpRegistry, calls: &[OpCall], options: &BatchOptions) -> RunReport {
+    let start = Instant::now();
+    let done: HashMap<&Path, &FileResult> = report.files
+        .iter()
+        .filter(|result| matches!(result.status, FileStatus::Updated | FileStatus::Unchanged))
+        .map(|result| (result.path.as_path(), result))
+        .collect();
+    let pending: Vec<&Path> = files
+        .iter()
+        .map(|file| file.as_ref())
+        .filter(|file| !done.contains_key(file))
+        .collect();
+    let mut retried = run_ops_with(&pending, registry, calls, options).into_iter();
+    let results = files
+        .iter()
+        .map(|file| match done.get(file.as_ref()) {
+            Some(result) => (*result).clone(),
+            None => retried.next().expect("one result per pending file"),
+        })
+        .collect();
+    let ops = calls.iter().map(|call| call.name.clone()).collect();
+    RunReport::new(ops, results, options.elapsed_since(start))
+}
+
+/// Reports, for each JSON file, whether `path` exists along with the type
+/// and a preview of its value. Files are never modified.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::{probe, ProbeStatus};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file_a = dir.path().join("a.json");
+///     let file_b = dir.path().join("b.json");
+///     std::fs::write(&file_a, r#"{"log": {"level": "debug"}}"#).unwrap();
+///     std::fs::write(&file_b, r#"{"log": {}}"#).unwrap();
+///
+///     let probes = probe(&[&file_a, &file_b], "log.level", None);
+///     assert_eq!(probes[0].status, ProbeStatus::Found {
+///         kind: "string".to_string(),
+///         preview: "\"debug\"".to_string(),
+///     });
+///     assert_eq!(probes[1].status, ProbeStatus::Missing);
+/// }
+/// ```
+pub fn probe<P: AsRef<Path>>(files: &[P], path: &str, separator: Option<&str>) -> Vec<Probe> {
+    files
+        .iter()
+        .map(|file| {
+            let status = match load_file(file.as_ref()) {
+                Ok(doc) => match json_read(path, doc.value(), separator) {
+                    Some(value) => ProbeStatus::Found {
+                        kind: value_kind(value).to_string(),
+                        preview: value_preview(value),
+                    },
+                    None => ProbeStatus::Missing,
+                },
+                Err(err) => ProbeStatus::Failed(err),
+            };
+            Probe {
+                path: file.as_ref().to_path_buf(),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Aggregates the distinct values found at `path` across JSON files along
+/// with how many files hold each of them. Files are never modified.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::batch::value_histogram;
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let mut files = vec![];
+///     for (i, level) in ["debug", "info", "debug"].iter().enumerate() {
+///         let file = dir.path().join(format!("{}.json", i));
+///         std::fs::write(&file, format!(r#"{{"log": {{"level": "{}"}}}}"#, level)).unwrap();
+///         files.push(file);
+///     }
+///
+///     let histogram = value_histogram(&files, "log.level", None);
+///     assert_eq!(histogram.counts["\"debug\""], 2);
+///     assert_eq!(histogram.counts["\"info\""], 1);
+///     assert_eq!(histogram.missing, 0);
+/// }
+/// ```
+pub fn value_histogram<P: AsRef<Path>>(files: &[P], path: &str, separator: Option<&str>) -> Histogram {
+    let mut histogram = Histogram::default();
+    for file in files {
+        match load_file(file.as_ref()) {
+            Ok(doc) => match json_read(path, doc.value(), separator) {
+                Some(value) => {
+                    *histogram.counts.entry(value.to_string()).or_insert(0) += 1;
+                }
+                None => histogram.missing += 1,
+            },
+            Err(_) => histogram.failed += 1,
+        }
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_files_are_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("unchanged.json");
+        let json_str = "{\"foo\":   {\"bar\": \"bingo!\"}}";
+        fs::write(&file, json_str).unwrap();
+        let results = update_files(
+            &[&file],
+            &[("foo.bar", Value::String("bingo!".to_string()))],
+            None,
+        );
+        assert_eq!(results[0].status, FileStatus::Unchanged);
+        assert_eq!(fs::read_to_string(&file).unwrap(), json_str);
+    }
+
+    #[test]
+    fn changed_files_are_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("changed.json");
+        fs::write(&file, r#"{"foo": {"bar": "bingo!"}}"#).unwrap();
+        let results = update_files(
+            &[&file],
+            &[
+                ("foo.bar", Value::String("updated!".to_string())),
+                ("foo.baz", Value::from(1)),
+            ],
+            None,
+        );
+        assert_eq!(results[0].status, FileStatus::Updated);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            r#"{"foo":{"bar":"updated!","baz":1}}"#,
+        );
+    }
+
+    #[test]
+    fn missing_parent_fails_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("failed.json");
+        let json_str = r#"{"foo": {"bar": "bingo!"}}"#;
+        fs::write(&file, json_str).unwrap();
+        let missing = dir.path().join("missing.json");
+        let results = update_files(
+            &[&file, &missing],
+            &[
+                ("foo.bar", Value::String("updated!".to_string())),
+                ("qux.bar", Value::from(1)),
+            ],
+            None,
+        );
+        assert!(matches!(results[0].status, FileStatus::Failed(_)));
+        assert!(matches!(results[1].status, FileStatus::Failed(_)));
+        assert_eq!(fs::read_to_string(&file).unwrap(), json_str);
+    }
+
+    #[test]
+    fn append_and_slice_tokens_update_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("tokens.json");
+        fs::write(&file, r#"{"tags": ["a", "b"], "ports": [80]}"#).unwrap();
+        let results = update_files(
+            &[&file],
+            &[("tags.[+]", Value::from("c")), ("tags.[0:1]", serde_json::json!(["z"]))],
+            None,
+        );
+        assert_eq!(results[0].status, FileStatus::Updated);
+        assert_eq!(fs::read_to_string(&file).unwrap(), r#"{"tags":["z","b","c"],"ports":[80]}"#);
+        let results = update_files(&[&file], &[("ports.[0:1]", Value::from(1))], None);
+        assert!(matches!(results[0].status, FileStatus::Failed(_)));
+    }
+
+    #[test]
+    fn probe_reports_each_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let found = dir.path().join("found.json");
+        let invalid = dir.path().join("invalid.json");
+        let json_str = r#"{"foo": {"bar": [1, 2, 3]}}"#;
+        fs::write(&found, json_str).unwrap();
+        fs::write(&invalid, "{").unwrap();
+        let probes = probe(&[&found, &invalid], "foo|bar", Some("|"));
+        assert_eq!(probes[0].path, found);
+        assert_eq!(probes[0].status, ProbeStatus::Found {
+            kind: "array".to_string(),
+            preview: "[1,2,3]".to_string(),
+        });
+        assert!(matches!(probes[1].status, ProbeStatus::Failed(_)));
+        assert_eq!(fs::read_to_string(&found).unwrap(), json_str);
+    }
+
+    #[test]
+    fn histogram_counts_missing_and_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = [
+            r#"{"foo": {"bar": 1}}"#,
+            r#"{"foo": {"bar": 1.0}}"#,
+            r#"{"foo": {"bar": {"baz": true}}}"#,
+            r#"{"foo": {}}"#,
+            "not json",
+        ];
+        let mut files = vec![];
+        for (i, source) in sources.iter().enumerate() {
+            let file = dir.path().join(format!("{}.json", i));
+            fs::write(&file, source).unwrap();
+            files.push(file);
+        }
+        let histogram = value_histogram(&files, "foo.bar", None);
+        assert_eq!(histogram.counts.len(), 3);
+        assert_eq!(histogram.counts["1"], 1);
+        assert_eq!(histogram.counts["1.0"], 1);
+        assert_eq!(histogram.counts[r#"{"baz":true}"#], 1);
+        assert_eq!(histogram.missing, 1);
+        assert_eq!(histogram.failed, 1);
+    }
+
+    #[test]
+    fn run_manifest_with_builtin_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("manifest.json");
+        fs::write(&file, r#"{"foo": {"bar": "bingo!"}}"#).unwrap();
+        let manifest: Value = serde_json::from_str(r#"
+            [
+                {"op": "set", "path": "foo.bar", "value": "updated!"},
+                {"op": "set", "path": "foo@baz", "separator": "@", "value": 1}
+            ]
+        "#).unwrap();
+        let calls = parse_manifest(&manifest).unwrap();
+        let registry = OpRegistry::new();
+        let results = run_ops(&[&file], &registry, &calls);
+        assert_eq!(results[0].status, FileStatus::Updated);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            r#"{"foo":{"bar":"updated!","baz":1}}"#,
+        );
+        let results = run_ops(&[&file], &registry, &calls);
+        assert_eq!(results[0].status, FileStatus::Unchanged);
+    }
+
+    #[test]
+    fn unknown_op_fails_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("unknown.json");
+        fs::write(&file, r#"{"foo": 1}"#).unwrap();
+        let registry = OpRegistry::new();
+        assert!(registry.names().contains(&"set"));
+        let calls = [OpCall::new("rotate", Value::Null)];
+        let results = run_ops(&[&file], &registry, &calls);
+        assert_eq!(
+            results[0].status,
+            FileStatus::Failed("unknown operation `rotate`".to_string()),
+        );
+        assert!(parse_manifest(&serde_json::json!([{"path": "a"}])).is_err());
+    }
+
+    #[test]
+    fn bounded_concurrency_keeps_input_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = vec![];
+        for i in 0..32 {
+            let file = dir.path().join(format!("{}.json", i));
+            fs::write(&file, format!(r#"{{"id": {}}}"#, i % 2)).unwrap();
+            files.push(file);
+        }
+        for (fsync, id) in [(FsyncPolicy::PerFile, 1), (FsyncPolicy::AtEnd, 2)] {
+            let options = BatchOptions::new().concurrency(3).atomic(true).fsync(fsync);
+            let results = update_files_with(
+                &files,
+                &[("id", Value::from(id))],
+                None,
+                &options,
+            );
+            let paths: Vec<&PathBuf> = results.iter().map(|r| &r.path).collect();
+            assert_eq!(paths, files.iter().collect::<Vec<&PathBuf>>());
+            for (i, result) in results.iter().enumerate() {
+                let expected = match (fsync, i % 2) {
+                    (FsyncPolicy::PerFile, 1) => FileStatus::Unchanged,
+                    _ => FileStatus::Updated,
+                };
+                assert_eq!(result.status, expected);
+                let saved: Value = serde_json::from_str(&fs::read_to_string(&files[i]).unwrap()).unwrap();
+                assert_eq!(saved["id"], id);
+            }
+        }
+    }
+
+    #[test]
+    fn locked_files_fail_or_skip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("locked.json");
+        fs::write(&file, r#"{"foo": 1}"#).unwrap();
+        let holder = File::create(lock_path(&file)).unwrap();
+        holder.lock().unwrap();
+        let options = BatchOptions::new()
+            .lock(true)
+            .atomic(true)
+            .lock_timeout(Duration::from_millis(50));
+        let updates = [("foo", Value::from(2))];
+        let results = update_files_with(&[&file], &updates, None, &options);
+        assert!(matches!(results[0].status, FileStatus::Failed(_)));
+        let options = options.lock_failure(LockFailure::Skip);
+        let results = update_files_with(&[&file], &updates, None, &options);
+        assert!(matches!(results[0].status, FileStatus::Skipped(_)));
+        holder.unlock().unwrap();
+        let results = update_files_with(&[&file], &updates, None, &options);
+        assert_eq!(results[0].status, FileStatus::Updated);
+        assert_eq!(fs::read_to_string(&file).unwrap(), r#"{"foo":2}"#);
+
+        // the sidecar outlives the atomic rename and still guards the file
+        holder.lock().unwrap();
+        let results = update_files_with(&[&file], &updates, None, &options);
+        assert!(matches!(results[0].status, FileStatus::Skipped(_)));
+        holder.unlock().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_batch_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("secret.json");
+        fs::write(&file, r#"{"token": "a"}"#).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        let options = BatchOptions::new().atomic(true);
+        let results = update_files_with(
+            &[&file],
+            &[("token", Value::String("b".to_string()))],
+            None,
+            &options,
+        );
+        assert_eq!(results[0].status, FileStatus::Updated);
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = dir.path().join("run.checkpoint");
+        let mut files = vec![];
+        for i in 0..4 {
+            let file = dir.path().join(format!("{}.json", i));
+            fs::write(&file, r#"{"foo": {}}"#).unwrap();
+            files.push(file);
+        }
+        fs::write(&files[1], "{").unwrap();
+        let registry = OpRegistry::new();
+        let calls = [OpCall::new("set", serde_json::json!({"path": "foo.bar", "value": 1}))];
+        let options = BatchOptions::new().checkpoint(&checkpoint);
+        // the run was interrupted before reaching the last file
+        run_ops_with(&files[..3], &registry, &calls, &options);
+
+        fs::write(&files[1], r#"{"foo": {}}"#).unwrap();
+        fs::write(&files[0], r#"{"foo": {"bar": 0}}"#).unwrap();
+        let saved = RunReport::parse(&fs::read_to_string(&checkpoint).unwrap()).unwrap();
+        assert_eq!(saved.totals().files(), 3);
+        let report = resume(&saved, &files, &registry, &calls, &BatchOptions::new());
+        let statuses: Vec<&FileStatus> = report.files.iter().map(|r| &r.status).collect();
+        assert_eq!(statuses, vec![&FileStatus::Updated; 4]);
+        // already updated files are not processed again
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), r#"{"foo": {"bar": 0}}"#);
+        assert_eq!(fs::read_to_string(&files[3]).unwrap(), r#"{"foo":{"bar":1}}"#);
+    }
+
+    #[test]
+    fn for_each_document_failures_leave_files_untouched() {
+        use std::sync::atomic::{AtomicI64, Ordering};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = vec![];
+        for (name, json_str) in [("a", r#"{"n": 1}"#), ("b", r#"{"n": "x"}"#)] {
+            let file = dir.path().join(format!("{}.json", name));
+            fs::write(&file, json_str).unwrap();
+            files.push(file);
+        }
+        let total = AtomicI64::new(0);
+        let results = for_each_document(&files, &total, |doc, total| {
+            let n = doc.read("n", None).and_then(Value::as_i64).ok_or("`n` is not a number")?;
+            total.fetch_add(n, Ordering::SeqCst);
+            doc.update("n", None, Value::from(0));
+            Ok(())
+        });
+        assert_eq!(results[0].status, FileStatus::Updated);
+        assert_eq!(results[1].status, FileStatus::Failed("`n` is not a number".to_string()));
+        assert_eq!(total.load(Ordering::SeqCst), 1);
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), r#"{"n":0}"#);
+        assert_eq!(fs::read_to_string(&files[1]).unwrap(), r#"{"n": "x"}"#);
+    }
+
+    #[test]
+    fn protected_paths_fail_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_str = r#"{"license": {"key": "abc"}, "apps": [{"id": 1, "name": "a"}]}"#;
+        let file = dir.path().join("config.json");
+        let options = BatchOptions::new().protect("license.**").protect("apps.[*].id");
+        let run = |updates: &[(&str, Value)]| {
+            fs::write(&file, json_str).unwrap();
+            update_files_with(&[&file], updates, None, &options).remove(0).status
+        };
+        assert_eq!(run(&[("apps.[0].name", Value::from("b"))]), FileStatus::Updated);
+        assert_eq!(run(&[("license.key", Value::from("abc"))]), FileStatus::Unchanged);
+        assert_eq!(
+            run(&[("license.key", Value::from("xyz"))]),
+            FileStatus::Failed("protected path `license.key` was modified".to_string()),
+        );
+        assert_eq!(
+            run(&[("license", Value::Null)]),
+            FileStatus::Failed("protected path `license.key` was deleted".to_string()),
+        );
+        assert_eq!(
+            run(&[("license.owner", Value::from("me"))]),
+            FileStatus::Failed("protected path `license.owner` was created".to_string()),
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), json_str);
+
+        fs::write(&file, json_str).unwrap();
+        let calls = [OpCall::new("set", serde_json::json!({"path": "apps.[0].id", "value": 2}))];
+        let results = run_ops_with(&[&file], &OpRegistry::new(), &calls, &options);
+        assert_eq!(results[0].status, FileStatus::Failed("protected path `apps.[0].id` was modified".to_string()));
+    }
+
+    #[test]
+    fn deterministic_runs_are_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<PathBuf> = (0..8).map(|i| dir.path().join(format!("{}.json", i))).collect();
+        let checkpoint = dir.path().join("checkpoint.jsonl");
+        let options = BatchOptions::new().deterministic(true).checkpoint(&checkpoint);
+        let calls = [OpCall::new("set", serde_json::json!({"path": "version", "value": 2}))];
+        let run = || {
+            for file in &files {
+                fs::write(file, r#"{"version": 1}"#).unwrap();
+            }
+            let _ = fs::remove_file(&checkpoint);
+            let report = run_ops_report(&files, &OpRegistry::new(), &calls, &options);
+            (report.to_json().to_string(), fs::read_to_string(&checkpoint).unwrap())
+        };
+        let first = run();
+        assert_eq!(run(), first);
+        let parsed = RunReport::parse(&first.1).unwrap();
+        assert_eq!(parsed.files.iter().map(|file| &file.path).collect::<Vec<_>>(), files.iter().collect::<Vec<_>>());
+        assert!(parsed.files.iter().all(|file| file.elapsed == Duration::ZERO));
+    }
+}
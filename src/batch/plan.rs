@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use crate::{json_diff, Document};
+use crate::nested::{Change, ChangeKind};
+use crate::write::{long_path, write_file};
+use super::{apply_update, check_protected, process, BatchOptions, FileResult, FileStatus};
+
+/// Edits computed by [plan] and not yet written, meant to be reviewed (see
+/// [Plan::to_json]) before being carried out by [apply].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plan {
+    pub files: Vec<PlannedFile>,
+}
+
+/// Planned outcome of a single file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    /// [FileStatus::Updated] when the file is to be rewritten,
+    /// [FileStatus::Unchanged] or [FileStatus::Failed] otherwise.
+    pub status: FileStatus,
+    /// SHA-256 of the file contents at planning time, hex encoded (empty
+    /// when the file could not be read).
+    pub sha256: String,
+    /// Path-level changes, for review.
+    pub changes: Vec<Change<Value>>,
+    /// Contents the file is to be rewritten with.
+    pub contents: Option<String>,
+}
+
+impl Plan {
+    /// Renders the plan as JSON:
+    ///
+    /// ```json
+    /// {
+    ///   "files": [
+    ///     {
+    ///       "path": "a.json",
+    ///       "status": "updated",
+    ///       "error": null,
+    ///       "sha256": "9f86d0...",
+    ///       "changes": [{"path": "version", "kind": "modified", "old": 1, "new": 2}],
+    ///       "contents": "{\"version\":2}"
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let files: Vec<Value> = self.files.iter().map(file_to_json).collect();
+        json!({ "files": files })
+    }
+
+    /// Parses a plan rendered by [Plan::to_json].
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(source).map_err(|err| err.to_string())?;
+        let files = value["files"]
+            .as_array()
+            .ok_or("plan must have a `files` array")?
+            .iter()
+            .map(file_from_json)
+            .collect::<Result<Vec<PlannedFile>, String>>()?;
+        Ok(Plan { files })
+    }
+}
+
+fn kind_name(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "added",
+        ChangeKind::Removed => "removed",
+        ChangeKind::Modified => "modified",
+    }
+}
+
+fn file_to_json(file: &PlannedFile) -> Value {
+    let (status, error) = match &file.status {
+        FileStatus::Updated => ("updated", None),
+        FileStatus::Unchanged => ("unchanged", None),
+        FileStatus::Failed(err) => ("failed", Some(err)),
+        FileStatus::Skipped(err) => ("skipped", Some(err)),
+    };
+    let changes: Vec<Value> = file.changes
+        .iter()
+        .map(|change| json!({
+            "path": change.path,
+            "kind": kind_name(change.kind),
+            "old": change.old,
+            "new": change.new,
+        }))
+        .collect();
+    json!({
+        "path": file.path.to_string_lossy(),
+        "status": status,
+        "error": error,
+        "sha256": file.sha256,
+        "changes": changes,
+        "contents": file.contents,
+    })
+}
+
+fn change_from_json(value: &Value) -> Result<Change<Value>, String> {
+    let path = value["path"].as_str().ok_or("change must have a string `path`")?;
+    let kind = match value["kind"].as_str() {
+        Some("added") => ChangeKind::Added,
+        Some("removed") => ChangeKind::Removed,
+        Some("modified") => ChangeKind::Modified,
+        _ => return Err(format!("unknown change kind for `{}`", path)),
+    };
+    Ok(Change {
+        path: path.to_string(),
+        kind,
+        old: (kind != ChangeKind::Added).then(|| value["old"].clone()),
+        new: (kind != ChangeKind::Removed).then(|| value["new"].clone()),
+    })
+}
+
+fn file_from_json(value: &Value) -> Result<PlannedFile, String> {
+    let path = value["path"].as_str().ok_or("file entry must have a string `path`")?;
+    let error = value["error"].as_str().unwrap_or_default().to_string();
+    let status = match value["status"].as_str() {
+        Some("updated") => FileStatus::Updated,
+        Some("unchanged") => FileStatus::Unchanged,
+        Some("failed") => FileStatus::Failed(error),
+        Some("skipped") => FileStatus::Skipped(error),
+        _ => return Err(format!("unknown status for `{}`", path)),
+    };
+    let contents = match &value["contents"] {
+        Value::String(contents) => Some(contents.clone()),
+        Value::Null => None,
+        _ => return Err(format!("`contents` of `{}` must be a string", path)),
+    };
+    if status == FileStatus::Updated && contents.is_none() {
+        return Err(format!("updated file `{}` has no `contents`", path));
+    }
+    let changes = match &value["changes"] {
+        Value::Array(changes) => changes.iter().map(change_from_json).collect::<Result<_, _>>()?,
+        Value::Null => vec![],
+        _ => return Err(format!("`changes` of `{}` must be an array", path)),
+    };
+    Ok(PlannedFile {
+        path: PathBuf::from(path),
+        status,
+        sha256: value["sha256"].as_str().unwrap_or_default().to_string(),
+        changes,
+        contents,
+    })
+}
+
+fn sha256(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn plan_file(file: &Path, updates: &[(&str, Value)], separator: Option<&str>, options: &BatchOptions) -> PlannedFile {
+    let mut planned = PlannedFile {
+        path: file.to_path_buf(),
+        status: FileStatus::Unchanged,
+        sha256: String::new(),
+        changes: vec![],
+        contents: None,
+    };
+    let source = match fs::read_to_string(long_path(file)) {
+        Ok(source) => source,
+        Err(err) => {
+            planned.status = FileStatus::Failed(err.to_string());
+            return planned;
+        }
+    };
+    planned.sha256 = sha256(source.as_bytes());
+    let mut doc = match Document::parse(&source) {
+        Ok(doc) => doc,
+        Err(err) => {
+            planned.status = FileStatus::Failed(err.to_string());
+            return planned;
+        }
+    };
+    let original = doc.value().clone();
+    for (path, new_value) in updates {
+        if let Err(err) = apply_update(&mut doc, path, separator, new_value) {
+            planned.status = FileStatus::Failed(err.render(doc.value(), separator));
+            return planned;
+        }
+    }
+    if !doc.is_dirty() {
+        return planned;
+    }
+    if let Err(err) = check_protected(&original, doc.value(), options) {
+        planned.status = FileStatus::Failed(err);
+        return planned;
+    }
+    planned.status = FileStatus::Updated;
+    planned.changes = json_diff(&original, doc.value());
+    planned.contents = Some(doc.to_string());
+    planned
+}
+
+fn apply_file(planned: &PlannedFile, options: &BatchOptions) -> FileStatus {
+    let contents = match (&planned.status, &planned.contents) {
+        (FileStatus::Updated, Some(contents)) => Some(contents),
+        (FileStatus::Unchanged, _) => None,
+        (status @ (FileStatus::Failed(_) | FileStatus::Skipped(_)), _) => return status.clone(),
+        (FileStatus::Updated, None) => return FileStatus::Failed("planned update has no contents".to_string()),
+    };
+    let current = match fs::read(long_path(&planned.path)) {
+        Ok(current) => current,
+        Err(err) => return FileStatus::Failed(err.to_string()),
+    };
+    if sha256(&current) != planned.sha256 {
+        return FileStatus::Failed("file changed since planning".to_string());
+    }
+    let contents = match contents {
+        Some(contents) => contents,
+        None => return FileStatus::Unchanged,
+    };
+    // the reviewed changes are what gets approved, so the contents written
+    // must be exactly the current file with those changes applied
+    let reviewed = match (serde_json::from_slice::<Value>(&current), serde_json::from_str::<Value>(contents)) {
+        (Ok(current), Ok(planned_value)) => json_diff(&current, &planned_value) == planned.changes,
+        _ => false,
+    };
+    if !reviewed {
+        return FileStatus::Failed("planned contents do not match the planned changes".to_string());
+    }
+    let written = write_file(
+        &planned.path,
+        contents,
+        options.atomic,
+        options.mode,
+        options.fsync == super::FsyncPolicy::PerFile,
+    );
+    match written {
+        Ok(_) => FileStatus::Updated,
+        Err(err) => FileStatus::Failed(err.to_string()),
+    }
+}
+
+/// Computes what [super::update_files_with] would do to each JSON file
+/// without writing anything, recording the SHA-256 of every file so that
+/// [apply] can refuse files edited in between.
+///
+/// Updates and protected paths (see [BatchOptions::protect]) are checked
+/// the same way as on [super::update_files_with].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::batch::{apply, plan, BatchOptions, FileStatus, Plan};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("config.json");
+///     std::fs::write(&file, r#"{"version": 1}"#).unwrap();
+///
+///     let options = BatchOptions::new();
+///     let planned = plan(&[&file], &[("version", Value::from(2))], None, &options);
+///     assert_eq!(planned.files[0].changes[0].path, "version");
+///     assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"{"version": 1}"#);
+///
+///     // the plan goes through review as JSON, then comes back
+///     let approved = Plan::parse(&planned.to_json().to_string()).unwrap();
+///     let results = apply(&approved, &options);
+///     assert_eq!(results[0].status, FileStatus::Updated);
+///     assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"{"version":2}"#);
+///
+///     // the file changed since planning
+///     assert!(matches!(apply(&approved, &options)[0].status, FileStatus::Failed(_)));
+/// }
+/// ```
+pub fn plan<P: AsRef<Path>>(files: &[P], updates: &[(&str, Value)], separator: Option<&str>, options: &BatchOptions) -> Plan {
+    Plan {
+        files: files
+            .iter()
+            .map(|file| plan_file(file.as_ref(), updates, separator, options))
+            .collect(),
+    }
+}
+
+/// Writes the files of a [Plan], refusing (as [FileStatus::Failed]) every
+/// file whose contents changed since [plan] was called, and every file
+/// whose planned contents differ from the file with the planned changes
+/// applied (e.g. a plan edited after review).
+///
+/// Files planned as unchanged are only checked, files that failed planning
+/// fail again with the same reason. Locking, atomic writes, concurrency and
+/// the other [BatchOptions] apply as on [super::update_files_with]; with
+/// locking enabled the check and the write happen under the same lock.
+pub fn apply(plan: &Plan, options: &BatchOptions) -> Vec<FileResult> {
+    let planned: HashMap<&Path, &PlannedFile> = plan.files
+        .iter()
+        .map(|file| (file.path.as_path(), file))
+        .collect();
+    let files: Vec<&Path> = plan.files.iter().map(|file| file.path.as_path()).collect();
+    process(&files, options, |file| apply_file(planned[file], options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_and_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_a = dir.path().join("a.json");
+        let file_b = dir.path().join("b.json");
+        fs::write(&file_a, r#"{"version": 2}"#).unwrap();
+        fs::write(&file_b, r#"{"license": "mit"}"#).unwrap();
+        let options = BatchOptions::new().protect("license");
+        let updates = [("version", Value::from(2)), ("license", Value::from("gpl"))];
+        let planned = plan(&[&file_a, &file_b], &updates, None, &options);
+        assert_eq!(planned.files[1].status, FileStatus::Failed("protected path `license` was modified".to_string()));
+        assert_eq!(Plan::parse(&planned.to_json().to_string()).unwrap(), planned);
+        assert_eq!(apply(&planned, &options)[1].status, planned.files[1].status);
+        assert_eq!(fs::read_to_string(&file_b).unwrap(), r#"{"license": "mit"}"#);
+
+        let planned = plan(&[&file_a], &updates[..1], None, &options);
+        assert_eq!(planned.files[0].status, FileStatus::Unchanged);
+        assert_eq!(apply(&planned, &options)[0].status, FileStatus::Unchanged);
+        fs::write(&file_a, r#"{"version": 3}"#).unwrap();
+        assert_eq!(
+            apply(&planned, &options)[0].status,
+            FileStatus::Failed("file changed since planning".to_string()),
+        );
+        assert!(Plan::parse(r#"{"files": [{"path": "a.json", "status": "updated"}]}"#).is_err());
+    }
+
+    #[test]
+    fn contents_must_match_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.json");
+        fs::write(&file, r#"{"version": 1, "owner": "me"}"#).unwrap();
+        let options = BatchOptions::new();
+        let planned = plan(&[&file], &[("version", Value::from(2))], None, &options);
+
+        let mut tampered = planned.clone();
+        tampered.files[0].contents = Some(r#"{"version": 2, "owner": "mallory"}"#.to_string());
+        assert_eq!(
+            apply(&tampered, &options)[0].status,
+            FileStatus::Failed("planned contents do not match the planned changes".to_string()),
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), r#"{"version": 1, "owner": "me"}"#);
+        assert_eq!(apply(&planned, &options)[0].status, FileStatus::Updated);
+    }
+}
@@ -0,0 +1,279 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use serde_json::{json, Value};
+use super::{FileResult, FileStatus};
+
+/// Number of files per [FileStatus] on a [RunReport].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Totals {
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl Totals {
+    /// Number of files processed.
+    pub fn files(&self) -> usize {
+        self.updated + self.unchanged + self.failed + self.skipped
+    }
+}
+
+/// Outcome of a whole batch run: the operations applied, the result of
+/// every file and how long the run took.
+///
+/// [RunReport::to_json] gives a machine-readable rendering meant to be kept
+/// as a CI artifact, [RunReport::summary] a short human one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunReport {
+    pub ops: Vec<String>,
+    pub files: Vec<FileResult>,
+    pub elapsed: Duration,
+}
+
+impl RunReport {
+    pub fn new(ops: Vec<String>, files: Vec<FileResult>, elapsed: Duration) -> Self {
+        RunReport { ops, files, elapsed }
+    }
+
+    pub fn totals(&self) -> Totals {
+        let mut totals = Totals::default();
+        for file in &self.files {
+            match file.status {
+                FileStatus::Updated => totals.updated += 1,
+                FileStatus::Unchanged => totals.unchanged += 1,
+                FileStatus::Failed(_) => totals.failed += 1,
+                FileStatus::Skipped(_) => totals.skipped += 1,
+            }
+        }
+        totals
+    }
+
+    /// Renders the report as JSON:
+    ///
+    /// ```json
+    /// {
+    ///   "ops": ["set"],
+    ///   "files": [
+    ///     {"path": "a.json", "status": "failed", "error": "...", "elapsed_ms": 0.42}
+    ///   ],
+    ///   "totals": {"files": 1, "updated": 0, "unchanged": 0, "failed": 1, "skipped": 0},
+    ///   "elapsed_ms": 1.5
+    /// }
+    /// ```
+    ///
+    /// `error` holds the reason of failed and skipped files and is `null`
+    /// otherwise.
+    pub fn to_json(&self) -> Value {
+        let totals = self.totals();
+        let files: Vec<Value> = self.files.iter().map(file_to_json).collect();
+        json!({
+            "ops": self.ops,
+            "files": files,
+            "totals": {
+                "files": totals.files(),
+                "updated": totals.updated,
+                "unchanged": totals.unchanged,
+                "failed": totals.failed,
+                "skipped": totals.skipped,
+            },
+            "elapsed_ms": millis(self.elapsed),
+        })
+    }
+
+    /// Parses a report rendered by [RunReport::to_json].
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let ops = value["ops"]
+            .as_array()
+            .ok_or("report must have an `ops` array")?
+            .iter()
+            .map(|op| op.as_str().map(|op| op.to_string()).ok_or("`ops` must hold strings"))
+            .collect::<Result<Vec<String>, &str>>()?;
+        let files = value["files"]
+            .as_array()
+            .ok_or("report must have a `files` array")?
+            .iter()
+            .map(file_from_json)
+            .collect::<Result<Vec<FileResult>, String>>()?;
+        Ok(RunReport {
+            ops,
+            files,
+            elapsed: duration(&value["elapsed_ms"]),
+        })
+    }
+
+    /// Parses either a report rendered by [RunReport::to_json] or a
+    /// checkpoint written while the run was going on (see
+    /// [super::BatchOptions::checkpoint]), one file entry per line. The
+    /// operations are unknown to a checkpoint, so they are left empty.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if let Ok(value) = serde_json::from_str::<Value>(source) {
+            if value.get("files").is_some() {
+                return Self::from_json(&value);
+            }
+        }
+        let mut files = vec![];
+        for (i, line) in source.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Value = serde_json::from_str(line)
+                .map_err(|err| format!("checkpoint line {}: {}", i + 1, err))?;
+            files.push(file_from_json(&value).map_err(|err| format!("checkpoint line {}: {}", i + 1, err))?);
+        }
+        let elapsed = files.iter().map(|file| file.elapsed).sum();
+        Ok(RunReport::new(vec![], files, elapsed))
+    }
+
+    /// Short human-readable summary: the totals followed by one line per
+    /// failed or skipped file.
+    pub fn summary(&self) -> String {
+        let totals = self.totals();
+        let mut lines = vec![format!(
+            "{} file(s) in {:.2}s: {} updated, {} unchanged, {} failed, {} skipped",
+            totals.files(),
+            self.elapsed.as_secs_f64(),
+            totals.updated,
+            totals.unchanged,
+            totals.failed,
+            totals.skipped,
+        )];
+        for file in &self.files {
+            match &file.status {
+                FileStatus::Failed(err) => {
+                    lines.push(format!("failed: {}: {}", file.path.display(), err));
+                }
+                FileStatus::Skipped(err) => {
+                    lines.push(format!("skipped: {}: {}", file.path.display(), err));
+                }
+                FileStatus::Updated | FileStatus::Unchanged => {}
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+pub(crate) fn file_to_json(file: &FileResult) -> Value {
+    let (status, error) = match &file.status {
+        FileStatus::Updated => ("updated", None),
+        FileStatus::Unchanged => ("unchanged", None),
+        FileStatus::Failed(err) => ("failed", Some(err)),
+        FileStatus::Skipped(err) => ("skipped", Some(err)),
+    };
+    json!({
+        "path": file.path.to_string_lossy(),
+        "status": status,
+        "error": error,
+        "elapsed_ms": millis(file.elapsed),
+    })
+}
+
+fn file_from_json(value: &Value) -> Result<FileResult, String> {
+    let path = value["path"].as_str().ok_or("file entry must have a string `path`")?;
+    let error = value["error"].as_str().unwrap_or_default().to_string();
+    let status = match value["status"].as_str() {
+        Some("updated") => FileStatus::Updated,
+        Some("unchanged") => FileStatus::Unchanged,
+        Some("failed") => FileStatus::Failed(error),
+        Some("skipped") => FileStatus::Skipped(error),
+        _ => return Err(format!("unknown status for `{}`", path)),
+    };
+    Ok(FileResult {
+        path: PathBuf::from(path),
+        status,
+        elapsed: duration(&value["elapsed_ms"]),
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn duration(millis: &Value) -> Duration {
+    Duration::from_secs_f64(millis.as_f64().unwrap_or(0.0).max(0.0) / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> RunReport {
+        let file = |path: &str, status: FileStatus| FileResult {
+            path: PathBuf::from(path),
+            status,
+            elapsed: Duration::from_millis(2),
+        };
+        RunReport::new(
+            vec!["set".to_string()],
+            vec![
+                file("a.json", FileStatus::Updated),
+                file("b.json", FileStatus::Unchanged),
+                file("c.json", FileStatus::Failed("missing key `foo`".to_string())),
+            ],
+            Duration::from_millis(1500),
+        )
+    }
+
+    #[test]
+    fn report_to_json() {
+        let value = report().to_json();
+        assert_eq!(value["ops"], json!(["set"]));
+        assert_eq!(value["files"][0], json!({
+            "path": "a.json",
+            "status": "updated",
+            "error": null,
+            "elapsed_ms": 2.0,
+        }));
+        assert_eq!(value["files"][2]["error"], "missing key `foo`");
+        assert_eq!(value["totals"], json!({
+            "files": 3,
+            "updated": 1,
+            "unchanged": 1,
+            "failed": 1,
+            "skipped": 0,
+        }));
+        assert_eq!(value["elapsed_ms"], 1500.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_round_trip() {
+        let report = report();
+        let json_str = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<RunReport>(&json_str).unwrap(), report);
+        let file: FileResult = serde_json::from_str(
+            r#"{"path": "d.json", "status": {"Skipped": "locked"}, "elapsed": {"secs": 0, "nanos": 0}}"#,
+        ).unwrap();
+        assert_eq!(file.status, FileStatus::Skipped("locked".to_string()));
+    }
+
+    #[test]
+    fn report_summary() {
+        assert_eq!(
+            report().summary(),
+            [
+                "3 file(s) in 1.50s: 1 updated, 1 unchanged, 1 failed, 0 skipped",
+                "failed: c.json: missing key `foo`",
+            ].join("\n"),
+        );
+    }
+
+    #[test]
+    fn parse_report_and_checkpoint() {
+        let report = report();
+        let parsed = RunReport::parse(&report.to_json().to_string()).unwrap();
+        assert_eq!(parsed, report);
+
+        let checkpoint: Vec<String> = report.files
+            .iter()
+            .map(|file| file_to_json(file).to_string())
+            .collect();
+        let parsed = RunReport::parse(&checkpoint.join("\n")).unwrap();
+        assert!(parsed.ops.is_empty());
+        assert_eq!(parsed.files, report.files);
+        assert_eq!(parsed.elapsed, Duration::from_millis(6));
+        assert!(RunReport::parse("{\"path\": \"a.json\"}").is_err());
+    }
+}
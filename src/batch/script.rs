@@ -0,0 +1,116 @@
+//! Scripted batch operation, enabled by the `rhai` feature.
+use std::cell::RefCell;
+use std::rc::Rc;
+use rhai::{Dynamic, Engine};
+use serde_json::Value;
+use super::Op;
+use crate::{json_read, nested};
+
+/// Built-in `script` operation running a [rhai](https://rhai.rs) script
+/// against the document: `{"source": "..."}` with an optional
+/// `"separator"`.
+///
+/// The script can call:
+/// - `read(path)`: value at `path`, or `()` when it does not exist;
+/// - `update(path, value)`: sets `value` at `path`, returning the old value
+///   or `()`;
+/// - `delete(path)`: removes the value at `path`, returning it or `()`.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::batch::{Op, ScriptOp};
+///
+/// fn main() {
+///     let mut data = json!({"replicas": 2, "name": "web"});
+///     let args = json!({"source": r#"
+///         if read("name") == "web" {
+///             update("replicas", read("replicas") * 2);
+///         }
+///     "#});
+///     ScriptOp.apply(&mut data, &args).unwrap();
+///     assert_eq!(data, json!({"replicas": 4, "name": "web"}));
+/// }
+/// ```
+pub struct ScriptOp;
+
+impl Op for ScriptOp {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn apply(&self, data: &mut Value, args: &Value) -> Result<(), String> {
+        let source = args["source"].as_str().ok_or("`script` expects a string `source`")?;
+        let separator = args["separator"].as_str().map(|s| s.to_string());
+        let doc = Rc::new(RefCell::new(std::mem::take(data)));
+        let mut engine = Engine::new();
+
+        let read_doc = doc.clone();
+        let read_separator = separator.clone();
+        engine.register_fn("read", move |path: &str| -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+            match json_read(path, &read_doc.borrow(), read_separator.as_deref()) {
+                Some(value) => rhai::serde::to_dynamic(value),
+                None => Ok(Dynamic::UNIT),
+            }
+        });
+        let update_doc = doc.clone();
+        let update_separator = separator.clone();
+        engine.register_fn("update", move |path: &str, value: Dynamic| -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+            let new_value: Value = rhai::serde::from_dynamic(&value)?;
+            let old_value = nested::update(
+                &mut *update_doc.borrow_mut(),
+                path,
+                update_separator.as_deref(),
+                new_value,
+            );
+            match old_value {
+                Some(value) => rhai::serde::to_dynamic(value),
+                None => Ok(Dynamic::UNIT),
+            }
+        });
+
+        let delete_doc = doc.clone();
+        let delete_separator = separator.clone();
+        engine.register_fn("delete", move |path: &str| -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+            match nested::delete(&mut *delete_doc.borrow_mut(), path, delete_separator.as_deref()) {
+                Some(value) => rhai::serde::to_dynamic(value),
+                None => Ok(Dynamic::UNIT),
+            }
+        });
+
+        let result = engine.run(source).map_err(|err| err.to_string());
+        *data = doc.take();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn script_reads_and_updates() {
+        let mut data = json!({"foo": {"bar": "bingo!", "n": 1, "gone": true}});
+        let args = json!({
+            "source": r#"
+                let old = update("foo@bar", read("foo@bar") + "?");
+                update("foo@old", old);
+                update("foo@n", read("foo@n") + 1);
+                delete("foo@gone");
+            "#,
+            "separator": "@",
+        });
+        ScriptOp.apply(&mut data, &args).unwrap();
+        assert_eq!(data, json!({"foo": {"bar": "bingo!?", "n": 2, "old": "bingo!"}}));
+    }
+
+    #[test]
+    fn script_errors_are_reported() {
+        let mut data = json!({"foo": 1});
+        let args = json!({"source": "update(\"foo\", 2); throw \"boom\";"});
+        let result = ScriptOp.apply(&mut data, &args);
+        assert!(result.unwrap_err().contains("boom"));
+        assert_eq!(data, json!({"foo": 2}));
+    }
+}
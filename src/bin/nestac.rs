@@ -0,0 +1,457 @@
+//! `nestac` command line: path access on JSON and TOML files.
+//!
+//! Enabled by the `cli` feature.
+//!
+//! ```text
+//! nestac get <file> <path> [-s <separator>]
+//! nestac set <file> <path> <value> [-s <separator>]
+//! nestac del <file> <path> [-s <separator>]
+//! nestac paths <file>
+//! nestac edit <file> <path> [-s <separator>]
+//! ```
+//!
+//! Files ending in `.toml` are handled as TOML, any other as JSON. `set`
+//! values are parsed as JSON (`8080`, `true`, `[1, 2]`, ...) and taken as a
+//! plain string otherwise; missing intermediate objects are created. `get`
+//! prints strings as is and any other value as JSON. `paths` lists every
+//! path in the syntax `get` takes. TOML files keep their comments and
+//! layout.
+//!
+//! `edit` opens the value at the path in `$VISUAL` (or `$EDITOR`, or `vi`)
+//! and puts it back once the editor exits: TOML tables are edited as TOML,
+//! any other value as JSON. The file is left untouched when the edited
+//! text does not parse. The temporary file is only readable by its owner
+//! and removed once the editor exits, whatever the outcome.
+//!
+//! Exits with `1` when the path does not exist and `2` on usage, I/O or
+//! parsing errors.
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, Table};
+use nestac::{json_delete, json_get_paths_with, json_read, json_update, json_upsert, Document, PathsOptions};
+use nestac::{toml_edit_delete, toml_edit_update, toml_get_paths_with, toml_read, toml_upsert, Path as NestacPath};
+
+const USAGE: &str = "\
+usage: nestac get <file> <path> [-s <separator>]
+       nestac set <file> <path> <value> [-s <separator>]
+       nestac del <file> <path> [-s <separator>]
+       nestac paths <file>
+       nestac edit <file> <path> [-s <separator>]";
+
+/// Reason why a command failed, mapped to the exit code.
+#[derive(Debug, PartialEq)]
+enum CliError {
+    /// The path does not exist on the file.
+    NotFound(String),
+    /// Anything else: bad arguments, I/O or parsing errors.
+    Failed(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::NotFound(message) | CliError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Parsed command line.
+#[derive(Debug, PartialEq)]
+struct Command<'a> {
+    name: &'a str,
+    file: &'a str,
+    args: Vec<&'a str>,
+    separator: Option<&'a str>,
+}
+
+fn parse_args(args: &[String]) -> Result<Command<'_>, CliError> {
+    let mut separator = None;
+    let mut positional = vec![];
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-s" | "--separator" => {
+                let value = iter.next().ok_or_else(|| usage("missing separator"))?;
+                separator = Some(value.as_str());
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let (name, rest) = positional.split_first().ok_or_else(|| usage("missing command"))?;
+    let expected = match *name {
+        "get" | "del" | "edit" => 2,
+        "set" => 3,
+        "paths" => 1,
+        other => return Err(usage(&format!("unknown command `{}`", other))),
+    };
+    if rest.len() != expected {
+        return Err(usage(&format!("`{}` expects {} argument(s)", name, expected)));
+    }
+    Ok(Command {
+        name,
+        file: rest[0],
+        args: rest[1..].to_vec(),
+        separator,
+    })
+}
+
+fn usage(reason: &str) -> CliError {
+    CliError::Failed(format!("{}\n{}", reason, USAGE))
+}
+
+/// Value given on the command line: JSON if it parses, a string otherwise.
+fn parse_value(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+/// Editor command of the user: `$VISUAL`, `$EDITOR` or `vi`.
+fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string())
+}
+
+/// Runs `command`, returning what should be printed. `editor` is the
+/// command `edit` opens the value with.
+fn run(command: &Command, editor: &str) -> Result<Option<String>, CliError> {
+    let source = fs::read_to_string(command.file)
+        .map_err(|err| CliError::Failed(format!("{}: {}", command.file, err)))?;
+    let is_toml = Path::new(command.file).extension().is_some_and(|ext| ext == "toml");
+    let output = if is_toml {
+        run_toml(command, &source, editor)?
+    } else {
+        run_json(command, &source, editor)?
+    };
+    match output {
+        Output::Print(text) => Ok(Some(text)),
+        Output::Write(content) => {
+            fs::write(command.file, content)
+                .map_err(|err| CliError::Failed(format!("{}: {}", command.file, err)))?;
+            Ok(None)
+        }
+        Output::Unchanged => Ok(None),
+    }
+}
+
+/// What a command produced.
+enum Output {
+    Print(String),
+    Write(String),
+    Unchanged,
+}
+
+/// Temporary file removed when dropped, whatever the exit path.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Creates a new `.<ext>` file in the temporary directory, failing
+    /// rather than following a file or symlink already there, only
+    /// readable and writable by its owner.
+    fn create(ext: &str) -> io::Result<(Self, File)> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        let mut attempt = 0;
+        loop {
+            let file = env::temp_dir().join(format!("nestac-edit-{}-{}-{}.{}", process::id(), nanos, attempt, ext));
+            let mut options = File::options();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            match options.open(&file) {
+                Ok(handle) => return Ok((TempFile(file), handle)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempt < 16 => attempt += 1,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Opens `text` in `editor` as a temporary `.<ext>` file and parses what
+/// was saved.
+fn edit_text<T, E, F>(text: &str, ext: &str, editor: &str, parse: F) -> Result<T, CliError>
+where
+    E: fmt::Display,
+    F: Fn(&str) -> Result<T, E>,
+{
+    let (file, mut handle) = TempFile::create(ext)
+        .map_err(|err| CliError::Failed(format!("cannot create a temporary file: {}", err)))?;
+    let failed = |err: &dyn fmt::Display| CliError::Failed(format!("{}: {}", file.0.display(), err));
+    handle.write_all(text.as_bytes()).map_err(|err| failed(&err))?;
+    drop(handle);
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or_else(|| CliError::Failed("the editor is empty".to_string()))?;
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(&file.0)
+        .status()
+        .map_err(|err| CliError::Failed(format!("cannot run `{}`: {}", editor, err)))?;
+    if !status.success() {
+        return Err(CliError::Failed(format!("`{}` exited with {}", editor, status)));
+    }
+    let edited = fs::read_to_string(&file.0).map_err(|err| failed(&err))?;
+    parse(&edited).map_err(|err| CliError::Failed(format!("the edited text does not parse: {}", err)))
+}
+
+fn run_json(command: &Command, source: &str, editor: &str) -> Result<Output, CliError> {
+    let mut doc = Document::parse(source)
+        .map_err(|err| CliError::Failed(format!("{}: {}", command.file, err)))?;
+    let separator = command.separator;
+    match command.name {
+        "get" => {
+            let path = command.args[0];
+            match json_read(path, doc.value(), separator) {
+                Some(Value::String(text)) => Ok(Output::Print(text.clone())),
+                Some(value) => Ok(Output::Print(value.to_string())),
+                None => Err(not_found(path)),
+            }
+        }
+        "set" => {
+            let (path, raw) = (command.args[0], command.args[1]);
+            json_upsert(doc.value_mut(), path, separator, parse_value(raw))
+                .map_err(|err| CliError::Failed(err.render(doc.value(), separator)))?;
+            Ok(Output::Write(doc.to_string()))
+        }
+        "del" => {
+            let path = command.args[0];
+            json_delete(doc.value_mut(), path, separator).ok_or_else(|| not_found(path))?;
+            Ok(Output::Write(doc.to_string()))
+        }
+        "edit" => {
+            let path = command.args[0];
+            let old_value = json_read(path, doc.value(), separator).ok_or_else(|| not_found(path))?;
+            let text = format!("{:#}\n", old_value);
+            let new_value: Value = edit_text(&text, "json", editor, |text| serde_json::from_str(text))?;
+            if &new_value == old_value {
+                return Ok(Output::Unchanged);
+            }
+            json_update(doc.value_mut(), path, separator, new_value);
+            Ok(Output::Write(doc.to_string()))
+        }
+        _ => Ok(Output::Print(json_get_paths_with(doc.value(), &paths_options(separator)).join("\n"))),
+    }
+}
+
+fn run_toml(command: &Command, source: &str, editor: &str) -> Result<Output, CliError> {
+    let parse_failed = |err: &dyn fmt::Display| CliError::Failed(format!("{}: {}", command.file, err));
+    let mut data: toml::Value = toml::from_str(source).map_err(|err| parse_failed(&err))?;
+    // writes go through toml_edit so comments and layout are kept
+    let mut doc: DocumentMut = source.parse().map_err(|err| parse_failed(&err))?;
+    let separator = command.separator;
+    match command.name {
+        "get" => {
+            let path = command.args[0];
+            match toml_read(path, &data, separator) {
+                Some(toml::Value::String(text)) => Ok(Output::Print(text.clone())),
+                Some(value) => Ok(Output::Print(value.to_string())),
+                None => Err(not_found(path)),
+            }
+        }
+        "set" => {
+            let (path, raw) = (command.args[0], command.args[1]);
+            let value = toml::Value::try_from(parse_value(raw))
+                .map_err(|err| CliError::Failed(format!("`{}`: {}", raw, err)))?;
+            let segments: Vec<String> = NestacPath::parse(path, separator)
+                .map_err(|err| CliError::Failed(err.to_string()))?
+                .segments()
+                .map(|segment| segment.to_string())
+                .collect();
+            // the tables `toml_upsert` creates, created on the document too
+            let missing: Vec<String> = (1..segments.len())
+                .map(|end| segments[..end].join("."))
+                .filter(|prefix| toml_read(prefix, &data, None).is_none())
+                .collect();
+            toml_upsert(&mut data, path, separator, value.clone())
+                .map_err(|err| CliError::Failed(err.to_string()))?;
+            for prefix in &missing {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                toml_edit_update(&mut doc, prefix, None, Item::Table(table));
+            }
+            toml_edit_update(&mut doc, &segments.join("."), None, Item::Value(edit_value(&value)));
+            Ok(Output::Write(doc.to_string()))
+        }
+        "del" => {
+            let path = command.args[0];
+            toml_edit_delete(&mut doc, path, separator).ok_or_else(|| not_found(path))?;
+            Ok(Output::Write(doc.to_string()))
+        }
+        "edit" => {
+            let path = command.args[0];
+            let old_value = toml_read(path, &data, separator).ok_or_else(|| not_found(path))?;
+            let (new_value, item) = match old_value {
+                toml::Value::Table(table) => {
+                    let text = toml::to_string(table).map_err(|err| CliError::Failed(err.to_string()))?;
+                    edit_text(&text, "toml", editor, |text| {
+                        let table: toml::Table = toml::from_str(text).map_err(|err| err.to_string())?;
+                        let edited: DocumentMut = text.parse().map_err(|err: toml_edit::TomlError| err.to_string())?;
+                        Ok::<_, String>((toml::Value::Table(table), Item::Table(edited.as_table().clone())))
+                    })?
+                }
+                value => {
+                    let text = serde_json::to_string_pretty(value)
+                        .map_err(|err| CliError::Failed(err.to_string()))? + "\n";
+                    let new_value = edit_text(&text, "json", editor, |text| {
+                        serde_json::from_str::<Value>(text)
+                            .map_err(|err| err.to_string())
+                            .and_then(|value| toml::Value::try_from(value).map_err(|err| err.to_string()))
+                    })?;
+                    let item = Item::Value(edit_value(&new_value));
+                    (new_value, item)
+                }
+            };
+            if &new_value == old_value {
+                return Ok(Output::Unchanged);
+            }
+            toml_edit_update(&mut doc, path, separator, item);
+            Ok(Output::Write(doc.to_string()))
+        }
+        _ => Ok(Output::Print(toml_get_paths_with(&data, &paths_options(separator)).join("\n"))),
+    }
+}
+
+/// `value` as a `toml_edit` value, tables written inline.
+fn edit_value(value: &toml::Value) -> toml_edit::Value {
+    match value {
+        toml::Value::String(text) => text.as_str().into(),
+        toml::Value::Integer(number) => (*number).into(),
+        toml::Value::Float(number) => (*number).into(),
+        toml::Value::Boolean(flag) => (*flag).into(),
+        toml::Value::Datetime(datetime) => (*datetime).into(),
+        toml::Value::Array(items) => items.iter().map(edit_value).collect::<toml_edit::Array>().into(),
+        toml::Value::Table(table) => table
+            .iter()
+            .map(|(key, child)| (key.clone(), edit_value(child)))
+            .collect::<toml_edit::InlineTable>()
+            .into(),
+    }
+}
+
+/// Paths listed by `paths`, written so they can be given back to `get`.
+fn paths_options(separator: Option<&str>) -> PathsOptions {
+    PathsOptions::new().symbol("").brackets(true).separator(separator.unwrap_or("."))
+}
+
+fn not_found(path: &str) -> CliError {
+    CliError::NotFound(format!("path `{}` not found", path))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse_args(&args).and_then(|command| run(&command, &editor())) {
+        Ok(Some(text)) => {
+            println!("{}", text);
+            ExitCode::SUCCESS
+        }
+        Ok(None) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("nestac: {}", err);
+            match err {
+                CliError::NotFound(_) => ExitCode::from(1),
+                CliError::Failed(_) => ExitCode::from(2),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nestac(args: &[&str]) -> Result<Option<String>, CliError> {
+        nestac_with(args, "false")
+    }
+
+    fn nestac_with(args: &[&str], editor: &str) -> Result<Option<String>, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args).and_then(|command| run(&command, editor))
+    }
+
+    #[test]
+    fn json_get_set_del() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.json");
+        let file = file.to_str().unwrap();
+        fs::write(file, "{\n  \"server\": {\n    \"host\": \"localhost\"\n  }\n}\n").unwrap();
+
+        assert_eq!(nestac(&["get", file, "server.host"]), Ok(Some("localhost".to_string())));
+        assert_eq!(nestac(&["set", file, "server/port", "8080", "-s", "/"]), Ok(None));
+        assert_eq!(nestac(&["get", file, "server"]), Ok(Some(r#"{"host":"localhost","port":8080}"#.to_string())));
+        assert_eq!(nestac(&["del", file, "server.host"]), Ok(None));
+        assert_eq!(
+            fs::read_to_string(file).unwrap(),
+            "{\n  \"server\": {\n    \"port\": 8080\n  }\n}\n",
+        );
+        assert!(matches!(nestac(&["get", file, "server.host"]), Err(CliError::NotFound(_))));
+        assert!(matches!(nestac(&["get", file]), Err(CliError::Failed(_))));
+    }
+
+    #[test]
+    fn toml_set_and_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Cargo.toml");
+        let file = file.to_str().unwrap();
+        fs::write(file, "# demo crate\n[package]\nname = \"demo\"  # keep short\n\n[dependencies]\nserde = \"1\"\n").unwrap();
+
+        assert_eq!(nestac(&["set", file, "package.version", "0.2.0"]), Ok(None));
+        assert_eq!(nestac(&["get", file, "package.version"]), Ok(Some("0.2.0".to_string())));
+        assert_eq!(nestac(&["set", file, "badges.ci.status", "ok"]), Ok(None));
+        assert_eq!(nestac(&["del", file, "dependencies.serde"]), Ok(None));
+        assert_eq!(
+            fs::read_to_string(file).unwrap(),
+            "# demo crate\n[package]\nname = \"demo\"  # keep short\nversion = \"0.2.0\"\n\n[dependencies]\n\n[badges.ci]\nstatus = \"ok\"\n",
+        );
+        let paths = nestac(&["paths", file]).unwrap().unwrap();
+        assert_eq!(paths, "badges\nbadges.ci\nbadges.ci.status\ndependencies\npackage\npackage.name\npackage.version");
+        for path in paths.lines() {
+            assert!(nestac(&["get", file, path]).is_ok());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_with_editor() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.json");
+        let file = file.to_str().unwrap();
+        fs::write(file, r#"{"server": {"host": "localhost"}, "debug": true}"#).unwrap();
+        let editor = dir.path().join("editor.sh");
+        let edited = dir.path().join("edited");
+        let script = format!(
+            "#!/bin/sh\nls -l \"$1\" | cut -c1-10 > {0}\necho \"$1\" >> {0}\ngrep -q localhost \"$1\" && printf '{{\"host\": \"example.org\"}}' > \"$1\"\n",
+            edited.display(),
+        );
+        fs::write(&editor, script).unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+        let editor = editor.to_str().unwrap();
+
+        assert_eq!(nestac_with(&["edit", file, "server"], editor), Ok(None));
+        assert_eq!(nestac(&["get", file, "server.host"]), Ok(Some("example.org".to_string())));
+        let seen = fs::read_to_string(&edited).unwrap();
+        let (mode, temp) = seen.trim_end().split_once('\n').unwrap();
+        assert_eq!(mode, "-rw-------");
+        assert!(!Path::new(temp).exists());
+
+        assert!(matches!(nestac_with(&["edit", file, "debug"], editor), Err(CliError::Failed(_))));
+        let seen = fs::read_to_string(&edited).unwrap();
+        assert!(!Path::new(seen.trim_end().split_once('\n').unwrap().1).exists());
+        assert!(matches!(nestac_with(&["edit", file, "missing"], editor), Err(CliError::NotFound(_))));
+        assert_eq!(nestac(&["get", file, "debug"]), Ok(Some("true".to_string())));
+    }
+}
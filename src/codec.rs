@@ -0,0 +1,223 @@
+//! Typed views of the values at some paths of a [crate::Document].
+//!
+//! A [Codec] converts between a [Value] and a Rust type. Registered on a
+//! document for a path pattern (see [crate::Document::register_codec]),
+//! it lets [crate::Document::read_decoded] and
+//! [crate::Document::update_encoded] work with that type at every
+//! matching path, keeping the conversion in one place.
+use std::any::{type_name, Any, TypeId};
+use std::fmt;
+use std::sync::Arc;
+use serde_json::Value;
+use crate::nested::path_matches;
+use crate::NestacError;
+
+/// Conversion between a [Value] and a Rust type.
+///
+/// # Examples:
+/// ```rust
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+/// use serde_json::{json, Value};
+/// use nestac::codec::Codec;
+/// use nestac::Document;
+///
+/// /// Unix timestamps, in seconds.
+/// struct UnixTime;
+///
+/// impl Codec for UnixTime {
+///     type Value = SystemTime;
+///
+///     fn decode(&self, value: &Value) -> Result<SystemTime, String> {
+///         let secs = value.as_u64().ok_or("expected a timestamp")?;
+///         Ok(UNIX_EPOCH + Duration::from_secs(secs))
+///     }
+///
+///     fn encode(&self, time: &SystemTime) -> Result<Value, String> {
+///         let secs = time.duration_since(UNIX_EPOCH).map_err(|err| err.to_string())?;
+///         Ok(json!(secs.as_secs()))
+///     }
+/// }
+///
+/// fn main() {
+///     let mut doc = Document::parse(r#"{"users": [{"created_at": 60}]}"#).unwrap();
+///     doc.register_codec("**.created_at", UnixTime);
+///
+///     let created_at: SystemTime = doc.read_decoded("users.[0].created_at", None).unwrap();
+///     assert_eq!(created_at, UNIX_EPOCH + Duration::from_secs(60));
+///
+///     doc.update_encoded("users.[0].created_at", None, created_at + Duration::from_secs(60)).unwrap();
+///     assert_eq!(doc.read("users.[0].created_at", None), Some(&json!(120)));
+/// }
+/// ```
+pub trait Codec: Send + Sync + 'static {
+    type Value: 'static;
+
+    /// Converts the [Value] stored on the document.
+    fn decode(&self, value: &Value) -> Result<Self::Value, String>;
+
+    /// Converts a value back into what is stored on the document.
+    fn encode(&self, value: &Self::Value) -> Result<Value, String>;
+}
+
+/// [Codec] with its type erased, so codecs of different types can be
+/// registered together.
+trait AnyCodec: Send + Sync {
+    fn decode_any(&self, value: &Value) -> Result<Box<dyn Any>, String>;
+
+    /// [None] when `value` is not of the codec's type.
+    fn encode_any(&self, value: &dyn Any) -> Option<Result<Value, String>>;
+
+    /// [TypeId] of [Codec::Value].
+    fn value_type(&self) -> TypeId;
+}
+
+impl<C: Codec> AnyCodec for C {
+    fn decode_any(&self, value: &Value) -> Result<Box<dyn Any>, String> {
+        self.decode(value).map(|decoded| Box::new(decoded) as Box<dyn Any>)
+    }
+
+    fn encode_any(&self, value: &dyn Any) -> Option<Result<Value, String>> {
+        value.downcast_ref::<C::Value>().map(|value| self.encode(value))
+    }
+
+    fn value_type(&self) -> TypeId {
+        TypeId::of::<C::Value>()
+    }
+}
+
+/// Codecs registered on a [crate::Document], each for a path pattern.
+#[derive(Clone, Default)]
+pub struct Codecs {
+    entries: Vec<(String, Arc<dyn AnyCodec>)>,
+}
+
+impl fmt::Debug for Codecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.entries.iter().map(|(pattern, _)| pattern)).finish()
+    }
+}
+
+impl Codecs {
+    /// Registers `codec` for the paths matched by `pattern`. Codecs
+    /// registered later take precedence.
+    pub fn register<C: Codec>(&mut self, pattern: &str, codec: C) {
+        self.entries.push((pattern.to_string(), Arc::new(codec)));
+    }
+
+    /// Patterns with a codec, in registration order.
+    pub fn patterns(&self) -> Vec<&str> {
+        self.entries.iter().map(|(pattern, _)| pattern.as_str()).collect()
+    }
+
+    /// Latest codec registered for `path` decoding into `T`.
+    fn find<T: 'static>(&self, path: &str, separator: &str) -> Option<&dyn AnyCodec> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|(pattern, _)| path_matches(pattern, path, separator))
+            .map(|(_, codec)| codec.as_ref())
+            .find(|codec| codec.value_type() == TypeId::of::<T>())
+    }
+
+    /// Decodes the [Value] at `path` into `T`.
+    pub(crate) fn decode<T: 'static>(&self, path: &str, separator: &str, value: &Value) -> Result<T, NestacError> {
+        let codec = self.find::<T>(path, separator).ok_or_else(|| missing(path, type_name::<T>()))?;
+        let decoded = codec.decode_any(value).map_err(|message| NestacError::Deserialize {
+            path: path.to_string(),
+            message,
+        })?;
+        Ok(*decoded.downcast::<T>().expect("the codec decodes into `T`"))
+    }
+
+    /// Encodes `value` for `path`.
+    pub(crate) fn encode<T: 'static>(&self, path: &str, separator: &str, value: &T) -> Result<Value, NestacError> {
+        let codec = self.find::<T>(path, separator).ok_or_else(|| missing(path, type_name::<T>()))?;
+        match codec.encode_any(value) {
+            Some(Ok(encoded)) => Ok(encoded),
+            Some(Err(message)) => Err(NestacError::Serialize { path: path.to_string(), message }),
+            None => Err(missing(path, type_name::<T>())),
+        }
+    }
+}
+
+fn missing(path: &str, type_name: &str) -> NestacError {
+    NestacError::Deserialize {
+        path: path.to_string(),
+        message: format!("no codec registered for `{}`", type_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    /// Strings holding a comma separated list.
+    struct Csv;
+
+    impl Codec for Csv {
+        type Value = Vec<String>;
+
+        fn decode(&self, value: &Value) -> Result<Vec<String>, String> {
+            let text = value.as_str().ok_or("expected a string")?;
+            Ok(text.split(',').map(str::to_string).collect())
+        }
+
+        fn encode(&self, items: &Vec<String>) -> Result<Value, String> {
+            Ok(Value::from(items.join(",")))
+        }
+    }
+
+    /// Numbers as text.
+    struct Text;
+
+    impl Codec for Text {
+        type Value = String;
+
+        fn decode(&self, value: &Value) -> Result<String, String> {
+            Ok(value.to_string())
+        }
+
+        fn encode(&self, text: &String) -> Result<Value, String> {
+            serde_json::from_str(text).map_err(|err| err.to_string())
+        }
+    }
+
+    #[test]
+    fn codecs_by_pattern_and_type() {
+        let mut doc = Document::parse(r#"{"hosts": "a,b", "apps": [{"port": 80, "tags": 1}]}"#).unwrap();
+        doc.register_codec("hosts", Csv);
+        doc.register_codec("apps.[*].*", Csv);
+        doc.register_codec("apps.[*].port", Text);
+        assert_eq!(doc.codecs().patterns(), vec!["hosts", "apps.[*].*", "apps.[*].port"]);
+
+        let hosts: Vec<String> = doc.read_decoded("hosts", None).unwrap();
+        assert_eq!(hosts, vec!["a", "b"]);
+        let port: String = doc.read_decoded("apps.[0].port", None).unwrap();
+        assert_eq!(port, "80");
+        assert!(matches!(
+            doc.read_decoded::<Vec<String>>("apps.[0].tags", None),
+            Err(NestacError::Deserialize { message, .. }) if message == "expected a string",
+        ));
+        assert!(matches!(doc.read_decoded::<String>("hosts", None), Err(NestacError::Deserialize { .. })));
+        assert!(matches!(doc.read_decoded::<String>("apps.[0].host", None), Err(NestacError::MissingKey { .. })));
+
+        doc.update_encoded("apps.[0].port", None, "8080".to_string()).unwrap();
+        assert_eq!(doc.read("apps.[0].port", None), Some(&Value::from(8080)));
+        assert!(matches!(
+            doc.update_encoded("apps.[0].port", None, "80 80".to_string()),
+            Err(NestacError::Serialize { .. }),
+        ));
+    }
+
+    #[test]
+    fn patterns_match_paths() {
+        assert!(path_matches("**.created_at", "created_at", "."));
+        assert!(path_matches("**.created_at", "users.[0].created_at", "."));
+        assert!(path_matches("users.[*].name", "users.[3].name", "."));
+        assert!(!path_matches("users.[*].name", "users.admin.name", "."));
+        assert!(path_matches("users/*/name", "users/admin/name", "/"));
+        assert!(!path_matches("users.*", "users.admin.name", "."));
+        assert!(path_matches("users.[*id=7]", "users.[id=7]", "."));
+    }
+}
@@ -0,0 +1,602 @@
+use std::fmt;
+use std::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use crate::codec::{Codec, Codecs};
+use crate::{json_read, json_try_read, json_upsert, nested, NestacError};
+
+/// Line ending style of a [Document].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+const BOM: &str = "\u{feff}";
+
+/// Mode of a [Document] allowing every operation (the default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadWrite;
+
+/// Mode of a [Document] only exposing read operations, see
+/// [Document::parse_untrusted].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnly;
+
+/// Traversal limits enforced by read-only documents.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::document::Limits;
+///
+/// fn main() {
+///     let limits = Limits::new().max_depth(8);
+///     assert_eq!(limits.get_max_depth(), 8);
+///     assert_eq!(Limits::default().get_max_path_tokens(), 32);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    max_len: usize,
+    max_depth: usize,
+    max_path_tokens: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_len: 1024 * 1024,
+            max_depth: 64,
+            max_path_tokens: 32,
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum length of the source, in bytes (default: 1 MiB).
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Maximum nesting of objects and arrays (default: `64`).
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Maximum number of tokens of a read path (default: `32`).
+    pub fn max_path_tokens(mut self, max_path_tokens: usize) -> Self {
+        self.max_path_tokens = max_path_tokens;
+        self
+    }
+
+    pub fn get_max_len(&self) -> usize {
+        self.max_len
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn get_max_path_tokens(&self) -> usize {
+        self.max_path_tokens
+    }
+}
+
+/// Reason why an untrusted document was rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum LimitError {
+    /// The source is not valid JSON.
+    #[error(transparent)]
+    Parse(#[from] serde_json::Error),
+    /// The source is longer than [Limits::max_len].
+    #[error("document is {len} bytes long, over the limit of {max}")]
+    TooLarge {
+        len: usize,
+        max: usize,
+    },
+    /// The document nests deeper than [Limits::max_depth].
+    #[error("document nests deeper than {max} levels")]
+    TooDeep {
+        max: usize,
+    },
+}
+
+/// A parsed JSON document that remembers its source text.
+///
+/// Serializing a document that was never modified (see
+/// [Document::is_dirty]) gives back the exact source bytes. Once modified,
+/// it is serialized again reusing the indentation, line ending, UTF-8 BOM
+/// and trailing newline detected on the source, so only whitespace inside
+/// lines (and key order, unless the `preserve_order` feature is enabled)
+/// may differ. [Document::set_bom] and [Document::set_line_ending] normalize
+/// the encoding on the next save.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::Document;
+///
+/// fn main() {
+///     let json_str = "{\n  \"foo\": {\n    \"bar\": \"bingo!\"\n  }\n}\n";
+///     let mut doc = Document::parse(json_str).unwrap();
+///     assert_eq!(doc.is_dirty(), false);
+///     assert_eq!(doc.to_string(), json_str);
+///
+///     doc.update("foo.bar", None, Value::String("bingo!".into()));
+///     assert_eq!(doc.is_dirty(), false);
+///
+///     doc.update("foo.bar", None, Value::String("updated!".into()));
+///     assert_eq!(doc.is_dirty(), true);
+///     assert_eq!(
+///         doc.to_string(),
+///         "{\n  \"foo\": {\n    \"bar\": \"updated!\"\n  }\n}\n",
+///     );
+/// }
+/// ```
+///
+/// Documents parsed from untrusted input with [Document::parse_untrusted]
+/// are [ReadOnly]: mutating methods do not exist on them, and reads are
+/// bound by [Limits].
+///
+/// ```rust,compile_fail
+/// use serde_json::Value;
+/// use nestac::document::{Document, Limits};
+///
+/// fn main() {
+///     let mut doc = Document::parse_untrusted(r#"{"foo": 1}"#, Limits::new()).unwrap();
+///     doc.update("foo", None, Value::from(2));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Document<M = ReadWrite> {
+    source: String,
+    value: Value,
+    dirty: bool,
+    bom: bool,
+    line_ending: LineEnding,
+    limits: Option<Limits>,
+    codecs: Codecs,
+    mode: PhantomData<M>,
+}
+
+impl Document<ReadWrite> {
+    /// Parses a JSON document keeping its source text. A leading UTF-8 BOM
+    /// is accepted and remembered, as is the line ending style.
+    pub fn parse(source: &str) -> Result<Self, serde_json::Error> {
+        Document::from_source(source.to_string()).map_err(|(_, err)| err)
+    }
+
+    /// Same as [Document::parse] but takes ownership of the source, giving
+    /// it back on failure (see [crate::DocumentPool]).
+    pub(crate) fn from_source(source: String) -> Result<Self, (String, serde_json::Error)> {
+        let content = source.strip_prefix(BOM);
+        let value = match serde_json::from_str(content.unwrap_or(&source)) {
+            Ok(value) => value,
+            Err(err) => return Err((source, err)),
+        };
+        let bom = content.is_some();
+        let line_ending = if source.contains("\r\n") {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        Ok(Document {
+            source,
+            value,
+            dirty: false,
+            bom,
+            line_ending,
+            limits: None,
+            codecs: Codecs::default(),
+            mode: PhantomData,
+        })
+    }
+
+    /// Mutable access to the parsed [Value]. The document is considered
+    /// modified from now on.
+    pub fn value_mut(&mut self) -> &mut Value {
+        self.dirty = true;
+        &mut self.value
+    }
+
+    /// Adds or removes the UTF-8 BOM on the next save.
+    pub fn set_bom(&mut self, bom: bool) {
+        if self.bom != bom {
+            self.bom = bom;
+            self.dirty = true;
+        }
+    }
+
+    /// Changes the line ending style used on the next save.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.dirty = true;
+        }
+    }
+
+    /// Whether the document was modified since it was parsed.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// See [crate::nested::update]. Writing a value equal to the current
+    /// one, or to a path that does not exist, does not mark the document as
+    /// modified.
+    pub fn update(&mut self, path: &str, separator: Option<&str>, new_value: Value) -> Option<Value> {
+        if json_read(path, &self.value, separator) == Some(&new_value) {
+            return Some(new_value);
+        }
+        match nested::replace_at(&mut self.value, path, separator, new_value) {
+            Ok(old_value) => {
+                self.dirty = true;
+                old_value
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Encodes `value` with the [Codec] registered for `path` and type `T`
+    /// (see [Document::register_codec]), then updates the path like
+    /// [Document::update] does, returning the old [Value].
+    ///
+    /// Fails with [NestacError::Deserialize] when no such codec is
+    /// registered and with [NestacError::Serialize] when encoding fails.
+    pub fn update_encoded<T: 'static>(&mut self, path: &str, separator: Option<&str>, value: T) -> Result<Option<Value>, NestacError> {
+        let encoded = self.codecs.encode(path, separator.unwrap_or("."), &value)?;
+        Ok(self.update(path, separator, encoded))
+    }
+
+    /// Serializes `value` and writes it at `path` like [crate::json_upsert],
+    /// creating the missing objects along the way, and returns the old
+    /// [Value]. As on [Document::update], writing a value equal to the
+    /// current one does not mark the document as modified.
+    pub fn upsert_serializable<T: Serialize>(&mut self, path: &str, separator: Option<&str>, value: &T) -> Result<Option<Value>, NestacError> {
+        let new_value = serde_json::to_value(value).map_err(|err| NestacError::Serialize {
+            path: path.to_string(),
+            message: err.to_string(),
+        })?;
+        if json_read(path, &self.value, separator) == Some(&new_value) {
+            return Ok(Some(new_value));
+        }
+        let old_value = json_upsert(&mut self.value, path, separator, new_value)?;
+        self.dirty = true;
+        Ok(old_value)
+    }
+
+    /// See [crate::nested::delete]. The document is only marked as modified
+    /// when something was removed.
+    pub fn delete(&mut self, path: &str, separator: Option<&str>) -> Option<Value> {
+        let removed = nested::delete(&mut self.value, path, separator);
+        if removed.is_some() {
+            self.dirty = true;
+        }
+        removed
+    }
+}
+
+impl Document<ReadOnly> {
+    /// Parses an untrusted JSON document into a read-only one, rejecting
+    /// sources longer or nesting deeper than `limits` allow. Reads on the
+    /// returned document give [None] for paths with more tokens than
+    /// allowed.
+    ///
+    /// # Examples:
+    /// ```rust
+    /// use nestac::document::{Document, LimitError, Limits};
+    ///
+    /// fn main() {
+    ///     let limits = Limits::new().max_depth(2).max_path_tokens(2);
+    ///     let doc = Document::parse_untrusted(r#"{"a": {"b": 1}}"#, limits.clone()).unwrap();
+    ///     assert_eq!(doc.read("a.b", None).unwrap(), 1);
+    ///     assert!(doc.read("a.b.c", None).is_none());
+    ///     let deep = Document::parse_untrusted(r#"{"a": {"b": [1]}}"#, limits);
+    ///     assert!(matches!(deep, Err(LimitError::TooDeep { max: 2 })));
+    /// }
+    /// ```
+    pub fn parse_untrusted(source: &str, limits: Limits) -> Result<Self, LimitError> {
+        if source.len() > limits.max_len {
+            return Err(LimitError::TooLarge {
+                len: source.len(),
+                max: limits.max_len,
+            });
+        }
+        let doc = Document::parse(source)?;
+        if depth(&doc.value) > limits.max_depth {
+            return Err(LimitError::TooDeep { max: limits.max_depth });
+        }
+        Ok(Document {
+            source: doc.source,
+            value: doc.value,
+            dirty: false,
+            bom: doc.bom,
+            line_ending: doc.line_ending,
+            limits: Some(limits),
+            codecs: doc.codecs,
+            mode: PhantomData,
+        })
+    }
+
+    /// Limits enforced by this document.
+    pub fn limits(&self) -> &Limits {
+        self.limits.as_ref().expect("read-only documents have limits")
+    }
+}
+
+impl<M> Document<M> {
+    /// The parsed [Value].
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consumes the document returning the parsed [Value].
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Consumes the document returning its source text.
+    pub(crate) fn into_source(self) -> String {
+        self.source
+    }
+
+    /// Whether the source starts with a UTF-8 BOM.
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+
+    /// Line ending style detected on the source.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// See [crate::json_read]. On [ReadOnly] documents, paths with more
+    /// tokens than [Limits::max_path_tokens] allows give [None].
+    pub fn read(&self, path: &str, separator: Option<&str>) -> Option<&Value> {
+        if let Some(limits) = &self.limits {
+            if nested::tokenize(path, separator.unwrap_or(".")).len() > limits.max_path_tokens {
+                return None;
+            }
+        }
+        json_read(path, &self.value, separator)
+    }
+
+    /// Registers `codec` for every path matched by `pattern` (e.g.
+    /// `**.created_at`), where wildcards work as on [crate::json_read_all]
+    /// but are matched against the path itself. For a given type, codecs
+    /// registered later take precedence. See [crate::codec].
+    pub fn register_codec<C: Codec>(&mut self, pattern: &str, codec: C) {
+        self.codecs.register(pattern, codec);
+    }
+
+    /// Codecs registered on the document.
+    pub fn codecs(&self) -> &Codecs {
+        &self.codecs
+    }
+
+    /// See [crate::json_read_as]. On [ReadOnly] documents, paths with more
+    /// tokens than [Limits::max_path_tokens] allows fail with
+    /// [NestacError::InvalidPath].
+    pub fn read_as<T: DeserializeOwned>(&self, path: &str, separator: Option<&str>) -> Result<T, NestacError> {
+        self.check_path_tokens(path, separator)?;
+        let value = json_try_read(path, &self.value, separator)?;
+        nested::deserialize_at(path, separator, value)
+    }
+
+    /// Reads the [Value] at `path` and decodes it into `T` with the
+    /// [Codec] registered for that path and type.
+    ///
+    /// Fails like [crate::json_try_read] when the path does not exist, and
+    /// with [NestacError::Deserialize] when no such codec is registered or
+    /// decoding fails.
+    pub fn read_decoded<T: 'static>(&self, path: &str, separator: Option<&str>) -> Result<T, NestacError> {
+        self.check_path_tokens(path, separator)?;
+        let value = json_try_read(path, &self.value, separator)?;
+        self.codecs.decode(path, separator.unwrap_or("."), value)
+    }
+
+    /// Refuses paths longer than [Limits::max_path_tokens] allows.
+    fn check_path_tokens(&self, path: &str, separator: Option<&str>) -> Result<(), NestacError> {
+        if let Some(limits) = &self.limits {
+            if nested::tokenize(path, separator.unwrap_or(".")).len() > limits.max_path_tokens {
+                return Err(NestacError::InvalidPath {
+                    path: path.to_string(),
+                    reason: format!("more than {} tokens", limits.max_path_tokens),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Indentation used by the first indented line of the source, if any.
+    fn indent(&self) -> Option<&str> {
+        self.source
+            .lines()
+            .skip(1)
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|indent| !indent.is_empty())
+    }
+}
+
+/// Nesting of objects and arrays of `value`; scalars have depth `0`.
+fn depth(value: &Value) -> usize {
+    let mut max = 0;
+    let mut stack = vec![(value, 0)];
+    while let Some((node, level)) = stack.pop() {
+        let children: Box<dyn Iterator<Item = &Value>> = match node {
+            Value::Object(map) => Box::new(map.values()),
+            Value::Array(items) => Box::new(items.iter()),
+            _ => continue,
+        };
+        max = max.max(level + 1);
+        stack.extend(children.map(|child| (child, level + 1)));
+    }
+    max
+}
+
+impl<M> fmt::Display for Document<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.dirty {
+            return f.write_str(&self.source);
+        }
+        let newline = match self.line_ending {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        };
+        if self.bom {
+            f.write_str(BOM)?;
+        }
+        let mut buf: Vec<u8> = vec![];
+        match self.indent() {
+            Some(indent) => {
+                let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+                let mut ser = Serializer::with_formatter(&mut buf, formatter);
+                self.value.serialize(&mut ser).map_err(|_| fmt::Error)?;
+            }
+            None => {
+                serde_json::to_writer(&mut buf, &self.value).map_err(|_| fmt::Error)?;
+            }
+        }
+        let content = String::from_utf8(buf).map_err(|_| fmt::Error)?;
+        f.write_str(&content.replace('\n', newline))?;
+        if self.source.ends_with('\n') {
+            f.write_str(newline)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_is_byte_identical() {
+        let fixtures = [
+            r#"{"foo": {"bar": "bingo!"}}"#,
+            "{\"b\":1,\"a\":[1,2,3]}",
+            "{\n\t\"b\": 1.50,\n\t\"a\": \"\\u00e9\"\n}\n",
+            "  [ 1 , {\"x\" : null} ]  \n\n",
+        ];
+        for json_str in fixtures {
+            let doc = Document::parse(json_str).unwrap();
+            assert!(!doc.is_dirty());
+            assert_eq!(doc.to_string(), json_str);
+        }
+    }
+
+    #[test]
+    fn modified_document_keeps_layout() {
+        let json_str = "{\n    \"foo\": [\n        1,\n        2\n    ]\n}";
+        let mut doc = Document::parse(json_str).unwrap();
+        let old_val = doc.update("foo.[1]", None, Value::from(3));
+        assert_eq!(old_val.unwrap(), 2);
+        assert!(doc.is_dirty());
+        assert_eq!(
+            doc.to_string(),
+            "{\n    \"foo\": [\n        1,\n        3\n    ]\n}",
+        );
+
+        let mut doc = Document::parse(r#"{"tags": ["a"]}"#).unwrap();
+        assert_eq!(doc.update("tags.[+]", None, Value::from("b")), None);
+        assert!(doc.is_dirty());
+        assert_eq!(doc.to_string(), r#"{"tags":["a","b"]}"#);
+        let mut doc = Document::parse(r#"{"tags": ["a"]}"#).unwrap();
+        assert_eq!(doc.update("tags.[5]", None, Value::from("b")), None);
+        assert!(!doc.is_dirty());
+    }
+
+    #[test]
+    fn value_mut_marks_dirty() {
+        let json_str = r#"{"foo": []}"#;
+        let mut doc = Document::parse(json_str).unwrap();
+        doc.value_mut()["foo"].as_array_mut().unwrap().push(Value::from(1));
+        assert!(doc.is_dirty());
+        assert_eq!(doc.to_string(), r#"{"foo":[1]}"#);
+    }
+
+    #[test]
+    fn bom_and_crlf_are_preserved() {
+        let json_str = "\u{feff}{\r\n  \"foo\": 1\r\n}\r\n";
+        let mut doc = Document::parse(json_str).unwrap();
+        assert!(doc.has_bom());
+        assert_eq!(doc.line_ending(), LineEnding::CrLf);
+        assert_eq!(doc.to_string(), json_str);
+        doc.update("foo", None, Value::from(2));
+        assert_eq!(doc.to_string(), "\u{feff}{\r\n  \"foo\": 2\r\n}\r\n");
+    }
+
+    #[test]
+    fn bom_and_crlf_can_be_normalized() {
+        let json_str = "\u{feff}{\r\n  \"foo\": 1\r\n}\r\n";
+        let mut doc = Document::parse(json_str).unwrap();
+        doc.set_bom(true);
+        doc.set_line_ending(LineEnding::CrLf);
+        assert!(!doc.is_dirty());
+        doc.set_bom(false);
+        doc.set_line_ending(LineEnding::Lf);
+        assert!(doc.is_dirty());
+        assert_eq!(doc.to_string(), "{\n  \"foo\": 1\n}\n");
+    }
+
+    #[test]
+    fn untrusted_documents_are_bound_by_limits() {
+        let json_str = r#"{"a": [{"b": 1}]}"#;
+        let too_large = Document::parse_untrusted(json_str, Limits::new().max_len(4));
+        assert!(matches!(too_large, Err(LimitError::TooLarge { len: 17, max: 4 })));
+        let too_deep = Document::parse_untrusted(json_str, Limits::new().max_depth(2));
+        assert!(matches!(too_deep, Err(LimitError::TooDeep { max: 2 })));
+        let doc = Document::parse_untrusted(json_str, Limits::new().max_depth(3)).unwrap();
+        assert_eq!(doc.read("a.[0].b", None).unwrap(), 1);
+        assert_eq!(doc.to_string(), json_str);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_views_read_and_apply() {
+        #[derive(crate::Nestac)]
+        #[nestac(separator = "/")]
+        struct Http {
+            #[nestac(path = "server/http/port")]
+            port: u16,
+            #[nestac(path = "server/http/tls")]
+            tls: Option<bool>,
+            #[nestac(path = "server/hosts")]
+            hosts: Vec<String>,
+            name: String,
+        }
+
+        let mut doc = Document::parse(r#"{"name": "api", "server": {"http": {"port": 80}, "hosts": ["a"]}}"#).unwrap();
+        let mut http = Http::from_document(&doc).unwrap();
+        assert_eq!((http.port, http.tls, http.name.as_str()), (80, None, "api"));
+        http.apply_to(&mut doc).unwrap();
+        assert!(!doc.is_dirty());
+
+        http.tls = Some(true);
+        http.hosts.push("b".to_string());
+        http.apply_to(&mut doc).unwrap();
+        assert_eq!(doc.read("server.http.tls", None), Some(&Value::from(true)));
+        assert_eq!(doc.read("server.hosts.[1]", None), Some(&Value::from("b")));
+
+        let mut view = Http::view(&mut doc).unwrap();
+        view.set_port(8080);
+        view.set_tls(None);
+        assert!(view.is_dirty());
+        assert_eq!(view.flush(), Ok(2));
+        assert_eq!(view.flush(), Ok(0));
+        assert_eq!(view.into_inner().port, 8080);
+        assert_eq!(doc.value(), &serde_json::json!({"name": "api", "server": {"http": {"port": 8080}, "hosts": ["a", "b"]}}));
+
+        doc.update("server.http.port", None, Value::from("80"));
+        assert!(matches!(Http::from_document(&doc), Err(NestacError::Deserialize { path, .. }) if path == "server/http/port"));
+    }
+}
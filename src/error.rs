@@ -0,0 +1,329 @@
+use serde_json::Value;
+use crate::json_read;
+
+/// Reason why a path could not be resolved against a document.
+///
+/// Every variant keeps the full `path`, the offending `segment`, its
+/// position (`index`) among the path tokens and its byte `offset` inside
+/// `path` so callers can point at the exact token that failed.
+///
+/// With the `miette` feature enabled it also implements
+/// `miette::Diagnostic`, labeling the offending token on the path string.
+/// With the `serde` feature enabled it can be serialized and deserialized.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NestacError {
+    /// The object reached by the path has no such key.
+    #[error("missing key `{segment}`")]
+    MissingKey {
+        path: String,
+        segment: String,
+        index: usize,
+        offset: usize,
+    },
+    /// The segment expected a different kind of node (e.g. indexing a
+    /// string or looking up a key on an array).
+    #[error("`{segment}` expects {expected} but found {found}")]
+    TypeMismatch {
+        path: String,
+        segment: String,
+        index: usize,
+        offset: usize,
+        expected: String,
+        found: String,
+    },
+    /// The array reached by the path is shorter than the requested index.
+    #[error("index `{segment}` out of bounds for length {len}")]
+    IndexOutOfBounds {
+        path: String,
+        segment: String,
+        index: usize,
+        offset: usize,
+        len: usize,
+    },
+    /// The path string itself could not be parsed.
+    #[error("invalid path: {reason}")]
+    InvalidPath {
+        path: String,
+        reason: String,
+    },
+    /// The value was found but could not be deserialized. `path` points at
+    /// the offending field, combining the read path with the path inside the
+    /// deserialized subtree.
+    #[error("cannot deserialize `{path}`: {message}")]
+    Deserialize {
+        path: String,
+        message: String,
+    },
+    /// The value to write at `path` could not be serialized into the
+    /// document's value type (see [crate::json_update_with_serializable]).
+    #[error("cannot serialize value for `{path}`: {message}")]
+    Serialize {
+        path: String,
+        message: String,
+    },
+    /// Both sides of a merge changed the value at `path` in different ways
+    /// (see [crate::json_merge3]).
+    #[error("conflicting changes at `{path}`")]
+    Conflict {
+        path: String,
+    },
+}
+
+impl NestacError {
+    /// Renders the error along with the path and the document around the
+    /// failure: the keys available on the parent object, the length of the
+    /// parent array or a preview of the value that could not be traversed.
+    ///
+    /// `data` and `separator` must be the ones used on the failed operation.
+    ///
+    /// # Examples:
+    /// ```rust
+    /// use serde_json::Value;
+    /// use nestac::json_try_read;
+    ///
+    /// fn main() {
+    ///     let json_str = r#"{"foo": {"bar": "bingo!", "baz": "!"}}"#;
+    ///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+    ///     let err = json_try_read("foo.qux", &json_data, None).unwrap_err();
+    ///     assert_eq!(
+    ///         err.render(&json_data, None),
+    ///         [
+    ///             "error: missing key `qux`",
+    ///             " --> foo.qux",
+    ///             "         ^^^",
+    ///             "  = `foo` has keys: bar, baz",
+    ///         ].join("\n"),
+    ///     );
+    /// }
+    /// ```
+    pub fn render(&self, data: &Value, separator: Option<&str>) -> String {
+        let (path, segment, offset) = match self {
+            NestacError::InvalidPath { path, .. }
+            | NestacError::Deserialize { path, .. }
+            | NestacError::Serialize { path, .. }
+            | NestacError::Conflict { path } => {
+                return format!("error: {}\n --> {}", self, path);
+            }
+            NestacError::MissingKey { path, segment, offset, .. }
+            | NestacError::TypeMismatch { path, segment, offset, .. }
+            | NestacError::IndexOutOfBounds { path, segment, offset, .. } => {
+                (path, segment, *offset)
+            }
+        };
+        let separator = separator.unwrap_or(".");
+        let parent_path = path[..offset].strip_suffix(separator).unwrap_or("");
+        let parent = match parent_path {
+            "" => Some(data),
+            _ => json_read(parent_path, data, Some(separator)),
+        };
+        let parent_name = match parent_path {
+            "" => "$",
+            _ => parent_path,
+        };
+        let context = match parent {
+            Some(Value::Object(map)) => format!(
+                "`{}` has keys: {}",
+                parent_name,
+                map.keys().cloned().collect::<Vec<String>>().join(", "),
+            ),
+            Some(Value::Array(items)) => format!(
+                "`{}` has {} element(s)",
+                parent_name,
+                items.len(),
+            ),
+            Some(value) => format!(
+                "`{}` is {}: {}",
+                parent_name,
+                value_kind(value),
+                value_preview(value),
+            ),
+            None => format!("`{}` could not be read", parent_name),
+        };
+        format!(
+            "error: {}\n --> {}\n     {}{}\n  = {}",
+            self,
+            path,
+            " ".repeat(offset),
+            "^".repeat(segment.len().max(1)),
+            context,
+        )
+    }
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for NestacError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        let code = match self {
+            NestacError::MissingKey { .. } => "nestac::missing_key",
+            NestacError::TypeMismatch { .. } => "nestac::type_mismatch",
+            NestacError::IndexOutOfBounds { .. } => "nestac::index_out_of_bounds",
+            NestacError::InvalidPath { .. } => "nestac::invalid_path",
+            NestacError::Deserialize { .. } => "nestac::deserialize",
+            NestacError::Serialize { .. } => "nestac::serialize",
+            NestacError::Conflict { .. } => "nestac::conflict",
+        };
+        Some(Box::new(code))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            NestacError::MissingKey { path, .. }
+            | NestacError::TypeMismatch { path, .. }
+            | NestacError::IndexOutOfBounds { path, .. }
+            | NestacError::InvalidPath { path, .. }
+            | NestacError::Deserialize { path, .. }
+            | NestacError::Serialize { path, .. }
+            | NestacError::Conflict { path } => Some(path),
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let (label, segment, offset) = match self {
+            NestacError::MissingKey { segment, offset, .. } => {
+                ("key not found".to_string(), segment, *offset)
+            }
+            NestacError::TypeMismatch { segment, offset, found, .. } => {
+                (format!("parent is {}", found), segment, *offset)
+            }
+            NestacError::IndexOutOfBounds { segment, offset, len, .. } => {
+                (format!("array has {} element(s)", len), segment, *offset)
+            }
+            NestacError::InvalidPath { .. }
+            | NestacError::Deserialize { .. }
+            | NestacError::Serialize { .. }
+            | NestacError::Conflict { .. } => return None,
+        };
+        Some(Box::new(std::iter::once(
+            miette::LabeledSpan::new(Some(label), offset, segment.len()),
+        )))
+    }
+}
+
+/// Human readable name of the kind of a [Value].
+pub(crate) fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Short single-line preview of a [Value].
+pub(crate) fn value_preview(value: &Value) -> String {
+    let preview = value.to_string();
+    match preview.char_indices().nth(40) {
+        Some((idx, _)) => format!("{}...", &preview[..idx]),
+        None => preview,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_try_read;
+    use serde_json::Result;
+
+    #[test]
+    fn render_index_out_of_bounds() {
+        let json_keypath = "foo.[3]";
+        let json_str = r#"{"foo": ["a", "b"]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            None,
+        ).unwrap_err();
+        assert_eq!(
+            err.render(json_data.as_ref().unwrap(), None),
+            [
+                "error: index `[3]` out of bounds for length 2",
+                " --> foo.[3]",
+                "         ^^^",
+                "  = `foo` has 2 element(s)",
+            ].join("\n"),
+        );
+    }
+
+    #[test]
+    fn render_type_mismatch_with_custom_separator() {
+        let json_keypath = "foo@bar@baz";
+        let json_str = r#"{"foo": {"bar": "bingo!"}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            Some("@"),
+        ).unwrap_err();
+        assert_eq!(
+            err.render(json_data.as_ref().unwrap(), Some("@")),
+            [
+                "error: `baz` expects object but found string",
+                " --> foo@bar@baz",
+                "             ^^^",
+                "  = `foo@bar` is string: \"bingo!\"",
+            ].join("\n"),
+        );
+    }
+
+    #[test]
+    fn render_missing_root_key() {
+        let json_keypath = "nope";
+        let json_str = r#"{"bar": 1, "foo": 2}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            None,
+        ).unwrap_err();
+        assert!(err.render(json_data.as_ref().unwrap(), None)
+            .ends_with("  = `$` has keys: bar, foo"));
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn diagnostic_labels_offending_token() {
+        use miette::Diagnostic;
+
+        let json_keypath = "foo.bar.baz";
+        let json_str = r#"{"foo": {"bar": {}}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            None,
+        ).unwrap_err();
+        assert_eq!(err.code().unwrap().to_string(), "nestac::missing_key");
+        let labels: Vec<miette::LabeledSpan> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].offset(), 8);
+        assert_eq!(labels[0].len(), 3);
+        assert_eq!(labels[0].label(), Some("key not found"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_round_trip() {
+        let err = NestacError::IndexOutOfBounds {
+            path: "foo.[3]".to_string(),
+            segment: "[3]".to_string(),
+            index: 1,
+            offset: 4,
+            len: 2,
+        };
+        let json_str = serde_json::to_string(&err).unwrap();
+        assert_eq!(
+            json_str,
+            r#"{"IndexOutOfBounds":{"path":"foo.[3]","segment":"[3]","index":1,"offset":4,"len":2}}"#,
+        );
+        let parsed: NestacError = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed, err);
+    }
+}
@@ -0,0 +1,87 @@
+//! Reading and updating documents stored on disk in a single call.
+//!
+//! See [crate::json_read_file] and [crate::json_update_file].
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::error::NestacError;
+use crate::write::{long_path, write_file};
+
+/// Reason why a file could not be read or updated.
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    /// The file could not be read or written.
+    #[error("{}: {source}", file.display())]
+    Io {
+        file: PathBuf,
+        source: io::Error,
+    },
+    /// The file content is not a valid document.
+    #[error("cannot parse {}: {message}", file.display())]
+    Parse {
+        file: PathBuf,
+        message: String,
+    },
+    /// The updated document could not be serialized back.
+    #[error("cannot serialize {}: {message}", file.display())]
+    Serialize {
+        file: PathBuf,
+        message: String,
+    },
+    /// The path could not be resolved against the document.
+    #[error(transparent)]
+    Path(#[from] NestacError),
+}
+
+/// Reads `file` and parses it with `parse`.
+pub(crate) fn load<V, F>(file: &Path, parse: F) -> Result<V, FileError>
+where
+    F: FnOnce(&str) -> Result<V, String>,
+{
+    let source = fs::read_to_string(long_path(file)).map_err(|source| FileError::Io {
+        file: file.to_path_buf(),
+        source,
+    })?;
+    parse(&source).map_err(|message| FileError::Parse {
+        file: file.to_path_buf(),
+        message,
+    })
+}
+
+/// Atomically replaces the content of `file` with `contents`, keeping its
+/// permissions.
+pub(crate) fn store(file: &Path, contents: &str) -> Result<(), FileError> {
+    write_file(file, contents, true, None, false).map_err(|source| FileError::Io {
+        file: file.to_path_buf(),
+        source,
+    })
+}
+
+/// Same as [load] but reads `file` through tokio.
+#[cfg(feature = "tokio")]
+pub(crate) async fn load_async<V, F>(file: &Path, parse: F) -> Result<V, FileError>
+where
+    F: FnOnce(&str) -> Result<V, String>,
+{
+    let source = tokio::fs::read_to_string(long_path(file)).await.map_err(|source| FileError::Io {
+        file: file.to_path_buf(),
+        source,
+    })?;
+    parse(&source).map_err(|message| FileError::Parse {
+        file: file.to_path_buf(),
+        message,
+    })
+}
+
+/// Same as [store] but runs the write on tokio's blocking pool.
+#[cfg(feature = "tokio")]
+pub(crate) async fn store_async(file: &Path, contents: String) -> Result<(), FileError> {
+    let target = file.to_path_buf();
+    let written = tokio::task::spawn_blocking(move || write_file(&target, &contents, true, None, false))
+        .await
+        .unwrap_or_else(|err| Err(io::Error::other(err)));
+    written.map_err(|source| FileError::Io {
+        file: file.to_path_buf(),
+        source,
+    })
+}
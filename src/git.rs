@@ -0,0 +1,250 @@
+//! Path-level history of files tracked by git.
+//!
+//! Enabled by the `git` feature.
+//!
+//! Walks the first-parent history of `HEAD`, reading the file at every
+//! commit and extracting the value at a path, so questions like "when did
+//! this timeout change and to what?" take a single call:
+//!
+//! ```rust,no_run
+//! use nestac::git::json_history;
+//!
+//! fn main() {
+//!     for revision in json_history("config/app.json", "server.timeout", None).unwrap() {
+//!         println!("{} {} {:?}", revision.commit, revision.author, revision.value);
+//!     }
+//! }
+//! ```
+//!
+//! [json_blame] points at the commit that last changed a value, regardless
+//! of later reformatting.
+use std::path::{Path, PathBuf};
+use git2::{Oid, Repository, Sort};
+use crate::nested::{self, NestedValue};
+
+/// Reason why the history of a file could not be walked.
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    /// The repository could not be opened or walked.
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+    /// The file could not be located on disk.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The file is not inside the working tree of a repository.
+    #[error("`{0}` is outside of the repository working tree")]
+    OutsideWorkdir(PathBuf),
+}
+
+/// Value at a path as introduced by a commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision<V> {
+    /// Hex id of the commit.
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    /// Commit time, in seconds since the unix epoch.
+    pub time: i64,
+    /// First line of the commit message.
+    pub summary: String,
+    /// Value at the path after the commit; [None] when the path (or the
+    /// file) was removed.
+    pub value: Option<V>,
+}
+
+/// Returns every commit that changed the value at `path` inside the JSON
+/// `file`, oldest first.
+///
+/// Only the first-parent history of `HEAD` is walked; commits touching the
+/// file without changing the value at `path` (reformatting, other keys) are
+/// left out, and so are commits where the file does not parse, the value
+/// being taken as unchanged. The repository is discovered from `file`,
+/// which does not need to exist on the working tree anymore.
+pub fn json_history(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Vec<Revision<serde_json::Value>>, GitError> {
+    history(file.as_ref(), path, separator, |text| {
+        serde_json::from_str(text).map_err(|err| err.to_string())
+    })
+}
+
+/// Returns every commit that changed the value at `path` inside the TOML
+/// `file`, oldest first.
+///
+/// Same semantics as [json_history].
+///
+/// Enabled by the `git` and `toml` features.
+#[cfg(feature = "toml")]
+pub fn toml_history(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Vec<Revision<toml::Value>>, GitError> {
+    history(file.as_ref(), path, separator, |text| {
+        toml::from_str(text).map_err(|err| err.to_string())
+    })
+}
+
+/// Returns the commit that last changed the value at `path` inside the
+/// JSON `file`, or [None] when the path never existed.
+///
+/// Unlike line-based `git blame`, reformatting the file or moving the key
+/// around does not count as a change: only commits changing the value
+/// itself do (see [json_history]). When the path was removed, the removing
+/// commit is returned with a [None] value.
+pub fn json_blame(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Option<Revision<serde_json::Value>>, GitError> {
+    json_history(file, path, separator).map(|mut revisions| revisions.pop())
+}
+
+/// Returns the commit that last changed the value at `path` inside the
+/// TOML `file`.
+///
+/// Same semantics as [json_blame].
+///
+/// Enabled by the `git` and `toml` features.
+#[cfg(feature = "toml")]
+pub fn toml_blame(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Option<Revision<toml::Value>>, GitError> {
+    toml_history(file, path, separator).map(|mut revisions| revisions.pop())
+}
+
+fn history<V, F>(file: &Path, path: &str, separator: Option<&str>, parse: F) -> Result<Vec<Revision<V>>, GitError>
+where
+    V: NestedValue + Clone + PartialEq,
+    F: Fn(&str) -> Result<V, String>,
+{
+    let (repo, relative) = locate(file)?;
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.simplify_first_parent()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    let mut revisions: Vec<Revision<V>> = vec![];
+    // blob of the file at the previous commit, [None] when it was missing
+    let mut previous_blob: Option<Option<Oid>> = None;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let entry = commit.tree()?.get_path(&relative).ok();
+        let blob_id = entry.as_ref().map(|entry| entry.id());
+        if previous_blob == Some(blob_id) {
+            continue;
+        }
+        previous_blob = Some(blob_id);
+        let value = match entry {
+            Some(entry) => {
+                let blob = entry.to_object(&repo)?.peel_to_blob()?;
+                let document = std::str::from_utf8(blob.content())
+                    .map_err(|err| err.to_string())
+                    .and_then(&parse);
+                match document {
+                    Ok(document) => nested::read(path, &document, separator).cloned(),
+                    // a broken revision tells nothing about the value
+                    Err(_) => continue,
+                }
+            }
+            None => None,
+        };
+        let previous = revisions.last().and_then(|revision| revision.value.as_ref());
+        if value.as_ref() == previous {
+            continue;
+        }
+        let author = commit.author();
+        revisions.push(Revision {
+            commit: commit.id().to_string(),
+            author: author.name().unwrap_or_default().to_string(),
+            email: author.email().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            value,
+        });
+    }
+    Ok(revisions)
+}
+
+/// Opens the repository holding `file` and returns the path of `file`
+/// relative to its working tree.
+fn locate(file: &Path) -> Result<(Repository, PathBuf), GitError> {
+    let parent = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = file.file_name()
+        .ok_or_else(|| GitError::OutsideWorkdir(file.to_path_buf()))?;
+    let absolute = parent.canonicalize()?.join(name);
+    let repo = Repository::discover(parent)?;
+    let workdir = repo.workdir()
+        .ok_or_else(|| GitError::OutsideWorkdir(file.to_path_buf()))?
+        .canonicalize()?;
+    let relative = absolute.strip_prefix(&workdir)
+        .map_err(|_| GitError::OutsideWorkdir(file.to_path_buf()))?
+        .to_path_buf();
+    Ok((repo, relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn commit(repo: &Repository, file: &str, content: &str, message: &str) {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(file), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Jane", "jane@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap();
+    }
+
+    #[test]
+    fn history_skips_commits_keeping_the_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "app.json", r#"{"timeout": 30}"#, "initial");
+        commit(&repo, "app.json", "{\n  \"timeout\": 30\n}", "reformat");
+        commit(&repo, "app.json", r#"{"timeout": 60, "retries": 3}"#, "raise timeout");
+        commit(&repo, "app.json", r#"{"retries": 3}"#, "drop timeout");
+
+        let revisions = json_history(dir.path().join("app.json"), "timeout", None).unwrap();
+        let changes: Vec<(&str, Option<serde_json::Value>)> = revisions
+            .iter()
+            .map(|revision| (revision.summary.as_str(), revision.value.clone()))
+            .collect();
+        assert_eq!(changes, vec![
+            ("initial", Some(json!(30))),
+            ("raise timeout", Some(json!(60))),
+            ("drop timeout", None),
+        ]);
+        assert_eq!(revisions[0].author, "Jane");
+    }
+
+    #[test]
+    fn blame_ignores_reformatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "app.json", r#"{"a": 1, "b": 1}"#, "initial");
+        commit(&repo, "app.json", r#"{"a": 2, "b": 1}"#, "bump a");
+        commit(&repo, "app.json", "{\n  \"b\": 1,\n  \"a\": 2\n}", "reformat");
+
+        let file = dir.path().join("app.json");
+        let blame = json_blame(&file, "a", None).unwrap().unwrap();
+        assert_eq!(blame.summary, "bump a");
+        assert_eq!(blame.value, Some(json!(2)));
+        assert_eq!(json_blame(&file, "b", None).unwrap().unwrap().summary, "initial");
+        assert_eq!(json_blame(&file, "c", None).unwrap(), None);
+    }
+
+    #[test]
+    fn unparsable_revisions_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "app.json", r#"{"a": 1}"#, "initial");
+        commit(&repo, "app.json", r#"{"a": 2"#, "broken");
+        commit(&repo, "other.json", "{}", "unrelated");
+        commit(&repo, "app.json", r#"{"a": 2}"#, "fix syntax");
+        commit(&repo, "app.json", r#"{"a": 3"#, "broken again");
+
+        let revisions = json_history(dir.path().join("app.json"), "a", None).unwrap();
+        let changes: Vec<(&str, Option<serde_json::Value>)> = revisions
+            .iter()
+            .map(|revision| (revision.summary.as_str(), revision.value.clone()))
+            .collect();
+        assert_eq!(changes, vec![("initial", Some(json!(1))), ("fix syntax", Some(json!(2)))]);
+    }
+}
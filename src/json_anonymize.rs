@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Returns the shape of `data` with every key replaced by a salted hash and
+/// every value by a placeholder of the same type (`""`, `0`, `false`), along
+/// with the mapping from hashed keys back to the original ones.
+///
+/// The shape can be attached to a bug report without leaking key names or
+/// values, and paths reported against it translated back with the mapping
+/// kept by the reporter. Keys are hashed with HMAC-SHA256 keyed by `salt`,
+/// so without the salt they cannot be recovered by hashing candidate key
+/// names: pick a long random salt and keep it secret. The same `salt`
+/// always gives the same hashes, on every platform.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_anonymize_paths;
+///
+/// fn main() {
+///     let data = json!({"db": {"password": "hunter2", "ports": [5432]}});
+///     let (shape, mapping) = json_anonymize_paths(&data, "s3cr3t");
+///     assert_eq!(mapping.len(), 3);
+///     let db = shape.as_object().unwrap().keys().next().unwrap();
+///     assert_eq!(mapping[db], "db");
+///     assert!(!shape.to_string().contains("password"));
+///     assert!(!shape.to_string().contains("hunter2"));
+/// }
+/// ```
+pub fn json_anonymize_paths(data: &Value, salt: &str) -> (Value, BTreeMap<String, String>) {
+    let mut mapping = BTreeMap::new();
+    let shape = anonymize(data, salt, &mut mapping);
+    (shape, mapping)
+}
+
+fn anonymize(data: &Value, salt: &str, mapping: &mut BTreeMap<String, String>) -> Value {
+    match data {
+        Value::Object(map) => {
+            let mut shape = Map::new();
+            for (key, value) in map {
+                let hashed = hash_key(key, salt);
+                mapping.insert(hashed.clone(), key.clone());
+                shape.insert(hashed, anonymize(value, salt, mapping));
+            }
+            Value::Object(shape)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| anonymize(item, salt, mapping)).collect()),
+        Value::String(_) => Value::String(String::new()),
+        Value::Number(_) => Value::from(0),
+        Value::Bool(_) => Value::Bool(false),
+        Value::Null => Value::Null,
+    }
+}
+
+/// `k` followed by the first 128 bits of the HMAC of `key`, in hex.
+fn hash_key(key: &str, salt: &str) -> String {
+    let mac = hmac_sha256(salt.as_bytes(), key.as_bytes());
+    let hex: String = mac[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("k{}", hex)
+}
+
+/// HMAC-SHA256 (RFC 2104) of `message` under `key`.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{json_get_paths, json_read};
+
+    #[test]
+    fn shape_keeps_structure() {
+        let data = json!({"a": {"b": [1, {"c": "x"}], "d": null}, "e": true});
+        let (shape, mapping) = json_anonymize_paths(&data, "salt");
+        assert_eq!(json_get_paths(&shape, None).len(), json_get_paths(&data, None).len());
+        let reverse: BTreeMap<&str, &str> = mapping.iter().map(|(k, v)| (v.as_str(), k.as_str())).collect();
+        let path = format!("{}.{}.[1].{}", reverse["a"], reverse["b"], reverse["c"]);
+        assert_eq!(json_read(&path, &shape, None).unwrap(), "");
+        assert_eq!(shape[reverse["e"]], false);
+
+        assert_eq!(json_anonymize_paths(&data, "salt").0, shape);
+        assert_ne!(json_anonymize_paths(&data, "pepper").0, shape);
+    }
+
+    #[test]
+    fn keys_are_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6
+        let hex = |mac: [u8; 32]| mac.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        assert_eq!(
+            hex(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+        assert_eq!(
+            hex(hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        );
+        assert_eq!(hash_key("what do ya want for nothing?", "Jefe"), "k5bdcc146bf60754e6a042426089575c7");
+    }
+}
@@ -0,0 +1,87 @@
+use serde_json::Value;
+use crate::{nested, NestacError};
+
+/// Whether the array at the token-based [str] path holds `value`.
+///
+/// Fails with [NestacError::TypeMismatch] when the path holds anything but
+/// an array, and with the reason the path could not be resolved otherwise.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_array_contains;
+///
+/// fn main() {
+///     let json_data = json!({"cors": {"origins": ["https://a.com"]}});
+///     let origin = json!("https://a.com");
+///     assert_eq!(json_array_contains(&json_data, "cors.origins", None, &origin), Ok(true));
+///     assert!(json_array_contains(&json_data, "cors", None, &origin).is_err());
+/// }
+/// ```
+pub fn json_array_contains(data: &Value, path: &str, separator: Option<&str>, value: &Value) -> Result<bool, NestacError> {
+    nested::array_contains(data, path, separator, value)
+}
+
+/// Appends `value` to the array at the token-based [str] path unless it
+/// already holds an equal element, and returns whether it was added.
+///
+/// Same failures as [json_array_contains]; `data` is left untouched on
+/// failure.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_array_add_unique;
+///
+/// fn main() {
+///     let mut json_data = json!({"cors": {"origins": ["https://a.com"]}});
+///     assert_eq!(json_array_add_unique(&mut json_data, "cors.origins", None, json!("https://b.com")), Ok(true));
+///     assert_eq!(json_array_add_unique(&mut json_data, "cors.origins", None, json!("https://a.com")), Ok(false));
+///     assert_eq!(json_data, json!({"cors": {"origins": ["https://a.com", "https://b.com"]}}));
+/// }
+/// ```
+pub fn json_array_add_unique(data: &mut Value, path: &str, separator: Option<&str>, value: Value) -> Result<bool, NestacError> {
+    nested::array_add_unique(data, path, separator, value)
+}
+
+/// Removes every element equal to `value` from the array at the
+/// token-based [str] path, and returns how many were removed.
+///
+/// Same failures as [json_array_contains]; `data` is left untouched on
+/// failure.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_array_remove_value;
+///
+/// fn main() {
+///     let mut json_data = json!({"tags": ["a", "b", "a"]});
+///     assert_eq!(json_array_remove_value(&mut json_data, "tags", None, &json!("a")), Ok(2));
+///     assert_eq!(json_array_remove_value(&mut json_data, "tags", None, &json!("c")), Ok(0));
+///     assert_eq!(json_data, json!({"tags": ["b"]}));
+/// }
+/// ```
+pub fn json_array_remove_value(data: &mut Value, path: &str, separator: Option<&str>, value: &Value) -> Result<usize, NestacError> {
+    nested::array_remove_value(data, path, separator, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn non_arrays_are_left_untouched() {
+        let mut json_data = json!({"origins": {"a": 1}, "list": [[1], [2]]});
+        let err = json_array_add_unique(&mut json_data, "origins", None, json!("x")).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { ref found, .. } if found == "object"));
+        assert_eq!(json_data["origins"], json!({"a": 1}));
+        let err = json_array_remove_value(&mut json_data, "missing", None, &json!(1)).unwrap_err();
+        assert!(matches!(err, NestacError::MissingKey { .. }));
+
+        assert_eq!(json_array_add_unique(&mut json_data, "list", None, json!([1])), Ok(false));
+        assert_eq!(json_array_remove_value(&mut json_data, "list", None, &json!([2])), Ok(1));
+        assert_eq!(json_data["list"], json!([[1]]));
+    }
+}
@@ -0,0 +1,169 @@
+use serde_json::Value;
+use crate::nested::escape_key;
+
+/// Inserts the `default` of every property described by a JSON Schema and
+/// missing from `data`, returning the paths added.
+///
+/// `properties` are walked into objects present on `data` (or just
+/// inserted from a default), `items` into every element of arrays, and
+/// `allOf` subschemas as well as local `$ref`s (`#/definitions/..`,
+/// `#/$defs/..`) are followed. Properties without a `default` are never
+/// created, and existing values are never changed.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_apply_defaults;
+///
+/// fn main() {
+///     let schema = json!({
+///         "type": "object",
+///         "properties": {
+///             "port": {"type": "integer", "default": 80},
+///             "tls": {
+///                 "type": "object",
+///                 "properties": {"enabled": {"type": "boolean", "default": false}}
+///             }
+///         }
+///     });
+///     let mut data = json!({"tls": {}});
+///     let added = json_apply_defaults(&mut data, &schema);
+///     assert_eq!(added, vec!["port", "tls.enabled"]);
+///     assert_eq!(data, json!({"port": 80, "tls": {"enabled": false}}));
+/// }
+/// ```
+pub fn json_apply_defaults(data: &mut Value, schema: &Value) -> Vec<String> {
+    let mut added = vec![];
+    apply_schema(data, schema, schema, &[], &mut added);
+    added
+}
+
+fn apply_schema(data: &mut Value, schema: &Value, root: &Value, path: &[String], added: &mut Vec<String>) {
+    let schema = resolve(schema, root);
+    if let Some(subschemas) = schema.get("allOf").and_then(Value::as_array) {
+        for subschema in subschemas {
+            apply_schema(data, subschema, root, path, added);
+        }
+    }
+    if let (Some(map), Some(properties)) = (data.as_object_mut(), schema.get("properties").and_then(Value::as_object)) {
+        for (key, property) in properties {
+            let property = resolve(property, root);
+            let mut child_path = path.to_vec();
+            child_path.push(escape_key(key, ".").into_owned());
+            if !map.contains_key(key) {
+                match property.get("default") {
+                    Some(default) => {
+                        map.insert(key.clone(), default.clone());
+                        added.push(child_path.join("."));
+                    }
+                    None => continue,
+                }
+            }
+            apply_schema(map.get_mut(key).expect("key inserted above"), property, root, &child_path, added);
+        }
+    }
+    if let (Some(items), Some(item_schema)) = (data.as_array_mut(), schema.get("items")) {
+        for (idx, item) in items.iter_mut().enumerate() {
+            let mut item_path = path.to_vec();
+            item_path.push(format!("[{}]", idx));
+            apply_schema(item, item_schema, root, &item_path, added);
+        }
+    }
+}
+
+/// Follows local `$ref`s, giving up on the ones that cannot be resolved.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    let mut schema = schema;
+    // bounded so reference cycles cannot loop forever
+    for _ in 0..32 {
+        let target = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| root.pointer(pointer));
+        match target {
+            Some(target) => schema = target,
+            None => break,
+        }
+    }
+    schema
+}
+
+/// Copies every key of `defaults` missing from `data`, walking into objects
+/// present on both, and returns the paths added.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_merge_defaults;
+///
+/// fn main() {
+///     let defaults = json!({"port": 80, "tls": {"enabled": false, "cert": null}});
+///     let mut data = json!({"tls": {"enabled": true}});
+///     let added = json_merge_defaults(&mut data, &defaults);
+///     assert_eq!(added, vec!["port", "tls.cert"]);
+///     assert_eq!(data, json!({"port": 80, "tls": {"enabled": true, "cert": null}}));
+/// }
+/// ```
+pub fn json_merge_defaults(data: &mut Value, defaults: &Value) -> Vec<String> {
+    let mut added = vec![];
+    merge(data, defaults, &[], &mut added);
+    added
+}
+
+fn merge(data: &mut Value, defaults: &Value, path: &[String], added: &mut Vec<String>) {
+    let (map, defaults) = match (data.as_object_mut(), defaults.as_object()) {
+        (Some(map), Some(defaults)) => (map, defaults),
+        _ => return,
+    };
+    for (key, default) in defaults {
+        let mut child_path = path.to_vec();
+        child_path.push(escape_key(key, ".").into_owned());
+        match map.get_mut(key) {
+            Some(value) => merge(value, default, &child_path, added),
+            None => {
+                map.insert(key.clone(), default.clone());
+                added.push(child_path.join("."));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schema_refs_items_and_all_of() {
+        let schema = json!({
+            "$defs": {
+                "server": {
+                    "type": "object",
+                    "properties": {
+                        "port": {"default": 80},
+                        "a.b": {"default": 1}
+                    }
+                }
+            },
+            "allOf": [{"properties": {"name": {"default": "app"}}}],
+            "properties": {
+                "servers": {"type": "array", "items": {"$ref": "#/$defs/server"}},
+                "main": {"$ref": "#/$defs/server"},
+                "extra": {"type": "object", "properties": {"x": {"default": 1}}}
+            }
+        });
+        let mut data = json!({"name": "web", "servers": [{"port": 8080}, {}]});
+        let mut added = json_apply_defaults(&mut data, &schema);
+        added.sort();
+        assert_eq!(added, vec![
+            "servers.[0].\"a.b\"",
+            "servers.[1].\"a.b\"",
+            "servers.[1].port",
+        ]);
+        assert_eq!(data["name"], "web");
+        assert_eq!(data["servers"][1], json!({"port": 80, "a.b": 1}));
+        assert!(data.get("main").is_none());
+        assert_eq!(crate::json_read(&added[0], &data, None).unwrap(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+use serde_json::Value;
+use crate::nested;
+
+/// Remove the [Value] addressed by the token-based [str] path and returns
+/// it.
+///
+/// The last token may be a key or a `[n]` index, in which case the
+/// following array elements are shifted. Returns [None] when the path does
+/// not exist.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_delete;
+///
+/// fn main() {
+///     let json_str = r#"{"foo": {"bar": "bingo!", "baz": [1, 2]}}"#;
+///     let mut json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let removed = json_delete(&mut json_data, "foo.bar", None);
+///     assert_eq!(removed.unwrap(), "bingo!");
+///     let removed = json_delete(&mut json_data, "foo@baz@[0]", Some("@"));
+///     assert_eq!(removed.unwrap(), 1);
+///     assert_eq!(json_data, json!({"foo": {"baz": [2]}}));
+/// }
+/// ```
+pub fn json_delete(data: &mut Value, path: &str, separator: Option<&str>) -> Option<Value> {
+    nested::delete(data, path, separator)
+}
+
+/// Remove every [Value] matched by the token-based [str] path and returns
+/// the concrete path and [Value] of each of them. Wildcards work as on
+/// [crate::json_read_all].
+///
+/// See [crate::nested::delete_all].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_delete_all;
+///
+/// fn main() {
+///     let mut json_data = json!({"env": [
+///         {"name": "DEBUG", "value": "1"},
+///         {"name": "PORT", "value": "80"},
+///         {"name": "DEBUG", "value": "2"}
+///     ]});
+///     let removed = json_delete_all(&mut json_data, "env.[*name=DEBUG]", None);
+///     assert_eq!(removed.len(), 2);
+///     assert_eq!(json_data, json!({"env": [{"name": "PORT", "value": "80"}]}));
+/// }
+/// ```
+pub fn json_delete_all(data: &mut Value, path: &str, separator: Option<&str>) -> Vec<(String, Value)> {
+    nested::delete_all(data, path, separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Result;
+
+    #[test]
+    fn delete_root_key_json() {
+        let json_str = r#"{"foo": "bingo!", "bar": 1}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let removed = json_delete(json_data.as_mut().unwrap(), "foo", None);
+        assert_eq!(removed.unwrap(), "bingo!");
+        assert!(json_delete(json_data.as_mut().unwrap(), "foo", None).is_none());
+        assert_eq!(json_data.unwrap().to_string(), r#"{"bar":1}"#);
+    }
+}
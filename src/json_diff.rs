@@ -0,0 +1,60 @@
+use serde_json::Value;
+use crate::nested::{self, Change};
+
+/// Returns the path-level changes turning `old` into `new`.
+///
+/// See [crate::nested::diff]. Applying every change with
+/// [crate::json_update] (or [crate::json_delete] for removed ones, last
+/// first) brings `old` in sync with `new`.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_diff;
+/// use nestac::nested::ChangeKind;
+///
+/// fn main() {
+///     let old = json!({"debug": true, "hosts": ["a"], "port": 80});
+///     let new = json!({"hosts": ["a", "b"], "name": "app", "port": 8080});
+///     let changes = json_diff(&old, &new);
+///     let summary: Vec<(&str, ChangeKind)> = changes
+///         .iter()
+///         .map(|change| (change.path.as_str(), change.kind))
+///         .collect();
+///     assert_eq!(summary, vec![
+///         ("debug", ChangeKind::Removed),
+///         ("hosts.[1]", ChangeKind::Added),
+///         ("port", ChangeKind::Modified),
+///         ("name", ChangeKind::Added),
+///     ]);
+///     assert_eq!(changes[2].old, Some(json!(80)));
+///     assert_eq!(changes[2].new, Some(json!(8080)));
+/// }
+/// ```
+pub fn json_diff(old: &Value, new: &Value) -> Vec<Change<Value>> {
+    nested::diff(old, new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nested::ChangeKind;
+    use serde_json::json;
+
+    #[test]
+    fn diff_reports_escaped_paths_and_kind_changes() {
+        let old = json!({"hosts": {"10.0.0.1": "a"}, "tls": {"on": true}});
+        let new = json!({"hosts": {"10.0.0.1": "b"}, "tls": true});
+        let changes = json_diff(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "hosts.\"10.0.0.1\"");
+        assert_eq!(changes[1].path, "tls");
+        assert_eq!(changes[1].kind, ChangeKind::Modified);
+        assert!(json_diff(&old, &old).is_empty());
+        let mut synced = old.clone();
+        for change in &changes {
+            crate::json_update(&mut synced, &change.path, None, change.new.clone().unwrap());
+        }
+        assert_eq!(synced, new);
+    }
+}
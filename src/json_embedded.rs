@@ -0,0 +1,311 @@
+use serde_json::Value;
+use crate::nested::{self, tokenize, Step, Token};
+#[cfg(feature = "base64")]
+use base64::Engine;
+
+/// A decoding step applied to a string by a `!codec` token suffix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Codec {
+    /// `!json`: the string holds a JSON document.
+    Json,
+    /// `!b64`: the string is base64 encoded. The alphabet and padding are
+    /// detected when decoding so the string is encoded back the same way;
+    /// strings with neither `+/` nor `-_` are taken as standard base64.
+    #[cfg(feature = "base64")]
+    Base64 { url_safe: bool, padded: bool },
+}
+
+impl Codec {
+    fn decode(self, value: &Value) -> Option<(Value, Codec)> {
+        let text = value.as_str()?;
+        match self {
+            Codec::Json => Some((serde_json::from_str(text).ok()?, self)),
+            #[cfg(feature = "base64")]
+            Codec::Base64 { .. } => {
+                let padded = text.ends_with('=');
+                let url_safe = text.contains(['-', '_']);
+                let codec = Codec::Base64 { url_safe, padded };
+                let bytes = base64_engine(url_safe, padded).decode(text.trim()).ok()?;
+                Some((Value::String(String::from_utf8(bytes).ok()?), codec))
+            }
+        }
+    }
+
+    fn encode(self, value: Value) -> Option<Value> {
+        match self {
+            Codec::Json => Some(Value::String(value.to_string())),
+            #[cfg(feature = "base64")]
+            Codec::Base64 { url_safe, padded } => {
+                Some(Value::String(base64_engine(url_safe, padded).encode(value.as_str()?)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "base64")]
+fn base64_engine(url_safe: bool, padded: bool) -> base64::engine::GeneralPurpose {
+    use base64::{alphabet, engine};
+
+    let alphabet = if url_safe { &alphabet::URL_SAFE } else { &alphabet::STANDARD };
+    let config = engine::GeneralPurposeConfig::new()
+        .with_encode_padding(padded)
+        .with_decode_padding_mode(engine::DecodePaddingMode::Indifferent);
+    engine::GeneralPurpose::new(alphabet, config)
+}
+
+/// Splits a token into its key and the codecs of its `!codec` suffixes, in
+/// decoding order. Unknown suffixes are part of the key.
+fn split_token(token: &str) -> (&str, Vec<Codec>) {
+    let mut key = token;
+    let mut codecs = vec![];
+    while let Some((rest, name)) = key.rsplit_once('!') {
+        match name {
+            "json" => codecs.push(Codec::Json),
+            #[cfg(feature = "base64")]
+            "b64" => codecs.push(Codec::Base64 { url_safe: false, padded: true }),
+            #[cfg(feature = "base64")]
+            "b64json" => codecs.extend([Codec::Json, Codec::Base64 { url_safe: false, padded: true }]),
+            _ => break,
+        }
+        key = rest;
+    }
+    codecs.reverse();
+    (key, codecs)
+}
+
+/// Decodes `value` through every codec, returning the decoded value and the
+/// codecs (with their detected settings) needed to encode it back.
+fn decode(value: &Value, codecs: &[Codec]) -> Option<(Value, Vec<Codec>)> {
+    let mut value = value.clone();
+    let mut used = vec![];
+    for codec in codecs {
+        let (decoded, codec) = codec.decode(&value)?;
+        value = decoded;
+        used.push(codec);
+    }
+    Some((value, used))
+}
+
+fn encode(value: Value, codecs: &[Codec]) -> Option<Value> {
+    codecs.iter().rev().try_fold(value, |value, codec| codec.encode(value))
+}
+
+/// Splits a path token into the step addressing the string holding an
+/// embedded document and the codecs of its `!codec` suffixes. Quoted or
+/// escaped tokens are plain keys, suffixes included.
+fn split_step<'t>(token: &'t Token) -> (Step<'t>, Vec<Codec>) {
+    if token.literal {
+        return (Step::Key(&token.text), vec![]);
+    }
+    let (key, codecs) = split_token(token.raw);
+    (Step::parse(key), codecs)
+}
+
+/// Return a [Value] based off the token-based [str] path, traversing
+/// documents embedded in strings.
+///
+/// A token suffixed by `!json` (e.g. `body!json`) reads the string stored
+/// under it, parses it as JSON and continues the traversal inside it. With
+/// the `base64` feature, `!b64` base64-decodes the string (standard or
+/// URL-safe alphabet, padded or not) and `!b64json` is short for
+/// `!b64!json`; suffixes are applied left to right. Any other token behaves
+/// like on [crate::json_read]. Returns [None] when the path does not exist
+/// or an embedded string cannot be decoded.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::json_read_embedded;
+///
+/// fn main() {
+///     let json_str = r#"{"payload": {"body": "{\"user\": {\"id\": 42}}"}}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let val = json_read_embedded("payload.body!json.user.id", &json_data, None);
+///     assert_eq!(val.unwrap(), 42);
+/// }
+/// ```
+pub fn json_read_embedded(path: &str, data: &Value, separator: Option<&str>) -> Option<Value> {
+    read_tokens(data, &tokenize(path, separator.unwrap_or(".")))
+}
+
+fn read_tokens(data: &Value, tokens: &[Token]) -> Option<Value> {
+    let mut sel_data = data;
+    for (i, token) in tokens.iter().enumerate() {
+        let (step, codecs) = split_step(token);
+        if codecs.is_empty() {
+            sel_data = token.step().get(sel_data)?;
+            continue;
+        }
+        let (embedded, _) = decode(step.get(sel_data)?, &codecs)?;
+        if i + 1 == tokens.len() {
+            return Some(embedded);
+        }
+        return read_tokens(&embedded, &tokens[i + 1..]);
+    }
+    Some(sel_data.clone())
+}
+
+/// Update a [Value] based off the token-based [str] path, traversing JSON
+/// documents embedded in strings, and returns the old [Value].
+///
+/// Token suffixes work as on [json_read_embedded]. Embedded documents that
+/// were effectively changed are encoded back into their string (as compact
+/// JSON, base64 keeping the detected alphabet and padding); the others are
+/// left byte-identical. Like
+/// [crate::nested::update], returns [None] when the path does not exist or
+/// the last key did not exist before the update.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_update_embedded;
+///
+/// fn main() {
+///     let json_str = r#"{"payload": {"body": "{\"user\": {\"id\": 42}}"}}"#;
+///     let mut json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let old_val = json_update_embedded(
+///         &mut json_data,
+///         "payload.body!json.user.id",
+///         None,
+///         Value::from(7),
+///     );
+///     assert_eq!(old_val.unwrap(), 42);
+///     assert_eq!(json_data, json!({"payload": {"body": "{\"user\":{\"id\":7}}"}}));
+/// }
+/// ```
+pub fn json_update_embedded(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Option<Value> {
+    let separator = separator.unwrap_or(".");
+    update_tokens(data, &tokenize(path, separator), separator, new_value)
+}
+
+fn update_tokens(data: &mut Value, tokens: &[Token], separator: &str, new_value: Value) -> Option<Value> {
+    let embedded_at = tokens.iter().position(|token| !split_step(token).1.is_empty());
+    let i = match embedded_at {
+        Some(i) => i,
+        None => {
+            let path: Vec<&str> = tokens.iter().map(|token| token.raw).collect();
+            return nested::update(data, &path.join(separator), Some(separator), new_value);
+        }
+    };
+    let mut sel_data = data;
+    for token in &tokens[..i] {
+        sel_data = token.step().get_mut(sel_data)?;
+    }
+    let (step, codecs) = split_step(&tokens[i]);
+    let holder = step.get_mut(sel_data)?;
+    let (mut embedded, codecs) = decode(holder, &codecs)?;
+    let old_value = if i + 1 == tokens.len() {
+        Some(std::mem::replace(&mut embedded, new_value))
+    } else {
+        let before = embedded.clone();
+        let old_value = update_tokens(&mut embedded, &tokens[i + 1..], separator, new_value);
+        if embedded == before {
+            return old_value;
+        }
+        old_value
+    };
+    *holder = encode(embedded, &codecs)?;
+    old_value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Result};
+
+    #[test]
+    fn read_nested_embedded_documents() {
+        let json_str = r#"{"events": [{"payload": "{\"body\": \"[1, {\\\"id\\\": 2}]\"}"}]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let val = json_read_embedded(
+            "events@[0]@payload!json@body!json@[1]@id",
+            json_data.as_ref().unwrap(),
+            Some("@"),
+        );
+        assert_eq!(val.unwrap(), 2);
+        let val = json_read_embedded("events.[0].payload!json", json_data.as_ref().unwrap(), None);
+        assert_eq!(val.unwrap(), json!({"body": "[1, {\"id\": 2}]"}));
+        assert!(json_read_embedded("events.[0]!json", json_data.as_ref().unwrap(), None).is_none());
+    }
+
+    #[test]
+    fn quoted_tokens_keep_their_suffix() {
+        let mut json_data = json!({"a!json": "{\"b\": 1}", "a": "{\"b\": 2}"});
+        assert_eq!(json_read_embedded(r#""a!json""#, &json_data, None).unwrap(), "{\"b\": 1}");
+        assert_eq!(json_read_embedded("a!json.b", &json_data, None).unwrap(), 2);
+        let old_val = json_update_embedded(&mut json_data, r#""a!json""#, None, Value::from("x"));
+        assert_eq!(old_val.unwrap(), "{\"b\": 1}");
+        assert_eq!(json_data, json!({"a!json": "x", "a": "{\"b\": 2}"}));
+    }
+
+    #[test]
+    fn update_reserializes_changed_documents_only() {
+        let json_str = r#"{"a": "{\"b\": \"{\\\"c\\\": 1}\", \"d\": 2}"}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let old_val = json_update_embedded(
+            json_data.as_mut().unwrap(),
+            "a!json.d",
+            None,
+            Value::from(2),
+        );
+        assert_eq!(old_val.unwrap(), 2);
+        assert_eq!(json_data.as_ref().unwrap()["a"], "{\"b\": \"{\\\"c\\\": 1}\", \"d\": 2}");
+
+        let old_val = json_update_embedded(
+            json_data.as_mut().unwrap(),
+            "a!json.b!json.c",
+            None,
+            Value::from(3),
+        );
+        assert_eq!(old_val.unwrap(), 1);
+        assert_eq!(
+            json_read_embedded("a!json.b!json", json_data.as_ref().unwrap(), None).unwrap(),
+            json!({"c": 3}),
+        );
+        assert_eq!(json_data.as_ref().unwrap()["a"], r#"{"b":"{\"c\":3}","d":2}"#);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64_round_trip_keeps_encoding() {
+        // {"sub":"42","admin":false}
+        let jwt = "eyJhbGciOiJub25lIn0.eyJzdWIiOiI0MiIsImFkbWluIjpmYWxzZX0";
+        let payload = jwt.split('.').nth(1).unwrap();
+        let json_str = format!(r#"{{"token": "{}", "user_data": "eyJydW5jbWQiOiBbXX0="}}"#, payload);
+        let mut json_data: Result<Value> = serde_json::from_str(&json_str);
+        assert!(json_data.is_ok());
+        let val = json_read_embedded("token!b64json.sub", json_data.as_ref().unwrap(), None);
+        assert_eq!(val.unwrap(), "42");
+        let val = json_read_embedded("token!b64!json.admin", json_data.as_ref().unwrap(), None);
+        assert_eq!(val.unwrap(), false);
+
+        let old_val = json_update_embedded(
+            json_data.as_mut().unwrap(),
+            "token!b64json.admin",
+            None,
+            Value::Bool(true),
+        );
+        assert_eq!(old_val.unwrap(), false);
+        let token = json_data.as_ref().unwrap()["token"].as_str().unwrap().to_string();
+        assert!(!token.ends_with('='));
+        assert_eq!(
+            json_read_embedded("token!b64json", json_data.as_ref().unwrap(), None).unwrap(),
+            json!({"sub": "42", "admin": true}),
+        );
+
+        json_update_embedded(
+            json_data.as_mut().unwrap(),
+            "user_data!b64json.runcmd",
+            None,
+            json!(["ls"]),
+        );
+        assert_eq!(json_data.as_ref().unwrap()["user_data"], "eyJydW5jbWQiOlsibHMiXX0=");
+
+        // unpadded without `+/` or `-_` defaults to the standard alphabet
+        let mut json_data = json!({"s": "aGk"});
+        json_update_embedded(&mut json_data, "s!b64", None, Value::from("???>"));
+        assert_eq!(json_data, json!({"s": "Pz8/Pg"}));
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::Path;
+use serde_json::Value;
+use crate::document::Document;
+use crate::file::{self, FileError};
+use crate::nested;
+
+/// Reads the JSON `file` and returns the [Value] at the token-based [str]
+/// path.
+///
+/// Fails with [FileError::Path] describing the token that could not be
+/// resolved (see [crate::json_try_read]), or with the I/O or parsing error.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_read_file;
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("app.json");
+///     std::fs::write(&file, r#"{"server": {"port": 8080}}"#).unwrap();
+///
+///     assert_eq!(json_read_file(&file, "server.port", None).unwrap(), json!(8080));
+///     assert!(json_read_file(&file, "server.host", None).is_err());
+/// }
+/// ```
+pub fn json_read_file(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Value, FileError> {
+    let data: Value = file::load(file.as_ref(), |source| {
+        serde_json::from_str(source).map_err(|err| err.to_string())
+    })?;
+    nested::extract(data, path, separator).map_err(|(_, err)| FileError::Path(err))
+}
+
+/// Updates the [Value] at the token-based [str] path of the JSON `file` and
+/// returns the old [Value].
+///
+/// Same semantics as [crate::json_try_update]. The file keeps its
+/// indentation, line endings and BOM (see [Document]) and is replaced
+/// atomically: the new content is written to a temporary file next to it
+/// and renamed over it, so readers never see a partial write. Nothing is
+/// written when the update fails or leaves the value as it was.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_read_file, json_update_file};
+///
+/// fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("app.json");
+///     std::fs::write(&file, "{\n    \"server\": {\n        \"port\": 8080\n    }\n}\n").unwrap();
+///
+///     let old_val = json_update_file(&file, "server.port", None, json!(9090)).unwrap();
+///     assert_eq!(old_val, Some(json!(8080)));
+///     assert_eq!(
+///         std::fs::read_to_string(&file).unwrap(),
+///         "{\n    \"server\": {\n        \"port\": 9090\n    }\n}\n",
+///     );
+/// }
+/// ```
+pub fn json_update_file(file: impl AsRef<Path>, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, FileError> {
+    let file = file.as_ref();
+    let mut doc = file::load(file, |source| {
+        Document::parse(source).map_err(|err| err.to_string())
+    })?;
+    nested::check_update(doc.value(), path, separator, &new_value)?;
+    let old_val = doc.update(path, separator, new_value);
+    if doc.is_dirty() {
+        file::store(file, &doc.to_string())?;
+    }
+    Ok(old_val)
+}
+
+/// Reads the JSON `file` through tokio and returns the [Value] at the
+/// token-based [str] path.
+///
+/// Same semantics as [json_read_file].
+///
+/// Enabled by the `tokio` feature.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_read_file_async;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let file = dir.path().join("app.json");
+///     std::fs::write(&file, r#"{"server": {"port": 8080}}"#).unwrap();
+///
+///     let port = json_read_file_async(&file, "server.port", None).await.unwrap();
+///     assert_eq!(port, json!(8080));
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn json_read_file_async(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Value, FileError> {
+    let data: Value = file::load_async(file.as_ref(), |source| {
+        serde_json::from_str(source).map_err(|err| err.to_string())
+    }).await?;
+    nested::extract(data, path, separator).map_err(|(_, err)| FileError::Path(err))
+}
+
+/// Updates the [Value] at the token-based [str] path of the JSON `file`
+/// through tokio and returns the old [Value].
+///
+/// Same semantics as [json_update_file]; the atomic write runs on tokio's
+/// blocking pool.
+///
+/// Enabled by the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub async fn json_update_file_async(file: impl AsRef<Path>, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, FileError> {
+    let file = file.as_ref();
+    let mut doc = file::load_async(file, |source| {
+        Document::parse(source).map_err(|err| err.to_string())
+    }).await?;
+    nested::check_update(doc.value(), path, separator, &new_value)?;
+    let old_val = doc.update(path, separator, new_value);
+    if doc.is_dirty() {
+        file::store_async(file, doc.to_string()).await?;
+    }
+    Ok(old_val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::NestacError;
+
+    #[test]
+    fn failed_update_leaves_file_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, r#"{"ports": [80]}"#).unwrap();
+
+        let err = json_update_file(&file, "ports.[3]", None, json!(443)).unwrap_err();
+        assert!(matches!(err, FileError::Path(NestacError::IndexOutOfBounds { len: 1, .. })));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"{"ports": [80]}"#);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let err = json_read_file(dir.path().join("missing.json"), "ports", None).unwrap_err();
+        assert!(matches!(err, FileError::Io { .. }));
+    }
+
+    #[test]
+    fn same_value_leaves_file_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, r#"{"ports": [80]}"#).unwrap();
+        let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        assert_eq!(json_update_file(&file, "ports.[0]", None, json!(80)).unwrap(), Some(json!(80)));
+        assert_eq!(std::fs::metadata(&file).unwrap().modified().unwrap(), modified);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"{"ports": [80]}"#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn update_through_symlink_writes_its_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("shared.json");
+        let link = dir.path().join("app.json");
+        std::fs::write(&target, r#"{"port": 80}"#).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        json_update_file(&link, "port", None, json!(443)).unwrap();
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(json_read_file(&target, "port", None).unwrap(), json!(443));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_update_keeps_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, "{\r\n  \"debug\": false\r\n}\r\n").unwrap();
+
+        let old_val = json_update_file_async(&file, "debug", None, json!(true)).await.unwrap();
+        assert_eq!(old_val, Some(json!(false)));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{\r\n  \"debug\": true\r\n}\r\n");
+        let err = json_update_file_async(&file, "debug.level", None, json!(1)).await.unwrap_err();
+        assert!(matches!(err, FileError::Path(NestacError::TypeMismatch { .. })));
+    }
+}
@@ -0,0 +1,193 @@
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use crate::error::{value_kind, NestacError};
+use crate::nested::{escape_key, tokenize, Step};
+
+/// Flattens `data` into a map from the path of every leaf to its value,
+/// e.g. to store a document on a key/value store like etcd or Consul.
+/// Entries keep the document order, so `[2]` comes before `[10]`.
+///
+/// Leaves are scalars and empty objects or arrays, so the document can be
+/// rebuilt with [json_unflatten]. Paths are joined with `separator`
+/// (default: `.`), keys are quoted when needed and array elements written
+/// as `[n]`, so every path also works with [crate::json_read].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_flatten;
+///
+/// fn main() {
+///     let data = json!({"a": {"b": [1, {"c": true}]}, "d": {}});
+///     let flat = json_flatten(&data, None);
+///     assert_eq!(flat["a.b.[0]"], json!(1));
+///     assert_eq!(flat["a.b.[1].c"], json!(true));
+///     assert_eq!(flat["d"], json!({}));
+///     assert_eq!(flat.len(), 3);
+///
+///     let flat = json_flatten(&data, Some("/"));
+///     assert!(flat.contains_key("a/b/[1]/c"));
+/// }
+/// ```
+pub fn json_flatten(data: &Value, separator: Option<&str>) -> IndexMap<String, Value> {
+    let separator = separator.unwrap_or(".");
+    let mut flat = IndexMap::new();
+    let mut path = vec![];
+    match data {
+        Value::Object(map) if map.is_empty() => {}
+        Value::Array(items) if items.is_empty() => {}
+        _ => flatten(data, separator, &mut path, &mut flat),
+    }
+    flat
+}
+
+fn flatten(node: &Value, separator: &str, path: &mut Vec<String>, flat: &mut IndexMap<String, Value>) {
+    match node {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                path.push(escape_key(key, separator).into_owned());
+                flatten(child, separator, path, flat);
+                path.pop();
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (idx, child) in items.iter().enumerate() {
+                path.push(format!("[{}]", idx));
+                flatten(child, separator, path, flat);
+                path.pop();
+            }
+        }
+        _ => {
+            flat.insert(path.join(separator), node.clone());
+        }
+    }
+}
+
+/// Rebuilds the document flattened by [json_flatten] from its `(path,
+/// value)` entries, given in any order.
+///
+/// `[n]` tokens create arrays (padded with `null`s until every index is
+/// set), any other token creates objects. An empty set of entries gives an
+/// empty object. Entries going through a scalar set by another entry (e.g.
+/// `a` and `a.b`), or through a different kind of container, are reported
+/// as a [NestacError].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_flatten, json_unflatten};
+///
+/// fn main() {
+///     let entries = vec![
+///         ("server/ports/[1]".to_string(), json!(443)),
+///         ("server/ports/[0]".to_string(), json!(80)),
+///         ("server/host".to_string(), json!("localhost")),
+///     ];
+///     let data = json_unflatten(entries, Some("/")).unwrap();
+///     assert_eq!(data, json!({"server": {"host": "localhost", "ports": [80, 443]}}));
+///     assert_eq!(json_unflatten(json_flatten(&data, None), None).unwrap(), data);
+/// }
+/// ```
+pub fn json_unflatten<I>(entries: I, separator: Option<&str>) -> Result<Value, NestacError>
+where
+    I: IntoIterator<Item = (String, Value)>,
+{
+    let separator = separator.unwrap_or(".");
+    let mut root = Value::Null;
+    for (path, value) in entries {
+        let tokens = tokenize(&path, separator);
+        let mut node = &mut root;
+        for (index, token) in tokens.iter().enumerate() {
+            let mismatch = |expected: &str, found: &Value| NestacError::TypeMismatch {
+                path: path.clone(),
+                segment: token.raw.to_string(),
+                index,
+                offset: token.offset,
+                expected: expected.to_string(),
+                found: value_kind(found).to_string(),
+            };
+            node = match token.step() {
+                Step::Key(key) => {
+                    if node.is_null() {
+                        *node = Value::Object(Map::new());
+                    }
+                    match node {
+                        Value::Object(map) => map.entry(key).or_insert(Value::Null),
+                        other => return Err(mismatch("object", other)),
+                    }
+                }
+                Step::Index(idx) => {
+                    if node.is_null() {
+                        *node = Value::Array(vec![]);
+                    }
+                    match node {
+                        Value::Array(items) => {
+                            if items.len() <= idx {
+                                items.resize(idx + 1, Value::Null);
+                            }
+                            &mut items[idx]
+                        }
+                        other => return Err(mismatch("array", other)),
+                    }
+                }
+                Step::Select(..) | Step::Slice(_) | Step::Append => {
+                    return Err(NestacError::InvalidPath {
+                        path: path.clone(),
+                        reason: format!("`{}` cannot be unflattened", token.raw),
+                    });
+                }
+            };
+        }
+        if !node.is_null() {
+            return Err(NestacError::InvalidPath {
+                path: path.clone(),
+                reason: "already set by another entry".to_string(),
+            });
+        }
+        *node = value;
+    }
+    Ok(match root {
+        Value::Null => Value::Object(Map::new()),
+        root => root,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip_with_escaped_keys_and_empty_containers() {
+        let data = json!({
+            "hosts": {"10.0.0.1": {"tags": []}},
+            "list": [[1, 2], {}],
+            "nothing": null
+        });
+        let flat = json_flatten(&data, None);
+        assert_eq!(flat["hosts.\"10.0.0.1\".tags"], json!([]));
+        assert_eq!(flat["list.[0].[1]"], json!(2));
+        assert_eq!(flat["nothing"], Value::Null);
+        assert_eq!(json_unflatten(flat, None).unwrap(), data);
+    }
+
+    #[test]
+    fn entries_keep_document_order() {
+        let items: Vec<Value> = (0..11).map(Value::from).collect();
+        let flat = json_flatten(&json!({"list": items, "z": 0}), None);
+        let paths: Vec<&str> = flat.keys().map(String::as_str).collect();
+        assert_eq!(paths[..3], ["list.[0]", "list.[1]", "list.[2]"]);
+        assert_eq!(paths[10..], ["list.[10]", "z"]);
+    }
+
+    #[test]
+    fn unflatten_conflicts_are_errors() {
+        let entries = vec![
+            ("a".to_string(), json!(1)),
+            ("a.b".to_string(), json!(2)),
+        ];
+        let err = json_unflatten(entries, None).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { index: 1, .. }));
+        assert_eq!(json_unflatten(vec![], None).unwrap(), json!({}));
+    }
+}
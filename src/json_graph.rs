@@ -0,0 +1,138 @@
+use serde_json::Value;
+use crate::error::{value_kind, value_preview};
+use crate::options::GraphOptions;
+
+/// Node of the graph drawn from a document, numbered in document order.
+struct Node {
+    label: String,
+    parent: Option<usize>,
+}
+
+/// Renders the nesting structure of `data` as a Graphviz `digraph`, one
+/// node per value labeled with its key (or `[index]`) and type, with an
+/// edge from every container to its children.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_to_dot, GraphOptions};
+///
+/// fn main() {
+///     let data = json!({"server": {"ports": [80, 443]}, "debug": true});
+///     let dot = json_to_dot(&data, &GraphOptions::new().previews(true));
+///     assert!(dot.starts_with("digraph nestac {\n"));
+///     assert!(dot.contains("n0 [label=\"root: object\"];"));
+///     assert!(dot.contains("[label=\"debug: boolean = true\"];"));
+///     assert!(dot.contains("[label=\"[1]: number = 443\"];"));
+///     assert!(dot.contains("n0 -> n1;"));
+///
+///     let dot = json_to_dot(&data, &GraphOptions::new().max_depth(1));
+///     assert!(dot.contains("[label=\"server: object ...\"]"));
+///     assert!(!dot.contains("ports"));
+/// }
+/// ```
+pub fn json_to_dot(data: &Value, options: &GraphOptions) -> String {
+    let mut out = String::from("digraph nestac {\n    node [shape=box];\n");
+    let nodes = graph_nodes(data, options);
+    for (id, node) in nodes.iter().enumerate() {
+        let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+        out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+    }
+    for (id, node) in nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            out.push_str(&format!("    n{} -> n{};\n", parent, id));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Renders the nesting structure of `data` as a Mermaid flowchart, see
+/// [json_to_dot].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_to_mermaid, GraphOptions};
+///
+/// fn main() {
+///     let data = json!({"tags": ["a"]});
+///     let chart = json_to_mermaid(&data, &GraphOptions::new().previews(true));
+///     assert_eq!(chart, [
+///         "flowchart TD",
+///         "    n0[\"root: object\"]",
+///         "    n1[\"tags: array\"]",
+///         "    n2[\"[0]: string = #quot;a#quot;\"]",
+///         "    n0 --> n1",
+///         "    n1 --> n2",
+///         "",
+///     ].join("\n"));
+/// }
+/// ```
+pub fn json_to_mermaid(data: &Value, options: &GraphOptions) -> String {
+    let mut out = String::from("flowchart TD\n");
+    let nodes = graph_nodes(data, options);
+    for (id, node) in nodes.iter().enumerate() {
+        let label = node.label.replace('"', "#quot;");
+        out.push_str(&format!("    n{}[\"{}\"]\n", id, label));
+    }
+    for (id, node) in nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            out.push_str(&format!("    n{} --> n{}\n", parent, id));
+        }
+    }
+    out
+}
+
+fn graph_nodes(data: &Value, options: &GraphOptions) -> Vec<Node> {
+    let mut nodes = vec![];
+    push_node(data, "root".to_string(), None, 0, options, &mut nodes);
+    nodes
+}
+
+fn push_node(node: &Value, name: String, parent: Option<usize>, depth: usize, options: &GraphOptions, nodes: &mut Vec<Node>) {
+    let cut = options.get_max_depth().is_some_and(|max| depth >= max);
+    let mut label = format!("{}: {}", name, value_kind(node));
+    match node {
+        Value::Array(items) if cut && !items.is_empty() => label.push_str(" ..."),
+        Value::Object(map) if cut && !map.is_empty() => label.push_str(" ..."),
+        Value::Array(_) | Value::Object(_) => {}
+        _ if options.get_previews() => label = format!("{} = {}", label, value_preview(node)),
+        _ => {}
+    }
+    let id = nodes.len();
+    nodes.push(Node { label, parent });
+    if cut {
+        return;
+    }
+    match node {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                push_node(item, format!("[{}]", i), Some(id), depth + 1, options, nodes);
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map {
+                push_node(child, key.clone(), Some(id), depth + 1, options, nodes);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn labels_are_escaped() {
+        let data = json!({"say \"hi\"": "a\\b"});
+        let options = GraphOptions::new().previews(true);
+        let dot = json_to_dot(&data, &options);
+        assert!(dot.contains(r#"n1 [label="say \"hi\": string = \"a\\\\b\""];"#));
+        let chart = json_to_mermaid(&data, &options);
+        assert!(chart.contains(r#"n1["say #quot;hi#quot;: string = #quot;a\\b#quot;"]"#));
+        assert_eq!(json_to_dot(&json!(1), &GraphOptions::new().max_depth(0)).lines().count(), 4);
+    }
+}
@@ -0,0 +1,64 @@
+use serde_json::Value;
+use crate::nested::{self, ID_KEY};
+
+/// Gives every object stored in an array that lacks `key` (default:
+/// [ID_KEY]) a generated id under that key, returning how many ids were
+/// assigned.
+///
+/// Elements can then be addressed by `[#id]` tokens, which keep working
+/// after the array is reordered. Ids stored under another key are looked up
+/// through [crate::ReadOptions::id_key] and [crate::UpdateOptions::id_key].
+/// See [crate::nested::assign_ids].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{json_assign_ids, json_read_with, json_update_with, ReadOptions, UpdateOptions};
+///
+/// fn main() {
+///     let mut json_data = json!({"jobs": [{"name": "lint"}, {"name": "test"}]});
+///     json_assign_ids(&mut json_data, Some("uid"));
+///     let path = format!("jobs.[#{}].name", json_data["jobs"][1]["uid"].as_str().unwrap());
+///
+///     json_data["jobs"].as_array_mut().unwrap().swap(0, 1);
+///     let options = UpdateOptions::new().id_key("uid");
+///     json_update_with(&mut json_data, &path, &options, Value::from("unit-test"));
+///     let options = ReadOptions::new().id_key("uid");
+///     assert_eq!(json_read_with(&path, &json_data, &options).unwrap(), "unit-test");
+///     assert_eq!(json_data["jobs"][0]["name"], "unit-test");
+/// }
+/// ```
+pub fn json_assign_ids(data: &mut Value, key: Option<&str>) -> usize {
+    nested::assign_ids(data, key.unwrap_or(ID_KEY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ids_are_unique_and_kept() {
+        let mut json_data = json!({
+            "a": [{"x": 1}, {"x": 2, "_id": "keep"}, 3],
+            "b": {"c": [{"d": [{}]}]}
+        });
+        assert_eq!(json_assign_ids(&mut json_data, None), 3);
+        assert_eq!(json_data["a"][1]["_id"], "keep");
+        assert_eq!(json_data["a"][2], 3);
+        let id = json_data["b"]["c"][0]["d"][0]["_id"].as_str().unwrap().to_string();
+        assert_eq!(id.len(), 8);
+        assert_ne!(json_data["a"][0]["_id"], json_data["b"]["c"][0]["_id"]);
+
+        let mut again = json!({
+            "a": [{"x": 1}, {"x": 2, "_id": "keep"}, 3],
+            "b": {"c": [{"d": [{}]}]}
+        });
+        json_assign_ids(&mut again, None);
+        assert_eq!(again, json_data);
+        assert_eq!(
+            crate::json_read(&format!("b.c.[0].d.[#{}]", id), &json_data, None),
+            Some(&json!({"_id": id})),
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+use serde_json::{Map, Value};
+use crate::error::value_kind;
+use crate::nested::escape_key;
+use crate::options::LintOptions;
+
+/// Suspicious structure detected by [json_lint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintRule {
+    /// An array holding values of different types (nulls aside).
+    MixedTypeArray,
+    /// An object holding keys that only differ by case (`name`, `Name`).
+    CaseCollidingKeys,
+    /// Objects nested in a chain where each one holds a single key, at
+    /// least [LintOptions::max_chain] long.
+    SingleKeyChain,
+    /// A numeric-looking key (`"0"`) next to an array, likely an element
+    /// written outside of it.
+    NumericKeyNearArray,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 4] = [
+        LintRule::MixedTypeArray,
+        LintRule::CaseCollidingKeys,
+        LintRule::SingleKeyChain,
+        LintRule::NumericKeyNearArray,
+    ];
+
+    /// Stable identifier of the rule, e.g. `mixed-type-array`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::MixedTypeArray => "mixed-type-array",
+            LintRule::CaseCollidingKeys => "case-colliding-keys",
+            LintRule::SingleKeyChain => "single-key-chain",
+            LintRule::NumericKeyNearArray => "numeric-key-near-array",
+        }
+    }
+}
+
+/// How serious a [LintIssue] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Issue reported by [json_lint].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LintIssue {
+    /// Path of the offending node, usable with [crate::json_read] (empty
+    /// for the root).
+    pub path: String,
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Looks for structures that are valid JSON but likely mistakes, see
+/// [LintRule], and returns them in document order.
+///
+/// Every rule is reported with the [Severity] set on `options`; disabled
+/// rules are not reported.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_lint, LintOptions, LintRule, Severity};
+///
+/// fn main() {
+///     let data = json!({
+///         "ports": [80, "443"],
+///         "user": {"name": "a", "Name": "b"},
+///     });
+///     let options = LintOptions::new().severity(LintRule::CaseCollidingKeys, Severity::Error);
+///     let issues = json_lint(&data, &options);
+///     assert_eq!(issues.len(), 2);
+///     assert_eq!(issues[0].path, "ports");
+///     assert_eq!(issues[0].rule.id(), "mixed-type-array");
+///     assert_eq!(issues[1].path, "user");
+///     assert_eq!(issues[1].severity, Severity::Error);
+///
+///     let options = LintOptions::new().allow(LintRule::MixedTypeArray);
+///     assert_eq!(json_lint(&json!([1, "a"]), &options), vec![]);
+/// }
+/// ```
+pub fn json_lint(data: &Value, options: &LintOptions) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    lint_node(data, String::new(), false, options, &mut issues);
+    issues
+}
+
+fn lint_node(node: &Value, path: String, in_chain: bool, options: &LintOptions, issues: &mut Vec<LintIssue>) {
+    let mut report = |rule: LintRule, path: &str, message: String| {
+        if let Some(severity) = options.get_severity(rule) {
+            issues.push(LintIssue { path: path.to_string(), rule, severity, message });
+        }
+    };
+    match node {
+        Value::Array(items) => {
+            let kinds: BTreeSet<&str> = items
+                .iter()
+                .filter(|item| !item.is_null())
+                .map(value_kind)
+                .collect();
+            if kinds.len() > 1 {
+                let kinds: Vec<&str> = kinds.into_iter().collect();
+                report(LintRule::MixedTypeArray, &path, format!("array mixes {}", kinds.join(", ")));
+            }
+            for (i, item) in items.iter().enumerate() {
+                lint_node(item, child_path(&path, &format!("[{}]", i)), false, options, issues);
+            }
+        }
+        Value::Object(map) => {
+            if let Some((a, b)) = case_collision(map) {
+                report(
+                    LintRule::CaseCollidingKeys,
+                    &path,
+                    format!("keys `{}` and `{}` only differ by case", a, b),
+                );
+            }
+            let chain = chain_len(node);
+            if !in_chain && chain >= options.get_max_chain() {
+                report(
+                    LintRule::SingleKeyChain,
+                    &path,
+                    format!("{} nested objects with a single key each", chain),
+                );
+            }
+            if let Some(array) = map.iter().find(|(_, value)| value.is_array()).map(|(key, _)| key) {
+                for key in map.keys().filter(|key| is_numeric(key)) {
+                    report(
+                        LintRule::NumericKeyNearArray,
+                        &child_path(&path, &escape_key(key, ".")),
+                        format!("numeric-looking key `{}` next to array `{}`", key, array),
+                    );
+                }
+            }
+            for (key, child) in map {
+                let child_in_chain = map.len() == 1 && child.is_object();
+                lint_node(child, child_path(&path, &escape_key(key, ".")), child_in_chain, options, issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn child_path(path: &str, token: &str) -> String {
+    match path.is_empty() {
+        true => token.to_string(),
+        false => [path, token].join("."),
+    }
+}
+
+/// First pair of keys equal once lowercased.
+fn case_collision(map: &Map<String, Value>) -> Option<(&str, &str)> {
+    let keys: Vec<&String> = map.keys().collect();
+    keys.iter().enumerate().find_map(|(i, a)| {
+        keys[i + 1..]
+            .iter()
+            .find(|b| a.to_lowercase() == b.to_lowercase())
+            .map(|b| (a.as_str(), b.as_str()))
+    })
+}
+
+/// Number of nested objects holding a single key, starting at `node`.
+fn chain_len(mut node: &Value) -> usize {
+    let mut len = 0;
+    while let Some(map) = node.as_object().filter(|map| map.len() == 1) {
+        len += 1;
+        node = map.values().next().expect("the object has one key");
+    }
+    len
+}
+
+fn is_numeric(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn chains_and_numeric_keys() {
+        let data = json!({
+            "a": {"b": {"c": {"d": {"e": 1}}}},
+            "list": {"items": [1, null, 2], "0": 3},
+        });
+        let issues = json_lint(&data, &LintOptions::new());
+        let found: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), issue.rule.id()))
+            .collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&("a", "single-key-chain")));
+        assert!(found.contains(&("list.0", "numeric-key-near-array")));
+        assert!(issues.iter().all(|issue| crate::json_read(&issue.path, &data, None).is_some()));
+
+        let options = LintOptions::new().max_chain(5);
+        assert_eq!(json_lint(&data, &options).len(), 1);
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::json_read;
+
+/// Returns the SHA-256 of the [Value] at each of `paths`, hex encoded and
+/// keyed by path, to be checked later with [json_verify].
+///
+/// Values are hashed in a canonical form (object keys sorted, no
+/// whitespace), so reformatting the document or reordering keys does not
+/// change them. Paths that do not exist are left out.
+///
+/// Enabled by the `manifest` feature.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_manifest, json_verify};
+///
+/// fn main() {
+///     let mut data = json!({"db": {"host": "db1", "port": 5432}, "replicas": 2});
+///     let manifest = json_manifest(&data, &["db", "replicas"], None);
+///     assert_eq!(manifest.len(), 2);
+///
+///     data["replicas"] = json!(3);
+///     assert_eq!(json_verify(&data, &manifest, None), vec!["replicas"]);
+/// }
+/// ```
+pub fn json_manifest(data: &Value, paths: &[&str], separator: Option<&str>) -> BTreeMap<String, String> {
+    paths
+        .iter()
+        .filter_map(|path| json_read(path, data, separator).map(|value| (path.to_string(), hash(value))))
+        .collect()
+}
+
+/// Returns the paths of `manifest` whose [Value] changed, or no longer
+/// exists, since the manifest was built by [json_manifest].
+///
+/// Enabled by the `manifest` feature.
+pub fn json_verify(data: &Value, manifest: &BTreeMap<String, String>, separator: Option<&str>) -> Vec<String> {
+    manifest
+        .iter()
+        .filter(|(path, expected)| {
+            json_read(path, data, separator).map(hash).as_ref() != Some(*expected)
+        })
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+fn hash(value: &Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical);
+    Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Writes `value` as compact JSON with object keys sorted.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, child)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(child, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, child) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(child, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn key_order_does_not_change_the_hash() {
+        let data: Value = serde_json::from_str(r#"{"a": {"x": 1, "y": [true, null]}}"#).unwrap();
+        let reordered: Value = serde_json::from_str(r#"{"a": {"y": [true, null], "x": 1}}"#).unwrap();
+        let manifest = json_manifest(&data, &["a", "missing"], None);
+        assert_eq!(manifest.keys().collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(manifest["a"].len(), 64);
+        assert!(json_verify(&reordered, &manifest, None).is_empty());
+        assert_eq!(json_verify(&json!({}), &manifest, None), vec!["a"]);
+    }
+}
@@ -0,0 +1,357 @@
+use std::convert::Infallible;
+use serde_json::{Map, Value};
+use crate::error::NestacError;
+use crate::nested::{self, escape_key, NestedValue};
+use crate::options::{ArrayMerge, MergeDepth, MergeStrategy, NullMerge};
+
+/// Merges two documents into a new one, failing with
+/// [NestacError::Conflict] on the first path holding different values on
+/// both sides.
+///
+/// Objects are merged key by key, keys present on a single side are kept
+/// and anything else (including arrays) must be equal on both sides.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_merge, NestacError};
+///
+/// fn main() {
+///     let left = json!({"server": {"port": 80}});
+///     let right = json!({"server": {"host": "localhost"}});
+///     let merged = json_merge(&left, &right).unwrap();
+///     assert_eq!(merged, json!({"server": {"port": 80, "host": "localhost"}}));
+///
+///     let right = json!({"server": {"port": 8080}});
+///     let err = json_merge(&left, &right).unwrap_err();
+///     assert_eq!(err, NestacError::Conflict { path: "server.port".to_string() });
+/// }
+/// ```
+pub fn json_merge(left: &Value, right: &Value) -> Result<Value, NestacError> {
+    merge(None, Some(left), Some(right), &[], &mut conflict)
+        .map(|merged| merged.unwrap_or(Value::Null))
+}
+
+/// Same as [json_merge] but conflicts are handed to `resolver` instead of
+/// failing.
+///
+/// `resolver` receives the conflicting path, the `left` and `right` values
+/// and the base (always [None] on a two-way merge, see
+/// [json_merge3_with]). A side is [None] when it lacks the key. The value
+/// returned is kept at the path, [None] leaves the key out.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_merge_with;
+///
+/// fn main() {
+///     let left = json!({"port": 80, "retries": 3});
+///     let right = json!({"port": 8080, "retries": 5});
+///     let merged = json_merge_with(&left, &right, |path, left, right, _base| {
+///         match path {
+///             "port" => left.cloned(),
+///             _ => right.cloned(),
+///         }
+///     });
+///     assert_eq!(merged, json!({"port": 80, "retries": 5}));
+/// }
+/// ```
+pub fn json_merge_with<F>(left: &Value, right: &Value, mut resolver: F) -> Value
+where
+    F: FnMut(&str, Option<&Value>, Option<&Value>, Option<&Value>) -> Option<Value>,
+{
+    json_merge3_with(None, left, right, &mut resolver)
+}
+
+/// Three-way merges `left` and `right`, both derived from `base`, failing
+/// with [NestacError::Conflict] on the first path changed differently on
+/// both sides.
+///
+/// A path changed on a single side (including keys added or removed) takes
+/// that side's value. Objects are merged key by key and anything else
+/// (including arrays) is compared as a whole.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_merge3;
+///
+/// fn main() {
+///     let base = json!({"port": 80, "host": "localhost", "debug": true});
+///     let left = json!({"port": 8080, "host": "localhost", "debug": true});
+///     let right = json!({"port": 80, "host": "0.0.0.0"});
+///     let merged = json_merge3(&base, &left, &right).unwrap();
+///     assert_eq!(merged, json!({"port": 8080, "host": "0.0.0.0"}));
+/// }
+/// ```
+pub fn json_merge3(base: &Value, left: &Value, right: &Value) -> Result<Value, NestacError> {
+    merge(Some(base), Some(left), Some(right), &[], &mut conflict)
+        .map(|merged| merged.unwrap_or(Value::Null))
+}
+
+/// Same as [json_merge3] but conflicts are handed to `resolver` instead of
+/// failing, enabling interactive or policy-driven resolution.
+///
+/// `resolver` receives the conflicting path, the `left`, `right` and
+/// `base` values, each [None] when that side lacks the key (e.g. removed
+/// on one side and changed on the other). The value returned is kept at
+/// the path, [None] leaves the key out.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_merge3_with;
+///
+/// fn main() {
+///     let base = json!({"timeout": 30, "retries": 3});
+///     let left = json!({"timeout": 60});
+///     let right = json!({"timeout": 45, "retries": 5});
+///     let mut conflicts = vec![];
+///     let merged = json_merge3_with(Some(&base), &left, &right, |path, left, right, _base| {
+///         conflicts.push(path.to_string());
+///         match (left, right) {
+///             (Some(left), Some(right)) if left.as_i64() > right.as_i64() => Some(left.clone()),
+///             (left, right) => right.or(left).cloned(),
+///         }
+///     });
+///     assert_eq!(merged, json!({"timeout": 60, "retries": 5}));
+///     assert_eq!(conflicts, vec!["timeout", "retries"]);
+/// }
+/// ```
+pub fn json_merge3_with<F>(base: Option<&Value>, left: &Value, right: &Value, mut resolver: F) -> Value
+where
+    F: FnMut(&str, Option<&Value>, Option<&Value>, Option<&Value>) -> Option<Value>,
+{
+    let mut resolve = |path: &str, left: Option<&Value>, right: Option<&Value>, base: Option<&Value>| {
+        Ok::<_, Infallible>(resolver(path, left, right, base))
+    };
+    match merge(base, Some(left), Some(right), &[], &mut resolve) {
+        Ok(merged) => merged.unwrap_or(Value::Null),
+        Err(never) => match never {},
+    }
+}
+
+/// Deep merges `src` into `dst`, e.g. to layer configuration overlays.
+///
+/// Objects are merged key by key, arrays present on both sides are combined
+/// according to [MergeStrategy::arrays] and `null`s of `src` are handled
+/// according to [MergeStrategy::nulls]. Any other value of `src` replaces
+/// the one of `dst`.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_merge_into, ArrayMerge, MergeStrategy, NullMerge};
+///
+/// fn main() {
+///     let mut config = json!({"hosts": ["a"], "log": {"level": "info", "file": "app.log"}});
+///     let overlay = json!({"hosts": ["b"], "log": {"level": "debug", "file": null}});
+///     let strategy = MergeStrategy::new()
+///         .arrays(ArrayMerge::Concat)
+///         .nulls(NullMerge::Delete);
+///     json_merge_into(&mut config, &overlay, &strategy);
+///     assert_eq!(config, json!({"hosts": ["a", "b"], "log": {"level": "debug"}}));
+/// }
+/// ```
+pub fn json_merge_into(dst: &mut Value, src: &Value, strategy: &MergeStrategy) {
+    if let (true, Value::Object(src_map)) = (dst.is_object(), src) {
+        for (key, value) in src_map {
+            merge_key(dst, key, value, strategy);
+        }
+        return;
+    }
+    match (dst, src) {
+        (Value::Array(dst_items), Value::Array(src_items)) => match strategy.get_arrays() {
+            ArrayMerge::Replace => *dst_items = src_items.clone(),
+            ArrayMerge::Concat => dst_items.extend(src_items.iter().cloned()),
+            ArrayMerge::ByIndex => {
+                for (idx, value) in src_items.iter().enumerate() {
+                    match dst_items.get_mut(idx) {
+                        Some(existing) => json_merge_into(existing, value, strategy),
+                        None => dst_items.push(value.clone()),
+                    }
+                }
+            }
+        },
+        (_, Value::Null) if strategy.get_nulls() != NullMerge::Set => {}
+        (dst, src) => *dst = src.clone(),
+    }
+}
+
+/// Merges the keys of `object` into the object at the token-based [str]
+/// path, creating it (and any missing parent object) when absent.
+///
+/// [MergeDepth::Shallow] overwrites every key of `object`, while
+/// [MergeDepth::Deep] merges objects present on both sides key by key.
+/// Fails with [NestacError::TypeMismatch] when the path holds anything but
+/// an object, and with the reason the path could not be resolved
+/// otherwise; `data` is left untouched on failure.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_merge_at, MergeDepth};
+///
+/// fn main() {
+///     let mut config = json!({"server": {"host": "localhost", "tls": {"cert": "a.pem"}}});
+///     let overlay = json!({"port": 8080, "tls": {"key": "a.key"}});
+///     let overlay = overlay.as_object().unwrap();
+///
+///     let mut shallow = config.clone();
+///     json_merge_at(&mut shallow, "server", None, overlay.clone(), MergeDepth::Shallow).unwrap();
+///     assert_eq!(shallow, json!({"server": {"host": "localhost", "port": 8080, "tls": {"key": "a.key"}}}));
+///
+///     json_merge_at(&mut config, "server", None, overlay.clone(), MergeDepth::Deep).unwrap();
+///     assert_eq!(config["server"]["tls"], json!({"cert": "a.pem", "key": "a.key"}));
+///
+///     json_merge_at(&mut config, "logging", None, overlay.clone(), MergeDepth::Deep).unwrap();
+///     assert_eq!(config["logging"]["port"], json!(8080));
+/// }
+/// ```
+pub fn json_merge_at(data: &mut Value, path: &str, separator: Option<&str>, object: Map<String, Value>, depth: MergeDepth) -> Result<(), NestacError> {
+    nested::merge_at(data, path, separator, object, depth)
+}
+
+/// Merges `value` into the `key` of the object `dst`.
+fn merge_key(dst: &mut Value, key: &str, value: &Value, strategy: &MergeStrategy) {
+    match (value, strategy.get_nulls()) {
+        (Value::Null, NullMerge::Skip) => {}
+        (Value::Null, NullMerge::Delete) => {
+            dst.remove_key(key);
+        }
+        _ => match dst.get_key_mut(key) {
+            Some(existing) => json_merge_into(existing, value, strategy),
+            None => {
+                let mut node = fresh(value);
+                json_merge_into(&mut node, value, strategy);
+                dst.insert_key(key, node).ok();
+            }
+        },
+    }
+}
+
+/// Empty container to merge `value` into when the destination lacks it, so
+/// `null`s nested in new objects are handled too.
+fn fresh(value: &Value) -> Value {
+    match value {
+        Value::Object(_) => Value::Object(Map::new()),
+        _ => Value::Null,
+    }
+}
+
+fn conflict(path: &str, _: Option<&Value>, _: Option<&Value>, _: Option<&Value>) -> Result<Option<Value>, NestacError> {
+    Err(NestacError::Conflict { path: path.to_string() })
+}
+
+/// Merges the values found at `path`, handing conflicts to `resolver`.
+fn merge<E, R>(
+    base: Option<&Value>,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    path: &[String],
+    resolver: &mut R,
+) -> Result<Option<Value>, E>
+where
+    R: FnMut(&str, Option<&Value>, Option<&Value>, Option<&Value>) -> Result<Option<Value>, E>,
+{
+    if left == right || right == base {
+        return Ok(left.cloned());
+    }
+    if left == base {
+        return Ok(right.cloned());
+    }
+    let (left_map, right_map) = match (left, right) {
+        (Some(Value::Object(left)), Some(Value::Object(right))) => (left, right),
+        _ => return resolver(&path.join("."), left, right, base),
+    };
+    let base_map = base.and_then(Value::as_object);
+    let mut merged = Map::new();
+    let right_only = right_map.keys().filter(|key| !left_map.contains_key(*key));
+    let keys = left_map.keys().chain(right_only);
+    for key in keys {
+        let mut child_path = path.to_vec();
+        child_path.push(escape_key(key, ".").into_owned());
+        let child = merge(
+            base_map.and_then(|map| map.get(key)),
+            left_map.get(key),
+            right_map.get(key),
+            &child_path,
+            resolver,
+        )?;
+        if let Some(child) = child {
+            merged.insert(key.clone(), child);
+        }
+    }
+    Ok(Some(Value::Object(merged)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge3_removal_against_change_is_a_conflict() {
+        let base = json!({"a": {"b": 1, "c": 2}});
+        let left = json!({"a": {"c": 2}});
+        let right = json!({"a": {"b": 5, "c": 2}});
+        let err = json_merge3(&base, &left, &right).unwrap_err();
+        assert_eq!(err, NestacError::Conflict { path: "a.b".to_string() });
+
+        let mut seen = vec![];
+        let merged = json_merge3_with(Some(&base), &left, &right, |path, left, right, base| {
+            seen.push((path.to_string(), left.cloned(), right.cloned(), base.cloned()));
+            None
+        });
+        assert_eq!(merged, json!({"a": {"c": 2}}));
+        assert_eq!(seen, vec![("a.b".to_string(), None, Some(json!(5)), Some(json!(1)))]);
+    }
+
+    #[test]
+    fn merge3_takes_one_sided_changes() {
+        let base = json!({"list": [1, 2], "keep": "x", "drop": true});
+        let left = json!({"list": [1, 2, 3], "keep": "x", "drop": true, "new": 1});
+        let right = json!({"list": [1, 2], "keep": "x"});
+        let merged = json_merge3(&base, &left, &right).unwrap();
+        assert_eq!(merged, json!({"list": [1, 2, 3], "keep": "x", "new": 1}));
+    }
+
+    #[test]
+    fn conflicting_paths_are_escaped() {
+        let left = json!({"hosts": {"10.0.0.1": "a"}});
+        let right = json!({"hosts": {"10.0.0.1": "b"}});
+        let err = json_merge(&left, &right).unwrap_err();
+        assert_eq!(err, NestacError::Conflict { path: "hosts.\"10.0.0.1\"".to_string() });
+    }
+
+    #[test]
+    fn merge_at_refuses_non_objects() {
+        let mut data = json!({"server": {"port": 80}, "hosts": ["a"]});
+        let overlay = json!({"port": 8080}).as_object().cloned().unwrap();
+        let err = json_merge_at(&mut data, "hosts", None, overlay.clone(), MergeDepth::Shallow).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { ref found, .. } if found == "array"));
+        let err = json_merge_at(&mut data, "server.port.x", None, overlay, MergeDepth::Shallow).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { index: 2, .. }));
+        assert_eq!(data, json!({"server": {"port": 80}, "hosts": ["a"]}));
+    }
+
+    #[test]
+    fn merge_into_by_index_skipping_nulls() {
+        let mut dst = json!({"servers": [{"port": 80, "tls": true}], "name": "app"});
+        let src = json!({
+            "servers": [{"port": 8080, "tls": null}, {"port": 9090}],
+            "name": null,
+            "extra": {"a": null, "b": 1}
+        });
+        let strategy = MergeStrategy::new()
+            .arrays(ArrayMerge::ByIndex)
+            .nulls(NullMerge::Skip);
+        json_merge_into(&mut dst, &src, &strategy);
+        assert_eq!(dst, json!({
+            "servers": [{"port": 8080, "tls": true}, {"port": 9090}],
+            "name": "app",
+            "extra": {"b": 1}
+        }));
+    }
+}
@@ -1,7 +1,14 @@
+use std::io::{self, Write};
 use serde_json::Value;
+use crate::nested;
+use crate::options::PathsOptions;
 
 /// Returns a [Vec] containing [String]s representing possible paths
 /// on JSON data
+///
+/// Paths come in pre-order: the root first, then every node right before
+/// its children, with keys in the object's iteration order and array
+/// elements by index. The order is stable, so the output can be diffed.
 /// 
 /// Examples:
 /// ```rust
@@ -33,29 +40,150 @@ use serde_json::Value;
 /// }
 /// ```
 pub fn json_get_paths(data: &Value, symbol: Option<String>) -> Vec<String> {
-    let symbol = symbol.unwrap_or("$".to_string());
-    let mut ret: Vec<String> = vec![];
-    if data.is_object() {
-        ret.push(symbol.clone());
-        for key_s in data.as_object().unwrap().keys() {
-            let child = data.as_object().unwrap().get(key_s).unwrap();
-            for path in json_get_paths(child, Some(key_s.to_string())) {
-                ret.push([symbol.clone(), path].join("."));
-            }
-        }
-    }
-    else if data.is_array() {
-        ret.push(symbol.clone());
-        for (i, child) in data.as_array().unwrap().iter().enumerate() {
-            for path in json_get_paths(&child, Some(i.to_string())) {
-                ret.push([symbol.clone(), path].join("."));
-            }
-        }
-    }
-    else {
-        ret.push(symbol.clone());
-    }
-    return ret;
+    nested::get_paths(data, symbol)
+}
+
+/// Same as [json_get_paths] with the format, filters and [crate::Order]
+/// taken from `options` (see [PathsOptions]): `leaves_only` keeps the
+/// terminal values only, `max_depth` the first levels and
+/// [crate::Order::PostOrder] lists children before their parent, e.g. to
+/// delete paths bottom-up.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_get_paths_with, Order, PathsOptions};
+///
+/// fn main() {
+///     let json_data = json!({"foo": {"bar": [1]}});
+///     let options = PathsOptions::new().order(Order::PostOrder);
+///     assert_eq!(
+///         json_get_paths_with(&json_data, &options),
+///         vec!["$.foo.bar.0", "$.foo.bar", "$.foo", "$"],
+///     );
+/// }
+/// ```
+pub fn json_get_paths_with(data: &Value, options: &PathsOptions) -> Vec<String> {
+    nested::get_paths_with(data, options)
+}
+
+/// Returns a lazy iterator over the paths [json_get_paths_with] would
+/// return, in the same order, so the paths of a huge document can be
+/// filtered or cut short without building the whole list.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_iter_paths, PathsOptions};
+///
+/// fn main() {
+///     let json_data = json!({"users": [{"name": "a"}, {"name": "b"}], "version": 1});
+///     let options = PathsOptions::new();
+///     let first_name = json_iter_paths(&json_data, &options).find(|path| path.ends_with(".name"));
+///     assert_eq!(first_name.as_deref(), Some("$.users.0.name"));
+/// }
+/// ```
+pub fn json_iter_paths<'a>(data: &'a Value, options: &'a PathsOptions) -> nested::Paths<'a, Value> {
+    nested::iter_paths(data, options)
+}
+
+/// Returns a lazy iterator over every path along with the [Value] it
+/// addresses, in the same format and order as [json_iter_paths], saving
+/// a read per path.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_entries, PathsOptions};
+///
+/// fn main() {
+///     let json_data = json!({"db": {"host": "db1", "port": 5432}});
+///     let options = PathsOptions::new().symbol("").brackets(true);
+///     let numbers: Vec<(String, u64)> = json_entries(&json_data, &options)
+///         .filter_map(|(path, value)| value.as_u64().map(|number| (path, number)))
+///         .collect();
+///     assert_eq!(numbers, vec![("db.port".to_string(), 5432)]);
+/// }
+/// ```
+pub fn json_entries<'a>(data: &'a Value, options: &'a PathsOptions) -> nested::Entries<'a, Value> {
+    nested::entries(data, options)
+}
+
+/// Returns the path of every [Value] for which `predicate` holds, the
+/// reverse lookup of [crate::json_read]. The root itself is not
+/// considered.
+///
+/// Paths come in document order and in the syntax of [crate::json_read],
+/// so they can be read or updated directly.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_find_paths_where;
+///
+/// fn main() {
+///     let json_data = json!({
+///         "db": {"password": "CHANGEME", "replicas": [{"password": "CHANGEME"}]},
+///         "admin": {"password": "s3cr3t"},
+///     });
+///     let mut paths = json_find_paths_where(&json_data, |value| value == "CHANGEME");
+///     paths.sort();
+///     assert_eq!(paths, vec!["db.password", "db.replicas.[0].password"]);
+/// }
+/// ```
+pub fn json_find_paths_where<F: Fn(&Value) -> bool>(data: &Value, predicate: F) -> Vec<String> {
+    nested::find_paths_where(data, predicate)
+}
+
+/// Returns the paths of the nodes matched by the glob-like `pattern`,
+/// without listing the whole document.
+///
+/// Any token of [crate::json_read_all] can be used: `*` for every child,
+/// `[*]` for every array element and `**` for every descendant, so a
+/// prefix is written `spec.**`. Paths come back in the syntax of
+/// [crate::json_read] (indices as `[n]`, keys escaped when needed, no
+/// symbol) and can be read or updated directly.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_get_paths_matching;
+///
+/// fn main() {
+///     let json_data = json!({"spec": {"containers": [
+///         {"name": "web", "image": "nginx"},
+///         {"name": "db", "image": "postgres"},
+///     ]}});
+///     assert_eq!(
+///         json_get_paths_matching(&json_data, "spec.containers.*.image", None),
+///         vec!["spec.containers.[0].image", "spec.containers.[1].image"],
+///     );
+///     assert_eq!(json_get_paths_matching(&json_data, "spec.**", None).len(), 7);
+///     assert!(json_get_paths_matching(&json_data, "status.**", None).is_empty());
+/// }
+/// ```
+pub fn json_get_paths_matching(data: &Value, pattern: &str, separator: Option<&str>) -> Vec<String> {
+    nested::get_paths_matching(data, pattern, separator)
+}
+
+/// Streams the paths [json_get_paths_with] would return to `writer` (a file,
+/// stdout, ...), one per line, without building a [Vec], so every path of
+/// a huge document can be dumped in constant extra memory.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_write_paths, PathsOptions};
+///
+/// fn main() {
+///     let json_data = json!({"foo": {"bar": [1]}});
+///     let mut out: Vec<u8> = vec![];
+///     json_write_paths(&json_data, &mut out, &PathsOptions::new()).unwrap();
+///     assert_eq!(String::from_utf8(out).unwrap(), "$\n$.foo\n$.foo.bar\n$.foo.bar.0\n");
+/// }
+/// ```
+pub fn json_write_paths<W: Write>(data: &Value, writer: &mut W, options: &PathsOptions) -> io::Result<()> {
+    nested::write_paths(data, writer, options)
 }
 
 #[cfg(test)]
@@ -108,4 +236,50 @@ mod tests {
         assert_eq!(paths[5], "$.hello.0");
         assert_eq!(paths[6], "$.hello.1");
     }
+
+    #[test]
+    fn post_order_reverses_parents_only() {
+        let json_str = r#"{"a": {"b": 1, "c": [2, 3]}, "d": 4}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        let data = json_data.as_ref().unwrap();
+        let options = PathsOptions::new().symbol("#").order(crate::Order::PostOrder);
+        let paths = json_get_paths_with(data, &options);
+        assert_eq!(paths, vec!["#.a.b", "#.a.c.0", "#.a.c.1", "#.a.c", "#.a", "#.d", "#"]);
+
+        let mut out: Vec<u8> = vec![];
+        json_write_paths(data, &mut out, &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), paths.join("\n") + "\n");
+    }
+
+    #[test]
+    fn bracket_paths_round_trip() {
+        let json_str = r#"{"a": [{"b/c": 1}, [2]], "[0]": {"*": true}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        let data = json_data.as_ref().unwrap();
+        let options = PathsOptions::new().symbol("").separator("/").brackets(true);
+        let paths = json_get_paths_with(data, &options);
+        assert_eq!(paths.len(), 7);
+        assert!(paths.contains(&"a/[0]/\"b/c\"".to_string()));
+        assert!(paths.contains(&"a/[1]/[0]".to_string()));
+        assert!(paths.contains(&"\"[0]\"/\"*\"".to_string()));
+        for path in &paths {
+            assert!(crate::json_read(path, data, Some("/")).is_some(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn entries_hold_the_value_at_each_path() {
+        let json_str = r#"{"a": [{"b": 1}, 2], "c": {"d": null}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        let data = json_data.as_ref().unwrap();
+        let options = PathsOptions::new().symbol("").brackets(true).order(crate::Order::PostOrder);
+        let entries: Vec<(String, &Value)> = json_entries(data, &options).collect();
+        assert_eq!(
+            entries.iter().map(|(path, _)| path.clone()).collect::<Vec<String>>(),
+            json_get_paths_with(data, &options),
+        );
+        for (path, value) in entries {
+            assert_eq!(crate::json_read(&path, data, None), Some(value));
+        }
+    }
 }
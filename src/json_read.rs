@@ -1,5 +1,9 @@
+use std::collections::HashMap;
 use serde_json::Value;
-use regex::Regex;
+use serde::de::DeserializeOwned;
+use crate::error::NestacError;
+use crate::options::ReadOptions;
+use crate::nested;
 
 /// Return a [Value] based off the token-based [str] path.
 /// 
@@ -31,26 +35,227 @@ use regex::Regex;
 ///     assert_eq!(val.unwrap(), "bingo!");
 /// }
 /// ```
+/// - Reading a key holding the token-separator, quoted or escaped
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::json_read;
+///
+/// fn main() {
+///     let json_str = r#"{"hosts": {"192.168.0.1": {"port": 22}}}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let val = json_read(r#"hosts."192.168.0.1".port"#, &json_data, None);
+///     assert_eq!(val.unwrap(), 22);
+///     let val = json_read(r"hosts.192\.168\.0\.1.port", &json_data, None);
+///     assert_eq!(val.unwrap(), 22);
+/// }
+/// ```
+/// - Reading the element of an array holding a given key-value pair:
+///   `[key=value]`
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::json_read;
+///
+/// fn main() {
+///     let key_path = "containers.[name=app].image";
+///     let json_str = r#"{"containers": [{"name": "proxy"}, {"name": "app", "image": "app:1.0"}]}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let val: Option<&Value> = json_read(key_path, &json_data, None);
+///     assert_eq!(val.unwrap(), "app:1.0");
+///     let val = json_read("containers.[?(@.name == 'app')].image", &json_data, None);
+///     assert_eq!(val.unwrap(), "app:1.0");
+/// }
+/// ```
 pub fn json_read<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Option<&'a Value> {
-    let tokens = path.split(separator.unwrap_or(".")).collect::<Vec<&str>>();
-    let re_vec_idx = Regex::new(r"^\[(\d+)\]$").unwrap();
-    let mut sel_data = Some(data);
-
-    for token in tokens {
-        let vec_idx = match re_vec_idx.captures(token) {
-            Some(cap) => Some(cap[1].parse::<usize>().unwrap()),
-            _ => None,
-        };
-        sel_data = match sel_data {
-            Some(value) => match vec_idx {
-                Some(idx) => value.get(idx),
-                None => value.get(token),
-            },
-            None => None,
-        };
-    }
+    nested::read(path, data, separator)
+}
+
+/// Same as [json_read] but configured through [ReadOptions].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::{json_read_with, ReadOptions};
+///
+/// fn main() {
+///     let json_str = r#"{"foo": {"bar": "bingo!"}}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let options = ReadOptions::new().separator("@");
+///     let val: Option<&Value> = json_read_with("foo@bar", &json_data, &options);
+///     assert_eq!(val.unwrap(), "bingo!");
+/// }
+/// ```
+pub fn json_read_with<'a>(path: &str, data: &'a Value, options: &ReadOptions) -> Option<&'a Value> {
+    let separator = options.get_separator();
+    let path = nested::with_id_key(path, separator, options.get_id_key());
+    json_read(&path, data, Some(separator))
+}
+
+/// Return a mutable reference to the [Value] at the token-based [str] path,
+/// to modify a subtree in place instead of replacing it.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_read_mut;
+///
+/// fn main() {
+///     let mut json_data = json!({"foo": {"tags": ["a"]}});
+///     if let Some(Value::Array(tags)) = json_read_mut("foo.tags", &mut json_data, None) {
+///         tags.push(Value::from("b"));
+///     }
+///     assert_eq!(json_data, json!({"foo": {"tags": ["a", "b"]}}));
+/// }
+/// ```
+pub fn json_read_mut<'a>(path: &str, data: &'a mut Value, separator: Option<&str>) -> Option<&'a mut Value> {
+    nested::read_mut(path, data, separator)
+}
+
+/// Returns a copy of the elements addressed by a path ending with a
+/// `[start:end]` token, as a new array [Value].
+///
+/// Slices work as in Python: both bounds are optional, negative ones count
+/// from the end of the array and out of range ones are clamped. Returns
+/// [None] when the path does not end with a slice or does not lead to an
+/// array. Updating such a path replaces the range, see [crate::json_update].
+///
+/// A slice addresses several elements, so [json_read] gives [None] for it
+/// and [json_try_read] fails with [NestacError::InvalidPath], while
+/// [json_read_all] returns each element of the range along with its index.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{json_read, json_read_all, json_read_slice, json_try_read, json_update, NestacError};
+///
+/// fn main() {
+///     let mut json_data = json!({"log": {"entries": ["a", "b", "c", "d"]}});
+///     assert_eq!(json_read_slice("log.entries.[0:2]", &json_data, None), Some(json!(["a", "b"])));
+///     assert_eq!(json_read_slice("log.entries.[-1:]", &json_data, None), Some(json!(["d"])));
+///     assert_eq!(json_read_slice("log.entries.[3:1]", &json_data, None), Some(json!([])));
+///     assert_eq!(json_read_slice("log.[0:1]", &json_data, None), None);
+///
+///     assert_eq!(json_read("log.entries.[0:2]", &json_data, None), None);
+///     assert!(matches!(
+///         json_try_read("log.entries.[0:2]", &json_data, None),
+///         Err(NestacError::InvalidPath { .. }),
+///     ));
+///     let found = json_read_all("log.entries.[1:3]", &json_data, None);
+///     assert_eq!(found, vec![
+///         ("log.entries.[1]".to_string(), &json!("b")),
+///         ("log.entries.[2]".to_string(), &json!("c")),
+///     ]);
+///
+///     let old_val = json_update(&mut json_data, "log.entries.[1:3]", None, json!(["x"]));
+///     assert_eq!(old_val, Some(json!(["b", "c"])));
+///     assert_eq!(json_data, json!({"log": {"entries": ["a", "x", "d"]}}));
+/// }
+/// ```
+pub fn json_read_slice(path: &str, data: &Value, separator: Option<&str>) -> Option<Value> {
+    nested::read_slice(path, data, separator)
+}
+
+/// Consumes the [Value] returning the one found at the token-based [str]
+/// path, without cloning it. When the path cannot be resolved the [Value]
+/// is given back along with a [NestacError].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_extract;
+///
+/// fn main() {
+///     let json_data = json!({"payload": {"items": [1, 2, 3]}, "meta": {}});
+///     let items: Value = json_extract(json_data, "payload.items", None).unwrap();
+///     assert_eq!(items, json!([1, 2, 3]));
+///
+///     let json_data = json!({"payload": {}});
+///     let (json_data, err) = json_extract(json_data, "payload.items", None).unwrap_err();
+///     assert_eq!(json_data, json!({"payload": {}}));
+///     assert_eq!(err.to_string(), "missing key `items`");
+/// }
+/// ```
+// The document is handed back as is, boxing it would defeat the purpose.
+#[allow(clippy::result_large_err)]
+pub fn json_extract(data: Value, path: &str, separator: Option<&str>) -> Result<Value, (Value, NestacError)> {
+    nested::extract(data, path, separator)
+}
+
+/// Return a [Value] based off the token-based [str] path or a [NestacError]
+/// describing which token could not be resolved.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::{json_try_read, NestacError};
+///
+/// fn main() {
+///     let json_str = r#"{"foo": {"bar": "bingo!"}}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     assert_eq!(json_try_read("foo.bar", &json_data, None).unwrap(), "bingo!");
+///     match json_try_read("foo.baz", &json_data, None) {
+///         Err(NestacError::MissingKey { segment, index, .. }) => {
+///             assert_eq!(segment, "baz");
+///             assert_eq!(index, 1);
+///         }
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub fn json_try_read<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Result<&'a Value, NestacError> {
+    nested::try_read(path, data, separator)
+}
+
+/// Returns the [Value] addressed by each path, walking `data` once.
+///
+/// Same as calling [json_read] for every path, but shared path prefixes are
+/// traversed only once. See [crate::nested::read_many].
+pub fn json_read_many<'a>(paths: &[&str], data: &'a Value, separator: Option<&str>) -> HashMap<String, Option<&'a Value>> {
+    nested::read_many(paths, data, separator)
+}
 
-    return sel_data;
+/// Return every [Value] matched by the token-based [str] path, along with
+/// its concrete path.
+///
+/// See [crate::nested::read_all] for the wildcard tokens: `*`, `[*]`,
+/// `[*key=value]`, `**` and `[start:end]`.
+pub fn json_read_all<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Vec<(String, &'a Value)> {
+    nested::read_all(path, data, separator)
+}
+
+/// Deserializes the [Value] found at the token-based [str] path into `T`.
+///
+/// When deserialization fails the returned [NestacError::Deserialize]
+/// carries the full path to the offending field: the read path followed by
+/// the path inside the subtree, using the same separator.
+///
+/// # Examples:
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_json::Value;
+/// use nestac::{json_read_as, NestacError};
+///
+/// #[derive(Deserialize)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// fn main() {
+///     let json_str = r#"{"servers": [{"port": 80}, {"port": "80"}]}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let server: Server = json_read_as("servers.[0]", &json_data, None).unwrap();
+///     assert_eq!(server.port, 80);
+///     let err = json_read_as::<Vec<Server>>("servers", &json_data, None);
+///     match err {
+///         Err(NestacError::Deserialize { path, .. }) => {
+///             assert_eq!(path, "servers.[1].port");
+///         }
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub fn json_read_as<T: DeserializeOwned>(path: &str, data: &Value, separator: Option<&str>) -> Result<T, NestacError> {
+    let value = json_try_read(path, data, separator)?;
+    nested::deserialize_at(path, separator, value)
 }
 
 #[cfg(test)]
@@ -137,4 +342,96 @@ mod tests {
         assert_eq!(val.is_none(), false);
         assert_eq!(val.unwrap(), "bingo!");
     }
+
+    #[test]
+    fn try_read_reports_failing_token() {
+        let json_keypath = "foo.[0].baz";
+        let json_separator: Option<&str> = None;
+        let json_str = r#"{"foo": [{"bar": "bingo!"}]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            json_separator,
+        );
+        assert_eq!(err, Err(NestacError::MissingKey {
+            path: "foo.[0].baz".to_string(),
+            segment: "baz".to_string(),
+            index: 2,
+            offset: 8,
+        }));
+    }
+
+    #[test]
+    fn try_read_reports_type_mismatch() {
+        let json_keypath = "foo.[0]";
+        let json_separator: Option<&str> = None;
+        let json_str = r#"{"foo": {"bar": "bingo!"}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let err = json_try_read(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            json_separator,
+        );
+        assert_eq!(err, Err(NestacError::TypeMismatch {
+            path: "foo.[0]".to_string(),
+            segment: "[0]".to_string(),
+            index: 1,
+            offset: 4,
+            expected: "array".to_string(),
+            found: "object".to_string(),
+        }));
+    }
+
+    #[test]
+    fn read_as_struct() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Bar {
+            baz: String,
+            qux: Vec<u8>,
+        }
+
+        let json_keypath = "foo|bar";
+        let json_separator: Option<&str> = Some("|");
+        let json_str = r#"{"foo": {"bar": {"baz": "bingo!", "qux": [1, 2]}}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let val: std::result::Result<Bar, NestacError> = json_read_as(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            json_separator,
+        );
+        assert_eq!(val.unwrap(), Bar {
+            baz: "bingo!".to_string(),
+            qux: vec![1, 2],
+        });
+    }
+
+    #[test]
+    fn read_as_reports_full_path() {
+        #[derive(serde::Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Bar {
+            qux: Vec<u8>,
+        }
+
+        let json_keypath = "foo|bar";
+        let json_separator: Option<&str> = Some("|");
+        let json_str = r#"{"foo": {"bar": {"qux": [1, 300]}}}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let val: std::result::Result<Bar, NestacError> = json_read_as(
+            json_keypath,
+            json_data.as_ref().unwrap(),
+            json_separator,
+        );
+        match val {
+            Err(NestacError::Deserialize { path, .. }) => {
+                assert_eq!(path, "foo|bar|qux|[1]");
+            }
+            _ => panic!("expected a deserialize error"),
+        }
+    }
 }
@@ -0,0 +1,160 @@
+//! Values stored with an expiry, e.g. to keep a cache inside a document.
+//!
+//! An expiring entry is an object holding the value under [VALUE_KEY] and
+//! its expiry, in seconds since the unix epoch, under [EXPIRES_AT_KEY]:
+//!
+//! ```json
+//! {"tokens": {"github": {"value": "abc", "expires_at": 1767225600}}}
+//! ```
+//!
+//! The current time is always passed in, so callers pick the clock.
+use serde_json::{json, Value};
+use crate::nested::escape_key;
+use crate::{json_read, json_upsert, NestacError};
+
+/// Key holding the value of an expiring entry.
+pub const VALUE_KEY: &str = "value";
+
+/// Key holding the expiry of an entry, in seconds since the unix epoch.
+pub const EXPIRES_AT_KEY: &str = "expires_at";
+
+/// Expiry of `node` when it is an expiring entry.
+fn expires_at(node: &Value) -> Option<i64> {
+    let entry = node.as_object()?;
+    if !entry.contains_key(VALUE_KEY) {
+        return None;
+    }
+    entry.get(EXPIRES_AT_KEY)?.as_i64()
+}
+
+/// Writes `value` at the token-based [str] path as an entry expiring at
+/// `expires_at`, creating missing parent objects, and returns the node it
+/// replaced.
+///
+/// Same failures as [crate::json_upsert].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_write_expiring;
+///
+/// fn main() {
+///     let mut cache = json!({});
+///     json_write_expiring(&mut cache, "tokens.github", None, json!("abc"), 1_000).unwrap();
+///     assert_eq!(cache, json!({"tokens": {"github": {"value": "abc", "expires_at": 1000}}}));
+/// }
+/// ```
+pub fn json_write_expiring(data: &mut Value, path: &str, separator: Option<&str>, value: Value, expires_at: i64) -> Result<Option<Value>, NestacError> {
+    json_upsert(data, path, separator, json!({VALUE_KEY: value, EXPIRES_AT_KEY: expires_at}))
+}
+
+/// Reads the token-based [str] path, returning the value of an expiring
+/// entry while `now` is before its expiry and [None] once it expired.
+///
+/// Nodes that are not expiring entries never expire and are returned as
+/// they are.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_read_unexpired, json_write_expiring};
+///
+/// fn main() {
+///     let mut cache = json!({"static": 1});
+///     json_write_expiring(&mut cache, "token", None, json!("abc"), 1_000).unwrap();
+///     assert_eq!(json_read_unexpired("token", &cache, None, 999), Some(&json!("abc")));
+///     assert_eq!(json_read_unexpired("token", &cache, None, 1_000), None);
+///     assert_eq!(json_read_unexpired("static", &cache, None, 1_000), Some(&json!(1)));
+/// }
+/// ```
+pub fn json_read_unexpired<'a>(path: &str, data: &'a Value, separator: Option<&str>, now: i64) -> Option<&'a Value> {
+    let node = json_read(path, data, separator)?;
+    match expires_at(node) {
+        Some(expiry) if expiry <= now => None,
+        Some(_) => node.get(VALUE_KEY),
+        None => Some(node),
+    }
+}
+
+/// Removes every expiring entry of `data` whose expiry is not after `now`,
+/// and returns their paths.
+///
+/// Paths are joined with `.`, keys are quoted when needed and array
+/// elements written as `[n]`, where `n` is the position of the element
+/// before the sweep.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_sweep_expired;
+///
+/// fn main() {
+///     let mut cache = json!({
+///         "a": {"value": 1, "expires_at": 10},
+///         "b": {"value": 2, "expires_at": 30},
+///         "list": [{"value": 3, "expires_at": 10}, 4]
+///     });
+///     assert_eq!(json_sweep_expired(&mut cache, 20), vec!["a", "list.[0]"]);
+///     assert_eq!(cache, json!({"b": {"value": 2, "expires_at": 30}, "list": [4]}));
+/// }
+/// ```
+pub fn json_sweep_expired(data: &mut Value, now: i64) -> Vec<String> {
+    let mut swept = vec![];
+    let mut path = vec![];
+    sweep(data, now, &mut path, &mut swept);
+    swept
+}
+
+fn sweep(node: &mut Value, now: i64, path: &mut Vec<String>, swept: &mut Vec<String>) {
+    let is_expired = |child: &Value| expires_at(child).is_some_and(|expiry| expiry <= now);
+    match node {
+        Value::Object(map) => {
+            let keys: Vec<String> = map.keys().cloned().collect();
+            for key in keys {
+                path.push(escape_key(&key, ".").into_owned());
+                if is_expired(&map[&key]) {
+                    swept.push(path.join("."));
+                    // `Map::remove` reorders the remaining keys with `preserve_order`
+                    map.retain(|k, _| *k != key);
+                } else if let Some(child) = map.get_mut(&key) {
+                    sweep(child, now, path, swept);
+                }
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            let mut idx = 0;
+            items.retain_mut(|child| {
+                path.push(format!("[{}]", idx));
+                idx += 1;
+                let expired = is_expired(child);
+                if expired {
+                    swept.push(path.join("."));
+                } else {
+                    sweep(child, now, path, swept);
+                }
+                path.pop();
+                !expired
+            });
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_descends_into_live_values() {
+        let mut cache = json!({
+            "10.0.0.1": {"value": {"inner": {"value": 1, "expires_at": 5}}, "expires_at": 50},
+            "plain": {"expires_at": 1},
+            "raw": {"value": 1, "expires_at": "soon"}
+        });
+        assert_eq!(json_sweep_expired(&mut cache, 5), vec!["\"10.0.0.1\".value.inner"]);
+        assert_eq!(cache["10.0.0.1"]["value"], json!({}));
+        assert_eq!(json_sweep_expired(&mut cache, 50), vec!["\"10.0.0.1\""]);
+        assert_eq!(cache, json!({"plain": {"expires_at": 1}, "raw": {"value": 1, "expires_at": "soon"}}));
+    }
+}
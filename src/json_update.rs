@@ -1,7 +1,21 @@
+use std::ops::ControlFlow;
+use serde::Serialize;
 use serde_json::Value;
+use crate::{json_read, nested, NestacError};
+use crate::options::UpdateOptions;
 
 /// Update a [Value] based off the token-based [str] path and returns a clone
 /// of the old [Value] 
+///
+/// `[n]` tokens address array elements, like on [crate::json_read]. Returns
+/// [None] when the path does not exist, or when the last key did not exist
+/// before the update (it is then inserted). Missing array elements are
+/// never created.
+///
+/// A last `[start:end]` token replaces that range of the array with the
+/// elements of `new_value`, which must be an array, see
+/// [crate::json_read_slice]. A last `[+]` token pushes `new_value` onto the
+/// end of the array instead, returning [None].
 /// 
 /// # Examples:
 /// - Updating a JSON data using the default token-separator: `.`
@@ -66,29 +80,352 @@ use serde_json::Value;
 ///     assert_eq!(new_val.unwrap(), "updated!");
 /// }
 /// ```
+/// - Appending to an array without knowing its length
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_update;
+///
+/// fn main() {
+///     let mut json_data = json!({"foo": {"tags": ["a"]}});
+///     let old_val = json_update(&mut json_data, "foo.tags.[+]", None, Value::from("b"));
+///     assert_eq!(old_val, None);
+///     assert_eq!(json_data, json!({"foo": {"tags": ["a", "b"]}}));
+/// }
+/// ```
 pub fn json_update<'a>(data: &'a mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Option<Value> {
-    let mut tokens = path.split(separator.unwrap_or(".")).peekable();
-    let mut sel_data = Some(data);
-    while let Some(token) = tokens.next() {
-        if tokens.peek().is_none() {
-            // last token
-            return sel_data.unwrap()
-                .as_object_mut()
-                .unwrap()
-                .insert(
-                    token.to_string(),
-                    new_value,
-                );
+    nested::update(data, path, separator, new_value)
+}
+
+/// Same as [json_update] but configured through [UpdateOptions].
+///
+/// With [UpdateOptions::keep_string_scalars], a number or boolean written
+/// over a string holding a number or boolean keeps the string
+/// representation.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::{json_update_with, json_read, UpdateOptions};
+///
+/// fn main() {
+///     let json_str = r#"{"networks": {"192.168.0.1": "bingo!"}}"#;
+///     let mut json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let options = UpdateOptions::new().separator("@");
+///     let old_val = json_update_with(
+///         &mut json_data,
+///         "networks@192.168.0.1",
+///         &options,
+///         Value::String("updated!".into()),
+///     );
+///     assert_eq!(old_val.unwrap(), "bingo!");
+///     let new_val = json_read("networks@192.168.0.1", &json_data, Some("@"));
+///     assert_eq!(new_val.unwrap(), "updated!");
+/// }
+/// ```
+/// - Keeping stringly-typed scalars
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{json_update_with, UpdateOptions};
+///
+/// fn main() {
+///     let mut json_data = json!({"port": "8080", "debug": "false"});
+///     let options = UpdateOptions::new().keep_string_scalars(true);
+///     json_update_with(&mut json_data, "port", &options, Value::from(9090));
+///     json_update_with(&mut json_data, "debug", &options, Value::Bool(true));
+///     assert_eq!(json_data, json!({"port": "9090", "debug": "true"}));
+/// }
+/// ```
+pub fn json_update_with(data: &mut Value, path: &str, options: &UpdateOptions, new_value: Value) -> Option<Value> {
+    let separator = Some(options.get_separator());
+    let path = &nested::with_id_key(path, options.get_separator(), options.get_id_key());
+    let new_value = match json_read(path, data, separator) {
+        Some(Value::String(current)) if options.get_keep_string_scalars()
+            && is_scalar(&new_value)
+            && serde_json::from_str::<Value>(current.trim()).is_ok_and(|v| is_scalar(&v)) => {
+            Value::String(new_value.to_string())
         }
-        sel_data = sel_data.unwrap().get_mut(token);
-    }
-    sel_data.cloned()
+        _ => new_value,
+    };
+    json_update(data, path, separator, new_value)
+}
+
+/// Same as [json_update] but returns a [NestacError] describing which token
+/// could not be resolved instead of panicking or doing nothing.
+///
+/// Every token but the last must exist; the last one may be a missing key,
+/// which is inserted (returning `Ok(None)`). `data` is left untouched on
+/// failure.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{json_try_update, NestacError};
+///
+/// fn main() {
+///     let mut json_data = json!({"foo": [{"bar": "bingo!"}]});
+///     let old_val = json_try_update(&mut json_data, "foo.[0].bar", None, Value::from(1));
+///     assert_eq!(old_val, Ok(Some(Value::from("bingo!"))));
+///     match json_try_update(&mut json_data, "foo.[3].bar", None, Value::from(1)) {
+///         Err(NestacError::IndexOutOfBounds { segment, len, .. }) => {
+///             assert_eq!(segment, "[3]");
+///             assert_eq!(len, 1);
+///         }
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub fn json_try_update(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, NestacError> {
+    nested::try_update(data, path, separator, new_value)
+}
+
+/// Update a [Value] based off the token-based [str] path, creating missing
+/// intermediate objects, and returns the old [Value].
+///
+/// Missing array elements are never created: an index out of bounds, or a
+/// token that cannot be traversed (e.g. a key on a string), is reported as
+/// a [NestacError] and leaves `data` untouched.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_upsert;
+///
+/// fn main() {
+///     let mut json_data = json!({});
+///     let old_val = json_upsert(&mut json_data, "foo.bar.baz", None, Value::from(1));
+///     assert_eq!(old_val, Ok(None));
+///     assert_eq!(json_data, json!({"foo": {"bar": {"baz": 1}}}));
+///     assert!(json_upsert(&mut json_data, "foo.bar.baz.qux", None, Value::Null).is_err());
+/// }
+/// ```
+pub fn json_upsert(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, NestacError> {
+    nested::upsert(data, path, separator, new_value)
+}
+
+/// Update every [Value] matched by the token-based [str] path and returns
+/// the concrete path and old [Value] of each of them.
+///
+/// See [crate::nested::update_all]; wildcards work as on
+/// [crate::json_read_all].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_update_all;
+///
+/// fn main() {
+///     let mut json_data = json!({"servers": [{"tls": false}, {"tls": true}]});
+///     let old_vals = json_update_all(&mut json_data, "servers.[*].tls", None, Value::Bool(true));
+///     assert_eq!(old_vals.len(), 2);
+///     assert_eq!(json_data, json!({"servers": [{"tls": true}, {"tls": true}]}));
+/// }
+/// ```
+pub fn json_update_all(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Vec<(String, Option<Value>)> {
+    nested::update_all(data, path, separator, new_value)
+}
+
+/// Applies several updates walking the [Value] once, and returns the old
+/// [Value] of each of them.
+///
+/// Same as calling [json_update] for every `(path, value)` in order, but
+/// shared path prefixes are traversed only once. See
+/// [crate::nested::update_many].
+pub fn json_update_many(data: &mut Value, updates: Vec<(&str, Value)>, separator: Option<&str>) -> Vec<Option<Value>> {
+    nested::update_many(data, updates, separator)
+}
+
+/// Replaces the [Value] at the token-based [str] path with the one derived
+/// by `transform` from it, and returns the old [Value].
+///
+/// Returns [None], without calling `transform`, when the path does not
+/// exist. See [crate::nested::transform].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::json_transform;
+///
+/// fn main() {
+///     let mut json_data = json!({"stats": {"hits": 41}, "name": "app"});
+///     json_transform(&mut json_data, "stats.hits", None, |old| {
+///         Value::from(old.as_i64().unwrap_or(0) + 1)
+///     });
+///     json_transform(&mut json_data, "name", None, |old| {
+///         Value::from(format!("{}-v2", old.as_str().unwrap_or_default()))
+///     });
+///     assert_eq!(json_data, json!({"stats": {"hits": 42}, "name": "app-v2"}));
+///     assert_eq!(json_transform(&mut json_data, "missing", None, |old| old.clone()), None);
+/// }
+/// ```
+pub fn json_transform<F: FnOnce(&Value) -> Value>(data: &mut Value, path: &str, separator: Option<&str>, transform: F) -> Option<Value> {
+    nested::transform(data, path, separator, transform)
+}
+
+/// Creates the missing parent objects and arrays of every path in `paths`,
+/// so later strict updates of those paths cannot fail on a missing parent,
+/// and returns how many were created.
+///
+/// A missing parent is created as an array when the token after it is
+/// `[n]` and as an object otherwise; array elements are only created by
+/// appending (`[n]` where `n` is the length of the array). Parents holding
+/// a different kind of value are reported as [NestacError::TypeMismatch].
+/// Paths are handled in order, see [crate::nested::ensure].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_ensure, json_try_update};
+///
+/// fn main() {
+///     let mut json_data = json!({"a": {"x": 1}});
+///     let created = json_ensure(&mut json_data, &["a.b.c", "a.list.[0].name"], None).unwrap();
+///     assert_eq!(created, 3);
+///     assert_eq!(json_data, json!({"a": {"x": 1, "b": {}, "list": [{}]}}));
+///
+///     json_try_update(&mut json_data, "a.list.[0].name", None, json!("first")).unwrap();
+///     assert_eq!(json_ensure(&mut json_data, &["a.b.c"], None), Ok(0));
+/// }
+/// ```
+pub fn json_ensure(data: &mut Value, paths: &[&str], separator: Option<&str>) -> Result<usize, NestacError> {
+    nested::ensure(data, paths, separator)
+}
+
+/// Calls `visit` on every [Value] with its path, transforming the whole
+/// document in one pass, and stops at the first [ControlFlow::Break].
+///
+/// Nodes are visited in pre-order, the root first with an empty path, and
+/// paths are written as on [crate::json_read]. A node replaced by `visit`
+/// has its new children visited. See [crate::nested::walk_mut].
+///
+/// # Examples:
+/// ```rust
+/// use std::ops::ControlFlow;
+/// use serde_json::{json, Value};
+/// use nestac::json_walk_mut;
+///
+/// fn main() {
+///     let mut json_data = json!({"name": " app ", "db": {"password": "s3cr3t", "hosts": [" a "]}});
+///     let flow: ControlFlow<()> = json_walk_mut(&mut json_data, |path, value| {
+///         if path.ends_with("password") {
+///             *value = Value::from("***");
+///         } else if let Value::String(text) = value {
+///             *text = text.trim().to_string();
+///         }
+///         ControlFlow::Continue(())
+///     });
+///     assert_eq!(flow, ControlFlow::Continue(()));
+///     assert_eq!(json_data, json!({"name": "app", "db": {"password": "***", "hosts": ["a"]}}));
+///
+///     // early exit, with the path of the first number
+///     let mut json_data = json!({"a": "x", "b": [1, 2]});
+///     let found = json_walk_mut(&mut json_data, |path, value| match value.is_number() {
+///         true => ControlFlow::Break(path.to_string()),
+///         false => ControlFlow::Continue(()),
+///     });
+///     assert_eq!(found, ControlFlow::Break("b.[0]".to_string()));
+/// }
+/// ```
+pub fn json_walk_mut<B, F: FnMut(&str, &mut Value) -> ControlFlow<B>>(data: &mut Value, visit: F) -> ControlFlow<B> {
+    nested::walk_mut(data, visit)
+}
+
+/// Flips the boolean at the token-based [str] path and returns its previous
+/// state.
+///
+/// Fails with [NestacError::TypeMismatch] when the path holds anything but a
+/// boolean, so a flag is never written over an object, and with the reason
+/// the path could not be resolved otherwise. `data` is left untouched on
+/// failure. See [crate::nested::set_flag].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_toggle, NestacError};
+///
+/// fn main() {
+///     let mut json_data = json!({"features": {"beta": false, "limits": {"rps": 10}}});
+///     assert_eq!(json_toggle(&mut json_data, "features.beta", None), Ok(false));
+///     assert_eq!(json_data["features"]["beta"], json!(true));
+///
+///     let err = json_toggle(&mut json_data, "features.limits", None).unwrap_err();
+///     assert!(matches!(err, NestacError::TypeMismatch { .. }));
+///     assert_eq!(json_data["features"]["limits"], json!({"rps": 10}));
+/// }
+/// ```
+pub fn json_toggle(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |flag| !flag)
+}
+
+/// Sets the boolean at the token-based [str] path to `true` and returns its
+/// previous state.
+///
+/// Same semantics as [json_toggle].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_enable;
+///
+/// fn main() {
+///     let mut json_data = json!({"features": {"beta": false}});
+///     assert_eq!(json_enable(&mut json_data, "features.beta", None), Ok(false));
+///     assert_eq!(json_enable(&mut json_data, "features.beta", None), Ok(true));
+///     assert!(json_enable(&mut json_data, "features.gamma", None).is_err());
+/// }
+/// ```
+pub fn json_enable(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |_| true)
+}
+
+/// Sets the boolean at the token-based [str] path to `false` and returns
+/// its previous state.
+///
+/// Same semantics as [json_toggle].
+pub fn json_disable(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |_| false)
+}
+
+/// Serializes `value` into a [Value] and writes it at the token-based [str]
+/// path, returning the old [Value].
+///
+/// Same semantics as [json_try_update]. A value that cannot be represented
+/// as JSON (e.g. a map with non-string keys) is reported as
+/// [NestacError::Serialize] and leaves `data` untouched.
+///
+/// # Examples:
+/// ```rust
+/// use serde::Serialize;
+/// use serde_json::json;
+/// use nestac::json_update_with_serializable;
+///
+/// #[derive(Serialize)]
+/// struct Http {
+///     port: u16,
+///     tls: bool,
+/// }
+///
+/// fn main() {
+///     let mut json_data = json!({"server": {"http": null}});
+///     let http = Http { port: 443, tls: true };
+///     json_update_with_serializable(&mut json_data, "server.http", None, &http).unwrap();
+///     assert_eq!(json_data, json!({"server": {"http": {"port": 443, "tls": true}}}));
+/// }
+/// ```
+pub fn json_update_with_serializable<T: Serialize>(data: &mut Value, path: &str, separator: Option<&str>, value: &T) -> Result<Option<Value>, NestacError> {
+    let new_value = serde_json::to_value(value).map_err(|err| NestacError::Serialize {
+        path: path.to_string(),
+        message: err.to_string(),
+    })?;
+    json_try_update(data, path, separator, new_value)
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::Number(_) | Value::Bool(_))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::json_read;
     use string_from::Str;
     use serde_json::Result;
 
@@ -169,4 +506,75 @@ mod tests {
         assert_eq!(new_val.is_none(), false);
         assert_eq!(new_val.unwrap(), "updated!");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keep_string_scalars_only_over_string_scalars() {
+        let json_str = r#"{"port": "8080", "name": "web", "replicas": 2}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let options = UpdateOptions::new().keep_string_scalars(true);
+        for (path, new_value) in [
+            ("port", Value::from(9090)),
+            ("name", Value::from(1)),
+            ("replicas", Value::from(3)),
+        ] {
+            json_update_with(json_data.as_mut().unwrap(), path, &options, new_value);
+        }
+        assert_eq!(
+            json_data.as_ref().unwrap(),
+            &serde_json::json!({"port": "9090", "name": 1, "replicas": 3}),
+        );
+        let old_val = json_update_with(
+            json_data.as_mut().unwrap(),
+            "port",
+            &UpdateOptions::new(),
+            Value::from(80),
+        );
+        assert_eq!(old_val.unwrap(), "9090");
+        assert_eq!(json_data.as_ref().unwrap()["port"], 80);
+    }
+
+    #[test]
+    fn update_inside_array_json() {
+        let json_keypath = "foo.[2].bar";
+        let json_separator: Option<&str> = None;
+        let json_str = r#"{"foo": [{}, {}, {"bar": "bingo!"}]}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let old_val = json_update(
+            json_data.as_mut().unwrap(),
+            json_keypath,
+            json_separator,
+            Value::String(Str!("updated!"))
+        );
+        assert_eq!(old_val.unwrap(), "bingo!");
+        let old_val = json_update(
+            json_data.as_mut().unwrap(),
+            "foo.[0]",
+            json_separator,
+            Value::from(0),
+        );
+        assert_eq!(old_val.unwrap(), serde_json::json!({}));
+        assert!(json_update(json_data.as_mut().unwrap(), "foo.[3].bar", None, Value::Null).is_none());
+        assert!(json_update(json_data.as_mut().unwrap(), "qux.bar", None, Value::Null).is_none());
+        assert_eq!(
+            json_data.unwrap().to_string(),
+            r#"{"foo":[0,{},{"bar":"updated!"}]}"#,
+        );
+    }
+
+    #[test]
+    fn ensure_fails_without_touching_the_path() {
+        let mut json_data = serde_json::json!({"a": {"list": [1], "name": "x"}});
+        let err = json_ensure(&mut json_data, &["b.c.d", "a.name.first"], None).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { index: 2, .. }));
+        assert_eq!(json_data["b"], serde_json::json!({"c": {}}));
+
+        let err = json_ensure(&mut json_data, &["a.list.[3].x"], None).unwrap_err();
+        assert!(matches!(err, NestacError::IndexOutOfBounds { len: 1, .. }));
+        let err = json_ensure(&mut json_data, &["a.new.[2].x"], None).unwrap_err();
+        assert!(matches!(err, NestacError::MissingKey { index: 2, .. }));
+        assert_eq!(json_ensure(&mut json_data, &["a.list.[1].x"], None), Ok(1));
+        assert_eq!(json_data["a"], serde_json::json!({"list": [1, {}], "name": "x"}));
+    }
+}
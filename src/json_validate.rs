@@ -0,0 +1,146 @@
+use serde_json::Value;
+use crate::nested;
+
+/// Returns the paths matched by `pattern` whose value is shared with at
+/// least one other matched path. An empty [Vec] means every value is unique.
+///
+/// `[*]` tokens in the pattern match every element of an array and `*`
+/// tokens every child of a map or array (see [crate::nested::read_all]).
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::json_validate_unique;
+///
+/// fn main() {
+///     let json_str = r#"{"users": [{"id": 1}, {"id": 2}, {"id": 1}]}"#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let violations = json_validate_unique("users.[*].id", &json_data, None);
+///     assert_eq!(violations, vec!["users.[0].id", "users.[2].id"]);
+/// }
+/// ```
+pub fn json_validate_unique(pattern: &str, data: &Value, separator: Option<&str>) -> Vec<String> {
+    let matches = nested::read_all(pattern, data, separator);
+    matches
+        .iter()
+        .filter(|(_, value)| matches.iter().filter(|(_, other)| other == value).count() > 1)
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Returns the paths matched by `pattern` whose value does not exist among
+/// the values matched by `target`. An empty [Vec] means every reference is
+/// satisfied.
+///
+/// Wildcards in both patterns work as on [json_validate_unique].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::Value;
+/// use nestac::json_validate_references;
+///
+/// fn main() {
+///     let json_str = r#"
+///     {
+///         "users": [{"id": 1}, {"id": 2}],
+///         "orders": [{"user_id": 2}, {"user_id": 3}]
+///     }
+///     "#;
+///     let json_data: Value = serde_json::from_str(json_str).unwrap();
+///     let violations = json_validate_references(
+///         "orders.[*].user_id",
+///         "users.[*].id",
+///         &json_data,
+///         None,
+///     );
+///     assert_eq!(violations, vec!["orders.[1].user_id"]);
+/// }
+/// ```
+pub fn json_validate_references(pattern: &str, target: &str, data: &Value, separator: Option<&str>) -> Vec<String> {
+    let targets: Vec<&Value> = nested::read_all(target, data, separator)
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    nested::read_all(pattern, data, separator)
+        .into_iter()
+        .filter(|(_, value)| !targets.contains(value))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Returns the paths of `data` matched by none of the `allowed` patterns,
+/// e.g. typos and stale keys on user-provided configuration. An empty [Vec]
+/// means every path is allowed.
+///
+/// See [crate::nested::unknown_paths].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::json_unknown_paths;
+///
+/// fn main() {
+///     let data = json!({"server": {"port": 80, "hots": "localhost"}});
+///     let unknown = json_unknown_paths(&data, &["server.port", "server.host"], None);
+///     assert_eq!(unknown, vec!["server.hots"]);
+/// }
+/// ```
+pub fn json_unknown_paths(data: &Value, allowed: &[&str], separator: Option<&str>) -> Vec<String> {
+    nested::unknown_paths(data, allowed, separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Result;
+
+    #[test]
+    fn unique_values_have_no_violations() {
+        let json_str = r#"{"users": [{"id": 1}, {"id": 2}, {"id": 3}]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let violations = json_validate_unique(
+            "users.[*].id",
+            json_data.as_ref().unwrap(),
+            None,
+        );
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn duplicated_values_are_reported() {
+        let json_str = r#"{"users": [{"id": "a"}, {"id": "b"}, {"id": "a"}]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let violations = json_validate_unique(
+            "users|[*]|id",
+            json_data.as_ref().unwrap(),
+            Some("|"),
+        );
+        assert_eq!(violations, vec!["users|[0]|id", "users|[2]|id"]);
+    }
+
+    #[test]
+    fn dangling_references_are_reported() {
+        let json_str = r#"
+            {
+                "users": [{"id": 1}, {"id": 2}],
+                "orders": [
+                    {"user_id": 1},
+                    {"user_id": 4},
+                    {"user_id": 2},
+                    {"user_id": 5}
+                ]
+            }
+        "#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let violations = json_validate_references(
+            "orders.[*].user_id",
+            "users.[*].id",
+            json_data.as_ref().unwrap(),
+            None,
+        );
+        assert_eq!(violations, vec!["orders.[1].user_id", "orders.[3].user_id"]);
+    }
+}
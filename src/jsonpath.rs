@@ -0,0 +1,598 @@
+//! [JSONPath](https://www.rfc-editor.org/rfc/rfc9535) queries over
+//! [Value]s.
+//!
+//! Supported: the root `$`, child names (`.name`, `['name']`), wildcards
+//! (`.*`, `[*]`), indices (`[0]`, `[-1]`), slices (`[1:5:2]`), unions
+//! (`[0,'a']`), descendants (`..name`, `..[0]`, `..*`) and filters
+//! (`[?(@.price < 10 && @.isbn)]`) comparing the current node (`@`) or the
+//! root (`$`) with literals using `==`, `!=`, `<`, `<=`, `>` and `>=`,
+//! combined with `&&`, `||`, `!` and parentheses. Function extensions are
+//! not supported.
+//!
+//! Every match comes with its path written as on [crate::json_read], so
+//! selectors can be turned into paths usable with the rest of the crate.
+use std::cmp::Ordering;
+use serde_json::Value;
+use crate::nested::escape_key;
+use crate::NestacError;
+
+/// Parsed JSONPath expression, see [crate::jsonpath].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    /// `..`: applies the selectors to the node and all its descendants.
+    descendants: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Or(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Exists(Query),
+    Compare(Operand, Comparison, Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Query(Query),
+    Literal(Value),
+}
+
+/// `@...` or `$...` within a filter.
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Parses a JSONPath expression, failing with
+    /// [NestacError::InvalidPath] on anything unsupported.
+    pub fn parse(expression: &str) -> Result<Self, NestacError> {
+        let mut parser = Parser { source: expression, chars: expression.chars().collect(), pos: 0 };
+        parser.skip_whitespace();
+        parser.expect('$')?;
+        let segments = parser.segments()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(JsonPath { segments }),
+            Some(c) => Err(parser.error(format!("unexpected `{}`", c))),
+        }
+    }
+
+    /// Returns the nodes of `data` matched by the expression, in document
+    /// order, along with their paths (empty for the root).
+    pub fn query<'a>(&self, data: &'a Value) -> Vec<(String, &'a Value)> {
+        select(&self.segments, vec![(String::new(), data)], data)
+    }
+}
+
+/// Parses `expression` as JSONPath (see [crate::jsonpath]) and returns
+/// the matched nodes of `data` along with their paths.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::{json_path_query, json_read};
+///
+/// fn main() {
+///     let json_data = json!({"store": {"book": [
+///         {"title": "Sayings", "price": 8.95},
+///         {"title": "Moby Dick", "price": 22.99},
+///         {"title": "Sword", "price": 8.99, "isbn": "0-553"},
+///     ]}});
+///     let matches = json_path_query("$.store.book[?(@.price < 10)].title", &json_data).unwrap();
+///     let paths: Vec<&str> = matches.iter().map(|(path, _)| path.as_str()).collect();
+///     assert_eq!(paths, vec!["store.book.[0].title", "store.book.[2].title"]);
+///     assert_eq!(json_read(paths[1], &json_data, None), Some(&json!("Sword")));
+///
+///     let matches = json_path_query("$..book[?(@.isbn)]['title']", &json_data).unwrap();
+///     assert_eq!(matches, vec![("store.book.[2].title".to_string(), &json!("Sword"))]);
+///     assert!(json_path_query("$.store[", &json_data).is_err());
+/// }
+/// ```
+pub fn json_path_query<'a>(expression: &str, data: &'a Value) -> Result<Vec<(String, &'a Value)>, NestacError> {
+    Ok(JsonPath::parse(expression)?.query(data))
+}
+
+fn select<'a>(segments: &[Segment], mut nodes: Vec<(String, &'a Value)>, root: &'a Value) -> Vec<(String, &'a Value)> {
+    for segment in segments {
+        let mut targets = vec![];
+        for (path, node) in nodes {
+            match segment.descendants {
+                true => descendants(path, node, &mut targets),
+                false => targets.push((path, node)),
+            }
+        }
+        nodes = vec![];
+        for (path, node) in &targets {
+            for selector in &segment.selectors {
+                select_children(selector, path, node, root, &mut nodes);
+            }
+        }
+    }
+    nodes
+}
+
+/// `node` and every node below it, in pre-order.
+fn descendants<'a>(path: String, node: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+    out.push((path.clone(), node));
+    for (child_path, child) in children(&path, node) {
+        descendants(child_path, child, out);
+    }
+}
+
+fn children<'a>(path: &str, node: &'a Value) -> Vec<(String, &'a Value)> {
+    match node {
+        Value::Object(map) => map.iter().map(|(key, child)| (child_path(path, &escape_key(key, ".")), child)).collect(),
+        Value::Array(items) => items.iter().enumerate().map(|(i, item)| (index_path(path, i), item)).collect(),
+        _ => vec![],
+    }
+}
+
+fn child_path(path: &str, token: &str) -> String {
+    match path.is_empty() {
+        true => token.to_string(),
+        false => [path, token].join("."),
+    }
+}
+
+fn index_path(path: &str, index: usize) -> String {
+    child_path(path, &format!("[{}]", index))
+}
+
+fn select_children<'a>(selector: &Selector, path: &str, node: &'a Value, root: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+    match selector {
+        Selector::Name(name) => {
+            if let Some(child) = node.as_object().and_then(|map| map.get(name)) {
+                out.push((child_path(path, &escape_key(name, ".")), child));
+            }
+        }
+        Selector::Wildcard => out.extend(children(path, node)),
+        Selector::Index(index) => {
+            if let Some(items) = node.as_array() {
+                let len = items.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+                if (0..len).contains(&index) {
+                    out.push((index_path(path, index as usize), &items[index as usize]));
+                }
+            }
+        }
+        Selector::Slice(start, end, step) => {
+            if let Some(items) = node.as_array() {
+                for i in slice_indices(items.len() as i64, *start, *end, *step) {
+                    out.push((index_path(path, i), &items[i]));
+                }
+            }
+        }
+        Selector::Filter(filter) => {
+            for (child_path, child) in children(path, node) {
+                if matches(filter, child, root) {
+                    out.push((child_path, child));
+                }
+            }
+        }
+    }
+}
+
+/// Indices selected by `[start:end:step]` on an array of `len` items.
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    match step.cmp(&0) {
+        Ordering::Equal => vec![],
+        Ordering::Greater => {
+            let lower = start.map_or(0, normalize).clamp(0, len);
+            let upper = end.map_or(len, normalize).clamp(0, len);
+            (lower..upper).step_by(step as usize).map(|i| i as usize).collect()
+        }
+        Ordering::Less => {
+            let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+            let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+            let mut indices = vec![];
+            let mut i = upper;
+            while i > lower {
+                indices.push(i as usize);
+                i += step;
+            }
+            indices
+        }
+    }
+}
+
+fn matches(filter: &Filter, current: &Value, root: &Value) -> bool {
+    match filter {
+        Filter::Or(a, b) => matches(a, current, root) || matches(b, current, root),
+        Filter::And(a, b) => matches(a, current, root) && matches(b, current, root),
+        Filter::Not(inner) => !matches(inner, current, root),
+        Filter::Exists(query) => !run(query, current, root).is_empty(),
+        Filter::Compare(left, comparison, right) => {
+            let left = operand_value(left, current, root);
+            let right = operand_value(right, current, root);
+            compare(left, *comparison, right)
+        }
+    }
+}
+
+fn run<'a>(query: &Query, current: &'a Value, root: &'a Value) -> Vec<(String, &'a Value)> {
+    let start = if query.absolute { root } else { current };
+    select(&query.segments, vec![(String::new(), start)], root)
+}
+
+/// Value of an operand; queries must select a single node.
+fn operand_value<'a>(operand: &'a Operand, current: &'a Value, root: &'a Value) -> Option<&'a Value> {
+    match operand {
+        Operand::Literal(value) => Some(value),
+        Operand::Query(query) => match run(query, current, root).as_slice() {
+            [(_, value)] => Some(value),
+            _ => None,
+        },
+    }
+}
+
+fn compare(left: Option<&Value>, comparison: Comparison, right: Option<&Value>) -> bool {
+    let equal = || match (left, right) {
+        (None, None) => true,
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a.as_f64() == b.as_f64(),
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    };
+    let order = || match (left?, right?) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match comparison {
+        Comparison::Eq => equal(),
+        Comparison::Ne => !equal(),
+        Comparison::Lt => order() == Some(Ordering::Less),
+        Comparison::Le => equal() || order() == Some(Ordering::Less),
+        Comparison::Gt => order() == Some(Ordering::Greater),
+        Comparison::Ge => equal() || order() == Some(Ordering::Greater),
+    }
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: String) -> NestacError {
+        NestacError::InvalidPath {
+            path: self.source.to_string(),
+            reason: format!("{} at offset {}", reason, self.pos),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_str(&self, text: &str) -> bool {
+        text.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.peek_str(text);
+        if found {
+            self.pos += text.chars().count();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), NestacError> {
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(found) => Err(self.error(format!("expected `{}`, found `{}`", c, found))),
+            None => Err(self.error(format!("expected `{}`", c))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, NestacError> {
+        let mut segments = vec![];
+        loop {
+            let segment = if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.bracket()?,
+                    _ => vec![self.dot_selector()?],
+                };
+                Segment { descendants: true, selectors }
+            } else if self.eat(".") {
+                Segment { descendants: false, selectors: vec![self.dot_selector()?] }
+            } else if self.peek() == Some('[') {
+                Segment { descendants: false, selectors: self.bracket()? }
+            } else {
+                return Ok(segments);
+            };
+            segments.push(segment);
+        }
+    }
+
+    /// `*` or a name after `.` or `..`.
+    fn dot_selector(&mut self) -> Result<Selector, NestacError> {
+        if self.eat("*") {
+            return Ok(Selector::Wildcard);
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || !c.is_ascii()) {
+            self.pos += 1;
+        }
+        match self.pos > start {
+            true => Ok(Selector::Name(self.chars[start..self.pos].iter().collect())),
+            false => Err(self.error("expected a name".to_string())),
+        }
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>, NestacError> {
+        self.expect('[')?;
+        let mut selectors = vec![];
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, NestacError> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                Ok(Selector::Filter(self.or()?))
+            }
+            Some(c) if c == '-' || c == ':' || c.is_ascii_digit() => {
+                let start = self.integer()?;
+                self.skip_whitespace();
+                if !self.eat(":") {
+                    return match start {
+                        Some(index) => Ok(Selector::Index(index)),
+                        None => Err(self.error("expected an index".to_string())),
+                    };
+                }
+                self.skip_whitespace();
+                let end = self.integer()?;
+                self.skip_whitespace();
+                let step = match self.eat(":") {
+                    true => {
+                        self.skip_whitespace();
+                        self.integer()?.unwrap_or(1)
+                    }
+                    false => 1,
+                };
+                Ok(Selector::Slice(start, end, step))
+            }
+            Some(c) => Err(self.error(format!("unexpected `{}`", c))),
+            None => Err(self.error("expected a selector".to_string())),
+        }
+    }
+
+    fn integer(&mut self) -> Result<Option<i64>, NestacError> {
+        let start = self.pos;
+        self.eat("-");
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().map(Some).map_err(|_| self.error(format!("`{}` is not a valid integer", digits)))
+    }
+
+    /// Quoted string, with JSON escapes.
+    fn string(&mut self) -> Result<String, NestacError> {
+        let quote = self.peek().expect("called on a quote");
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string".to_string())),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = self.chars.iter().skip(self.pos + 1).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                            self.pos += 4;
+                            code.ok_or_else(|| self.error(format!("invalid escape `\\u{}`", hex)))?
+                        }
+                        Some(c @ ('\\' | '/' | '\'' | '"')) => c,
+                        _ => return Err(self.error("invalid escape".to_string())),
+                    };
+                    text.push(escaped);
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, NestacError> {
+        let mut filter = self.and()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("||") {
+                return Ok(filter);
+            }
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+    }
+
+    fn and(&mut self) -> Result<Filter, NestacError> {
+        let mut filter = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("&&") {
+                return Ok(filter);
+            }
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Filter, NestacError> {
+        self.skip_whitespace();
+        if !self.peek_str("!=") && self.eat("!") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let filter = self.or()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(filter);
+        }
+        let left = self.operand()?;
+        self.skip_whitespace();
+        let comparisons = [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ];
+        match comparisons.into_iter().find(|(text, _)| self.eat(text)) {
+            Some((_, comparison)) => {
+                self.skip_whitespace();
+                Ok(Filter::Compare(left, comparison, self.operand()?))
+            }
+            None => match left {
+                Operand::Query(query) => Ok(Filter::Exists(query)),
+                Operand::Literal(_) => Err(self.error("expected a comparison".to_string())),
+            },
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, NestacError> {
+        match self.peek() {
+            Some(c @ ('@' | '$')) => {
+                self.pos += 1;
+                let segments = self.segments()?;
+                Ok(Operand::Query(Query { absolute: c == '$', segments }))
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(Value::from(self.string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                serde_json::from_str::<serde_json::Number>(&number)
+                    .map(|number| Operand::Literal(Value::Number(number)))
+                    .map_err(|_| self.error(format!("`{}` is not a valid number", number)))
+            }
+            _ => {
+                for (keyword, value) in [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::Null)] {
+                    if self.eat(keyword) {
+                        return Ok(Operand::Literal(value));
+                    }
+                }
+                Err(self.error("expected `@`, `$` or a literal".to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(expression: &str, data: &Value) -> Vec<String> {
+        json_path_query(expression, data).unwrap().into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn selectors_and_paths() {
+        let data = json!({"a.b": 1, "list": [0, 1, 2, 3, 4], "nested": {"list": [{"x": 1}]}});
+        assert_eq!(paths("$", &data), vec![""]);
+        assert_eq!(paths("$['a.b']", &data), vec!["\"a.b\""]);
+        assert_eq!(paths("$.list[-1]", &data), vec!["list.[4]"]);
+        assert_eq!(paths("$.list[1:4:2]", &data), vec!["list.[1]", "list.[3]"]);
+        assert_eq!(paths("$.list[::-2]", &data), vec!["list.[4]", "list.[2]", "list.[0]"]);
+        assert_eq!(paths("$.list[0, -1]", &data), vec!["list.[0]", "list.[4]"]);
+        assert_eq!(paths("$..list[0]", &data), vec!["list.[0]", "nested.list.[0]"]);
+        assert_eq!(paths("$.nested.*", &data), vec!["nested.list"]);
+        assert_eq!(paths("$.missing[0]", &data), Vec::<String>::new());
+    }
+
+    #[test]
+    fn filters() {
+        let data = json!({"items": [
+            {"id": 1, "tags": ["a"], "on": true},
+            {"id": 2, "name": "b", "on": false},
+            {"id": 3.0, "name": "c"},
+        ], "max": 2});
+        assert_eq!(paths("$.items[?@.id >= 2 && !@.tags]", &data), vec!["items.[1]", "items.[2]"]);
+        assert_eq!(paths("$.items[?(@.id == 3 || @.on == true)].id", &data), vec!["items.[0].id", "items.[2].id"]);
+        assert_eq!(paths("$.items[?(@.id < $.max)]", &data), vec!["items.[0]"]);
+        assert_eq!(paths("$.items[?(@.name > 'b')]", &data), vec!["items.[2]"]);
+        assert_eq!(paths("$.items[?(@.missing == @.other)].id", &data).len(), 3);
+        assert_eq!(paths("$.items[?(@.on != false)]", &data), vec!["items.[0]", "items.[2]"]);
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in ["", "store", "$.", "$[", "$[?(@.a == )]", "$['a]", "$.a b", "$[?(1)]"] {
+            assert!(
+                matches!(JsonPath::parse(expression), Err(NestacError::InvalidPath { .. })),
+                "{:?}",
+                expression,
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Helpers for `Cargo.toml` manifests parsed as a [toml::Value].
+use toml::Value;
+use crate::{toml_read, NestedValue};
+
+/// Tables holding dependencies on a manifest, `target.'cfg(..)'` tables
+/// aside.
+pub const DEPENDENCY_TABLES: [&str; 4] = [
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "workspace.dependencies",
+];
+
+/// Version of the package (`package.version`).
+pub fn package_version(doc: &Value) -> Option<&str> {
+    toml_read("package.version", doc, None)?.as_str()
+}
+
+/// Sets the version of the package, returning the old one. Returns [None]
+/// without changing anything when the manifest has no `package.version`
+/// (e.g. it is inherited from the workspace).
+pub fn set_package_version(doc: &mut Value, version: &str) -> Option<String> {
+    package_version(doc)?;
+    let old = crate::toml_update(doc, "package.version", None, Value::String(version.to_string()))?;
+    old.as_str().map(|old| old.to_string())
+}
+
+/// Version requirement of a dependency, looked up on every
+/// [DEPENDENCY_TABLES] in order. Both `name = "1.0"` and
+/// `name = { version = "1.0" }` forms are understood.
+pub fn dependency_version<'a>(doc: &'a Value, name: &str) -> Option<&'a str> {
+    DEPENDENCY_TABLES.iter().find_map(|table| {
+        let dependency = toml_read(table, doc, None)?.get_key(name)?;
+        match dependency {
+            Value::String(version) => Some(version.as_str()),
+            _ => dependency.get_key("version")?.as_str(),
+        }
+    })
+}
+
+/// Sets the version requirement of a dependency on every
+/// [DEPENDENCY_TABLES] declaring it, returning how many entries were
+/// updated. Dependencies without a version (`path`, `git` or
+/// `workspace = true`) are left untouched.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::knownformats::cargo;
+///
+/// fn main() {
+///     let mut doc: toml::Value = toml::from_str(r#"
+///         [dependencies]
+///         serde = { version = "1.0", features = ["derive"] }
+///
+///         [dev-dependencies]
+///         serde = "1.0"
+///     "#).unwrap();
+///     assert_eq!(cargo::set_dependency_version(&mut doc, "serde", "1.0.200"), 2);
+///     assert_eq!(cargo::dependency_version(&doc, "serde"), Some("1.0.200"));
+///     assert_eq!(cargo::set_dependency_version(&mut doc, "rand", "0.8"), 0);
+/// }
+/// ```
+pub fn set_dependency_version(doc: &mut Value, name: &str, version: &str) -> usize {
+    let mut updated = 0;
+    for table in DEPENDENCY_TABLES {
+        let dependency = match crate::nested::read_mut(table, doc, None).and_then(|t| t.get_key_mut(name)) {
+            Some(dependency) => dependency,
+            None => continue,
+        };
+        let target = match dependency {
+            Value::String(_) => dependency,
+            _ => match dependency.get_key_mut("version") {
+                Some(target) => target,
+                None => continue,
+            },
+        };
+        *target = Value::String(version.to_string());
+        updated += 1;
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_and_workspace_versions() {
+        let toml_str = r#"
+            [package]
+            name = "demo"
+            version = "0.1.0"
+
+            [workspace.dependencies]
+            regex = "1.10"
+
+            [dependencies]
+            regex = { workspace = true }
+            local = { path = "../local" }
+        "#;
+        let mut doc: Result<Value, _> = toml::from_str(toml_str);
+        assert!(doc.is_ok());
+        let doc = doc.as_mut().unwrap();
+        assert_eq!(set_package_version(doc, "0.2.0"), Some("0.1.0".to_string()));
+        assert_eq!(package_version(doc), Some("0.2.0"));
+        assert_eq!(dependency_version(doc, "regex"), Some("1.10"));
+        assert_eq!(set_dependency_version(doc, "regex", "1.11"), 1);
+        assert_eq!(set_dependency_version(doc, "local", "1.0"), 0);
+        assert_eq!(toml_read("workspace.dependencies.regex", doc, None).unwrap().as_str(), Some("1.11"));
+        assert!(toml_read("dependencies.local.version", doc, None).is_none());
+    }
+}
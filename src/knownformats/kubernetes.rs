@@ -0,0 +1,99 @@
+//! Helpers for Kubernetes manifests parsed as a [Value], addressing
+//! containers by name through `[name=...]` path tokens.
+use serde_json::Value;
+use crate::{json_read, json_update};
+
+/// Where the pod spec lives on the supported kinds: `Pod`, workloads
+/// templating pods (`Deployment`, `StatefulSet`, `DaemonSet`, `ReplicaSet`,
+/// `Job`) and `CronJob`.
+pub const POD_SPECS: [&str; 3] = [
+    "spec",
+    "spec.template.spec",
+    "spec.jobTemplate.spec.template.spec",
+];
+
+/// Arrays of containers on a pod spec.
+pub const CONTAINER_ARRAYS: [&str; 2] = ["containers", "initContainers"];
+
+/// Paths of the containers named `name` on the manifest.
+fn container_paths<'a>(doc: &'a Value, name: &'a str) -> impl Iterator<Item = String> + 'a {
+    POD_SPECS.iter().flat_map(move |spec| {
+        CONTAINER_ARRAYS.iter().filter_map(move |containers| {
+            let path = format!("{}.{}.[name={}]", spec, containers, name);
+            json_read(&path, doc, None).map(|_| path)
+        })
+    })
+}
+
+/// Image of the container named `name`.
+pub fn image<'a>(doc: &'a Value, name: &str) -> Option<&'a str> {
+    let path = container_paths(doc, name).next()?;
+    json_read(&format!("{}.image", path), doc, None)?.as_str()
+}
+
+/// Sets the image of every container named `name`, returning how many
+/// were updated.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::knownformats::kubernetes;
+///
+/// fn main() {
+///     let mut doc = json!({
+///         "kind": "Deployment",
+///         "spec": {"template": {"spec": {"containers": [
+///             {"name": "proxy", "image": "envoy:1.29"},
+///             {"name": "app", "image": "app:1.0"}
+///         ]}}}
+///     });
+///     assert_eq!(kubernetes::set_image(&mut doc, "app", "app:1.1"), 1);
+///     assert_eq!(kubernetes::image(&doc, "app"), Some("app:1.1"));
+///     assert_eq!(kubernetes::image(&doc, "proxy"), Some("envoy:1.29"));
+/// }
+/// ```
+pub fn set_image(doc: &mut Value, name: &str, image: &str) -> usize {
+    set_field(doc, name, "image", Value::String(image.to_string()))
+}
+
+/// Sets the `resources` (requests and limits) of every container named
+/// `name`, returning how many were updated.
+pub fn set_resources(doc: &mut Value, name: &str, resources: Value) -> usize {
+    set_field(doc, name, "resources", resources)
+}
+
+fn set_field(doc: &mut Value, name: &str, field: &str, value: Value) -> usize {
+    let paths: Vec<String> = container_paths(doc, name).collect();
+    for path in &paths {
+        json_update(doc, &format!("{}.{}", path, field), None, value.clone());
+    }
+    paths.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn containers_are_found_on_every_kind() {
+        let mut pod = json!({"spec": {
+            "initContainers": [{"name": "migrate", "image": "app:1.0"}],
+            "containers": [{"name": "app", "image": "app:1.0"}]
+        }});
+        let mut cron_job = json!({"spec": {"jobTemplate": {"spec": {"template": {"spec": {
+            "containers": [{"name": "app", "image": "app:1.0"}]
+        }}}}}});
+        assert_eq!(set_image(&mut pod, "migrate", "app:2.0"), 1);
+        assert_eq!(pod["spec"]["initContainers"][0]["image"], "app:2.0");
+        assert_eq!(pod["spec"]["containers"][0]["image"], "app:1.0");
+
+        let resources = json!({"limits": {"memory": "256Mi"}});
+        assert_eq!(set_resources(&mut cron_job, "app", resources.clone()), 1);
+        assert_eq!(
+            json_read("spec.jobTemplate.spec.template.spec.containers.[0].resources", &cron_job, None),
+            Some(&resources),
+        );
+        assert_eq!(set_image(&mut cron_job, "web", "web:1.0"), 0);
+    }
+}
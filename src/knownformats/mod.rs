@@ -0,0 +1,13 @@
+//! Typed helpers for well-known configuration files, built on the path
+//! functions.
+//!
+//! Enabled by the `knownformats` feature.
+//!
+//! - [cargo] for `Cargo.toml`;
+//! - [kubernetes] for Kubernetes manifests;
+//! - [package_json] for `package.json`;
+//! - [pyproject] for `pyproject.toml` (PEP 621 and Poetry).
+pub mod cargo;
+pub mod kubernetes;
+pub mod package_json;
+pub mod pyproject;
@@ -0,0 +1,321 @@
+//! Helpers for `package.json` manifests parsed as a [Value] (or edited
+//! through a [crate::Document] to keep their layout).
+//!
+//! New entries are placed the way npm does: top-level sections follow the
+//! conventional `package.json` order, dependencies are kept sorted by name
+//! and scripts are appended. Key order is only kept by `serde_json` with
+//! the `preserve_order` feature; without it objects are always sorted.
+use serde_json::{Map, Value};
+use crate::{json_read, NestedValue};
+
+/// Conventional order of the top-level keys of a `package.json`.
+const TOP_LEVEL_ORDER: [&str; 26] = [
+    "name",
+    "version",
+    "private",
+    "description",
+    "keywords",
+    "homepage",
+    "bugs",
+    "license",
+    "author",
+    "contributors",
+    "funding",
+    "type",
+    "main",
+    "module",
+    "types",
+    "exports",
+    "bin",
+    "files",
+    "workspaces",
+    "scripts",
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+    "engines",
+    "packageManager",
+];
+
+/// Kind of dependency, i.e. the object it is declared on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// `dependencies`
+    Prod,
+    /// `devDependencies`
+    Dev,
+    /// `peerDependencies`
+    Peer,
+    /// `optionalDependencies`
+    Optional,
+}
+
+impl DependencyKind {
+    /// Key of the object holding this kind of dependency.
+    pub fn key(&self) -> &'static str {
+        match self {
+            DependencyKind::Prod => "dependencies",
+            DependencyKind::Dev => "devDependencies",
+            DependencyKind::Peer => "peerDependencies",
+            DependencyKind::Optional => "optionalDependencies",
+        }
+    }
+}
+
+/// Objects holding dependencies on a manifest.
+pub const DEPENDENCY_OBJECTS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Version of the package.
+pub fn version(doc: &Value) -> Option<&str> {
+    json_read("version", doc, None)?.as_str()
+}
+
+/// Sets the version of the package, returning the old one.
+pub fn set_version(doc: &mut Value, version: &str) -> Option<String> {
+    let old = crate::nested::update(doc, "version", None, Value::String(version.to_string()))?;
+    old.as_str().map(|old| old.to_string())
+}
+
+/// Version range of a dependency, looked up on every [DEPENDENCY_OBJECTS]
+/// in order.
+pub fn dependency_version<'a>(doc: &'a Value, name: &str) -> Option<&'a str> {
+    DEPENDENCY_OBJECTS
+        .iter()
+        .find_map(|object| json_read(object, doc, None)?.get_key(name)?.as_str())
+}
+
+/// Sets the version range of a dependency on every [DEPENDENCY_OBJECTS]
+/// declaring it, returning how many entries were updated.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::knownformats::package_json;
+///
+/// fn main() {
+///     let mut doc = json!({
+///         "name": "web",
+///         "dependencies": {"lodash.merge": "^4.6.0"},
+///         "peerDependencies": {"lodash.merge": "^4.0.0"}
+///     });
+///     assert_eq!(package_json::set_dependency_version(&mut doc, "lodash.merge", "^4.6.2"), 2);
+///     assert_eq!(doc["peerDependencies"]["lodash.merge"], "^4.6.2");
+/// }
+/// ```
+pub fn set_dependency_version(doc: &mut Value, name: &str, version: &str) -> usize {
+    let mut updated = 0;
+    for object in DEPENDENCY_OBJECTS {
+        let dependencies = match crate::nested::read_mut(object, doc, None) {
+            Some(dependencies) => dependencies,
+            None => continue,
+        };
+        if let Some(dependency) = dependencies.get_key_mut(name) {
+            *dependency = Value::String(version.to_string());
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Inserts `key` into `map` before the first key `goes_before` says it
+/// must precede, keeping the order of the other keys.
+fn insert_ordered(map: &mut Map<String, Value>, key: &str, value: Value, goes_before: impl Fn(&str) -> bool) {
+    if let Some(old) = map.get_mut(key) {
+        *old = value;
+        return;
+    }
+    let mut entries = std::mem::take(map).into_iter().peekable();
+    while let Some((k, _)) = entries.peek() {
+        if goes_before(k) {
+            break;
+        }
+        let (k, v) = entries.next().unwrap();
+        map.insert(k, v);
+    }
+    map.insert(key.to_string(), value);
+    map.extend(entries);
+}
+
+/// Top-level entry stored under `key`, created with `default` in its
+/// conventional position when missing. Returns [None] when the document is
+/// not an object.
+fn top_level<'a>(doc: &'a mut Value, key: &str, default: Value) -> Option<&'a mut Value> {
+    let map = doc.as_object_mut()?;
+    if !map.contains_key(key) {
+        let rank = |k: &str| TOP_LEVEL_ORDER.iter().position(|known| *known == k);
+        let own_rank = rank(key).unwrap_or(TOP_LEVEL_ORDER.len());
+        insert_ordered(map, key, default, |k| rank(k).is_some_and(|r| r > own_rank));
+    }
+    map.get_mut(key)
+}
+
+/// Top-level object stored under `key`, created when missing.
+fn section<'a>(doc: &'a mut Value, key: &str) -> Option<&'a mut Map<String, Value>> {
+    top_level(doc, key, Value::Object(Map::new()))?.as_object_mut()
+}
+
+/// Command of a script.
+pub fn script<'a>(doc: &'a Value, name: &str) -> Option<&'a str> {
+    json_read("scripts", doc, None)?.get_key(name)?.as_str()
+}
+
+/// Sets a script, returning the old command. New scripts are appended and
+/// the `scripts` object is created when missing.
+pub fn set_script(doc: &mut Value, name: &str, command: &str) -> Option<String> {
+    let scripts = section(doc, "scripts")?;
+    let old = scripts.insert(name.to_string(), Value::String(command.to_string()))?;
+    old.as_str().map(|old| old.to_string())
+}
+
+/// Removes a script, returning its command.
+pub fn remove_script(doc: &mut Value, name: &str) -> Option<String> {
+    let old = doc.get_key_mut("scripts")?.remove_key(name)?;
+    old.as_str().map(|old| old.to_string())
+}
+
+/// Adds (or updates) a dependency of the given kind, keeping the object
+/// sorted by name like npm does. A dependency moved between `dependencies`,
+/// `devDependencies` and `optionalDependencies` is removed from the other
+/// two, since npm only installs it from one of them; peer dependencies are
+/// independent.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::Document;
+/// use nestac::knownformats::package_json::{self, DependencyKind};
+///
+/// fn main() {
+///     let json_str = "{\n  \"name\": \"web\",\n  \"devDependencies\": {\n    \"react\": \"^18.0.0\"\n  }\n}\n";
+///     let mut doc = Document::parse(json_str).unwrap();
+///     package_json::add_dependency(doc.value_mut(), DependencyKind::Prod, "react", "^18.3.1");
+///     assert_eq!(package_json::dependency_version(doc.value(), "react"), Some("^18.3.1"));
+///     assert!(doc.read("devDependencies.react", None).is_none());
+///     assert!(doc.to_string().contains("\n  \"dependencies\": {\n    \"react\": \"^18.3.1\"\n  },\n"));
+/// }
+/// ```
+pub fn add_dependency(doc: &mut Value, kind: DependencyKind, name: &str, version: &str) {
+    if kind != DependencyKind::Peer {
+        for other in [DependencyKind::Prod, DependencyKind::Dev, DependencyKind::Optional] {
+            if other == kind {
+                continue;
+            }
+            if let Some(dependencies) = doc.get_key_mut(other.key()) {
+                dependencies.remove_key(name);
+            }
+        }
+    }
+    if let Some(dependencies) = section(doc, kind.key()) {
+        insert_ordered(dependencies, name, Value::String(version.to_string()), |k| k > name);
+    }
+}
+
+/// Removes a dependency from every [DEPENDENCY_OBJECTS], returning how many
+/// entries were removed.
+pub fn remove_dependency(doc: &mut Value, name: &str) -> usize {
+    DEPENDENCY_OBJECTS
+        .iter()
+        .filter_map(|object| doc.get_key_mut(object)?.remove_key(name))
+        .count()
+}
+
+/// Workspace globs, declared either as an array or as the `packages` of an
+/// object (Yarn).
+pub fn workspaces(doc: &Value) -> Vec<&str> {
+    let workspaces = match json_read("workspaces", doc, None) {
+        Some(Value::Object(map)) => map.get("packages"),
+        workspaces => workspaces,
+    };
+    workspaces
+        .and_then(|w| w.as_array())
+        .map(|items| items.iter().filter_map(|item| item.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Adds a workspace glob unless already declared, creating `workspaces` as
+/// an array when missing. Returns whether the glob was added.
+pub fn add_workspace(doc: &mut Value, glob: &str) -> bool {
+    if workspaces(doc).contains(&glob) {
+        return false;
+    }
+    let items = match top_level(doc, "workspaces", Value::Array(vec![])) {
+        Some(Value::Object(workspaces)) => workspaces.get_mut("packages").and_then(|p| p.as_array_mut()),
+        Some(workspaces) => workspaces.as_array_mut(),
+        None => None,
+    };
+    match items {
+        Some(items) => {
+            items.push(Value::String(glob.to_string()));
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Result;
+
+    #[test]
+    fn versions_through_document() {
+        let json_str = "{\n  \"devDependencies\": {\n    \"@types/node\": \"^20.0.0\"\n  },\n  \"version\": \"1.0.0\"\n}\n";
+        let doc = crate::Document::parse(json_str);
+        assert!(doc.is_ok());
+        let mut doc = doc.unwrap();
+        assert_eq!(dependency_version(doc.value(), "@types/node"), Some("^20.0.0"));
+        assert_eq!(set_dependency_version(doc.value_mut(), "@types/node", "^22.0.0"), 1);
+        assert_eq!(set_version(doc.value_mut(), "1.1.0"), Some("1.0.0".to_string()));
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"devDependencies\": {\n    \"@types/node\": \"^22.0.0\"\n  },\n  \"version\": \"1.1.0\"\n}\n",
+        );
+        let json_data: Result<Value> = serde_json::from_str("{}");
+        assert!(version(json_data.as_ref().unwrap()).is_none());
+    }
+
+    #[test]
+    fn scripts_and_workspaces() {
+        let mut doc = serde_json::json!({"name": "root", "workspaces": {"packages": ["apps/*"]}});
+        assert!(add_workspace(&mut doc, "libs/*"));
+        assert!(!add_workspace(&mut doc, "apps/*"));
+        assert_eq!(workspaces(&doc), vec!["apps/*", "libs/*"]);
+        assert_eq!(set_script(&mut doc, "test", "vitest"), None);
+        assert_eq!(set_script(&mut doc, "build", "vite build"), None);
+        assert_eq!(set_script(&mut doc, "test", "vitest run"), Some("vitest".to_string()));
+        assert_eq!(script(&doc, "test"), Some("vitest run"));
+        assert_eq!(remove_script(&mut doc, "build"), Some("vite build".to_string()));
+        assert_eq!(remove_script(&mut doc, "build"), None);
+
+        let mut doc = serde_json::json!({"name": "app"});
+        assert!(add_workspace(&mut doc, "packages/*"));
+        assert_eq!(doc["workspaces"], serde_json::json!(["packages/*"]));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn placement_follows_npm_conventions() {
+        let json_str = r#"{"name": "web", "version": "1.0.0", "engines": {"node": ">=20"}}"#;
+        let mut doc: Value = serde_json::from_str(json_str).unwrap();
+        add_dependency(&mut doc, DependencyKind::Dev, "vite", "^5.0.0");
+        add_dependency(&mut doc, DependencyKind::Dev, "eslint", "^9.0.0");
+        add_dependency(&mut doc, DependencyKind::Dev, "typescript", "^5.4.0");
+        set_script(&mut doc, "dev", "vite");
+        add_dependency(&mut doc, DependencyKind::Optional, "typescript", "^5.5.0");
+        assert_eq!(remove_dependency(&mut doc, "eslint"), 1);
+        assert_eq!(
+            doc.to_string(),
+            concat!(
+                r#"{"name":"web","version":"1.0.0","scripts":{"dev":"vite"},"#,
+                r#""devDependencies":{"vite":"^5.0.0"},"#,
+                r#""optionalDependencies":{"typescript":"^5.5.0"},"#,
+                r#""engines":{"node":">=20"}}"#,
+            ),
+        );
+    }
+}
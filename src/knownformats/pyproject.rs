@@ -0,0 +1,149 @@
+//! Helpers for `pyproject.toml` files parsed as a [toml::Value], covering
+//! PEP 621 (`[project]`) and Poetry (`[tool.poetry]`) metadata.
+use toml::Value;
+use crate::{toml_read, NestedValue};
+
+/// Version of the project (`project.version`, then
+/// `tool.poetry.version`).
+pub fn version(doc: &Value) -> Option<&str> {
+    ["project.version", "tool.poetry.version"]
+        .iter()
+        .find_map(|path| toml_read(path, doc, None)?.as_str())
+}
+
+/// Name of a PEP 508 requirement (`requests[socks]>=2.0; ...` gives
+/// `requests`), normalized as per PEP 503.
+fn requirement_name(requirement: &str) -> String {
+    let end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(requirement.len());
+    normalize(&requirement[..end])
+}
+
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+/// Sets the version specifier of a dependency (e.g. `>=2.31`) on
+/// `project.dependencies`, every `project.optional-dependencies` group and
+/// `tool.poetry.dependencies`, returning how many entries were updated.
+///
+/// PEP 508 entries keep their extras and markers; Poetry entries may be a
+/// plain string or a table with a `version`. Names are compared as per
+/// PEP 503 (`Foo_Bar` matches `foo-bar`).
+///
+/// # Examples:
+/// ```rust
+/// use nestac::knownformats::pyproject;
+///
+/// fn main() {
+///     let mut doc: toml::Value = toml::from_str(r#"
+///         [project]
+///         dependencies = ["requests[socks]>=2.0; python_version > '3.8'", "rich"]
+///     "#).unwrap();
+///     assert_eq!(pyproject::set_dependency_version(&mut doc, "Requests", ">=2.31"), 1);
+///     assert_eq!(
+///         doc["project"]["dependencies"][0].as_str(),
+///         Some("requests[socks]>=2.31; python_version > '3.8'"),
+///     );
+/// }
+/// ```
+pub fn set_dependency_version(doc: &mut Value, name: &str, specifier: &str) -> usize {
+    let name = normalize(name);
+    let mut updated = 0;
+    if let Some(list) = crate::nested::read_mut("project.dependencies", doc, None) {
+        updated += set_requirements(list, &name, specifier);
+    }
+    if let Some(Value::Table(groups)) = crate::nested::read_mut("project.optional-dependencies", doc, None) {
+        for (_, list) in groups.iter_mut() {
+            updated += set_requirements(list, &name, specifier);
+        }
+    }
+    if let Some(Value::Table(dependencies)) = crate::nested::read_mut("tool.poetry.dependencies", doc, None) {
+        for (key, dependency) in dependencies.iter_mut() {
+            if normalize(key) != name {
+                continue;
+            }
+            let target = match dependency {
+                Value::String(_) => dependency,
+                _ => match dependency.get_key_mut("version") {
+                    Some(target) => target,
+                    None => continue,
+                },
+            };
+            *target = Value::String(specifier.to_string());
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Sets the specifier of the PEP 508 requirements of a list matching the
+/// normalized `name`.
+fn set_requirements(list: &mut Value, name: &str, specifier: &str) -> usize {
+    let mut updated = 0;
+    for item in list.as_array_mut().into_iter().flatten() {
+        let requirement = match item.as_str() {
+            Some(requirement) if requirement_name(requirement) == name => requirement,
+            _ => continue,
+        };
+        *item = Value::String(replace_specifier(requirement, specifier));
+        updated += 1;
+    }
+    updated
+}
+
+/// Replaces the version specifier of a PEP 508 requirement, keeping its
+/// name, extras and markers.
+fn replace_specifier(requirement: &str, specifier: &str) -> String {
+    let (spec, markers) = match requirement.split_once(';') {
+        Some((spec, markers)) => (spec, Some(markers)),
+        None => (requirement, None),
+    };
+    let head_end = match spec.find(']') {
+        Some(idx) => idx + 1,
+        None => spec
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .unwrap_or(spec.len()),
+    };
+    let mut ret = format!("{}{}", spec[..head_end].trim_end(), specifier);
+    if let Some(markers) = markers {
+        ret.push(';');
+        ret.push_str(markers);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poetry_and_optional_dependencies() {
+        let toml_str = r#"
+            [project]
+            version = "0.3.0"
+
+            [project.optional-dependencies]
+            dev = ["black==23.1", "Flake8 >= 6"]
+
+            [tool.poetry.dependencies]
+            python = "^3.10"
+            flake8 = { version = "^6.0", optional = true }
+        "#;
+        let mut doc: Result<Value, _> = toml::from_str(toml_str);
+        assert!(doc.is_ok());
+        let doc = doc.as_mut().unwrap();
+        assert_eq!(version(doc), Some("0.3.0"));
+        assert_eq!(set_dependency_version(doc, "flake8", ">=7"), 2);
+        assert_eq!(
+            toml_read("project.optional-dependencies.dev.[1]", doc, None).unwrap().as_str(),
+            Some("Flake8>=7"),
+        );
+        assert_eq!(
+            toml_read("tool.poetry.dependencies.flake8.version", doc, None).unwrap().as_str(),
+            Some(">=7"),
+        );
+        assert_eq!(set_dependency_version(doc, "numpy", ">=2"), 0);
+    }
+}
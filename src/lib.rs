@@ -17,11 +17,139 @@
 //! to make the property updates like.
 //! 
 //! So here we are.
+//!
+//! # Reproducibility
+//!
+//! Outputs only depend on the inputs, so they can be hashed by hermetic
+//! builds:
+//!
+//! - paths are listed in document order ([json_get_paths], [json_entries],
+//!   [json_find_paths_where], [json_diff()], [json_lint()], [jsonpath] and
+//!   their TOML counterparts), which is key order unless the
+//!   `preserve_order` feature keeps the order of the source;
+//! - [Document] writes back the source text untouched and edits with the
+//!   indentation, line endings and BOM of the source;
+//! - ids of [json_assign_ids] are the same on every run and platform, and
+//!   so are the HMAC-SHA256 hashes of [json_anonymize_paths] for a given
+//!   salt;
+//! - batch results come in input order, and
+//!   `batch::BatchOptions::deterministic` also zeroes elapsed times and
+//!   writes checkpoints in input order.
+//!
+//! [json_read_many] returns a [std::collections::HashMap], whose iteration
+//! order is not reproducible: sort its keys before writing them out.
 
+// lets the code generated by `#[derive(Nestac)]` name `::nestac` here too
+extern crate self as nestac;
+
+pub mod error;
+pub mod options;
+pub mod nested;
+pub mod path;
+pub mod document;
+pub mod codec;
+pub mod pool;
+pub mod file;
+mod write;
 pub mod json_read;
 pub mod json_update;
+pub mod json_delete;
 pub mod json_paths;
+pub mod json_validate;
+pub mod json_embedded;
+pub mod json_ids;
+pub mod json_anonymize;
+pub mod json_defaults;
+pub mod json_merge;
+pub mod json_diff;
+pub mod json_flatten;
+pub mod json_file;
+pub mod json_array;
+pub mod json_ttl;
+pub mod json_lint;
+pub mod json_graph;
+pub mod jsonpath;
+#[cfg(feature = "manifest")]
+pub mod json_manifest;
+pub mod map_read;
+#[cfg(feature = "toml")]
+pub mod toml_read;
+#[cfg(feature = "toml")]
+pub mod toml_update;
+#[cfg(feature = "toml")]
+pub mod toml_delete;
+#[cfg(feature = "toml")]
+pub mod toml_paths;
+#[cfg(feature = "toml")]
+pub mod toml_merge;
+#[cfg(feature = "toml")]
+pub mod toml_diff;
+#[cfg(feature = "toml")]
+pub mod toml_file;
+#[cfg(feature = "toml")]
+pub mod toml_array;
+#[cfg(feature = "toml")]
+pub mod toml_document;
+#[cfg(feature = "toml")]
+pub mod toml_layout;
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "knownformats")]
+pub mod knownformats;
+#[cfg(feature = "git")]
+pub mod git;
 
-pub use json_read::json_read;
-pub use json_update::json_update;
-pub use json_paths::json_get_paths;
+pub use error::NestacError;
+pub use options::{ArrayMerge, GraphOptions, KeyOrder, LayoutOptions, LintOptions, MergeDepth, MergeStrategy, NullMerge, Order, PathsOptions, ReadOptions, UpdateOptions};
+pub use nested::NestedValue;
+pub use path::{Path, Segment};
+pub use document::{Document, LineEnding};
+#[cfg(feature = "derive")]
+pub use nestac_derive::Nestac;
+pub use pool::DocumentPool;
+pub use file::FileError;
+pub use json_read::{json_extract, json_read, json_read_all, json_read_as, json_read_many, json_read_mut, json_read_slice, json_read_with, json_try_read};
+pub use json_update::{json_disable, json_enable, json_ensure, json_toggle, json_transform, json_try_update, json_update, json_update_all, json_update_many, json_update_with, json_update_with_serializable, json_upsert, json_walk_mut};
+pub use json_delete::{json_delete, json_delete_all};
+pub use json_paths::{json_entries, json_find_paths_where, json_get_paths, json_get_paths_matching, json_get_paths_with, json_iter_paths, json_write_paths};
+pub use json_validate::{json_unknown_paths, json_validate_references, json_validate_unique};
+pub use json_embedded::{json_read_embedded, json_update_embedded};
+pub use json_ids::json_assign_ids;
+pub use json_anonymize::json_anonymize_paths;
+pub use json_defaults::{json_apply_defaults, json_merge_defaults};
+pub use json_merge::{json_merge, json_merge3, json_merge3_with, json_merge_at, json_merge_into, json_merge_with};
+pub use json_diff::json_diff;
+pub use json_flatten::{json_flatten, json_unflatten};
+pub use json_file::{json_read_file, json_update_file};
+#[cfg(feature = "tokio")]
+pub use json_file::{json_read_file_async, json_update_file_async};
+pub use json_array::{json_array_add_unique, json_array_contains, json_array_remove_value};
+pub use json_ttl::{json_read_unexpired, json_sweep_expired, json_write_expiring};
+pub use json_lint::{json_lint, LintIssue, LintRule, Severity};
+pub use json_graph::{json_to_dot, json_to_mermaid};
+pub use jsonpath::{json_path_query, JsonPath};
+#[cfg(feature = "manifest")]
+pub use json_manifest::{json_manifest, json_verify};
+pub use map_read::{map_read, PathNode};
+#[cfg(feature = "toml")]
+pub use toml_read::{toml_extract, toml_read, toml_read_all, toml_read_as, toml_read_many, toml_read_mut, toml_read_slice, toml_try_read};
+#[cfg(feature = "toml")]
+pub use toml_update::{toml_disable, toml_enable, toml_ensure, toml_toggle, toml_transform, toml_try_update, toml_update, toml_update_all, toml_update_many, toml_update_with_serializable, toml_upsert, toml_walk_mut};
+#[cfg(feature = "toml")]
+pub use toml_delete::{toml_delete, toml_delete_all};
+#[cfg(feature = "toml")]
+pub use toml_paths::{toml_entries, toml_find_paths_where, toml_get_paths, toml_get_paths_matching, toml_get_paths_with, toml_iter_paths, toml_write_paths};
+#[cfg(feature = "toml")]
+pub use toml_merge::{toml_merge_at, toml_merge_into};
+#[cfg(feature = "toml")]
+pub use toml_diff::toml_diff;
+#[cfg(feature = "toml")]
+pub use toml_file::{toml_read_file, toml_update_file};
+#[cfg(all(feature = "toml", feature = "tokio"))]
+pub use toml_file::{toml_read_file_async, toml_update_file_async};
+#[cfg(feature = "toml")]
+pub use toml_array::{toml_array_add_unique, toml_array_contains, toml_array_remove_value};
+#[cfg(feature = "toml")]
+pub use toml_document::{toml_edit_delete, toml_edit_update};
+#[cfg(feature = "toml")]
+pub use toml_layout::toml_to_string_at;
@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, HashMap};
+use serde_json::Value;
+use crate::nested::{parse_index, tokenize};
+
+/// A node of a standard collection tree (`HashMap`, `BTreeMap`, `Vec` and
+/// primitives) that can be traversed by [map_read].
+///
+/// Only the selected node is converted into a [Value], the rest of the
+/// structure is walked in place.
+pub trait PathNode {
+    /// Child addressed by a single path token (a key or a `[n]` index).
+    fn child(&self, token: &str) -> Option<&dyn PathNode>;
+
+    /// Converts this node (and its children) into a [Value].
+    fn to_value(&self) -> Value;
+}
+
+impl<T: PathNode> PathNode for HashMap<String, T> {
+    fn child(&self, token: &str) -> Option<&dyn PathNode> {
+        self.get(token).map(|v| v as &dyn PathNode)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(self.iter().map(|(k, v)| (k.clone(), v.to_value())).collect())
+    }
+}
+
+impl<T: PathNode> PathNode for BTreeMap<String, T> {
+    fn child(&self, token: &str) -> Option<&dyn PathNode> {
+        self.get(token).map(|v| v as &dyn PathNode)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(self.iter().map(|(k, v)| (k.clone(), v.to_value())).collect())
+    }
+}
+
+impl<T: PathNode> PathNode for Vec<T> {
+    fn child(&self, token: &str) -> Option<&dyn PathNode> {
+        self.get(parse_index(token)?).map(|v| v as &dyn PathNode)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(|v| v.to_value()).collect())
+    }
+}
+
+impl<T: PathNode> PathNode for Option<T> {
+    fn child(&self, token: &str) -> Option<&dyn PathNode> {
+        self.as_ref()?.child(token)
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Some(v) => v.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl PathNode for Value {
+    fn child(&self, token: &str) -> Option<&dyn PathNode> {
+        match parse_index(token) {
+            Some(idx) => self.get(idx),
+            None => self.get(token),
+        }.map(|v| v as &dyn PathNode)
+    }
+
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+macro_rules! leaf_path_node {
+    ($($t:ty),*) => {
+        $(
+            impl PathNode for $t {
+                fn child(&self, _token: &str) -> Option<&dyn PathNode> {
+                    None
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::from(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+leaf_path_node!(String, &str, bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+/// Return a [Value] based off the token-based [str] path, reading straight
+/// from standard collections of primitives.
+///
+/// # Examples:
+/// ```rust
+/// use std::collections::{BTreeMap, HashMap};
+/// use serde_json::{json, Value};
+/// use nestac::map_read;
+///
+/// fn main() {
+///     let mut ports: HashMap<String, u16> = HashMap::new();
+///     ports.insert("http".to_string(), 80);
+///     let mut servers: BTreeMap<String, Vec<HashMap<String, u16>>> = BTreeMap::new();
+///     servers.insert("web".to_string(), vec![ports]);
+///
+///     let val: Option<Value> = map_read("web.[0].http", &servers, None);
+///     assert_eq!(val.unwrap(), 80);
+///     let val: Option<Value> = map_read("web.[0]", &servers, None);
+///     assert_eq!(val.unwrap(), json!({"http": 80}));
+/// }
+/// ```
+pub fn map_read<N: PathNode + ?Sized>(path: &str, data: &N, separator: Option<&str>) -> Option<Value> {
+    let mut tokens = tokenize(path, separator.unwrap_or(".")).into_iter();
+    let first = tokens.next()?;
+    let mut sel_data = data.child(&first.text)?;
+    for token in tokens {
+        sel_data = sel_data.child(&token.text)?;
+    }
+    Some(sel_data.to_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_flat_hashmap() {
+        let mut data: HashMap<String, String> = HashMap::new();
+        data.insert("foo".to_string(), "bar".to_string());
+        let val = map_read("foo", &data, None);
+        assert_eq!(val.unwrap(), "bar");
+        assert!(map_read("bar", &data, None).is_none());
+    }
+
+    #[test]
+    fn read_nested_btreemap_with_custom_separator() {
+        let mut inner: BTreeMap<String, Option<f64>> = BTreeMap::new();
+        inner.insert("192.168.0.1".to_string(), Some(0.5));
+        inner.insert("10.0.0.1".to_string(), None);
+        let mut data: BTreeMap<String, BTreeMap<String, Option<f64>>> = BTreeMap::new();
+        data.insert("networks".to_string(), inner);
+        let val = map_read("networks@192.168.0.1", &data, Some("@"));
+        assert_eq!(val.unwrap(), 0.5);
+        let val = map_read("networks@10.0.0.1", &data, Some("@"));
+        assert_eq!(val.unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn read_vec_of_maps() {
+        let mut first: HashMap<String, bool> = HashMap::new();
+        first.insert("enabled".to_string(), true);
+        let data: Vec<HashMap<String, bool>> = vec![first];
+        let val = map_read("[0].enabled", &data, None);
+        assert_eq!(val.unwrap(), true);
+        assert!(map_read("[1].enabled", &data, None).is_none());
+    }
+}
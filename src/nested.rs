@@ -0,0 +1,2746 @@
+//! Format-agnostic path access.
+//!
+//! Any tree type implementing [NestedValue] gets [read], [read_many], [update],
+//! [update_many], [upsert], [delete], [read_all], [update_all],
+//! [delete_all] and [get_paths] with the
+//! same path semantics as the `json_*` functions:
+//! path tokens are joined by a separator (default `.`), `[n]` tokens
+//! address array elements and `[key=value]` tokens address the first
+//! element of an array that is a map holding `value` under `key` (`[#id]`
+//! being short for `[_id=id]`, see [assign_ids], and `[?(@.key==value)]`
+//! the JSONPath-like spelling of `[key=value]`). Keys
+//! holding the separator (or looking like any other kind of token) are
+//! written within double quotes, e.g. `hosts."192.168.0.1".port`, or with
+//! a backslash before the separator, e.g. `hosts.192\.168\.0\.1.port`.
+//!
+//! Implementations are provided for [serde_json::Value] and, with the `toml`
+//! feature, for `toml::Value`, so generic code can operate on either format:
+//!
+//! ```rust
+//! use nestac::nested::{self, NestedValue};
+//!
+//! fn port<V: NestedValue>(data: &V) -> Option<&V> {
+//!     nested::read("server.port", data, None)
+//! }
+//!
+//! fn main() {
+//!     let json_data: serde_json::Value = serde_json::from_str(
+//!         r#"{"server": {"port": 80}}"#,
+//!     ).unwrap();
+//!     assert_eq!(port(&json_data).unwrap(), 80);
+//! }
+//! ```
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use serde_json::Value;
+use crate::NestacError;
+use crate::options::{MergeDepth, Order, PathsOptions};
+
+/// A node of an in-memory tree that can be traversed with path strings.
+///
+/// Implementors must uphold the following:
+/// - a node is either a map (`keys` returns [Some]), an array (`array_len`
+///   returns [Some]) or a leaf (both return [None]);
+/// - `get_key`/`get_key_mut` only return children of maps and
+///   `get_index`/`get_index_mut` only return children of arrays;
+/// - `insert_key` inserts into maps only, giving the value back as [Err]
+///   when the node is not a map;
+/// - `remove_key`/`remove_index` only remove children of maps and arrays
+///   respectively, keeping the order of the remaining children.
+///
+/// # Examples:
+/// - Reading a custom tree type
+/// ```rust
+/// use nestac::nested::{NestedValue, read, get_paths};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Node {
+///     Leaf(i64),
+///     Branch(Vec<(String, Node)>),
+///     List(Vec<Node>),
+/// }
+///
+/// impl NestedValue for Node {
+///     fn get_key(&self, key: &str) -> Option<&Self> {
+///         match self {
+///             Node::Branch(children) => children.iter()
+///                 .find(|(k, _)| k == key)
+///                 .map(|(_, v)| v),
+///             _ => None,
+///         }
+///     }
+///     fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+///         match self {
+///             Node::Branch(children) => children.iter_mut()
+///                 .find(|(k, _)| k == key)
+///                 .map(|(_, v)| v),
+///             _ => None,
+///         }
+///     }
+///     fn get_index(&self, index: usize) -> Option<&Self> {
+///         match self {
+///             Node::List(items) => items.get(index),
+///             _ => None,
+///         }
+///     }
+///     fn get_index_mut(&mut self, index: usize) -> Option<&mut Self> {
+///         match self {
+///             Node::List(items) => items.get_mut(index),
+///             _ => None,
+///         }
+///     }
+///     fn insert_key(&mut self, key: &str, value: Self) -> Result<Option<Self>, Self> {
+///         match self {
+///             Node::Branch(children) => {
+///                 match children.iter_mut().find(|(k, _)| k == key) {
+///                     Some((_, old)) => Ok(Some(std::mem::replace(old, value))),
+///                     None => {
+///                         children.push((key.to_string(), value));
+///                         Ok(None)
+///                     }
+///                 }
+///             }
+///             _ => Err(value),
+///         }
+///     }
+///     fn remove_key(&mut self, key: &str) -> Option<Self> {
+///         match self {
+///             Node::Branch(children) => {
+///                 let idx = children.iter().position(|(k, _)| k == key)?;
+///                 Some(children.remove(idx).1)
+///             }
+///             _ => None,
+///         }
+///     }
+///     fn remove_index(&mut self, index: usize) -> Option<Self> {
+///         match self {
+///             Node::List(items) if index < items.len() => Some(items.remove(index)),
+///             _ => None,
+///         }
+///     }
+///     fn new_map() -> Self {
+///         Node::Branch(vec![])
+///     }
+///     fn keys(&self) -> Option<Vec<String>> {
+///         match self {
+///             Node::Branch(children) => Some(
+///                 children.iter().map(|(k, _)| k.clone()).collect()
+///             ),
+///             _ => None,
+///         }
+///     }
+///     fn array_len(&self) -> Option<usize> {
+///         match self {
+///             Node::List(items) => Some(items.len()),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let tree = Node::Branch(vec![
+///         ("foo".to_string(), Node::List(vec![Node::Leaf(42)])),
+///     ]);
+///     assert_eq!(read("foo.[0]", &tree, None), Some(&Node::Leaf(42)));
+///     assert_eq!(get_paths(&tree, None), vec!["$", "$.foo", "$.foo.0"]);
+/// }
+/// ```
+pub trait NestedValue: Sized {
+    /// Child stored under `key` when the node is a map.
+    fn get_key(&self, key: &str) -> Option<&Self>;
+
+    /// Mutable child stored under `key` when the node is a map.
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self>;
+
+    /// Child stored at `index` when the node is an array.
+    fn get_index(&self, index: usize) -> Option<&Self>;
+
+    /// Mutable child stored at `index` when the node is an array.
+    fn get_index_mut(&mut self, index: usize) -> Option<&mut Self>;
+
+    /// Inserts `value` under `key` returning the previous child, or gives
+    /// `value` back as [Err] when the node is not a map.
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<Option<Self>, Self>;
+
+    /// Removes and returns the child stored under `key` when the node is a
+    /// map.
+    fn remove_key(&mut self, key: &str) -> Option<Self>;
+
+    /// Removes and returns the child stored at `index` when the node is an
+    /// array, shifting the following elements.
+    fn remove_index(&mut self, index: usize) -> Option<Self>;
+
+    /// An empty map, used to create missing intermediate nodes.
+    fn new_map() -> Self;
+
+    /// Keys of the node, in iteration order, when the node is a map.
+    fn keys(&self) -> Option<Vec<String>>;
+
+    /// Number of elements when the node is an array.
+    fn array_len(&self) -> Option<usize>;
+
+    /// Text of a scalar node, matched against the value of `[key=value]`
+    /// tokens. Nodes without one (the default) never match.
+    fn as_text(&self) -> Option<String> {
+        None
+    }
+
+    /// A string node, used to store the ids generated by [assign_ids].
+    /// Types without strings return [None] (the default).
+    fn new_string(_text: &str) -> Option<Self> {
+        None
+    }
+
+    /// An empty array, used by [ensure] to create missing intermediate
+    /// arrays. Types without arrays return [None] (the default).
+    fn new_array() -> Option<Self> {
+        None
+    }
+
+    /// Appends `value` when the node is an array, or gives `value` back as
+    /// [Err] otherwise (the default).
+    fn push_index(&mut self, value: Self) -> Result<(), Self> {
+        Err(value)
+    }
+
+    /// Mutable flag of a boolean node, used by [set_flag]. Types without
+    /// booleans return [None] (the default).
+    fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        None
+    }
+
+    /// Human readable name of the kind of node, as reported on
+    /// [NestacError::TypeMismatch].
+    fn kind(&self) -> &'static str {
+        if self.keys().is_some() {
+            "object"
+        } else if self.array_len().is_some() {
+            "array"
+        } else {
+            "scalar"
+        }
+    }
+}
+
+impl NestedValue for Value {
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_object()?.get(key)
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Self> {
+        self.as_array()?.get(index)
+    }
+
+    fn get_index_mut(&mut self, index: usize) -> Option<&mut Self> {
+        self.as_array_mut()?.get_mut(index)
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<Option<Self>, Self> {
+        match self.as_object_mut() {
+            Some(map) => Ok(map.insert(key.to_string(), value)),
+            None => Err(value),
+        }
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        let map = self.as_object_mut()?;
+        if !map.contains_key(key) {
+            return None;
+        }
+        // `Map::remove` swaps the last entry in with `preserve_order`, so the
+        // map is rebuilt to keep the order of the remaining keys.
+        let mut removed = None;
+        for (k, v) in std::mem::take(map) {
+            if k == key {
+                removed = Some(v);
+            } else {
+                map.insert(k, v);
+            }
+        }
+        removed
+    }
+
+    fn remove_index(&mut self, index: usize) -> Option<Self> {
+        let items = self.as_array_mut()?;
+        if index < items.len() {
+            Some(items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    fn push_index(&mut self, value: Self) -> Result<(), Self> {
+        match self.as_array_mut() {
+            Some(items) => {
+                items.push(value);
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    fn new_map() -> Self {
+        Value::Object(serde_json::Map::new())
+    }
+
+    fn new_array() -> Option<Self> {
+        Some(Value::Array(vec![]))
+    }
+
+    fn keys(&self) -> Option<Vec<String>> {
+        Some(self.as_object()?.keys().cloned().collect())
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        Some(self.as_array()?.len())
+    }
+
+    fn new_string(text: &str) -> Option<Self> {
+        Some(Value::String(text.to_string()))
+    }
+
+    fn as_text(&self) -> Option<String> {
+        match self {
+            Value::String(text) => Some(text.clone()),
+            Value::Number(_) | Value::Bool(_) => Some(self.to_string()),
+            _ => None,
+        }
+    }
+
+    fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Value::Bool(flag) => Some(flag),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        crate::error::value_kind(self)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl NestedValue for toml::Value {
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_table()?.get(key)
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_table_mut()?.get_mut(key)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Self> {
+        self.as_array()?.get(index)
+    }
+
+    fn get_index_mut(&mut self, index: usize) -> Option<&mut Self> {
+        self.as_array_mut()?.get_mut(index)
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<Option<Self>, Self> {
+        match self.as_table_mut() {
+            Some(map) => Ok(map.insert(key.to_string(), value)),
+            None => Err(value),
+        }
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        self.as_table_mut()?.remove(key)
+    }
+
+    fn remove_index(&mut self, index: usize) -> Option<Self> {
+        let items = self.as_array_mut()?;
+        if index < items.len() {
+            Some(items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    fn push_index(&mut self, value: Self) -> Result<(), Self> {
+        match self.as_array_mut() {
+            Some(items) => {
+                items.push(value);
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    fn new_map() -> Self {
+        toml::Value::Table(toml::Table::new())
+    }
+
+    fn new_array() -> Option<Self> {
+        Some(toml::Value::Array(vec![]))
+    }
+
+    fn keys(&self) -> Option<Vec<String>> {
+        Some(self.as_table()?.keys().cloned().collect())
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        Some(self.as_array()?.len())
+    }
+
+    fn new_string(text: &str) -> Option<Self> {
+        Some(toml::Value::String(text.to_string()))
+    }
+
+    fn as_text(&self) -> Option<String> {
+        match self {
+            toml::Value::String(text) => Some(text.clone()),
+            toml::Value::Array(_) | toml::Value::Table(_) => None,
+            _ => Some(self.to_string()),
+        }
+    }
+
+    fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            toml::Value::Boolean(flag) => Some(flag),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        self.type_str()
+    }
+}
+
+/// A token of a path string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Token<'p> {
+    /// The token as written on the path.
+    pub(crate) raw: &'p str,
+    /// The token with its quotes and escapes resolved.
+    pub(crate) text: Cow<'p, str>,
+    /// Where `raw` starts on the path.
+    pub(crate) offset: usize,
+    /// Whether the token was quoted or escaped, making it a plain key.
+    pub(crate) literal: bool,
+}
+
+impl Token<'_> {
+    pub(crate) fn step(&self) -> Step<'_> {
+        if self.literal {
+            Step::Key(&self.text)
+        } else {
+            Step::parse(self.raw)
+        }
+    }
+
+    fn wildcard(&self) -> Option<Wildcard<'_>> {
+        if self.literal {
+            None
+        } else {
+            Wildcard::parse(self.raw)
+        }
+    }
+}
+
+/// Splits `path` into tokens on `separator`.
+///
+/// A token wrapped in double quotes is a plain key, e.g. `"192.168.0.1"`,
+/// and a backslash escapes the separator, a double quote or another
+/// backslash. Any other backslash, or a quote not closing right before a
+/// separator, is kept as is.
+pub(crate) fn tokenize<'p>(path: &'p str, separator: &str) -> Vec<Token<'p>> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut text = String::new();
+    let mut literal = false;
+    let mut quoted = false;
+    let mut i = 0;
+    while i < path.len() {
+        let rest = &path[i..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            let len = if !separator.is_empty() && escaped.starts_with(separator) {
+                separator.len()
+            } else if escaped.starts_with(['"', '\\']) {
+                1
+            } else {
+                0
+            };
+            if len > 0 {
+                text.push_str(&escaped[..len]);
+                literal = true;
+                i += 1 + len;
+                continue;
+            }
+        }
+        if quoted {
+            if rest.starts_with('"') && ends_token(&rest[1..], separator) {
+                quoted = false;
+                i += 1;
+                continue;
+            }
+        } else if i == start && rest.starts_with('"') && closes_quote(&rest[1..], separator) {
+            quoted = true;
+            literal = true;
+            i += 1;
+            continue;
+        } else if let Some(len) = filter_len(rest).filter(|_| i == start) {
+            // `[?(@.key==value)]` is kept whole, separators included
+            text.push_str(&rest[..len]);
+            i += len;
+            continue;
+        } else if !separator.is_empty() && rest.starts_with(separator) {
+            tokens.push(token(path, start, i, std::mem::take(&mut text), literal));
+            literal = false;
+            i += separator.len();
+            start = i;
+            continue;
+        }
+        let c = rest.chars().next().expect("not at the end of the path");
+        text.push(c);
+        i += c.len_utf8();
+    }
+    tokens.push(token(path, start, path.len(), text, literal));
+    tokens
+}
+
+fn token(path: &str, start: usize, end: usize, text: String, literal: bool) -> Token<'_> {
+    let raw = &path[start..end];
+    Token {
+        raw,
+        text: if literal { Cow::Owned(text) } else { Cow::Borrowed(raw) },
+        offset: start,
+        literal,
+    }
+}
+
+/// Whether a token ends at the start of `rest`.
+fn ends_token(rest: &str, separator: &str) -> bool {
+    rest.is_empty() || (!separator.is_empty() && rest.starts_with(separator))
+}
+
+/// Whether a quote opened right before `rest` is closed at the end of the
+/// token.
+fn closes_quote(rest: &str, separator: &str) -> bool {
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' if ends_token(&rest[i + 1..], separator) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Writes `key` as a path token, quoting it when it holds the separator,
+/// a quote or a backslash, or would be read as anything but a key.
+pub(crate) fn escape_key<'k>(key: &'k str, separator: &str) -> Cow<'k, str> {
+    let plain = !key.contains(['"', '\\'])
+        && (separator.is_empty() || !key.contains(separator))
+        && matches!(Step::parse(key), Step::Key(_))
+        && Wildcard::parse(key).is_none();
+    if plain {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\"")))
+    }
+}
+
+/// Returns the array index of a `[n]` token.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    let digits = token.strip_prefix('[')?.strip_suffix(']')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<usize>().ok()
+}
+
+/// A `[start:end]` token, addressing a range of array elements the way
+/// Python slices do: both bounds are optional, negative ones count from the
+/// end of the array and out of range ones are clamped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Slice {
+    pub(crate) start: Option<i64>,
+    pub(crate) end: Option<i64>,
+}
+
+impl Slice {
+    pub(crate) fn parse(token: &str) -> Option<Self> {
+        let (start, end) = token.strip_prefix('[')?.strip_suffix(']')?.split_once(':')?;
+        let bound = |text: &str| -> Option<Option<i64>> {
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            match text.is_empty() {
+                true => Some(None),
+                false if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => text.parse().ok().map(Some),
+                false => None,
+            }
+        };
+        Some(Slice { start: bound(start)?, end: bound(end)? })
+    }
+
+    /// Elements addressed on an array of `len` elements.
+    pub(crate) fn range(&self, len: usize) -> std::ops::Range<usize> {
+        let len = len as i64;
+        let clamp = |bound: i64| if bound < 0 { (len + bound).max(0) } else { bound.min(len) };
+        let start = self.start.map_or(0, clamp);
+        let end = self.end.map_or(len, clamp).max(start);
+        start as usize..end as usize
+    }
+}
+
+/// Last token of a path pushing the new value onto the array it follows,
+/// see [update].
+const APPEND: &str = "[+]";
+
+/// Replaces the `range` of the array `node` with the elements of the array
+/// `items`, returning the elements replaced as an array.
+fn splice<V: NestedValue>(node: &mut V, range: std::ops::Range<usize>, mut items: V) -> Option<V> {
+    let mut removed = V::new_array()?;
+    let len = node.array_len()?;
+    let count = items.array_len()?;
+    let mut tail = vec![];
+    for idx in (range.start..len).rev() {
+        tail.push(node.remove_index(idx)?);
+    }
+    let mut new_items = vec![];
+    for idx in (0..count).rev() {
+        new_items.push(items.remove_index(idx)?);
+    }
+    for item in new_items.into_iter().rev() {
+        node.push_index(item).ok()?;
+    }
+    for (offset, item) in tail.into_iter().rev().enumerate() {
+        match offset < range.len() {
+            true => removed.push_index(item).ok()?,
+            false => node.push_index(item).ok()?,
+        }
+    }
+    Some(removed)
+}
+
+/// Returns the elements addressed by a path ending with a `[start:end]`
+/// token as a new array, see [crate::json_read_slice].
+pub fn read_slice<V: NestedValue + Clone>(path: &str, data: &V, separator: Option<&str>) -> Option<V> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last()?;
+    let slice = match last.step() {
+        Step::Slice(slice) => slice,
+        _ => return None,
+    };
+    let mut sel_data = data;
+    for token in parents {
+        sel_data = token.step().get(sel_data)?;
+    }
+    let mut sliced = V::new_array()?;
+    for idx in slice.range(sel_data.array_len()?) {
+        sliced.push_index(sel_data.get_index(idx)?.clone()).ok()?;
+    }
+    Some(sliced)
+}
+
+/// Key under which [assign_ids] stores ids by default, and that `[#id]`
+/// tokens look up.
+pub const ID_KEY: &str = "_id";
+
+/// Returns the id of a `[#id]` token.
+fn parse_id(token: &str) -> Option<&str> {
+    let id = token.strip_prefix("[#")?.strip_suffix(']')?;
+    if id.is_empty() {
+        return None;
+    }
+    Some(id)
+}
+
+/// Length of the `[?(...)]` filter token at the start of `rest`, running
+/// to the end of `rest` when the filter is not terminated.
+fn filter_len(rest: &str) -> Option<usize> {
+    if !rest.starts_with("[?(") {
+        return None;
+    }
+    Some(filter_end(rest).unwrap_or(rest.len()))
+}
+
+/// End of the `[?(...)]` filter opening `rest`: the first `)]` outside a
+/// quoted value, where a backslash escapes the character after it.
+fn filter_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut chars = rest.char_indices().skip(3);
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if rest[i..].starts_with(")]") => return Some(i + 2),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Returns the key and value of a `[key=value]` token, or of its
+/// `[?(@.key==value)]` filter form where the value may be quoted.
+pub(crate) fn parse_selector(token: &str) -> Option<(&str, &str)> {
+    let inner = token.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = match inner.strip_prefix("?(") {
+        Some(filter) => {
+            let (key, value) = filter.strip_suffix(')')?.trim().strip_prefix("@.")?.split_once("==")?;
+            (key.trim(), unquote(value.trim()))
+        }
+        None => inner.split_once('=')?,
+    };
+    if key.is_empty() {
+        return None;
+    }
+    Some((key, value))
+}
+
+/// `text` without the single or double quotes around it, if any.
+fn unquote(text: &str) -> &str {
+    ['\'', '"']
+        .into_iter()
+        .find_map(|quote| text.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(text)
+}
+
+/// A path token, resolved against the node it is looked up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step<'t> {
+    Key(&'t str),
+    Index(usize),
+    /// `[key=value]`
+    Select(&'t str, &'t str),
+    /// `[start:end]`
+    Slice(Slice),
+    /// `[+]`, past the last element of an array
+    Append,
+}
+
+impl<'t> Step<'t> {
+    pub(crate) fn parse(token: &'t str) -> Self {
+        if let Some(idx) = parse_index(token) {
+            Step::Index(idx)
+        } else if let Some(id) = parse_id(token) {
+            Step::Select(ID_KEY, id)
+        } else if let Some((key, value)) = parse_selector(token) {
+            // `[*key=value]` selects the first match outside of `*_all`
+            Step::Select(key.strip_prefix('*').unwrap_or(key), value)
+        } else if let Some(slice) = Slice::parse(token) {
+            Step::Slice(slice)
+        } else if token == APPEND {
+            Step::Append
+        } else {
+            Step::Key(token)
+        }
+    }
+
+    /// Index addressed on the array `node`, or [None] for keys, slices,
+    /// appends and selectors matching no element.
+    pub(crate) fn index<V: NestedValue>(&self, node: &V) -> Option<usize> {
+        match *self {
+            Step::Key(_) | Step::Slice(_) | Step::Append => None,
+            Step::Index(idx) => Some(idx),
+            Step::Select(..) => (0..node.array_len()?).find(|&idx| self.matches(node, idx)),
+        }
+    }
+
+    /// Whether the element at `idx` of the array `node` is selected by a
+    /// `[key=value]` step.
+    fn matches<V: NestedValue>(&self, node: &V, idx: usize) -> bool {
+        match *self {
+            Step::Select(key, value) => node.get_index(idx)
+                .and_then(|item| item.get_key(key))
+                .and_then(|item| item.as_text())
+                .is_some_and(|text| text == value),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn get<'a, V: NestedValue>(&self, node: &'a V) -> Option<&'a V> {
+        match *self {
+            Step::Key(key) => node.get_key(key),
+            _ => node.get_index(self.index(node)?),
+        }
+    }
+
+    pub(crate) fn get_mut<'a, V: NestedValue>(&self, node: &'a mut V) -> Option<&'a mut V> {
+        match *self {
+            Step::Key(key) => node.get_key_mut(key),
+            _ => {
+                let idx = self.index(node)?;
+                node.get_index_mut(idx)
+            }
+        }
+    }
+
+    /// Sets the child addressed on `node` and returns the old one, see
+    /// [update]. `new_value` is given back as [Err] when `node` was left
+    /// untouched.
+    pub(crate) fn replace<V: NestedValue>(&self, node: &mut V, new_value: V) -> Result<Option<V>, V> {
+        match *self {
+            Step::Key(key) => node.insert_key(key, new_value),
+            Step::Slice(slice) => match (node.array_len(), new_value.array_len()) {
+                (Some(len), Some(_)) => Ok(splice(node, slice.range(len), new_value)),
+                _ => Err(new_value),
+            },
+            Step::Append => node.push_index(new_value).map(|()| None),
+            _ => match self.get_mut(node) {
+                Some(old) => Ok(Some(std::mem::replace(old, new_value))),
+                None => Err(new_value),
+            },
+        }
+    }
+
+    /// Removes the child addressed on `node`, see [delete].
+    pub(crate) fn remove<V: NestedValue>(&self, node: &mut V) -> Option<V> {
+        match *self {
+            Step::Key(key) => node.remove_key(key),
+            Step::Slice(slice) => {
+                let range = slice.range(node.array_len()?);
+                splice(node, range, V::new_array()?)
+            }
+            Step::Append => None,
+            _ => {
+                let idx = self.index(node)?;
+                node.remove_index(idx)
+            }
+        }
+    }
+}
+
+/// Return a node based off the token-based [str] path.
+///
+/// See [crate::json_read] for the path format. A `[start:end]` token
+/// addresses several elements, which cannot be borrowed as one node: it is
+/// read with [read_slice] or [read_all], and is [None] here.
+pub fn read<'a, V: NestedValue>(path: &str, data: &'a V, separator: Option<&str>) -> Option<&'a V> {
+    let mut sel_data = data;
+    for token in tokenize(path, separator.unwrap_or(".")) {
+        sel_data = token.step().get(sel_data)?;
+    }
+    Some(sel_data)
+}
+
+/// Returns the node addressed by each path, walking the tree once.
+///
+/// Each path works as on [read]; paths sharing a prefix traverse it only
+/// once. Paths that do not exist map to [None].
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::nested;
+///
+/// fn main() {
+///     let data = json!({"server": {"host": "localhost", "port": 80}});
+///     let found = nested::read_many(&["server.host", "server.port", "client"], &data, None);
+///     assert_eq!(found["server.port"], Some(&json!(80)));
+///     assert_eq!(found["client"], None);
+/// }
+/// ```
+pub fn read_many<'a, V: NestedValue>(paths: &[&str], data: &'a V, separator: Option<&str>) -> HashMap<String, Option<&'a V>> {
+    let separator = separator.unwrap_or(".");
+    let mut found = HashMap::new();
+    let pending = paths.iter().map(|path| (*path, tokenize(path, separator))).collect();
+    read_into(data, pending, 0, &mut found);
+    for path in paths {
+        found.entry(path.to_string()).or_insert(None);
+    }
+    found
+}
+
+/// Looks `pending` paths up below `node`, which their first `depth` tokens
+/// lead to.
+fn read_into<'a, V: NestedValue>(node: &'a V, pending: Vec<(&str, Vec<Token>)>, depth: usize, found: &mut HashMap<String, Option<&'a V>>) {
+    // group by child, in order of first appearance
+    let mut groups: Vec<Vec<(&str, Vec<Token>)>> = vec![];
+    for read in pending {
+        let step = read.1[depth].step();
+        match groups.iter_mut().find(|group| group[0].1[depth].step() == step) {
+            Some(group) => group.push(read),
+            None => groups.push(vec![read]),
+        }
+    }
+    for group in groups {
+        let child = match group[0].1[depth].step().get(node) {
+            Some(child) => child,
+            None => continue,
+        };
+        let (here, deeper): (Vec<_>, Vec<_>) = group
+            .into_iter()
+            .partition(|(_, tokens)| tokens.len() == depth + 1);
+        for (path, _) in here {
+            found.insert(path.to_string(), Some(child));
+        }
+        if !deeper.is_empty() {
+            read_into(child, deeper, depth + 1, found);
+        }
+    }
+}
+
+/// Looks `token` up on `node`, describing why it could not be resolved.
+fn try_child<'a, V: NestedValue>(node: &'a V, path: &str, token: &Token, index: usize) -> Result<Option<&'a V>, NestacError> {
+    check_token(path, token)?;
+    try_step(node, token.step(), path, token.raw, index, token.offset)
+}
+
+/// Reports a `token` of `path` that cannot be parsed: an index too large to
+/// be addressed or a `[?(` filter missing its closing `)]`.
+pub(crate) fn check_token(path: &str, token: &Token) -> Result<(), NestacError> {
+    if token.literal {
+        return Ok(());
+    }
+    if let Some(Err(reason)) = index_token(token.raw) {
+        return Err(NestacError::InvalidPath {
+            path: path.to_string(),
+            reason: format!("`{}` is not a valid index: {}", token.raw, reason),
+        });
+    }
+    if token.raw.starts_with("[?(") && filter_end(token.raw).is_none() {
+        return Err(NestacError::InvalidPath {
+            path: path.to_string(),
+            reason: format!("`{}` is not a terminated filter, expected `)]`", token.raw),
+        });
+    }
+    Ok(())
+}
+
+/// Looks an already parsed `token` up on `node`, describing why it could
+/// not be resolved.
+pub(crate) fn try_step<'a, V: NestedValue>(node: &'a V, step: Step, path: &str, token: &str, index: usize, offset: usize) -> Result<Option<&'a V>, NestacError> {
+    let mismatch = |expected: &str| NestacError::TypeMismatch {
+        path: path.to_string(),
+        segment: token.to_string(),
+        index,
+        offset,
+        expected: expected.to_string(),
+        found: node.kind().to_string(),
+    };
+    match step {
+        Step::Key(_) if node.keys().is_some() => Ok(step.get(node)),
+        Step::Key(_) => Err(mismatch("object")),
+        Step::Index(idx) => match node.array_len() {
+            Some(len) if idx < len => Ok(node.get_index(idx)),
+            Some(len) => Err(NestacError::IndexOutOfBounds {
+                path: path.to_string(),
+                segment: token.to_string(),
+                index,
+                offset,
+                len,
+            }),
+            None => Err(mismatch("array")),
+        },
+        Step::Select(..) if node.array_len().is_some() => Ok(step.get(node)),
+        Step::Select(..) => Err(mismatch("array")),
+        Step::Slice(_) => Err(NestacError::InvalidPath {
+            path: path.to_string(),
+            reason: format!("`{}` addresses several elements, read it with `read_slice`", token),
+        }),
+        Step::Append => Err(NestacError::InvalidPath {
+            path: path.to_string(),
+            reason: format!("`{}` can only end the path of an update", token),
+        }),
+    }
+}
+
+/// Parses a `[n]` token, reporting indexes too large to be addressed.
+pub(crate) fn index_token(token: &str) -> Option<Result<usize, std::num::ParseIntError>> {
+    let digits = token.strip_prefix('[')?.strip_suffix(']')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(digits.parse::<usize>())
+}
+
+/// Walks `tokens` of `path`, reporting failures against the whole `path`.
+fn try_walk<'a, V: NestedValue>(path: &str, tokens: &[Token], data: &'a V) -> Result<&'a V, NestacError> {
+    let mut sel_data = data;
+    for (index, token) in tokens.iter().enumerate() {
+        sel_data = match try_child(sel_data, path, token, index)? {
+            Some(child) => child,
+            None => return Err(NestacError::MissingKey {
+                path: path.to_string(),
+                segment: token.raw.to_string(),
+                index,
+                offset: token.offset,
+            }),
+        };
+    }
+    Ok(sel_data)
+}
+
+/// Same as [read] but describes which token could not be resolved.
+/// A `[start:end]` token is reported as [NestacError::InvalidPath].
+///
+/// See [crate::json_try_read].
+pub fn try_read<'a, V: NestedValue>(path: &str, data: &'a V, separator: Option<&str>) -> Result<&'a V, NestacError> {
+    try_walk(path, &tokenize(path, separator.unwrap_or(".")), data)
+}
+
+/// Deserializes `deserializer` (the value read at `path`) into `T`,
+/// reporting failures as [NestacError::Deserialize] pointing at the
+/// offending field: `path` followed by the path inside the value.
+///
+/// See [crate::json_read_as].
+pub(crate) fn deserialize_at<'de, T, D>(path: &str, separator: Option<&str>, deserializer: D) -> Result<T, NestacError>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+    D::Error: std::fmt::Display,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let separator = separator.unwrap_or(".");
+        let mut tokens = vec![path.to_string()];
+        for segment in err.path().iter() {
+            match segment {
+                serde_path_to_error::Segment::Seq { index } => {
+                    tokens.push(format!("[{}]", index));
+                }
+                serde_path_to_error::Segment::Map { key } => {
+                    tokens.push(key.to_string());
+                }
+                serde_path_to_error::Segment::Enum { variant } => {
+                    tokens.push(variant.to_string());
+                }
+                serde_path_to_error::Segment::Unknown => {
+                    tokens.push("?".to_string());
+                }
+            }
+        }
+        NestacError::Deserialize {
+            path: tokens.join(separator),
+            message: err.into_inner().to_string(),
+        }
+    })
+}
+
+/// Same as [update] but describes why the path could not be updated
+/// instead of returning [None].
+///
+/// Every token but the last must exist. The last one may be a missing key,
+/// which is inserted (returning `Ok(None)`), but not a missing array
+/// element. `data` is left untouched on failure.
+pub fn try_update<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>, new_value: V) -> Result<Option<V>, NestacError> {
+    check_update(&*data, path, separator, &new_value)?;
+    Ok(update(data, path, separator, new_value))
+}
+
+/// Fails, like [try_update] does, when `new_value` cannot be written at
+/// `path`.
+pub(crate) fn check_update<V: NestedValue>(data: &V, path: &str, separator: Option<&str>, new_value: &V) -> Result<(), NestacError> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last().expect("a path has at least one token");
+    let parent = try_walk(path, parents, data)?;
+    let mismatch = |found: &V| NestacError::TypeMismatch {
+        path: path.to_string(),
+        segment: last.raw.to_string(),
+        index: parents.len(),
+        offset: last.offset,
+        expected: "array".to_string(),
+        found: found.kind().to_string(),
+    };
+    match last.step() {
+        Step::Slice(_) => {
+            if parent.array_len().is_none() {
+                return Err(mismatch(parent));
+            }
+            if new_value.array_len().is_none() {
+                return Err(mismatch(new_value));
+            }
+        }
+        Step::Append => {
+            if parent.array_len().is_none() {
+                return Err(mismatch(parent));
+            }
+        }
+        _ => {
+            try_child(parent, path, last, parents.len())?;
+        }
+    }
+    Ok(())
+}
+
+/// Consumes `data` returning the node at the token-based [str] path, or
+/// gives `data` back along with the reason why the path could not be
+/// resolved.
+///
+/// See [crate::json_extract].
+pub fn extract<V: NestedValue>(mut data: V, path: &str, separator: Option<&str>) -> Result<V, (V, NestacError)> {
+    if let Err(err) = try_read(path, &data, separator) {
+        return Err((data, err));
+    }
+    let node = read_mut(path, &mut data, separator).expect("the path was resolved");
+    Ok(std::mem::replace(node, V::new_map()))
+}
+
+/// Same as [read] but returns a mutable reference to the node.
+pub fn read_mut<'a, V: NestedValue>(path: &str, data: &'a mut V, separator: Option<&str>) -> Option<&'a mut V> {
+    let mut sel_data = data;
+    for token in tokenize(path, separator.unwrap_or(".")) {
+        sel_data = token.step().get_mut(sel_data)?;
+    }
+    Some(sel_data)
+}
+
+/// Replaces the node at the token-based [str] path with the one derived by
+/// `transform` from it, and returns the old node.
+///
+/// Returns [None], without calling `transform`, when the path does not
+/// exist.
+pub fn transform<V, F>(data: &mut V, path: &str, separator: Option<&str>, transform: F) -> Option<V>
+where
+    V: NestedValue,
+    F: FnOnce(&V) -> V,
+{
+    let node = read_mut(path, data, separator)?;
+    let new_value = transform(node);
+    Some(std::mem::replace(node, new_value))
+}
+
+/// Calls `visit` on every node in pre-order, the root first, with its path
+/// written as on [read] (empty for the root), stopping at the first
+/// [ControlFlow::Break].
+///
+/// The children of a node are walked after `visit` returns, so a node
+/// replaced by `visit` has its new children walked. See
+/// [crate::json_walk_mut].
+pub fn walk_mut<V, B, F>(data: &mut V, mut visit: F) -> ControlFlow<B>
+where
+    V: NestedValue,
+    F: FnMut(&str, &mut V) -> ControlFlow<B>,
+{
+    walk_node(data, String::new(), &mut visit)
+}
+
+fn walk_node<V, B, F>(node: &mut V, path: String, visit: &mut F) -> ControlFlow<B>
+where
+    V: NestedValue,
+    F: FnMut(&str, &mut V) -> ControlFlow<B>,
+{
+    visit(&path, node)?;
+    let join = |token: &str| match path.is_empty() {
+        true => token.to_string(),
+        false => [path.as_str(), token].join("."),
+    };
+    if let Some(keys) = node.keys() {
+        for key in keys {
+            if let Some(child) = node.get_key_mut(&key) {
+                walk_node(child, join(&escape_key(&key, ".")), visit)?;
+            }
+        }
+    } else if let Some(len) = node.array_len() {
+        for i in 0..len {
+            if let Some(child) = node.get_index_mut(i) {
+                walk_node(child, join(&format!("[{}]", i)), visit)?;
+            }
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Replaces the boolean at the token-based [str] path with the one derived
+/// by `flip` from it, and returns the previous state.
+///
+/// Fails with [NestacError::TypeMismatch] when the node is not a boolean,
+/// so a flag is never written over an object or any other value, and with
+/// the reason the path could not be resolved otherwise. `data` is left
+/// untouched on failure.
+///
+/// See [crate::json_toggle].
+pub fn set_flag<V, F>(data: &mut V, path: &str, separator: Option<&str>, flip: F) -> Result<bool, NestacError>
+where
+    V: NestedValue,
+    F: FnOnce(bool) -> bool,
+{
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    try_walk(path, &tokens, &*data)?;
+    let node = read_mut(path, data, separator).expect("the path was resolved");
+    let found = node.kind();
+    let flag = match node.as_bool_mut() {
+        Some(flag) => flag,
+        None => return Err(leaf_mismatch(path, &tokens, "boolean", found)),
+    };
+    let previous = *flag;
+    *flag = flip(previous);
+    Ok(previous)
+}
+
+/// Mismatch reported on the last token of `path` when the node it leads to
+/// is not of the `expected` kind.
+fn leaf_mismatch(path: &str, tokens: &[Token], expected: &str, found: &str) -> NestacError {
+    let last = tokens.last().expect("a path has at least one token");
+    NestacError::TypeMismatch {
+        path: path.to_string(),
+        segment: last.raw.to_string(),
+        index: tokens.len() - 1,
+        offset: last.offset,
+        expected: expected.to_string(),
+        found: found.to_string(),
+    }
+}
+
+/// Resolves the array at `path`, describing why it could not be reached.
+fn try_array<'a, V: NestedValue>(data: &'a V, path: &str, separator: Option<&str>) -> Result<&'a V, NestacError> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let node = try_walk(path, &tokens, data)?;
+    if node.array_len().is_none() {
+        return Err(leaf_mismatch(path, &tokens, "array", node.kind()));
+    }
+    Ok(node)
+}
+
+fn array_items<V: NestedValue>(node: &V) -> impl Iterator<Item = &V> {
+    (0..node.array_len().unwrap_or(0)).filter_map(move |idx| node.get_index(idx))
+}
+
+/// Whether the array at the token-based [str] path holds `value`.
+///
+/// Fails with [NestacError::TypeMismatch] when the path holds anything but
+/// an array, and with the reason the path could not be resolved otherwise.
+///
+/// See [crate::json_array_contains].
+pub fn array_contains<V: NestedValue + PartialEq>(data: &V, path: &str, separator: Option<&str>, value: &V) -> Result<bool, NestacError> {
+    let node = try_array(data, path, separator)?;
+    Ok(array_items(node).any(|item| item == value))
+}
+
+/// Appends `value` to the array at the token-based [str] path unless it
+/// already holds an equal element, and returns whether it was added.
+///
+/// Same failures as [array_contains]; `data` is left untouched on failure.
+///
+/// See [crate::json_array_add_unique].
+pub fn array_add_unique<V: NestedValue + PartialEq>(data: &mut V, path: &str, separator: Option<&str>, value: V) -> Result<bool, NestacError> {
+    if array_contains(data, path, separator, &value)? {
+        return Ok(false);
+    }
+    let node = read_mut(path, data, separator).expect("the path was resolved");
+    if node.push_index(value).is_err() {
+        unreachable!("the path was resolved to an array");
+    }
+    Ok(true)
+}
+
+/// Removes every element equal to `value` from the array at the
+/// token-based [str] path, and returns how many were removed.
+///
+/// Same failures as [array_contains]; `data` is left untouched on failure.
+///
+/// See [crate::json_array_remove_value].
+pub fn array_remove_value<V: NestedValue + PartialEq>(data: &mut V, path: &str, separator: Option<&str>, value: &V) -> Result<usize, NestacError> {
+    try_array(data, path, separator)?;
+    let node = read_mut(path, data, separator).expect("the path was resolved");
+    let mut removed = 0;
+    let mut idx = 0;
+    while let Some(item) = node.get_index(idx) {
+        if item == value {
+            node.remove_index(idx);
+            removed += 1;
+        } else {
+            idx += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Update a node based off the token-based [str] path and returns the old
+/// node.
+///
+/// Returns [None] when the path does not exist or, for the last token, when
+/// the key did not exist before the update. Missing array elements are never
+/// created.
+///
+/// A last `[start:end]` token replaces that range of the array with the
+/// elements of `new_value`, which must be an array, and returns the
+/// elements replaced as an array. A last `[+]` token pushes `new_value` onto
+/// the end of the array, returning [None].
+pub fn update<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>, new_value: V) -> Option<V> {
+    replace_at(data, path, separator, new_value).ok().flatten()
+}
+
+/// Same as [update] but tells whether `data` was changed, giving
+/// `new_value` back as [Err] when the path could not be updated.
+pub(crate) fn replace_at<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>, new_value: V) -> Result<Option<V>, V> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last().expect("a path has at least one token");
+    let mut sel_data = data;
+    for token in parents {
+        sel_data = match token.step().get_mut(sel_data) {
+            Some(child) => child,
+            None => return Err(new_value),
+        };
+    }
+    last.step().replace(sel_data, new_value)
+}
+
+/// An update waiting to be applied by [update_many].
+struct Pending<'p, V> {
+    /// Position of the update, where its old node is returned.
+    slot: usize,
+    tokens: Vec<Token<'p>>,
+    value: V,
+}
+
+/// Applies several updates walking the tree once, and returns the old node
+/// of each of them (in the order of `updates`).
+///
+/// Each update works as on [update]. Updates sharing a prefix traverse it
+/// only once; updates addressing the same node, or one below the other,
+/// through the same tokens are applied in the order given. Wildcards are
+/// not expanded.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::nested;
+///
+/// fn main() {
+///     let mut data = json!({"server": {"host": "localhost", "port": 80}});
+///     let old_vals = nested::update_many(&mut data, vec![
+///         ("server.port", Value::from(8080)),
+///         ("server.tls", Value::Bool(true)),
+///         ("client.port", Value::from(80)),
+///     ], None);
+///     assert_eq!(old_vals, vec![Some(Value::from(80)), None, None]);
+///     assert_eq!(data, json!({"server": {"host": "localhost", "port": 8080, "tls": true}}));
+/// }
+/// ```
+pub fn update_many<V: NestedValue>(data: &mut V, updates: Vec<(&str, V)>, separator: Option<&str>) -> Vec<Option<V>> {
+    let separator = separator.unwrap_or(".");
+    let mut old_values = Vec::new();
+    old_values.resize_with(updates.len(), || None);
+    let pending = updates
+        .into_iter()
+        .enumerate()
+        .map(|(slot, (path, value))| Pending { slot, tokens: tokenize(path, separator), value })
+        .collect();
+    apply_many(data, pending, 0, &mut old_values);
+    old_values
+}
+
+/// Applies `pending` updates to `node`, which their first `depth` tokens
+/// lead to.
+fn apply_many<V: NestedValue>(node: &mut V, pending: Vec<Pending<V>>, depth: usize, old_values: &mut [Option<V>]) {
+    // group by child, in order of first appearance
+    let mut groups: Vec<Vec<Pending<V>>> = vec![];
+    for update in pending {
+        let step = update.tokens[depth].step();
+        match groups.iter_mut().find(|group| group[0].tokens[depth].step() == step) {
+            Some(group) => group.push(update),
+            None => groups.push(vec![update]),
+        }
+    }
+    for group in groups {
+        let mut deeper = vec![];
+        for update in group {
+            if update.tokens.len() == depth + 1 {
+                apply_deeper(node, std::mem::take(&mut deeper), depth, old_values);
+                old_values[update.slot] = update.tokens[depth].step().replace(node, update.value).ok().flatten();
+            } else {
+                deeper.push(update);
+            }
+        }
+        apply_deeper(node, deeper, depth, old_values);
+    }
+}
+
+/// Applies updates going below the child of `node` they all address.
+fn apply_deeper<V: NestedValue>(node: &mut V, pending: Vec<Pending<V>>, depth: usize, old_values: &mut [Option<V>]) {
+    let child = match pending.first() {
+        Some(update) => update.tokens[depth].step().get_mut(node),
+        None => return,
+    };
+    if let Some(child) = child {
+        apply_many(child, pending, depth + 1, old_values);
+    }
+}
+
+/// Update a node based off the token-based [str] path, creating missing
+/// intermediate maps, and returns the old node.
+///
+/// Missing array elements are never created: an index out of bounds, or a
+/// token that cannot be traversed (e.g. a key on a scalar), is reported as a
+/// [NestacError] and leaves `data` untouched. A last `[start:end]` or `[+]`
+/// token works on an existing array, as on [update].
+pub fn upsert<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>, new_value: V) -> Result<Option<V>, NestacError> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let last = tokens.last().expect("a path has at least one token");
+    if matches!(last.step(), Step::Slice(_) | Step::Append) {
+        return try_update(data, path, separator, new_value);
+    }
+    // validate first so a failure does not leave half-created maps behind
+    let mut sel_data = &*data;
+    for (index, token) in tokens.iter().enumerate() {
+        let last = index + 1 == tokens.len();
+        sel_data = match try_child(sel_data, path, token, index)? {
+            Some(child) => child,
+            None if last => break,
+            None => {
+                // maps are created from here on, which cannot be indexed
+                let is_key = |token: &Token| matches!(token.step(), Step::Key(_));
+                if !tokens[index..].iter().all(is_key) {
+                    return Err(NestacError::MissingKey {
+                        path: path.to_string(),
+                        segment: token.raw.to_string(),
+                        index,
+                        offset: token.offset,
+                    });
+                }
+                break;
+            }
+        };
+    }
+    let (last, parents) = tokens.split_last().expect("a path has at least one token");
+    let mut sel_data = data;
+    for token in parents {
+        let step = token.step();
+        if let Step::Key(key) = step {
+            if sel_data.get_key(key).is_none() {
+                let _ = sel_data.insert_key(key, V::new_map());
+            }
+        }
+        sel_data = step.get_mut(sel_data).expect("path validated above");
+    }
+    Ok(last.step().replace(sel_data, new_value).ok().flatten())
+}
+
+/// Creates the missing parents of every path in `paths`, so later updates
+/// of those paths cannot fail on a missing parent, and returns how many
+/// containers were created.
+///
+/// Every token but the last must lead to a container; a missing one is
+/// created as an array when the following token is `[n]` and as a map
+/// otherwise. Missing array elements are only created by appending (`[n]`
+/// where `n` is the length of the array) and `[key=value]` tokens must
+/// already match. Paths are handled in order: when one fails, the ones
+/// before it are already ensured and it is left untouched.
+///
+/// See [crate::json_ensure].
+pub fn ensure<V: NestedValue>(data: &mut V, paths: &[&str], separator: Option<&str>) -> Result<usize, NestacError> {
+    let mut created = 0;
+    for path in paths {
+        created += ensure_path(data, path, separator)?;
+    }
+    Ok(created)
+}
+
+fn ensure_path<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>) -> Result<usize, NestacError> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last().expect("a path has at least one token");
+    // validate first so a failure does not leave half-created containers behind
+    let mut sel_data = &*data;
+    let mut complete = true;
+    for (index, token) in parents.iter().enumerate() {
+        match try_child(sel_data, path, token, index) {
+            Ok(Some(child)) => sel_data = child,
+            Ok(None) => complete = false,
+            Err(NestacError::IndexOutOfBounds { len, .. }) if token.step() == Step::Index(len) => complete = false,
+            Err(err) => return Err(err),
+        }
+        if !complete {
+            check_creatable::<V>(path, &tokens, index)?;
+            break;
+        }
+    }
+    if complete {
+        // the last parent exists: it must still be able to hold the last token
+        match try_child(sel_data, path, last, parents.len()) {
+            Ok(_) | Err(NestacError::IndexOutOfBounds { .. }) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let mut created = 0;
+    let mut sel_data = data;
+    for (index, token) in parents.iter().enumerate() {
+        let step = token.step();
+        if step.get(sel_data).is_none() {
+            let container = match tokens[index + 1].step() {
+                Step::Key(_) => V::new_map(),
+                _ => V::new_array().expect("checked by check_creatable"),
+            };
+            let _ = match step {
+                Step::Key(key) => sel_data.insert_key(key, container).map(|_| ()),
+                _ => sel_data.push_index(container),
+            };
+            created += 1;
+        }
+        sel_data = step.get_mut(sel_data).expect("path validated above");
+    }
+    Ok(created)
+}
+
+/// Fails when the parents of `path` from the token at `from` on cannot be
+/// created by [ensure].
+fn check_creatable<V: NestedValue>(path: &str, tokens: &[Token], from: usize) -> Result<(), NestacError> {
+    for index in from..tokens.len() - 1 {
+        let token = &tokens[index];
+        let step = token.step();
+        // containers created past `from` are empty: only `[0]` can be appended
+        let appendable = index == from || matches!(step, Step::Key(_) | Step::Index(0));
+        if matches!(step, Step::Select(..)) || !appendable {
+            return Err(NestacError::MissingKey {
+                path: path.to_string(),
+                segment: token.raw.to_string(),
+                index,
+                offset: token.offset,
+            });
+        }
+        if !matches!(tokens[index + 1].step(), Step::Key(_)) && V::new_array().is_none() {
+            return Err(NestacError::InvalidPath {
+                path: path.to_string(),
+                reason: format!("cannot create an array for `{}`", token.raw),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Merges `entries` into the map at the token-based [str] path, creating it
+/// (and any missing intermediate map) when absent.
+///
+/// Fails with [NestacError::TypeMismatch] when the path holds anything but
+/// a map, and with the reason the path could not be resolved otherwise.
+/// `data` is left untouched on failure.
+///
+/// See [crate::json_merge_at].
+pub fn merge_at<V, I>(data: &mut V, path: &str, separator: Option<&str>, entries: I, depth: MergeDepth) -> Result<(), NestacError>
+where
+    V: NestedValue,
+    I: IntoIterator<Item = (String, V)>,
+{
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    match try_walk(path, &tokens, &*data) {
+        Ok(node) if node.keys().is_none() => {
+            return Err(leaf_mismatch(path, &tokens, "object", node.kind()));
+        }
+        Ok(_) => {}
+        Err(NestacError::MissingKey { .. }) => {
+            upsert(data, path, separator, V::new_map())?;
+        }
+        Err(err) => return Err(err),
+    }
+    let node = read_mut(path, data, separator).expect("the path was resolved");
+    merge_entries(node, entries, depth);
+    Ok(())
+}
+
+fn merge_entries<V, I>(node: &mut V, entries: I, depth: MergeDepth)
+where
+    V: NestedValue,
+    I: IntoIterator<Item = (String, V)>,
+{
+    for (key, value) in entries {
+        match node.get_key_mut(&key) {
+            Some(existing) if depth == MergeDepth::Deep && existing.keys().is_some() && value.keys().is_some() => {
+                merge_entries(existing, into_entries(value), depth);
+            }
+            _ => {
+                let _ = node.insert_key(&key, value);
+            }
+        }
+    }
+}
+
+/// Takes the `(key, child)` pairs out of a map node.
+fn into_entries<V: NestedValue>(mut node: V) -> Vec<(String, V)> {
+    let keys = node.keys().unwrap_or_default();
+    keys.into_iter()
+        .filter_map(|key| node.remove_key(&key).map(|child| (key, child)))
+        .collect()
+}
+
+/// Remove the node addressed by the token-based [str] path and returns it.
+///
+/// The last token may be a key or a `[n]` index; removing an array element
+/// shifts the following ones. Returns [None] when the path does not exist.
+pub fn delete<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>) -> Option<V> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last()?;
+    let mut sel_data = data;
+    for token in parents {
+        sel_data = token.step().get_mut(sel_data)?;
+    }
+    last.step().remove(sel_data)
+}
+
+/// A token matching several children of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wildcard<'t> {
+    /// `*`: every child of a map or array.
+    Any,
+    /// `[*]`: every element of an array.
+    Elements,
+    /// `[*key=value]`: every element of an array that is a map holding
+    /// `value` under `key`.
+    Select(&'t str, &'t str),
+    /// `**`: every descendant.
+    Descendants,
+}
+
+impl<'t> Wildcard<'t> {
+    fn parse(token: &'t str) -> Option<Self> {
+        match token {
+            "*" => Some(Wildcard::Any),
+            "[*]" => Some(Wildcard::Elements),
+            "**" => Some(Wildcard::Descendants),
+            _ if token.starts_with("[*") => {
+                let (key, value) = parse_selector(token)?;
+                Some(Wildcard::Select(&key[1..], value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Children matched on `node`, along with the token addressing each of
+    /// them. [Wildcard::Descendants] is handled by [descendants].
+    fn children<'a, V: NestedValue>(&self, node: &'a V, separator: &str) -> Vec<(String, &'a V)> {
+        if let Some(len) = node.array_len() {
+            (0..len)
+                .filter(|&idx| match *self {
+                    Wildcard::Select(key, value) => Step::Select(key, value).matches(node, idx),
+                    _ => true,
+                })
+                .filter_map(|idx| Some((format!("[{}]", idx), node.get_index(idx)?)))
+                .collect()
+        } else if matches!(self, Wildcard::Any | Wildcard::Descendants) {
+            node.keys()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|key| {
+                    let child = node.get_key(&key)?;
+                    Some((escape_key(&key, separator).into_owned(), child))
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Whether the concrete `path` is matched by `pattern`, comparing tokens
+/// only: wildcards work as on [read_all], except `[*key=value]` which only
+/// matches the same selector, since the data is not looked at.
+pub(crate) fn path_matches(pattern: &str, path: &str, separator: &str) -> bool {
+    let pattern = tokenize(pattern, separator);
+    let path = tokenize(path, separator);
+    // matched[j]: whether the pattern tokens seen so far match path[..j]
+    let mut matched = vec![false; path.len() + 1];
+    matched[0] = true;
+    for token in &pattern {
+        let wildcard = token.wildcard();
+        let mut next = vec![false; path.len() + 1];
+        for j in 0..=path.len() {
+            next[j] = match wildcard {
+                Some(Wildcard::Descendants) => matched[j] || (j > 0 && next[j - 1]),
+                _ if j == 0 => false,
+                Some(Wildcard::Any) => matched[j - 1],
+                Some(Wildcard::Elements) => {
+                    matched[j - 1] && matches!(path[j - 1].step(), Step::Index(_) | Step::Select(..))
+                }
+                Some(Wildcard::Select(..)) | None => matched[j - 1] && token.step() == path[j - 1].step(),
+            };
+        }
+        matched = next;
+    }
+    matched[path.len()]
+}
+
+/// Return every node matched by the token-based [str] path, along with its
+/// concrete path (wildcards replaced by the key or `[n]` index of each
+/// match), in document order.
+///
+/// A `*` token matches every child of a map or array, a `[*]` token every
+/// element of an array and a `[*key=value]` token every element of an array
+/// that is a map holding `value` under `key` (where `[key=value]` only
+/// matches the first one). A `**` token matches every descendant, at any
+/// depth, and the node itself when followed by more tokens: `**.password`
+/// matches every `password` key of the document. A `[start:end]` token
+/// matches every element of the range, see [read_slice]. Any other token
+/// works as on [read]. Paths that do not exist are left out.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{json_read_all, json_update};
+///
+/// fn main() {
+///     let mut json_data = json!({"servers": {"a": {"port": 80}, "b": {"port": 8080}}});
+///     let ports: Vec<(String, u64)> = json_read_all("servers.*.port", &json_data, None)
+///         .into_iter()
+///         .map(|(path, port)| (path, port.as_u64().unwrap()))
+///         .collect();
+///     for (path, port) in ports {
+///         json_update(&mut json_data, &path, None, Value::from(port + 1));
+///     }
+///     assert_eq!(json_data, json!({"servers": {"a": {"port": 81}, "b": {"port": 8081}}}));
+/// }
+/// ```
+pub fn read_all<'a, V: NestedValue>(path: &str, data: &'a V, separator: Option<&str>) -> Vec<(String, &'a V)> {
+    let separator = separator.unwrap_or(".");
+    expand(&tokenize(path, separator), data, separator, false)
+        .into_iter()
+        .map(|(tokens, node)| (tokens.join(separator), node))
+        .collect()
+}
+
+/// Returns the path of every node below the root for which `predicate`
+/// holds, in document order, written as on [read].
+///
+/// See [crate::json_find_paths_where].
+pub fn find_paths_where<V, F>(data: &V, predicate: F) -> Vec<String>
+where
+    V: NestedValue,
+    F: Fn(&V) -> bool,
+{
+    let options = PathsOptions::new().symbol("").brackets(true);
+    entries(data, &options)
+        .filter(|(_, node)| predicate(node))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Returns the concrete path of every node matched by `pattern`, where
+/// wildcards work as on [read_all], walking only the parts of the tree the
+/// pattern can reach.
+///
+/// See [crate::json_get_paths_matching].
+pub fn get_paths_matching<V: NestedValue>(data: &V, pattern: &str, separator: Option<&str>) -> Vec<String> {
+    let separator = separator.unwrap_or(".");
+    expand(&tokenize(pattern, separator), data, separator, false)
+        .into_iter()
+        .map(|(tokens, _)| tokens.join(separator))
+        .collect()
+}
+
+/// Concrete tokens of every node matched by `tokens`. A `**` token matches
+/// zero levels unless it is the last one and the path is not `open`, i.e.
+/// followed by more tokens the caller handles.
+fn expand<'a, V: NestedValue>(tokens: &[Token], data: &'a V, separator: &str, open: bool) -> Vec<(Vec<String>, &'a V)> {
+    let mut matches: Vec<(Vec<String>, &V)> = vec![(vec![], data)];
+    for (index, token) in tokens.iter().enumerate() {
+        let mut next = vec![];
+        for (tokens_so_far, node) in matches {
+            let children = match token.wildcard() {
+                Some(Wildcard::Descendants) => {
+                    if open || index + 1 < tokens.len() {
+                        next.push((tokens_so_far.clone(), node));
+                    }
+                    descendants(node, &tokens_so_far, separator, &mut next);
+                    continue;
+                }
+                Some(wildcard) => wildcard.children(node, separator),
+                None => match token.step() {
+                    Step::Slice(slice) => node
+                        .array_len()
+                        .map(|len| slice.range(len))
+                        .unwrap_or_default()
+                        .filter_map(|idx| Some((format!("[{}]", idx), node.get_index(idx)?)))
+                        .collect(),
+                    step => step.get(node).map(|child| (token.raw.to_string(), child)).into_iter().collect(),
+                },
+            };
+            for (child_token, child) in children {
+                let mut child_tokens = tokens_so_far.clone();
+                child_tokens.push(child_token);
+                next.push((child_tokens, child));
+            }
+        }
+        matches = next;
+    }
+    matches
+}
+
+/// Pushes every descendant of `node` (at `tokens`) to `out`, in document
+/// order.
+fn descendants<'a, V: NestedValue>(node: &'a V, tokens: &[String], separator: &str, out: &mut Vec<(Vec<String>, &'a V)>) {
+    for (child_token, child) in Wildcard::Any.children(node, separator) {
+        let mut child_tokens = tokens.to_vec();
+        child_tokens.push(child_token);
+        out.push((child_tokens.clone(), child));
+        descendants(child, &child_tokens, separator, out);
+    }
+}
+
+/// Update every node matched by the token-based [str] path, where
+/// wildcards work as on [read_all], and returns the concrete path and old
+/// node of each of them.
+///
+/// Like [update], a last key token is inserted on every matched map that
+/// lacks it (its old node being [None]), a last `[+]` token appends to
+/// every matched array, while missing array elements are never created.
+/// After a `**` token, which reaches every map of the subtree, a last key
+/// token only replaces the keys that already exist. Matches replaced along
+/// with an earlier one (e.g. `**` matching both a node and its children)
+/// are left out.
+pub fn update_all<V: NestedValue + Clone>(data: &mut V, path: &str, separator: Option<&str>, new_value: V) -> Vec<(String, Option<V>)> {
+    let separator = separator.unwrap_or(".");
+    let tokens = tokenize(path, separator);
+    let (last, parents) = tokens.split_last().expect("a path has at least one token");
+    let existing_only = matches!(last.step(), Step::Key(_))
+        && parents.iter().any(|token| token.wildcard() == Some(Wildcard::Descendants));
+    let paths: Vec<String> = if last.wildcard().is_some() {
+        expand(&tokens, &*data, separator, false)
+            .into_iter()
+            .map(|(tokens, _)| tokens.join(separator))
+            .collect()
+    } else {
+        expand(parents, &*data, separator, true)
+            .into_iter()
+            .map(|(mut tokens, _)| {
+                tokens.push(last.raw.to_string());
+                tokens.join(separator)
+            })
+            .collect()
+    };
+    // Only the first matches get a clone, the last one takes `new_value`.
+    let new_values = std::iter::repeat_n(new_value, paths.len());
+    paths
+        .into_iter()
+        .zip(new_values)
+        .filter_map(|(path, new_value)| {
+            if existing_only && read(&path, &*data, Some(separator)).is_none() {
+                return None;
+            }
+            let old_value = replace_at(data, &path, Some(separator), new_value).ok()?;
+            Some((path, old_value))
+        })
+        .collect()
+}
+
+/// Remove every node matched by the token-based [str] path, where wildcards
+/// work as on [read_all], and returns the concrete path and node of each of
+/// them in document order.
+///
+/// Concrete paths are those the nodes had before anything was removed.
+pub fn delete_all<V: NestedValue>(data: &mut V, path: &str, separator: Option<&str>) -> Vec<(String, V)> {
+    let separator = separator.unwrap_or(".");
+    let paths: Vec<String> = read_all(path, &*data, Some(separator))
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    // removing from the end keeps the indexes of earlier matches valid
+    let mut removed: Vec<(String, V)> = paths
+        .into_iter()
+        .rev()
+        .filter_map(|path| {
+            let node = delete(data, &path, Some(separator))?;
+            Some((path, node))
+        })
+        .collect();
+    removed.reverse();
+    removed
+}
+
+/// How a child hangs from its parent.
+enum ChildKey {
+    Key(String),
+    Index(usize),
+}
+
+/// Returns the paths of `data` matched by none of the `allowed` patterns,
+/// nor leading to a node one of them could match.
+///
+/// Patterns may hold any token understood by [read_all]. Nodes matched by
+/// a pattern are allowed along with everything below them, so only the
+/// topmost unknown path of a subtree is reported. Scalars not matched by a
+/// pattern are always reported, even when some pattern goes through them
+/// (e.g. `server` being a number for a `server.port` pattern).
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::nested;
+///
+/// fn main() {
+///     let data = json!({
+///         "server": {"port": 80, "prot": 443, "tls": {"cert": "a.pem"}},
+///         "plugins": [{"name": "gzip", "level": 9}],
+///         "stale": true
+///     });
+///     let allowed = ["server.port", "server.tls", "plugins.[*].name"];
+///     let unknown = nested::unknown_paths(&data, &allowed, None);
+///     assert_eq!(unknown.len(), 3);
+///     assert!(unknown.contains(&"server.prot".to_string()));
+///     assert!(unknown.contains(&"plugins.[0].level".to_string()));
+///     assert!(unknown.contains(&"stale".to_string()));
+/// }
+/// ```
+pub fn unknown_paths<V: NestedValue>(data: &V, allowed: &[&str], separator: Option<&str>) -> Vec<String> {
+    let separator = separator.unwrap_or(".");
+    let patterns: Vec<Vec<Token>> = allowed.iter().map(|pattern| tokenize(pattern, separator)).collect();
+    let states = skip_descendants(&patterns, (0..patterns.len()).map(|p| (p, 0)).collect());
+    let mut unknown = vec![];
+    find_unknown(data, &patterns, &states, &[], separator, &mut unknown);
+    unknown
+}
+
+/// Adds to `states`, pairs of a pattern and how many of its tokens were
+/// matched, the ones reached by letting `**` tokens match nothing.
+fn skip_descendants(patterns: &[Vec<Token>], mut states: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut i = 0;
+    while i < states.len() {
+        let (p, pos) = states[i];
+        let descendants = patterns[p].get(pos).and_then(Token::wildcard) == Some(Wildcard::Descendants);
+        if descendants && !states.contains(&(p, pos + 1)) {
+            states.push((p, pos + 1));
+        }
+        i += 1;
+    }
+    states
+}
+
+fn find_unknown<V: NestedValue>(node: &V, patterns: &[Vec<Token>], states: &[(usize, usize)], path: &[String], separator: &str, unknown: &mut Vec<String>) {
+    let children: Vec<(ChildKey, &V)> = if let Some(len) = node.array_len() {
+        (0..len)
+            .filter_map(|idx| Some((ChildKey::Index(idx), node.get_index(idx)?)))
+            .collect()
+    } else {
+        node.keys()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|key| {
+                let child = node.get_key(&key)?;
+                Some((ChildKey::Key(key), child))
+            })
+            .collect()
+    };
+    for (key, child) in children {
+        let next: Vec<(usize, usize)> = states
+            .iter()
+            .filter(|(p, pos)| *pos < patterns[*p].len())
+            .filter_map(|&(p, pos)| {
+                let token = &patterns[p][pos];
+                let matched = match (token.wildcard(), &key) {
+                    (Some(Wildcard::Descendants), _) => return Some((p, pos)),
+                    (Some(Wildcard::Any), _) => true,
+                    (Some(Wildcard::Elements), ChildKey::Index(_)) => true,
+                    (Some(Wildcard::Select(k, v)), ChildKey::Index(idx)) => Step::Select(k, v).matches(node, *idx),
+                    (Some(_), ChildKey::Key(_)) => false,
+                    (None, ChildKey::Key(key)) => token.step() == Step::Key(key),
+                    (None, ChildKey::Index(idx)) => match token.step() {
+                        Step::Index(i) => i == *idx,
+                        step @ Step::Select(..) => step.matches(node, *idx),
+                        Step::Slice(slice) => node.array_len().is_some_and(|len| slice.range(len).contains(idx)),
+                        Step::Key(_) | Step::Append => false,
+                    },
+                };
+                if matched {
+                    Some((p, pos + 1))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let next = skip_descendants(patterns, next);
+        let mut child_path = path.to_vec();
+        child_path.push(match &key {
+            ChildKey::Key(key) => escape_key(key, separator).into_owned(),
+            ChildKey::Index(idx) => format!("[{}]", idx),
+        });
+        if next.iter().any(|&(p, pos)| pos == patterns[p].len()) {
+            continue;
+        }
+        let leaf = child.keys().is_none() && child.array_len().is_none();
+        if next.is_empty() || leaf {
+            unknown.push(child_path.join(separator));
+        } else {
+            find_unknown(child, patterns, &next, &child_path, separator, unknown);
+        }
+    }
+}
+
+/// Gives every map stored in an array that lacks `key` a generated id
+/// under that key, returning how many ids were assigned.
+///
+/// Ids are 8 hexadecimal digits, unique among the elements of the array
+/// and derived from the position of the element, so running the pass again
+/// on the same document gives the same ids. Once stored they move along
+/// with their element, so `[#id]` tokens (for [ID_KEY]) or `[key=id]`
+/// tokens keep addressing it after the array is reordered.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::json;
+/// use nestac::nested::{self, ID_KEY};
+///
+/// fn main() {
+///     let mut data = json!({"steps": [{"run": "build"}, {"run": "test"}]});
+///     assert_eq!(nested::assign_ids(&mut data, ID_KEY), 2);
+///     let id = data["steps"][1]["_id"].as_str().unwrap().to_string();
+///
+///     data["steps"].as_array_mut().unwrap().reverse();
+///     let step = nested::read(&format!("steps.[#{}].run", id), &data, None);
+///     assert_eq!(step.unwrap(), "test");
+///     assert_eq!(nested::assign_ids(&mut data, ID_KEY), 0);
+/// }
+/// ```
+pub fn assign_ids<V: NestedValue>(data: &mut V, key: &str) -> usize {
+    assign_ids_at(data, key, "$")
+}
+
+fn assign_ids_at<V: NestedValue>(node: &mut V, key: &str, path: &str) -> usize {
+    let mut count = 0;
+    if let Some(len) = node.array_len() {
+        let mut taken: Vec<String> = (0..len)
+            .filter_map(|idx| node.get_index(idx)?.get_key(key)?.as_text())
+            .collect();
+        for idx in 0..len {
+            let item = node.get_index_mut(idx).expect("index within bounds");
+            if item.keys().is_some() && item.get_key(key).is_none() {
+                let id = (0u32..)
+                    .map(|attempt| generate_id(path, idx, attempt))
+                    .find(|id| !taken.contains(id))
+                    .expect("an unused id");
+                if let Some(value) = V::new_string(&id) {
+                    let _ = item.insert_key(key, value);
+                    taken.push(id);
+                    count += 1;
+                }
+            }
+            count += assign_ids_at(item, key, &format!("{}.[{}]", path, idx));
+        }
+    } else if let Some(keys) = node.keys() {
+        for child_key in keys {
+            if let Some(child) = node.get_key_mut(&child_key) {
+                count += assign_ids_at(child, key, &format!("{}.{}", path, child_key));
+            }
+        }
+    }
+    count
+}
+
+fn generate_id(path: &str, idx: usize, attempt: u32) -> String {
+    let hash = stable_hash(&[path.as_bytes(), &(idx as u64).to_le_bytes(), &attempt.to_le_bytes()]);
+    format!("{:08x}", hash as u32)
+}
+
+/// 64-bit FNV-1a hash of `parts`, each followed by a `0xff` byte. Unlike
+/// [std::collections::hash_map::DefaultHasher], the result is the same
+/// across runs, platforms and compiler versions.
+/// It is only meant for generated ids: it is fast to brute-force and must
+/// not be used to hide data.
+pub(crate) fn stable_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.iter().chain([&0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Rewrites the `[#id]` tokens of `path` into `[key=id]` tokens, for ids
+/// stored under another key than [ID_KEY].
+pub(crate) fn with_id_key<'p>(path: &'p str, separator: &str, key: &str) -> Cow<'p, str> {
+    let tokens = tokenize(path, separator);
+    if key == ID_KEY || tokens.iter().all(|token| token.literal || parse_id(token.raw).is_none()) {
+        return Cow::Borrowed(path);
+    }
+    let tokens: Vec<String> = tokens
+        .iter()
+        .map(|token| match (token.literal, parse_id(token.raw)) {
+            (false, Some(id)) => format!("[{}={}]", key, id),
+            _ => token.raw.to_string(),
+        })
+        .collect();
+    Cow::Owned(tokens.join(separator))
+}
+
+/// Kind of a [Change].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Difference found at a path by [diff].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Change<V> {
+    /// Path of the node, usable with [read] and [update] (`.` separated,
+    /// empty for the root).
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Node on the old tree, [None] when [ChangeKind::Added].
+    pub old: Option<V>,
+    /// Node on the new tree, [None] when [ChangeKind::Removed].
+    pub new: Option<V>,
+}
+
+/// Returns the path-level changes turning `old` into `new`, in document
+/// order.
+///
+/// Maps are compared key by key and arrays index by index, so only the
+/// topmost differing nodes are reported: a key present on a single side is
+/// [ChangeKind::Added] or [ChangeKind::Removed] as a whole, and nodes of
+/// different kinds or differing scalars are [ChangeKind::Modified].
+///
+/// See [crate::json_diff].
+pub fn diff<V: NestedValue + Clone + PartialEq>(old: &V, new: &V) -> Vec<Change<V>> {
+    let mut changes = vec![];
+    diff_node(old, new, &mut vec![], &mut changes);
+    changes
+}
+
+fn diff_node<V: NestedValue + Clone + PartialEq>(old: &V, new: &V, path: &mut Vec<String>, changes: &mut Vec<Change<V>>) {
+    if old == new {
+        return;
+    }
+    match (old.keys(), new.keys(), old.array_len(), new.array_len()) {
+        (Some(old_keys), Some(new_keys), _, _) => {
+            for key in &old_keys {
+                path.push(escape_key(key, ".").into_owned());
+                match new.get_key(key) {
+                    Some(new_child) => diff_node(old.get_key(key).unwrap(), new_child, path, changes),
+                    None => push_change(changes, path, old.get_key(key), None),
+                }
+                path.pop();
+            }
+            for key in new_keys.iter().filter(|key| !old_keys.contains(key)) {
+                path.push(escape_key(key, ".").into_owned());
+                push_change(changes, path, None, new.get_key(key));
+                path.pop();
+            }
+        }
+        (_, _, Some(old_len), Some(new_len)) => {
+            for idx in 0..old_len.max(new_len) {
+                path.push(format!("[{}]", idx));
+                match (old.get_index(idx), new.get_index(idx)) {
+                    (Some(old_child), Some(new_child)) => diff_node(old_child, new_child, path, changes),
+                    (old_child, new_child) => push_change(changes, path, old_child, new_child),
+                }
+                path.pop();
+            }
+        }
+        _ => push_change(changes, path, Some(old), Some(new)),
+    }
+}
+
+fn push_change<V: Clone>(changes: &mut Vec<Change<V>>, path: &[String], old: Option<&V>, new: Option<&V>) {
+    let kind = match (old, new) {
+        (None, _) => ChangeKind::Added,
+        (_, None) => ChangeKind::Removed,
+        _ => ChangeKind::Modified,
+    };
+    changes.push(Change {
+        path: path.join("."),
+        kind,
+        old: old.cloned(),
+        new: new.cloned(),
+    });
+}
+
+/// Returns a [Vec] containing [String]s representing every path of the
+/// tree, prefixed by `symbol` (default: `$`), in pre-order: every node
+/// comes before its children and siblings keep the document order.
+///
+/// See [crate::json_get_paths] for the output format.
+pub fn get_paths<V: NestedValue>(data: &V, symbol: Option<String>) -> Vec<String> {
+    let symbol = symbol.unwrap_or("$".to_string());
+    get_paths_with(data, &PathsOptions::new().symbol(&symbol))
+}
+
+/// Same as [get_paths] with the format, filters and [crate::Order] taken
+/// from `options`.
+///
+/// See [crate::json_get_paths_with].
+pub fn get_paths_with<V: NestedValue>(data: &V, options: &PathsOptions) -> Vec<String> {
+    iter_paths(data, options).collect()
+}
+
+/// Writes every path of the tree to `writer`, one per line, in the same
+/// format and order as [get_paths_with] but without collecting them.
+///
+/// See [crate::json_write_paths].
+pub fn write_paths<V: NestedValue, W: Write>(data: &V, writer: &mut W, options: &PathsOptions) -> io::Result<()> {
+    for path in iter_paths(data, options) {
+        writer.write_all(path.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Returns an iterator over the paths [get_paths_with] would return,
+/// walking the tree as it is consumed.
+///
+/// See [crate::json_iter_paths].
+pub fn iter_paths<'a, V: NestedValue>(data: &'a V, options: &'a PathsOptions) -> Paths<'a, V> {
+    Paths { entries: entries(data, options) }
+}
+
+/// Returns an iterator over every path of the tree along with its node, in
+/// the same format and order as [iter_paths].
+///
+/// See [crate::json_entries].
+pub fn entries<'a, V: NestedValue>(data: &'a V, options: &'a PathsOptions) -> Entries<'a, V> {
+    Entries {
+        options,
+        stack: vec![Frame::new(data, options.get_symbol().to_string(), !options.get_symbol().is_empty(), 0, options)],
+    }
+}
+
+/// Lazy iterator over the paths of a tree, see [iter_paths].
+pub struct Paths<'a, V> {
+    entries: Entries<'a, V>,
+}
+
+impl<V: NestedValue> Iterator for Paths<'_, V> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.entries.next().map(|(path, _)| path)
+    }
+}
+
+/// Lazy iterator over the paths and nodes of a tree, see [entries].
+pub struct Entries<'a, V> {
+    options: &'a PathsOptions,
+    stack: Vec<Frame<'a, V>>,
+}
+
+/// A node whose children are being walked by [Entries].
+struct Frame<'a, V> {
+    node: &'a V,
+    path: String,
+    /// Whether the node has a path of its own (the root has none without a
+    /// symbol).
+    named: bool,
+    /// Whether the node has no children at all.
+    leaf: bool,
+    depth: usize,
+    /// Whether the children are walked, i.e. the maximum depth is not
+    /// reached.
+    expand: bool,
+    keys: Option<Vec<String>>,
+    next: usize,
+    entered: bool,
+}
+
+impl<'a, V: NestedValue> Frame<'a, V> {
+    fn new(node: &'a V, path: String, named: bool, depth: usize, options: &PathsOptions) -> Self {
+        let keys = node.keys();
+        let leaf = match &keys {
+            Some(keys) => keys.is_empty(),
+            None => node.array_len().unwrap_or(0) == 0,
+        };
+        let expand = options.get_max_depth().is_none_or(|max_depth| depth < max_depth);
+        Frame { node, path, named, leaf, depth, expand, keys, next: 0, entered: false }
+    }
+
+    /// Whether the node is listed.
+    fn listed(&self, options: &PathsOptions) -> bool {
+        self.named && (self.leaf || !options.get_leaves_only())
+    }
+
+    /// Next child of the node along with its path.
+    fn next_child(&mut self, options: &PathsOptions) -> Option<(&'a V, String)> {
+        if !self.expand {
+            return None;
+        }
+        let node = self.node;
+        let (child, token) = match &self.keys {
+            Some(keys) => {
+                let key = keys.get(self.next)?;
+                let token = match options.get_brackets() {
+                    true => escape_key(key, options.get_separator()).into_owned(),
+                    false => key.clone(),
+                };
+                (node.get_key(key)?, token)
+            }
+            None if self.next < node.array_len()? => {
+                let token = match options.get_brackets() {
+                    true => format!("[{}]", self.next),
+                    false => self.next.to_string(),
+                };
+                (node.get_index(self.next)?, token)
+            }
+            None => return None,
+        };
+        self.next += 1;
+        let path = match self.named {
+            true => [self.path.as_str(), token.as_str()].join(options.get_separator()),
+            false => token,
+        };
+        Some((child, path))
+    }
+}
+
+impl<'a, V: NestedValue> Iterator for Entries<'a, V> {
+    type Item = (String, &'a V);
+
+    fn next(&mut self) -> Option<(String, &'a V)> {
+        let order = self.options.get_order();
+        loop {
+            let frame = self.stack.last_mut()?;
+            if !frame.entered {
+                frame.entered = true;
+                if frame.listed(self.options) && order == Order::PreOrder {
+                    return Some((frame.path.clone(), frame.node));
+                }
+            }
+            match frame.next_child(self.options) {
+                Some((child, path)) => {
+                    let depth = frame.depth + 1;
+                    self.stack.push(Frame::new(child, path, true, depth, self.options));
+                }
+                None => {
+                    let frame = self.stack.pop()?;
+                    if frame.listed(self.options) && order == Order::PostOrder {
+                        return Some((frame.path, frame.node));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Result;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_changes_and_options() {
+        let old = serde_json::json!({"a": 1, "b": [1]});
+        let new = serde_json::json!({"a": 2, "c": true});
+        let changes = diff(&old, &new);
+        let json_str = serde_json::to_string(&changes).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Change<Value>>>(&json_str).unwrap(), changes);
+        assert_eq!(serde_json::to_value(ChangeKind::Added).unwrap(), "Added");
+
+        let options = PathsOptions::new().symbol("").max_depth(2).order(Order::PostOrder);
+        let json_str = serde_json::to_string(&options).unwrap();
+        assert_eq!(serde_json::from_str::<PathsOptions>(&json_str).unwrap(), options);
+    }
+
+    #[test]
+    fn parse_index_tokens() {
+        assert_eq!(parse_index("[0]"), Some(0));
+        assert_eq!(parse_index("[42]"), Some(42));
+        assert_eq!(parse_index("[]"), None);
+        assert_eq!(parse_index("[-1]"), None);
+        assert_eq!(parse_index("foo"), None);
+    }
+
+    #[test]
+    fn stable_hash_is_pinned() {
+        assert_eq!(stable_hash(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(stable_hash(&[b"ab", b"c"]), stable_hash(&[b"a", b"bc"]));
+        assert_eq!(generate_id("items", 0, 0), generate_id("items", 0, 0));
+        assert_eq!(generate_id("items", 0, 0), format!("{:08x}", stable_hash(&[b"items", &[0; 8], &[0; 4]]) as u32));
+    }
+
+    #[test]
+    fn slice_tokens() {
+        let range = |token: &str, len: usize| Slice::parse(token).map(|slice| slice.range(len));
+        assert_eq!(range("[1:3]", 5), Some(1..3));
+        assert_eq!(range("[:]", 5), Some(0..5));
+        assert_eq!(range("[-2:]", 5), Some(3..5));
+        assert_eq!(range("[:-10]", 5), Some(0..0));
+        assert_eq!(range("[2:99]", 5), Some(2..5));
+        assert_eq!(range("[a:1]", 5), None);
+        assert_eq!(range("[-:1]", 5), None);
+        assert_eq!(escape_key("[0:1]", "."), "\"[0:1]\"");
+
+        let mut data = serde_json::json!({"tags": [1, 2, 3], "name": "x"});
+        assert_eq!(update(&mut data, "tags.[1:1]", None, serde_json::json!([9, 9])), Some(serde_json::json!([])));
+        assert_eq!(data["tags"], serde_json::json!([1, 9, 9, 2, 3]));
+        assert_eq!(update(&mut data, "tags.[1:]", None, Value::from(0)), None);
+        assert!(matches!(
+            try_update(&mut data, "tags.[:2]", None, Value::from(0)),
+            Err(NestacError::TypeMismatch { expected, found, .. }) if expected == "array" && found == "number",
+        ));
+        assert!(matches!(
+            try_update(&mut data, "name.[:2]", None, serde_json::json!([])),
+            Err(NestacError::TypeMismatch { segment, .. }) if segment == "[:2]",
+        ));
+        assert_eq!(try_update(&mut data, "tags.[3:]", None, serde_json::json!([])), Ok(Some(serde_json::json!([2, 3]))));
+        assert_eq!(data, serde_json::json!({"tags": [1, 9, 9], "name": "x"}));
+
+        assert_eq!(read("tags.[0:2]", &data, None), None);
+        assert!(matches!(try_read("tags.[0:2]", &data, None), Err(NestacError::InvalidPath { .. })));
+        let found: Vec<String> = read_all("tags.[-2:]", &data, None).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(found, vec!["tags.[1]", "tags.[2]"]);
+        let old_vals = update_many(&mut data, vec![("tags.[0:1]", serde_json::json!([7])), ("name", Value::from("y"))], None);
+        assert_eq!(old_vals, vec![Some(serde_json::json!([1])), Some(Value::from("x"))]);
+        assert_eq!(upsert(&mut data, "tags.[1:]", None, serde_json::json!([8])), Ok(Some(serde_json::json!([9, 9]))));
+        assert_eq!(delete(&mut data, "tags.[:1]", None), Some(serde_json::json!([7])));
+        assert_eq!(data, serde_json::json!({"tags": [8], "name": "y"}));
+    }
+
+    #[test]
+    fn append_token() {
+        let mut data = serde_json::json!({"tags": [1], "name": "x"});
+        assert_eq!(update(&mut data, "tags.[+]", None, Value::from(2)), None);
+        assert_eq!(update(&mut data, "name.[+]", None, Value::from(3)), None);
+        assert_eq!(update(&mut data, "tags.\"[+]\"", None, Value::from(4)), None);
+        assert_eq!(escape_key("[+]", "."), "\"[+]\"");
+        assert!(matches!(
+            upsert(&mut data, "missing.[+]", None, Value::from(5)),
+            Err(NestacError::MissingKey { segment, .. }) if segment == "missing",
+        ));
+        assert!(matches!(try_read("tags.[+]", &data, None), Err(NestacError::InvalidPath { .. })));
+        assert_eq!(data, serde_json::json!({"tags": [1, 2], "name": "x"}));
+
+        let mut data = serde_json::json!({"tags": [1], "m": {}, "a": {"t": []}, "b": {"t": [0]}});
+        let old_vals = update_many(&mut data, vec![("tags.[+]", Value::from(2)), ("m.[+]", Value::from(3)), ("tags.[+]", Value::from(4))], None);
+        assert_eq!(old_vals, vec![None, None, None]);
+        let appended: Vec<String> = update_all(&mut data, "*.t.[+]", None, Value::from(5)).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(appended, vec!["a.t.[+]", "b.t.[+]"]);
+        assert_eq!(data, serde_json::json!({"tags": [1, 2, 4], "m": {}, "a": {"t": [5]}, "b": {"t": [0, 5]}}));
+    }
+
+    #[test]
+    fn filter_tokens() {
+        let mut data = serde_json::json!({"items": [{"id": 1, "qty": 2}, {"id": 3, "qty": 4, "name": "a.b"}]});
+        assert_eq!(parse_selector("[?(@.id==3)]"), Some(("id", "3")));
+        assert_eq!(parse_selector("[?( @.name == \"a.b\" )]"), Some(("name", "a.b")));
+        assert_eq!(parse_selector("[?(@.id)]"), None);
+        assert_eq!(read("items.[?(@.id==3)].qty", &data, None), Some(&Value::from(4)));
+        assert_eq!(read("items/[?(@.name=='a.b')]/qty", &data, Some("/")), Some(&Value::from(4)));
+        assert_eq!(update(&mut data, "items.[?(@.name=='a.b')].qty", None, Value::from(5)), Some(Value::from(4)));
+        assert_eq!(delete(&mut data, "items.[?(@.id==1)]", None), Some(serde_json::json!({"id": 1, "qty": 2})));
+        assert_eq!(read("items.[?(@.id==1)]", &data, None), None);
+        assert_eq!(data, serde_json::json!({"items": [{"id": 3, "qty": 5, "name": "a.b"}]}));
+
+        let mut data = serde_json::json!({"items": [{"n": "x)]y", "q": 1}], "x": {"q": 2}});
+        let tokens: Vec<&str> = tokenize("items.[?(@.n=='x)]y')].q", ".").iter().map(|token| token.raw).collect();
+        assert_eq!(tokens, vec!["items", "[?(@.n=='x)]y')]", "q"]);
+        assert_eq!(read("items.[?(@.n==\"x)]y\")].q", &data, None), Some(&Value::from(1)));
+        assert_eq!(tokenize("[?(@.n=='a\\')]b')]", ".").len(), 1);
+        assert!(matches!(try_read("items.[?(@.n==x.q", &data, None), Err(NestacError::InvalidPath { .. })));
+        assert!(matches!(try_update(&mut data, "items.[?(@.n==x.q", None, Value::from(3)), Err(NestacError::InvalidPath { .. })));
+        assert_eq!(read("items.[?(@.n==x.q", &data, None), None);
+    }
+
+    #[test]
+    fn update_array_element() {
+        let json_str = r#"{"foo": [{"bar": "bingo!"}]}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let old_val = update(
+            json_data.as_mut().unwrap(),
+            "foo.[0].bar",
+            None,
+            Value::String("updated!".to_string()),
+        );
+        assert_eq!(old_val.unwrap(), "bingo!");
+        assert_eq!(
+            read("foo.[0].bar", json_data.as_ref().unwrap(), None).unwrap(),
+            "updated!",
+        );
+    }
+
+    #[test]
+    fn update_missing_path_is_none() {
+        let json_str = r#"{"foo": ["bingo!"]}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let old_val = update(
+            json_data.as_mut().unwrap(),
+            "foo.[3]",
+            None,
+            Value::Null,
+        );
+        assert!(old_val.is_none());
+        let old_val = update(
+            json_data.as_mut().unwrap(),
+            "bar.baz",
+            None,
+            Value::Null,
+        );
+        assert!(old_val.is_none());
+    }
+
+    #[test]
+    fn delete_keys_and_elements() {
+        let json_str = r#"{"foo": {"bar": [1, 2, 3], "baz": true, "qux": null}}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let removed = delete(json_data.as_mut().unwrap(), "foo.bar.[0]", None);
+        assert_eq!(removed.unwrap(), 1);
+        let removed = delete(json_data.as_mut().unwrap(), "foo.baz", None);
+        assert_eq!(removed.unwrap(), true);
+        assert!(delete(json_data.as_mut().unwrap(), "foo.bar.[2]", None).is_none());
+        assert!(delete(json_data.as_mut().unwrap(), "nope.baz", None).is_none());
+        assert_eq!(
+            json_data.unwrap().to_string(),
+            r#"{"foo":{"bar":[2,3],"qux":null}}"#,
+        );
+    }
+
+    #[test]
+    fn upsert_creates_intermediate_maps() {
+        let mut json_data = Value::Null;
+        let err = upsert(&mut json_data, "foo.bar", None, Value::from(1)).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { index: 0, .. }));
+
+        let mut json_data = serde_json::json!({"foo": {"list": [{}]}, "s": "x"});
+        let old_val = upsert(&mut json_data, "foo.bar.baz", None, Value::from(1)).unwrap();
+        assert!(old_val.is_none());
+        let old_val = upsert(&mut json_data, "foo.list.[0].a.b", None, Value::from(2)).unwrap();
+        assert!(old_val.is_none());
+        let old_val = upsert(&mut json_data, "foo.bar.baz", None, Value::from(3)).unwrap();
+        assert_eq!(old_val.unwrap(), 1);
+        assert_eq!(json_data, serde_json::json!({
+            "foo": {"list": [{"a": {"b": 2}}], "bar": {"baz": 3}},
+            "s": "x",
+        }));
+
+        for path in ["foo.list.[1].a", "s.a.b", "new.[0]", "foo.new.a.[0]"] {
+            assert!(upsert(&mut json_data, path, None, Value::Null).is_err(), "{}", path);
+        }
+        assert!(json_data["foo"].get("new").is_none());
+    }
+
+    #[test]
+    fn try_update_reports_failing_token() {
+        let mut json_data = serde_json::json!({"foo": [{"bar": 1}], "s": "x"});
+        let old_val = try_update(&mut json_data, "foo.[0].bar", None, Value::from(2));
+        assert_eq!(old_val.unwrap().unwrap(), 1);
+        assert_eq!(try_update(&mut json_data, "foo.[0].baz", None, Value::from(3)), Ok(None));
+        assert_eq!(
+            try_update(&mut json_data, "foo.[1]", None, Value::Null),
+            Err(NestacError::IndexOutOfBounds {
+                path: "foo.[1]".to_string(),
+                segment: "[1]".to_string(),
+                index: 1,
+                offset: 4,
+                len: 1,
+            }),
+        );
+        assert_eq!(
+            try_update(&mut json_data, "s.a.b", None, Value::Null),
+            Err(NestacError::TypeMismatch {
+                path: "s.a.b".to_string(),
+                segment: "a".to_string(),
+                index: 1,
+                offset: 2,
+                expected: "object".to_string(),
+                found: "string".to_string(),
+            }),
+        );
+        assert!(matches!(
+            try_update(&mut json_data, "nope.a", None, Value::Null),
+            Err(NestacError::MissingKey { index: 0, .. }),
+        ));
+        assert_eq!(json_data, serde_json::json!({"foo": [{"bar": 2, "baz": 3}], "s": "x"}));
+    }
+
+    #[test]
+    fn select_array_element_by_key() {
+        let json_str = r#"{"items": [{"name": "a", "n": 1}, {"name": "b", "n": 2}, {"id": 3, "n": 3}]}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_mut().unwrap();
+        assert_eq!(read("items.[name=b].n", data, None).unwrap(), 2);
+        assert_eq!(read("items.[id=3].n", data, None).unwrap(), 3);
+        assert!(read("items.[name=c].n", data, None).is_none());
+        assert_eq!(update(data, "items.[name=a].n", None, Value::from(10)).unwrap(), 1);
+        assert_eq!(delete(data, "items.[name=b]", None).unwrap()["n"], 2);
+        assert_eq!(read("items.[1].n", data, None).unwrap(), 3);
+        assert!(matches!(
+            upsert(data, "items.[name=c].n", None, Value::Null),
+            Err(NestacError::MissingKey { index: 1, .. }),
+        ));
+        assert!(matches!(
+            try_read("items.[0].name.[name=a]", data, None),
+            Err(NestacError::TypeMismatch { index: 3, .. }),
+        ));
+    }
+
+    #[test]
+    fn wildcards_match_every_child() {
+        let json_str = r#"{"servers": [{"port": 80}, {"host": "b"}, {"port": 443}], "tags": {"a": 1, "b": 2}}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_mut().unwrap();
+        let ports: Vec<String> = read_all("servers.[*].port", data, None)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(ports, vec!["servers.[0].port", "servers.[2].port"]);
+        assert_eq!(read_all("tags.*", data, None).len(), 2);
+        assert!(read_all("tags.[*]", data, None).is_empty());
+
+        let old_vals = update_all(data, "servers.*.port", None, Value::from(8080));
+        assert_eq!(old_vals.len(), 3);
+        assert_eq!(old_vals[1], ("servers.[1].port".to_string(), None));
+        assert_eq!(read("servers.[1].port", data, None).unwrap(), 8080);
+        update_all(data, "tags.*", None, Value::Null);
+        assert_eq!(read("tags", data, None).unwrap(), &serde_json::json!({"a": null, "b": null}));
+    }
+
+    #[test]
+    fn select_all_array_elements_by_key() {
+        let json_str = r#"{"jobs": [{"os": "linux", "n": 1}, {"os": "mac", "n": 2}, {"os": "linux", "n": 3}]}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_mut().unwrap();
+        assert_eq!(read("jobs.[*os=linux].n", data, None).unwrap(), 1);
+        let paths: Vec<String> = read_all("jobs.[*os=linux].n", data, None)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, vec!["jobs.[0].n", "jobs.[2].n"]);
+        assert_eq!(update_all(data, "jobs.[*os=mac].n", None, Value::from(0)).len(), 1);
+        let removed = delete_all(data, "jobs.[*os=linux]", None);
+        assert_eq!(removed[1].0, "jobs.[2]");
+        assert_eq!(read("jobs", data, None).unwrap(), &serde_json::json!([{"os": "mac", "n": 0}]));
+    }
+
+    #[test]
+    fn recursive_descent_matches_any_depth() {
+        let json_str = r#"{"password": "a", "db": {"password": "b", "replicas": [{"password": "c"}]}}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_mut().unwrap();
+        let paths: Vec<String> = read_all("**.password", data, None)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths.len(), 3);
+        assert!(paths.contains(&"db.replicas.[0].password".to_string()));
+        assert_eq!(read_all("db.**", data, None).len(), 4);
+
+        let old_vals = update_all(data, "**.password", None, Value::from("***"));
+        assert_eq!(old_vals.len(), 3);
+        assert_eq!(read("db.replicas.[0].password", data, None).unwrap(), "***");
+        assert_eq!(delete_all(data, "**.password", None).len(), 3);
+        assert!(read_all("**.password", data, None).is_empty());
+    }
+
+    #[test]
+    fn recursive_descent_only_replaces_existing_keys() {
+        let mut data = serde_json::json!({"db": {"password": "x", "opts": {"ssl": true}}, "tags": [{"a": 1}]});
+        let old_vals = update_all(&mut data, "**.password", None, Value::from("***"));
+        assert_eq!(old_vals, vec![("db.password".to_string(), Some(Value::from("x")))]);
+        assert_eq!(data, serde_json::json!({"db": {"password": "***", "opts": {"ssl": true}}, "tags": [{"a": 1}]}));
+        assert!(update_all(&mut data, "**.missing", None, Value::from(1)).is_empty());
+        assert_eq!(update_all(&mut data, "*.opts", None, Value::from(1)).len(), 1);
+    }
+
+    #[test]
+    fn quoted_and_escaped_keys() {
+        let json_str = r#"{"hosts": {"192.168.0.1": {"port": 22}, "[0]": 1, "a\\b": 2, "*": {"x.y": 3}}}"#;
+        let mut json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_mut().unwrap();
+        assert_eq!(read(r#"hosts."192.168.0.1".port"#, data, None).unwrap(), 22);
+        assert_eq!(read(r"hosts.192\.168\.0\.1.port", data, None).unwrap(), 22);
+        assert_eq!(read(r#"hosts."[0]""#, data, None).unwrap(), 1);
+        assert_eq!(read(r"hosts.a\b", data, None).unwrap(), 2);
+        assert_eq!(update(data, r#"hosts."*"."x.y""#, None, Value::from(4)).unwrap(), 3);
+        assert!(matches!(
+            try_read(r#"hosts."10.0.0.1".port"#, data, None),
+            Err(NestacError::MissingKey { index: 1, offset: 6, .. }),
+        ));
+
+        // concrete paths quote the keys that need it, so they can be reused
+        for (path, node) in read_all("hosts.*", data, None) {
+            assert_eq!(read(&path, data, None), Some(node));
+        }
+        assert_eq!(escape_key("192.168.0.1", "."), r#""192.168.0.1""#);
+        assert_eq!(escape_key(r#"a"b"#, "@"), r#""a\"b""#);
+        assert_eq!(escape_key("plain", "."), "plain");
+    }
+
+    #[test]
+    fn update_many_matches_sequential_updates() {
+        let json_str = r#"{"a": {"b": 1, "c": [{"d": 2}]}, "e": 3}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let updates = vec![
+            ("a.c.[0].d", Value::from(20)),
+            ("e", Value::from(30)),
+            ("a.b", Value::from(10)),
+            ("a.c.[0]", serde_json::json!({"x": 1})),
+            ("a.c.[0].x", Value::from(2)),
+            ("a.c.[1]", Value::Null),
+            ("a.b.z", Value::Null),
+        ];
+        let mut sequential = json_data.as_ref().unwrap().clone();
+        let expected: Vec<Option<Value>> = updates
+            .iter()
+            .map(|(path, value)| update(&mut sequential, path, None, value.clone()))
+            .collect();
+        let mut batched = json_data.unwrap();
+        assert_eq!(update_many(&mut batched, updates, None), expected);
+        assert_eq!(batched, sequential);
+    }
+
+    #[test]
+    fn read_many_matches_read() {
+        let json_str = r#"{"a": {"b": 1, "c": [{"d": 2}]}, "e": 3}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_ref().unwrap();
+        let paths = ["a.c.[0].d", "e", "a", "a.b", "a.c.[1]", "a.b.z", "x.y", "a.c.[0].d"];
+        let found = read_many(&paths, data, None);
+        assert_eq!(found.len(), 7);
+        for path in paths {
+            assert_eq!(found[path], read(path, data, None));
+        }
+    }
+
+    #[test]
+    fn unknown_paths_with_wildcards() {
+        let json_str = r#"{"a": {"b": 1, "c": {"d": 2, "e": 3}}, "f": [{"g": 1, "h": 2}], "i.j": 1}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let data = json_data.as_ref().unwrap();
+        let mut unknown = unknown_paths(data, &["a.*.d", "f.[*].g", "**.b"], None);
+        unknown.sort();
+        assert_eq!(unknown, vec!["\"i.j\"", "a.c.e", "f.[0].h"]);
+        assert!(unknown_paths(data, &["**"], None).is_empty());
+        assert!(unknown_paths(data, &["a", "f", "\"i.j\""], None).is_empty());
+    }
+
+    #[test]
+    fn write_paths_matches_get_paths() {
+        let json_str = r#"{"a": [{"b": 1}, 2], "c": {"d": {}}, "e": []}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        assert!(json_data.is_ok());
+        let mut out: Vec<u8> = vec![];
+        let options = PathsOptions::new().symbol("root");
+        write_paths(json_data.as_ref().unwrap(), &mut out, &options).unwrap();
+        let mut expected = get_paths(json_data.as_ref().unwrap(), Some("root".to_string())).join("\n");
+        expected.push('\n');
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn iter_paths_matches_get_paths() {
+        let json_str = r#"{"a": [{"b": 1}, 2], "c": {"d": {}}, "": [[]]}"#;
+        let json_data: Result<Value> = serde_json::from_str(json_str);
+        let data = json_data.as_ref().unwrap();
+        for options in [
+            PathsOptions::new(),
+            PathsOptions::new().order(Order::PostOrder),
+            PathsOptions::new().symbol("").brackets(true),
+            PathsOptions::new().symbol("").order(Order::PostOrder),
+        ] {
+            let paths: Vec<String> = iter_paths(data, &options).collect();
+            let mut out: Vec<u8> = vec![];
+            write_paths(data, &mut out, &options).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(), paths);
+        }
+        let options = PathsOptions::new();
+        assert_eq!(iter_paths(data, &options).count(), get_paths(data, None).len());
+        assert!(iter_paths(data, &options).eq(get_paths(data, None)));
+        let options = PathsOptions::new().symbol("").brackets(true);
+        let paths: Vec<String> = iter_paths(data, &options).collect();
+        assert!(paths.iter().all(|path| read(path, data, None).is_some()));
+        assert_eq!(paths.len(), 8);
+    }
+}
@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use crate::json_lint::{LintRule, Severity};
+use crate::nested::ID_KEY;
+
+/// Options accepted by [crate::json_read_with].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::ReadOptions;
+///
+/// fn main() {
+///     let options = ReadOptions::new().separator("@");
+///     assert_eq!(options.get_separator(), "@");
+///     assert_eq!(ReadOptions::default().get_separator(), ".");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOptions {
+    separator: String,
+    id_key: String,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            separator: ".".to_string(),
+            id_key: ID_KEY.to_string(),
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token-separator used to split the path (default: `.`).
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Key looked up by `[#id]` tokens (default: [ID_KEY]), see
+    /// [crate::nested::assign_ids].
+    pub fn id_key(mut self, id_key: &str) -> Self {
+        self.id_key = id_key.to_string();
+        self
+    }
+
+    pub fn get_separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn get_id_key(&self) -> &str {
+        &self.id_key
+    }
+}
+
+/// Options accepted by [crate::json_update_with].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::UpdateOptions;
+///
+/// fn main() {
+///     let options = UpdateOptions::new().separator("@");
+///     assert_eq!(options.get_separator(), "@");
+///     assert_eq!(UpdateOptions::default().get_separator(), ".");
+///     assert_eq!(options.get_keep_string_scalars(), false);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateOptions {
+    separator: String,
+    keep_string_scalars: bool,
+    id_key: String,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        UpdateOptions {
+            separator: ".".to_string(),
+            keep_string_scalars: false,
+            id_key: ID_KEY.to_string(),
+        }
+    }
+}
+
+impl UpdateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token-separator used to split the path (default: `.`).
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Whether a number or boolean written over a string holding a number
+    /// or boolean (e.g. `"8080"`) is stored as a string too (default:
+    /// `false`).
+    pub fn keep_string_scalars(mut self, keep_string_scalars: bool) -> Self {
+        self.keep_string_scalars = keep_string_scalars;
+        self
+    }
+
+    /// Key looked up by `[#id]` tokens (default: [ID_KEY]), see
+    /// [crate::nested::assign_ids].
+    pub fn id_key(mut self, id_key: &str) -> Self {
+        self.id_key = id_key.to_string();
+        self
+    }
+
+    pub fn get_separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn get_keep_string_scalars(&self) -> bool {
+        self.keep_string_scalars
+    }
+
+    pub fn get_id_key(&self) -> &str {
+        &self.id_key
+    }
+}
+
+/// How [crate::json_merge_into] combines an array of the source with the
+/// array found at the same path of the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The source array replaces the destination one.
+    Replace,
+    /// The source elements are appended to the destination ones.
+    Concat,
+    /// Elements are merged pairwise by index, extra source elements are
+    /// appended.
+    ByIndex,
+}
+
+/// How [crate::json_merge_into] handles `null`s on the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullMerge {
+    /// `null` is written like any other value.
+    Set,
+    /// `null` leaves the destination as it is.
+    Skip,
+    /// `null` removes the key from the destination (as on JSON Merge Patch).
+    Delete,
+}
+
+/// How [crate::json_merge_at] combines the keys of the overlay with the
+/// object found at the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeDepth {
+    /// Every key of the overlay replaces the one of the object.
+    Shallow,
+    /// Objects present on both sides are merged key by key, anything else
+    /// (including arrays) is replaced.
+    Deep,
+}
+
+/// Options accepted by [crate::json_merge_into].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::{ArrayMerge, MergeStrategy, NullMerge};
+///
+/// fn main() {
+///     let strategy = MergeStrategy::new().arrays(ArrayMerge::Concat);
+///     assert_eq!(strategy.get_arrays(), ArrayMerge::Concat);
+///     assert_eq!(MergeStrategy::default().get_arrays(), ArrayMerge::Replace);
+///     assert_eq!(MergeStrategy::default().get_nulls(), NullMerge::Set);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeStrategy {
+    arrays: ArrayMerge,
+    nulls: NullMerge,
+}
+
+impl Default for MergeStrategy {
+    fn default() -> Self {
+        MergeStrategy {
+            arrays: ArrayMerge::Replace,
+            nulls: NullMerge::Set,
+        }
+    }
+}
+
+impl MergeStrategy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How arrays present on both sides are combined (default:
+    /// [ArrayMerge::Replace]).
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// How `null`s on the source are handled (default: [NullMerge::Set]).
+    pub fn nulls(mut self, nulls: NullMerge) -> Self {
+        self.nulls = nulls;
+        self
+    }
+
+    pub fn get_arrays(&self) -> ArrayMerge {
+        self.arrays
+    }
+
+    pub fn get_nulls(&self) -> NullMerge {
+        self.nulls
+    }
+}
+
+/// Order in which [crate::json_get_paths_with] lists the paths of a
+/// document.
+///
+/// Siblings always come in document order: object keys in iteration order
+/// (sorted, unless serde_json's `preserve_order` feature keeps them as
+/// written) and array elements by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Order {
+    /// Every node comes before its children, so the root path comes first.
+    PreOrder,
+    /// Every node comes after its children, so the root path comes last.
+    PostOrder,
+}
+
+/// Options accepted by [crate::json_get_paths_with] and
+/// [crate::json_write_paths].
+///
+/// By default paths look like `$.servers.0.host`. With brackets enabled,
+/// array indices are written as `[0]` tokens and keys holding the
+/// separator or looking like another token are quoted, so that with an
+/// empty symbol every path can be given back to [crate::json_read] or
+/// [crate::json_update] with the same separator.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::{Order, PathsOptions};
+///
+/// fn main() {
+///     let options = PathsOptions::new().symbol("root").order(Order::PostOrder);
+///     assert_eq!(options.get_symbol(), "root");
+///     assert_eq!(options.get_order(), Order::PostOrder);
+///     assert_eq!(PathsOptions::default().get_symbol(), "$");
+///     assert_eq!(PathsOptions::default().get_order(), Order::PreOrder);
+///     assert_eq!(PathsOptions::default().get_separator(), ".");
+///     assert_eq!(PathsOptions::default().get_brackets(), false);
+///     assert_eq!(PathsOptions::default().get_leaves_only(), false);
+///     assert_eq!(PathsOptions::default().get_max_depth(), None);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathsOptions {
+    symbol: String,
+    separator: String,
+    brackets: bool,
+    leaves_only: bool,
+    max_depth: Option<usize>,
+    order: Order,
+}
+
+impl Default for PathsOptions {
+    fn default() -> Self {
+        PathsOptions {
+            symbol: "$".to_string(),
+            separator: ".".to_string(),
+            brackets: false,
+            leaves_only: false,
+            max_depth: None,
+            order: Order::PreOrder,
+        }
+    }
+}
+
+impl PathsOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token prefixing every path, standing for the root (default: `$`).
+    /// When empty, the root itself is left out and paths start with the
+    /// first key or index.
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = symbol.to_string();
+        self
+    }
+
+    /// Separator between path tokens (default: `.`).
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Whether array indices are written as `[n]` tokens and keys escaped
+    /// as path tokens (default: `false`).
+    pub fn brackets(mut self, brackets: bool) -> Self {
+        self.brackets = brackets;
+        self
+    }
+
+    /// Whether only nodes without children (scalars, empty objects and
+    /// arrays) are listed (default: `false`).
+    pub fn leaves_only(mut self, leaves_only: bool) -> Self {
+        self.leaves_only = leaves_only;
+        self
+    }
+
+    /// Deepest level listed, the children of the root being on level `1`
+    /// (default: no limit). Nodes below it are not even walked.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Order of the paths (default: [Order::PreOrder]).
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn get_symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn get_separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn get_brackets(&self) -> bool {
+        self.brackets
+    }
+
+    pub fn get_leaves_only(&self) -> bool {
+        self.leaves_only
+    }
+
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub fn get_order(&self) -> Order {
+        self.order
+    }
+}
+
+/// Options accepted by [crate::json_lint].
+///
+/// Every rule is enabled by default, reported as [Severity::Warning] except
+/// for [LintRule::SingleKeyChain], reported as [Severity::Info].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::{LintOptions, LintRule, Severity};
+///
+/// fn main() {
+///     let options = LintOptions::new()
+///         .severity(LintRule::MixedTypeArray, Severity::Error)
+///         .allow(LintRule::SingleKeyChain)
+///         .max_chain(6);
+///     assert_eq!(options.get_severity(LintRule::MixedTypeArray), Some(Severity::Error));
+///     assert_eq!(options.get_severity(LintRule::SingleKeyChain), None);
+///     assert_eq!(options.get_max_chain(), 6);
+///     assert_eq!(LintOptions::default().get_max_chain(), 4);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintOptions {
+    severities: BTreeMap<LintRule, Option<Severity>>,
+    max_chain: usize,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        let severities = LintRule::ALL
+            .into_iter()
+            .map(|rule| match rule {
+                LintRule::SingleKeyChain => (rule, Some(Severity::Info)),
+                _ => (rule, Some(Severity::Warning)),
+            })
+            .collect();
+        LintOptions {
+            severities,
+            max_chain: 4,
+        }
+    }
+}
+
+impl LintOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Severity `rule` is reported with, enabling it.
+    pub fn severity(mut self, rule: LintRule, severity: Severity) -> Self {
+        self.severities.insert(rule, Some(severity));
+        self
+    }
+
+    /// Disables `rule`.
+    pub fn allow(mut self, rule: LintRule) -> Self {
+        self.severities.insert(rule, None);
+        self
+    }
+
+    /// Length from which a chain of single-key objects is reported
+    /// (default: `4`).
+    pub fn max_chain(mut self, max_chain: usize) -> Self {
+        self.max_chain = max_chain;
+        self
+    }
+
+    /// Severity of `rule`, [None] when disabled.
+    pub fn get_severity(&self, rule: LintRule) -> Option<Severity> {
+        self.severities.get(&rule).copied().flatten()
+    }
+
+    pub fn get_max_chain(&self) -> usize {
+        self.max_chain
+    }
+}
+
+/// Options accepted by [crate::json_to_dot] and [crate::json_to_mermaid].
+///
+/// # Examples:
+/// ```rust
+/// use nestac::GraphOptions;
+///
+/// fn main() {
+///     let options = GraphOptions::new().previews(true).max_depth(2);
+///     assert!(options.get_previews());
+///     assert_eq!(options.get_max_depth(), Some(2));
+///     assert_eq!(GraphOptions::default().get_max_depth(), None);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphOptions {
+    previews: bool,
+    max_depth: Option<usize>,
+}
+
+impl GraphOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether scalar nodes show a preview of their value (default:
+    /// `false`).
+    pub fn previews(mut self, previews: bool) -> Self {
+        self.previews = previews;
+        self
+    }
+
+    /// Deepest level drawn, the root being `0` (default: unlimited).
+    /// Containers cut off at this level are marked with `...`.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn get_previews(&self) -> bool {
+        self.previews
+    }
+
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+}
+
+/// Order of the keys of every table written by `toml_to_string_at`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Iteration order of the map: sorted, unless the `preserve_order`
+    /// feature of the `toml` crate keeps the order keys were inserted in.
+    #[default]
+    AsIs,
+    /// Alphabetical order.
+    Sorted,
+    /// Order the keys are written in on this TOML source, typically the
+    /// file being saved, so rewriting it only moves what changed. Keys not
+    /// found on it follow in iteration order; a source that cannot be
+    /// parsed leaves every key in iteration order.
+    Source(String),
+}
+
+/// Options accepted by `toml_to_string_at`, enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::{KeyOrder, LayoutOptions};
+///
+/// fn main() {
+///     let options = LayoutOptions::new()
+///         .inline_tables(2)
+///         .expand_arrays_of_tables(false)
+///         .key_order(KeyOrder::Sorted);
+///     assert_eq!(options.get_inline_tables(), Some(2));
+///     assert!(!options.get_expand_arrays_of_tables());
+///     assert_eq!(options.get_key_order(), &KeyOrder::Sorted);
+///     assert_eq!(LayoutOptions::default().get_inline_tables(), None);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutOptions {
+    inline_tables: Option<usize>,
+    expand_arrays_of_tables: bool,
+    key_order: KeyOrder,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            inline_tables: None,
+            expand_arrays_of_tables: true,
+            key_order: KeyOrder::default(),
+        }
+    }
+}
+
+impl LayoutOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes tables holding at most `max_keys` keys, none of them a table
+    /// or an array of tables, inline (`server = { port = 80 }`) instead of
+    /// under a `[server]` header (default: never).
+    pub fn inline_tables(mut self, max_keys: usize) -> Self {
+        self.inline_tables = Some(max_keys);
+        self
+    }
+
+    /// Whether arrays of tables are written as `[[name]]` sections, or as
+    /// an inline array of inline tables (default: `true`).
+    pub fn expand_arrays_of_tables(mut self, expand: bool) -> Self {
+        self.expand_arrays_of_tables = expand;
+        self
+    }
+
+    /// Order of the keys of every table (default: [KeyOrder::AsIs]).
+    pub fn key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    pub fn get_inline_tables(&self) -> Option<usize> {
+        self.inline_tables
+    }
+
+    pub fn get_expand_arrays_of_tables(&self) -> bool {
+        self.expand_arrays_of_tables
+    }
+
+    pub fn get_key_order(&self) -> &KeyOrder {
+        &self.key_order
+    }
+}
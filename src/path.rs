@@ -0,0 +1,289 @@
+//! Paths parsed once and reused across many lookups.
+use std::fmt;
+use std::str::FromStr;
+use crate::nested::{check_token, escape_key, tokenize, try_step, NestedValue, Slice, Step};
+use crate::NestacError;
+
+/// A single step of a [Path].
+///
+/// Displayed as the token addressing it on a `.` separated path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Segment {
+    /// A key of a map.
+    Key(String),
+    /// An element of an array, written `[n]`.
+    Index(usize),
+    /// The first element of an array that is a map holding `value` under
+    /// `key`, written `[key=value]`.
+    Select { key: String, value: String },
+    /// A range of elements of an array, written `[start:end]`, see
+    /// [crate::json_read_slice].
+    Slice { start: Option<i64>, end: Option<i64> },
+    /// Past the last element of an array, written `[+]`: updating it
+    /// appends to the array.
+    Append,
+}
+
+impl Segment {
+    fn step(&self) -> Step<'_> {
+        match self {
+            Segment::Key(key) => Step::Key(key),
+            Segment::Index(idx) => Step::Index(*idx),
+            Segment::Select { key, value } => Step::Select(key, value),
+            Segment::Slice { start, end } => Step::Slice(Slice { start: *start, end: *end }),
+            Segment::Append => Step::Append,
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => f.write_str(&escape_key(key, ".")),
+            Segment::Index(idx) => write!(f, "[{}]", idx),
+            // the filter form is kept whole, separators included
+            Segment::Select { key, value } if key.contains('.') || value.contains('.') => {
+                write!(f, "[?(@.{}==\"{}\")]", key, value)
+            }
+            Segment::Select { key, value } => write!(f, "[{}={}]", key, value),
+            Segment::Slice { start, end } => {
+                let bound = |bound: &Option<i64>| bound.map(|bound| bound.to_string()).unwrap_or_default();
+                write!(f, "[{}:{}]", bound(start), bound(end))
+            }
+            Segment::Append => f.write_str("[+]"),
+        }
+    }
+}
+
+/// A segment along with where it was written on the path string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Token {
+    segment: Segment,
+    offset: usize,
+    len: usize,
+}
+
+/// A token-based path parsed once into [Segment]s, to be run against many
+/// documents of any format implementing [NestedValue].
+///
+/// Lookups behave like the `nested::*` functions called with the source
+/// path string, without splitting and parsing it again.
+///
+/// With the `serde` feature enabled it is serialized as the string of its
+/// segments joined by `.`, which is parsed back with the default
+/// separator.
+///
+/// # Examples:
+/// ```rust
+/// use serde_json::{json, Value};
+/// use nestac::{Path, Segment};
+///
+/// fn main() {
+///     let path = Path::parse("servers@[0]@port", Some("@")).unwrap();
+///     assert_eq!(path.segments().nth(1), Some(&Segment::Index(0)));
+///
+///     let mut docs = vec![
+///         json!({"servers": [{"port": 80}]}),
+///         json!({"servers": [{"port": 8080}]}),
+///     ];
+///     for doc in docs.iter_mut() {
+///         let port = path.read(doc).and_then(|v| v.as_u64()).unwrap();
+///         path.update(doc, Value::from(port + 1));
+///     }
+///     assert_eq!(docs[1], json!({"servers": [{"port": 8081}]}));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path {
+    raw: String,
+    tokens: Vec<Token>,
+}
+
+impl Path {
+    /// Parses a token-based path, split by `separator` (default: `.`).
+    ///
+    /// Fails with [NestacError::InvalidPath] when an index is too large to
+    /// be addressed or a `[?(` filter is not terminated.
+    pub fn parse(path: &str, separator: Option<&str>) -> Result<Self, NestacError> {
+        let mut tokens = vec![];
+        for token in tokenize(path, separator.unwrap_or(".")) {
+            check_token(path, &token)?;
+            let segment = match token.step() {
+                Step::Key(key) => Segment::Key(key.to_string()),
+                Step::Index(idx) => Segment::Index(idx),
+                Step::Select(key, value) => Segment::Select {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                },
+                Step::Slice(slice) => Segment::Slice { start: slice.start, end: slice.end },
+                Step::Append => Segment::Append,
+            };
+            tokens.push(Token { segment, offset: token.offset, len: token.raw.len() });
+        }
+        Ok(Path {
+            raw: path.to_string(),
+            tokens,
+        })
+    }
+
+    /// The path string it was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.tokens.iter().map(|token| &token.segment)
+    }
+
+    /// See [crate::nested::read].
+    pub fn read<'a, V: NestedValue>(&self, data: &'a V) -> Option<&'a V> {
+        self.tokens.iter().try_fold(data, |node, token| token.segment.step().get(node))
+    }
+
+    /// See [crate::nested::read_mut].
+    pub fn read_mut<'a, V: NestedValue>(&self, data: &'a mut V) -> Option<&'a mut V> {
+        walk_mut(&self.tokens, data)
+    }
+
+    /// See [crate::nested::try_read].
+    pub fn try_read<'a, V: NestedValue>(&self, data: &'a V) -> Result<&'a V, NestacError> {
+        let mut sel_data = data;
+        for (index, token) in self.tokens.iter().enumerate() {
+            let text = &self.raw[token.offset..token.offset + token.len];
+            let step = token.segment.step();
+            sel_data = match try_step(sel_data, step, &self.raw, text, index, token.offset)? {
+                Some(child) => child,
+                None => return Err(NestacError::MissingKey {
+                    path: self.raw.clone(),
+                    segment: text.to_string(),
+                    index,
+                    offset: token.offset,
+                }),
+            };
+        }
+        Ok(sel_data)
+    }
+
+    /// See [crate::nested::update].
+    pub fn update<V: NestedValue>(&self, data: &mut V, new_value: V) -> Option<V> {
+        let (last, parents) = self.tokens.split_last()?;
+        let parent = walk_mut(parents, data)?;
+        last.segment.step().replace(parent, new_value).ok().flatten()
+    }
+
+    /// See [crate::nested::delete].
+    pub fn delete<V: NestedValue>(&self, data: &mut V) -> Option<V> {
+        let (last, parents) = self.tokens.split_last()?;
+        let parent = walk_mut(parents, data)?;
+        last.segment.step().remove(parent)
+    }
+}
+
+fn walk_mut<'a, V: NestedValue>(tokens: &[Token], data: &'a mut V) -> Option<&'a mut V> {
+    tokens.iter().try_fold(data, |node, token| token.segment.step().get_mut(node))
+}
+
+/// Parses a path split by the default `.` separator.
+impl FromStr for Path {
+    type Err = NestacError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        Path::parse(path, None)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Path {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tokens: Vec<String> = self.segments().map(Segment::to_string).collect();
+        serializer.serialize_str(&tokens.join("."))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Path {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Path::parse(&raw, None).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn parse_segments() {
+        let path: Path = "foo.[2].[name=app].bar.[1:-1]".parse().unwrap();
+        assert_eq!(path.segments().cloned().collect::<Vec<Segment>>(), vec![
+            Segment::Key("foo".to_string()),
+            Segment::Index(2),
+            Segment::Select { key: "name".to_string(), value: "app".to_string() },
+            Segment::Key("bar".to_string()),
+            Segment::Slice { start: Some(1), end: Some(-1) },
+        ]);
+        assert_eq!(path.to_string(), "foo.[2].[name=app].bar.[1:-1]");
+        assert!(matches!(
+            Path::parse("foo.[99999999999999999999999]", None),
+            Err(NestacError::InvalidPath { .. }),
+        ));
+        assert!(matches!(
+            Path::parse("foo.[?(@.n==x.q", None),
+            Err(NestacError::InvalidPath { .. }),
+        ));
+
+        let path: Path = "tags.[+]".parse().unwrap();
+        assert_eq!(path.segments().nth(1), Some(&Segment::Append));
+        let mut json_data = json!({"tags": [1]});
+        assert_eq!(path.update(&mut json_data, Value::from(2)), None);
+        assert_eq!(json_data, json!({"tags": [1, 2]}));
+    }
+
+    #[test]
+    fn same_results_as_string_paths() {
+        let json_data = json!({"foo": [{"bar": 1}, "x"]});
+        for raw in ["foo", "foo.[0].bar", "foo.[1].bar", "foo.[2]", "foo.[bar=1]", "foo.[bar=2]", "nope", "foo.bar", "foo.[1:]"] {
+            let path: Path = raw.parse().unwrap();
+            assert_eq!(path.read(&json_data), crate::json_read(raw, &json_data, None));
+            assert_eq!(path.try_read(&json_data), crate::json_try_read(raw, &json_data, None));
+            let mut by_path = json_data.clone();
+            let mut by_str = json_data.clone();
+            assert_eq!(
+                path.update(&mut by_path, Value::Null),
+                crate::nested::update(&mut by_str, raw, None, Value::Null),
+            );
+            assert_eq!(
+                path.update(&mut by_path, json!([2])),
+                crate::nested::update(&mut by_str, raw, None, json!([2])),
+            );
+            assert_eq!(by_path, by_str);
+            assert_eq!(path.delete(&mut by_path), crate::json_delete(&mut by_str, raw, None));
+            assert_eq!(by_path, by_str);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_round_trip() {
+        let path = Path::parse(r#"hosts@192.168.0.1@[?(@.name=="a.b")]@[1:]@[+]"#, Some("@")).unwrap();
+        let json_str = serde_json::to_string(&path).unwrap();
+        assert_eq!(json_str, r#""hosts.\"192.168.0.1\".[?(@.name==\"a.b\")].[1:].[+]""#);
+        let parsed: Path = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed.segments().collect::<Vec<_>>(), path.segments().collect::<Vec<_>>());
+        let path: Path = "foo.[2].[name=app]".parse().unwrap();
+        assert_eq!(serde_json::from_str::<Path>(&serde_json::to_string(&path).unwrap()).unwrap(), path);
+
+        let segment = Segment::Slice { start: None, end: Some(-1) };
+        let json_str = serde_json::to_string(&segment).unwrap();
+        assert_eq!(serde_json::from_str::<Segment>(&json_str).unwrap(), segment);
+        assert!(serde_json::from_str::<Path>(r#""foo.[99999999999999999999999]""#).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::document::Document;
+
+/// Pool of source buffers reused between [Document] parses.
+///
+/// Every [Document] keeps a copy of its source; parsing through a pool
+/// copies (or reads) it into a buffer left by a previously
+/// [recycled](DocumentPool::recycle) document instead of allocating a new
+/// one, cutting allocation churn on services parsing many similar documents.
+/// `serde_json` does not allow reusing the maps of a parsed [Value], so
+/// only the source buffers are pooled.
+///
+/// The pool is [Sync] and can be shared between threads.
+///
+/// [Value]: serde_json::Value
+///
+/// # Examples:
+/// ```rust
+/// use nestac::DocumentPool;
+///
+/// fn main() {
+///     let pool = DocumentPool::new();
+///     for json_str in [r#"{"id": 1}"#, r#"{"id": 2}"#] {
+///         let doc = pool.parse(json_str).unwrap();
+///         assert!(doc.read("id", None).is_some());
+///         pool.recycle(doc);
+///     }
+///     assert_eq!(pool.len(), 1);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DocumentPool {
+    buffers: Mutex<Vec<String>>,
+    max_buffers: usize,
+}
+
+impl Default for DocumentPool {
+    fn default() -> Self {
+        DocumentPool::with_max_buffers(64)
+    }
+}
+
+impl DocumentPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pool keeping at most `max_buffers` idle buffers; extra recycled
+    /// buffers are dropped.
+    pub fn with_max_buffers(max_buffers: usize) -> Self {
+        DocumentPool {
+            buffers: Mutex::new(vec![]),
+            max_buffers,
+        }
+    }
+
+    /// Number of idle buffers.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether there is no idle buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Same as [Document::parse], copying `source` into a pooled buffer.
+    pub fn parse(&self, source: &str) -> Result<Document, serde_json::Error> {
+        let mut buffer = self.take();
+        buffer.push_str(source);
+        self.parse_buffer(buffer)
+    }
+
+    /// Reads and parses the file at `path` into a pooled buffer. Parse
+    /// errors are reported as [io::ErrorKind::InvalidData].
+    pub fn load(&self, path: impl AsRef<Path>) -> io::Result<Document> {
+        let mut buffer = self.take();
+        if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut buffer)) {
+            self.give_back(buffer);
+            return Err(err);
+        }
+        Ok(self.parse_buffer(buffer)?)
+    }
+
+    /// Gives the source buffer of `doc` back to the pool.
+    pub fn recycle(&self, doc: Document) {
+        self.give_back(doc.into_source());
+    }
+
+    fn parse_buffer(&self, buffer: String) -> Result<Document, serde_json::Error> {
+        Document::from_source(buffer).map_err(|(buffer, err)| {
+            self.give_back(buffer);
+            err
+        })
+    }
+
+    fn take(&self) -> String {
+        self.lock().pop().unwrap_or_default()
+    }
+
+    fn give_back(&self, mut buffer: String) {
+        buffer.clear();
+        let mut buffers = self.lock();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.buffers.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_are_reused_and_bounded() {
+        let pool = DocumentPool::with_max_buffers(1);
+        let doc = pool.parse(r#"{"foo": "a long enough value"}"#).unwrap();
+        let other = pool.parse("{}").unwrap();
+        pool.recycle(doc);
+        pool.recycle(other);
+        assert_eq!(pool.len(), 1);
+
+        let doc = pool.parse("[1]").unwrap();
+        assert!(pool.is_empty());
+        assert_eq!(doc.to_string(), "[1]");
+        assert!(doc.into_source().capacity() >= 30);
+
+        assert!(pool.parse("{").is_err());
+        assert_eq!(pool.len(), 1);
+        let err = pool.load("/nonexistent/config.json").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,47 @@
+use toml::Value;
+use crate::{nested, NestacError};
+
+/// Whether the array at the token-based [str] path holds `value`.
+///
+/// Same semantics as [crate::json_array_contains].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_array_contains(data: &Value, path: &str, separator: Option<&str>, value: &Value) -> Result<bool, NestacError> {
+    nested::array_contains(data, path, separator, value)
+}
+
+/// Appends `value` to the array at the token-based [str] path unless it
+/// already holds an equal element, and returns whether it was added.
+///
+/// Same semantics as [crate::json_array_add_unique].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_array_add_unique(data: &mut Value, path: &str, separator: Option<&str>, value: Value) -> Result<bool, NestacError> {
+    nested::array_add_unique(data, path, separator, value)
+}
+
+/// Removes every element equal to `value` from the array at the
+/// token-based [str] path, and returns how many were removed.
+///
+/// Same semantics as [crate::json_array_remove_value].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_array_remove_value(data: &mut Value, path: &str, separator: Option<&str>, value: &Value) -> Result<usize, NestacError> {
+    nested::array_remove_value(data, path, separator, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maintain_allowed_origins() {
+        let mut toml_data: Value = toml::from_str("[cors]\norigins = [\"https://a.com\"]\n").unwrap();
+        let origin = Value::from("https://b.com");
+        assert_eq!(toml_array_add_unique(&mut toml_data, "cors.origins", None, origin.clone()), Ok(true));
+        assert_eq!(toml_array_add_unique(&mut toml_data, "cors.origins", None, origin.clone()), Ok(false));
+        assert_eq!(toml_array_contains(&toml_data, "cors.origins", None, &origin), Ok(true));
+        assert_eq!(toml_array_remove_value(&mut toml_data, "cors.origins", None, &Value::from("https://a.com")), Ok(1));
+        assert_eq!(toml::to_string(&toml_data).unwrap(), "[cors]\norigins = [\"https://b.com\"]\n");
+    }
+}
@@ -0,0 +1,85 @@
+use toml::Value;
+use crate::nested;
+
+/// Remove the [Value] addressed by the token-based [str] path and returns
+/// it.
+///
+/// Same path format and signature as [crate::json_delete].
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::{toml_delete, toml_read};
+///
+/// fn main() {
+///     let toml_str = r#"
+///         [foo]
+///         bar = "bingo!"
+///         ports = [80, 443]
+///     "#;
+///     let mut toml_data: Value = toml::from_str(toml_str).unwrap();
+///     let removed = toml_delete(&mut toml_data, "foo.bar", None);
+///     assert_eq!(removed.unwrap().as_str(), Some("bingo!"));
+///     let removed = toml_delete(&mut toml_data, "foo.ports.[0]", None);
+///     assert_eq!(removed.unwrap().as_integer(), Some(80));
+///     assert!(toml_read("foo.bar", &toml_data, None).is_none());
+/// }
+/// ```
+pub fn toml_delete(data: &mut Value, path: &str, separator: Option<&str>) -> Option<Value> {
+    nested::delete(data, path, separator)
+}
+
+/// Remove every [Value] matched by the token-based [str] path and returns
+/// the concrete path and [Value] of each of them.
+///
+/// Same semantics as [crate::json_delete_all].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_delete_all(data: &mut Value, path: &str, separator: Option<&str>) -> Vec<(String, Value)> {
+    nested::delete_all(data, path, separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delete_table() {
+        let toml_str = r#"
+            [foo.bar]
+            baz = 1
+
+            [qux]
+            n = 2
+        "#;
+        let mut toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let removed = toml_delete(toml_data.as_mut().unwrap(), "foo|bar", Some("|"));
+        assert_eq!(removed.unwrap().as_table().unwrap().len(), 1);
+        assert!(toml_delete(toml_data.as_mut().unwrap(), "foo.bar", None).is_none());
+        assert_eq!(toml::to_string(toml_data.as_ref().unwrap()).unwrap(), "[foo]\n\n[qux]\nn = 2\n");
+    }
+
+    #[test]
+    fn delete_tables_by_key() {
+        let toml_str = r#"
+            [[bin]]
+            name = "cli"
+            path = "src/main.rs"
+
+            [[bin]]
+            name = "bench"
+            path = "benches/main.rs"
+            test = false
+        "#;
+        let mut toml_data: Value = toml::from_str(toml_str).unwrap();
+        let removed = toml_delete(&mut toml_data, "bin.[name=bench].test", None);
+        assert_eq!(removed.unwrap().as_bool(), Some(false));
+        let removed = toml_delete_all(&mut toml_data, "bin.[*name=cli]", None);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].0, "bin.[0]");
+        assert_eq!(crate::toml_read("bin.[0].name", &toml_data, None).unwrap().as_str(), Some("bench"));
+    }
+}
\ No newline at end of file
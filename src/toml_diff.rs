@@ -0,0 +1,27 @@
+use toml::Value;
+use crate::nested::{self, Change};
+
+/// Returns the path-level changes turning `old` into `new`.
+///
+/// Same semantics as [crate::json_diff].
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::toml_diff;
+/// use nestac::nested::ChangeKind;
+///
+/// fn main() {
+///     let old: Value = toml::from_str("[package]\nversion = \"0.1.0\"\n").unwrap();
+///     let new: Value = toml::from_str("[package]\nversion = \"0.2.0\"\n").unwrap();
+///     let changes = toml_diff(&old, &new);
+///     assert_eq!(changes.len(), 1);
+///     assert_eq!(changes[0].path, "package.version");
+///     assert_eq!(changes[0].kind, ChangeKind::Modified);
+/// }
+/// ```
+pub fn toml_diff(old: &Value, new: &Value) -> Vec<Change<Value>> {
+    nested::diff(old, new)
+}
@@ -0,0 +1,350 @@
+//! Format-preserving updates on TOML documents, backed by `toml_edit`.
+//!
+//! [crate::toml_update] and [crate::toml_delete] work on a parsed
+//! [toml::Value], so writing it back drops comments, key order and
+//! formatting. The functions here apply the same paths to a
+//! [DocumentMut] instead, leaving everything but the touched node as it
+//! was written.
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+use crate::nested::{tokenize, Step};
+
+/// A node reached while walking a [DocumentMut]: tables hold [Item]s,
+/// arrays of tables hold [Table]s and inline arrays hold [Value]s.
+enum Node<'a> {
+    Item(&'a mut Item),
+    Table(&'a mut Table),
+    Value(&'a mut Value),
+}
+
+/// Elements of an array node.
+enum Elements<'a> {
+    Tables(&'a mut ArrayOfTables),
+    Values(&'a mut toml_edit::Array),
+}
+
+impl<'a> Node<'a> {
+    /// Whether the node is written inline, so its children must be values.
+    fn is_inline(&self) -> bool {
+        matches!(self, Node::Value(_) | Node::Item(Item::Value(_)))
+    }
+
+    fn table_like(self) -> Option<&'a mut dyn TableLike> {
+        match self {
+            Node::Item(item) => item.as_table_like_mut(),
+            Node::Table(table) => Some(table),
+            Node::Value(value) => value.as_inline_table_mut().map(|table| table as &mut dyn TableLike),
+        }
+    }
+
+    fn elements(self) -> Option<Elements<'a>> {
+        match self {
+            Node::Item(Item::ArrayOfTables(tables)) => Some(Elements::Tables(tables)),
+            Node::Item(Item::Value(Value::Array(values))) | Node::Value(Value::Array(values)) => {
+                Some(Elements::Values(values))
+            }
+            _ => None,
+        }
+    }
+
+    fn child(self, step: Step) -> Option<Node<'a>> {
+        match step {
+            Step::Key(key) => self.table_like()?.get_mut(key).map(Node::Item),
+            _ => {
+                let elements = self.elements()?;
+                let idx = elements.index(step)?;
+                match elements {
+                    Elements::Tables(tables) => tables.get_mut(idx).map(Node::Table),
+                    Elements::Values(values) => values.get_mut(idx).map(Node::Value),
+                }
+            }
+        }
+    }
+
+    fn replace(self, step: Step, new_value: Item) -> Option<Item> {
+        let new_value = if self.is_inline() {
+            Item::Value(new_value.into_value().ok()?)
+        } else {
+            new_value
+        };
+        if let Step::Append = step {
+            match self.elements()? {
+                Elements::Tables(tables) => tables.push(new_value.into_table().ok()?),
+                Elements::Values(values) => {
+                    let mut new_value = new_value.into_value().ok()?;
+                    // a new line of a multi-line array indents like the others
+                    if let Some(prefix) = values.iter().last().and_then(|last| last.decor().prefix()).cloned() {
+                        if values.len() > 1 || prefix.as_str().is_some_and(|prefix| prefix.contains('\n')) {
+                            new_value.decor_mut().set_prefix(prefix);
+                        }
+                    }
+                    values.push_formatted(new_value);
+                }
+            }
+            return None;
+        }
+        if let Step::Key(key) = step {
+            let table = self.table_like()?;
+            return match table.get_mut(key) {
+                Some(existing) => {
+                    let mut new_value = new_value;
+                    keep_layout(existing, &mut new_value);
+                    Some(std::mem::replace(existing, new_value))
+                }
+                None => {
+                    table.insert(key, new_value);
+                    None
+                }
+            };
+        }
+        let elements = self.elements()?;
+        let idx = elements.index(step)?;
+        match elements {
+            Elements::Tables(tables) => {
+                let existing = tables.get_mut(idx)?;
+                let mut new_table = new_value.into_table().ok()?;
+                keep_table_layout(existing, &mut new_table);
+                Some(Item::Table(std::mem::replace(existing, new_table)))
+            }
+            Elements::Values(values) => {
+                let existing = values.get_mut(idx)?;
+                let mut new_value = new_value.into_value().ok()?;
+                *new_value.decor_mut() = existing.decor().clone();
+                Some(Item::Value(std::mem::replace(existing, new_value)))
+            }
+        }
+    }
+
+    fn remove(self, step: Step) -> Option<Item> {
+        if let Step::Key(key) = step {
+            return self.table_like()?.remove(key);
+        }
+        let elements = self.elements()?;
+        let idx = elements.index(step)?;
+        match elements {
+            Elements::Tables(tables) if idx < tables.len() => Some(Item::Table(tables.remove(idx))),
+            Elements::Values(values) if idx < values.len() => {
+                let removed = values.remove(idx);
+                // the new first element takes the spacing after the `[`
+                if let (0, Some(first)) = (idx, values.get_mut(0)) {
+                    let prefix = removed.decor().prefix().cloned().unwrap_or_default();
+                    first.decor_mut().set_prefix(prefix);
+                }
+                Some(Item::Value(removed))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Elements<'_> {
+    /// Position of the element addressed by an `[n]` or `[key=value]` step.
+    fn index(&self, step: Step) -> Option<usize> {
+        match step {
+            Step::Key(_) | Step::Slice(_) | Step::Append => None,
+            Step::Index(idx) => Some(idx),
+            Step::Select(key, value) => {
+                let matches = |item: Option<&Item>| {
+                    item.and_then(Item::as_value).and_then(text).is_some_and(|text| text == value)
+                };
+                match self {
+                    Elements::Tables(tables) => tables.iter().position(|table| matches(table.get(key))),
+                    Elements::Values(values) => values.iter().position(|value| {
+                        matches(value.as_inline_table().and_then(|table| TableLike::get(table, key)))
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Text of a scalar, as matched by `[key=value]` tokens (see
+/// [crate::NestedValue::as_text]).
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.value().clone()),
+        Value::Integer(number) => Some(number.value().to_string()),
+        Value::Float(number) => Some(toml::Value::Float(*number.value()).to_string()),
+        Value::Boolean(flag) => Some(flag.value().to_string()),
+        Value::Datetime(datetime) => Some(datetime.value().to_string()),
+        Value::Array(_) | Value::InlineTable(_) => None,
+    }
+}
+
+/// Gives `new_value` the surrounding whitespace and comments of the item it
+/// replaces.
+fn keep_layout(old: &Item, new_value: &mut Item) {
+    match (old, new_value) {
+        (Item::Value(old), Item::Value(new_value)) => *new_value.decor_mut() = old.decor().clone(),
+        (Item::Table(old), Item::Table(new_value)) => keep_table_layout(old, new_value),
+        _ => {}
+    }
+}
+
+/// Gives `new_table` the header comments and position of the table it
+/// replaces.
+fn keep_table_layout(old: &Table, new_table: &mut Table) {
+    *new_table.decor_mut() = old.decor().clone();
+    if let Some(position) = old.position() {
+        new_table.set_position(position);
+    }
+}
+
+/// Update the [Item] at the token-based [str] path of a [DocumentMut] and
+/// returns the old [Item], keeping comments and formatting.
+///
+/// Same semantics as [crate::toml_update]: returns [None] when the path does
+/// not exist, or when the last key did not exist before the update (it is
+/// then inserted). It is also [None], leaving `doc` untouched, when
+/// `new_value` cannot be stored at the path (e.g. a scalar in an array of
+/// tables). The new value takes over the whitespace and trailing comment of
+/// the one it replaces. A last `[+]` token appends to an array, or to an
+/// array of tables when `new_value` is a table, indented like the elements
+/// already there.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml_edit::{value, DocumentMut};
+/// use nestac::toml_edit_update;
+///
+/// fn main() {
+///     let mut doc: DocumentMut = r#"
+/// [server]
+/// host = "localhost"
+/// port = 8080  # keep in sync with the proxy
+/// "#.parse().unwrap();
+///
+///     let old_val = toml_edit_update(&mut doc, "server.port", None, value(9090));
+///     assert_eq!(old_val.unwrap().as_integer(), Some(8080));
+///     assert_eq!(doc.to_string(), r#"
+/// [server]
+/// host = "localhost"
+/// port = 9090  # keep in sync with the proxy
+/// "#);
+/// }
+/// ```
+pub fn toml_edit_update(doc: &mut DocumentMut, path: &str, separator: Option<&str>, new_value: Item) -> Option<Item> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last()?;
+    let mut node = Node::Table(doc.as_table_mut());
+    for token in parents {
+        node = node.child(token.step())?;
+    }
+    node.replace(last.step(), new_value)
+}
+
+/// Remove the [Item] addressed by the token-based [str] path of a
+/// [DocumentMut] and returns it, keeping comments and formatting of the
+/// rest of the document.
+///
+/// Same semantics as [crate::toml_delete]. Comments attached to the removed
+/// key go with it.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml_edit::DocumentMut;
+/// use nestac::toml_edit_delete;
+///
+/// fn main() {
+///     let mut doc: DocumentMut = r#"
+/// [server]
+/// host = "localhost"  # public name
+/// legacy = true  # deprecated
+/// ports = [80, 443]
+/// "#.parse().unwrap();
+///
+///     assert!(toml_edit_delete(&mut doc, "server.legacy", None).is_some());
+///     assert!(toml_edit_delete(&mut doc, "server.ports.[0]", None).is_some());
+///     assert!(toml_edit_delete(&mut doc, "server.missing", None).is_none());
+///     assert_eq!(doc.to_string(), r#"
+/// [server]
+/// host = "localhost"  # public name
+/// ports = [443]
+/// "#);
+/// }
+/// ```
+pub fn toml_edit_delete(doc: &mut DocumentMut, path: &str, separator: Option<&str>) -> Option<Item> {
+    let tokens = tokenize(path, separator.unwrap_or("."));
+    let (last, parents) = tokens.split_last()?;
+    let mut node = Node::Table(doc.as_table_mut());
+    for token in parents {
+        node = node.child(token.step())?;
+    }
+    node.remove(last.step())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml_edit::value;
+
+    #[test]
+    fn update_inside_arrays_of_tables_and_inline_tables() {
+        let source = r#"[[servers]]
+name = "a"  # primary
+port = 80
+
+[[servers]]
+name = "b"
+tls = { enabled = false, cert = "b.pem" }
+"#;
+        let mut doc: DocumentMut = source.parse().unwrap();
+        let old_val = toml_edit_update(&mut doc, "servers.[name=a].port", None, value(8080));
+        assert_eq!(old_val.unwrap().as_integer(), Some(80));
+        let old_val = toml_edit_update(&mut doc, "servers.[1].tls.enabled", None, value(true));
+        assert_eq!(old_val.unwrap().as_bool(), Some(false));
+        assert!(toml_edit_update(&mut doc, "servers.[0]", None, value(1)).is_none());
+        assert!(toml_edit_update(&mut doc, "servers.[5].port", None, value(1)).is_none());
+        assert_eq!(doc.to_string(), source.replace("80", "8080").replace("false", "true"));
+
+        let removed = toml_edit_delete(&mut doc, "servers.[name=a]", None).unwrap();
+        assert!(removed.is_table());
+        assert_eq!(
+            doc.to_string(),
+            "\n[[servers]]\nname = \"b\"\ntls = { enabled = true, cert = \"b.pem\" }\n",
+        );
+    }
+
+    #[test]
+    fn append_keeps_layout() {
+        let source = r#"ports = [80]  # public
+hosts = [
+    "a",
+    "b",
+]
+
+[[servers]]
+name = "a"  # primary
+
+[cache]
+size = 1
+"#;
+        let mut doc: DocumentMut = source.parse().unwrap();
+        assert!(toml_edit_update(&mut doc, "ports.[+]", None, value(443)).is_none());
+        assert!(toml_edit_update(&mut doc, "hosts.[+]", None, value("c")).is_none());
+        let mut server = Table::new();
+        server.insert("name", value("b"));
+        assert!(toml_edit_update(&mut doc, "servers.[+]", None, Item::Table(server)).is_none());
+        assert!(toml_edit_update(&mut doc, "servers.[+]", None, value(1)).is_none());
+        assert!(toml_edit_update(&mut doc, "cache.[+]", None, value(1)).is_none());
+        assert_eq!(doc.to_string(), r#"ports = [80, 443]  # public
+hosts = [
+    "a",
+    "b",
+    "c",
+]
+
+[[servers]]
+name = "a"  # primary
+
+[[servers]]
+name = "b"
+
+[cache]
+size = 1
+"#);
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+use toml::Value;
+use crate::file::{self, FileError};
+use crate::nested;
+
+/// Reads the TOML `file` and returns the [Value] at the token-based [str]
+/// path.
+///
+/// Same semantics as [crate::json_read_file].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_read_file(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Value, FileError> {
+    let data: Value = file::load(file.as_ref(), |source| {
+        toml::from_str(source).map_err(|err| err.to_string())
+    })?;
+    nested::extract(data, path, separator).map_err(|(_, err)| FileError::Path(err))
+}
+
+/// Updates the [Value] at the token-based [str] path of the TOML `file` and
+/// returns the old [Value].
+///
+/// Same semantics as [crate::json_update_file], but the file is written
+/// back from the parsed [Value]: comments and formatting are not kept.
+/// Nothing is written when the value is unchanged.
+///
+/// Enabled by the `toml` feature.
+pub fn toml_update_file(file: impl AsRef<Path>, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, FileError> {
+    let file = file.as_ref();
+    let mut data: Value = file::load(file, |source| {
+        toml::from_str(source).map_err(|err| err.to_string())
+    })?;
+    if nested::read(path, &data, separator) == Some(&new_value) {
+        return Ok(Some(new_value));
+    }
+    let old_val = nested::try_update(&mut data, path, separator, new_value)?;
+    let contents = toml::to_string(&data).map_err(|err| FileError::Serialize {
+        file: file.to_path_buf(),
+        message: err.to_string(),
+    })?;
+    file::store(file, &contents)?;
+    Ok(old_val)
+}
+
+/// Reads the TOML `file` through tokio and returns the [Value] at the
+/// token-based [str] path.
+///
+/// Same semantics as [crate::json_read_file_async].
+///
+/// Enabled by the `toml` and `tokio` features.
+#[cfg(feature = "tokio")]
+pub async fn toml_read_file_async(file: impl AsRef<Path>, path: &str, separator: Option<&str>) -> Result<Value, FileError> {
+    let data: Value = file::load_async(file.as_ref(), |source| {
+        toml::from_str(source).map_err(|err| err.to_string())
+    }).await?;
+    nested::extract(data, path, separator).map_err(|(_, err)| FileError::Path(err))
+}
+
+/// Updates the [Value] at the token-based [str] path of the TOML `file`
+/// through tokio and returns the old [Value].
+///
+/// Same semantics as [toml_update_file].
+///
+/// Enabled by the `toml` and `tokio` features.
+#[cfg(feature = "tokio")]
+pub async fn toml_update_file_async(file: impl AsRef<Path>, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, FileError> {
+    let file = file.as_ref();
+    let mut data: Value = file::load_async(file, |source| {
+        toml::from_str(source).map_err(|err| err.to_string())
+    }).await?;
+    if nested::read(path, &data, separator) == Some(&new_value) {
+        return Ok(Some(new_value));
+    }
+    let old_val = nested::try_update(&mut data, path, separator, new_value)?;
+    let contents = toml::to_string(&data).map_err(|err| FileError::Serialize {
+        file: file.to_path_buf(),
+        message: err.to_string(),
+    })?;
+    file::store_async(file, contents).await?;
+    Ok(old_val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Cargo.toml");
+        std::fs::write(&file, "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+
+        let old_val = toml_update_file(&file, "package.version", None, Value::from("0.2.0")).unwrap();
+        assert_eq!(old_val, Some(Value::from("0.1.0")));
+        assert_eq!(toml_read_file(&file, "package.version", None).unwrap(), Value::from("0.2.0"));
+
+        std::fs::write(&file, "[package]  # kept\nversion = \"0.2.0\"\n").unwrap();
+        let old_val = toml_update_file(&file, "package.version", None, Value::from("0.2.0")).unwrap();
+        assert_eq!(old_val, Some(Value::from("0.2.0")));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "[package]  # kept\nversion = \"0.2.0\"\n");
+
+        std::fs::write(&file, "[package\n").unwrap();
+        let err = toml_read_file(&file, "package", None).unwrap_err();
+        assert!(matches!(err, FileError::Parse { .. }));
+    }
+}
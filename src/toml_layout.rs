@@ -0,0 +1,181 @@
+//! Layout-controlled TOML serialization.
+//!
+//! `toml::to_string` writes every table under its own header, in key
+//! order, so saving a document after a single update can reshuffle the
+//! whole file. [toml_to_string_at] lets callers pick which tables are
+//! written inline, how arrays of tables are written and how keys are
+//! ordered.
+use std::collections::HashMap;
+use toml::{Table, Value};
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, TableLike};
+use crate::options::{KeyOrder, LayoutOptions};
+
+/// Position of every key path of a source document, see [KeyOrder::Source].
+type SourceOrder = HashMap<String, usize>;
+
+/// Writes `data` as a TOML document laid out as `options` say.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::{toml_to_string_at, toml_update, KeyOrder, LayoutOptions};
+///
+/// fn main() {
+///     let source = "name = \"api\"\n\n[server]\nport = 80\nhost = \"localhost\"\n\n[[plugins]]\nid = \"gzip\"\n";
+///     let mut data: toml::Value = toml::from_str(source).unwrap();
+///     toml_update(&mut data, "server.port", None, toml::Value::Integer(8080));
+///
+///     let options = LayoutOptions::new().key_order(KeyOrder::Source(source.to_string()));
+///     let saved = toml_to_string_at(data.as_table().unwrap(), &options);
+///     assert_eq!(saved, source.replace("80", "8080"));
+///
+///     let options = LayoutOptions::new().inline_tables(2).expand_arrays_of_tables(false);
+///     let saved = toml_to_string_at(data.as_table().unwrap(), &options);
+///     assert_eq!(saved, [
+///         "name = \"api\"",
+///         "plugins = [{ id = \"gzip\" }]",
+///         "server = { host = \"localhost\", port = 8080 }",
+///         "",
+///     ].join("\n"));
+/// }
+/// ```
+pub fn toml_to_string_at(data: &Table, options: &LayoutOptions) -> String {
+    let mut order = SourceOrder::new();
+    if let KeyOrder::Source(source) = options.get_key_order() {
+        if let Ok(doc) = source.parse::<DocumentMut>() {
+            source_order(doc.as_table(), "", &mut order);
+        }
+    }
+    let mut doc = DocumentMut::new();
+    fill_table(doc.as_table_mut(), data, "", options, &order);
+    doc.to_string()
+}
+
+/// Records the position of every key path below `table`, in the order
+/// they are written on the source.
+fn source_order(table: &dyn TableLike, path: &str, order: &mut SourceOrder) {
+    for (key, item) in table.iter() {
+        let item_path = key_path(path, key);
+        let position = order.len();
+        order.entry(item_path.clone()).or_insert(position);
+        if let Some(child) = item.as_table_like() {
+            source_order(child, &item_path, order);
+        } else if let Some(tables) = item.as_array_of_tables() {
+            for child in tables.iter() {
+                source_order(child, &item_path, order);
+            }
+        } else if let Some(values) = item.as_array() {
+            for child in values.iter().filter_map(|value| value.as_inline_table()) {
+                source_order(child, &item_path, order);
+            }
+        }
+    }
+}
+
+/// Path of `key` below `path`, array indexes left out so every element of
+/// an array shares the order of the first one.
+fn key_path(path: &str, key: &str) -> String {
+    format!("{}\u{0}{}", path, key)
+}
+
+/// Entries of `table` in the order asked for.
+fn ordered<'a>(table: &'a Table, path: &str, options: &LayoutOptions, order: &SourceOrder) -> Vec<(&'a String, &'a Value)> {
+    let mut entries: Vec<(&String, &Value)> = table.iter().collect();
+    match options.get_key_order() {
+        KeyOrder::AsIs => {}
+        KeyOrder::Sorted => entries.sort_by_key(|(key, _)| *key),
+        KeyOrder::Source(_) => {
+            entries.sort_by_key(|(key, _)| order.get(&key_path(path, key)).copied().unwrap_or(usize::MAX));
+        }
+    }
+    entries
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty() && items.iter().all(Value::is_table),
+        _ => false,
+    }
+}
+
+/// Whether `table` is written inline under its parent.
+fn is_inline(table: &Table, options: &LayoutOptions) -> bool {
+    options.get_inline_tables().is_some_and(|max_keys| {
+        table.len() <= max_keys && table.values().all(|value| !value.is_table() && !is_array_of_tables(value))
+    })
+}
+
+fn fill_table(out: &mut toml_edit::Table, table: &Table, path: &str, options: &LayoutOptions, order: &SourceOrder) {
+    for (key, value) in ordered(table, path, options, order) {
+        let item_path = key_path(path, key);
+        let item = match value {
+            Value::Table(child) if !is_inline(child, options) => {
+                let mut child_out = toml_edit::Table::new();
+                // only written when it holds more than other tables
+                child_out.set_implicit(!child.is_empty());
+                fill_table(&mut child_out, child, &item_path, options, order);
+                Item::Table(child_out)
+            }
+            Value::Array(items) if options.get_expand_arrays_of_tables() && is_array_of_tables(value) => {
+                let mut tables = ArrayOfTables::new();
+                for child in items.iter().filter_map(Value::as_table) {
+                    let mut child_out = toml_edit::Table::new();
+                    fill_table(&mut child_out, child, &item_path, options, order);
+                    tables.push(child_out);
+                }
+                Item::ArrayOfTables(tables)
+            }
+            _ => Item::Value(inline_value(value, &item_path, options, order)),
+        };
+        out.insert(key, item);
+    }
+}
+
+fn inline_value(value: &Value, path: &str, options: &LayoutOptions, order: &SourceOrder) -> toml_edit::Value {
+    match value {
+        Value::String(text) => text.as_str().into(),
+        Value::Integer(number) => (*number).into(),
+        Value::Float(number) => (*number).into(),
+        Value::Boolean(flag) => (*flag).into(),
+        Value::Datetime(datetime) => (*datetime).into(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| inline_value(item, path, options, order))
+            .collect::<toml_edit::Array>()
+            .into(),
+        Value::Table(table) => {
+            let mut inline = InlineTable::new();
+            for (key, child) in ordered(table, path, options, order) {
+                inline.insert(key, inline_value(child, &key_path(path, key), options, order));
+            }
+            inline.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_tables_and_orders() {
+        let data: Table = toml::from_str(r#"
+            b = 1
+            a = [1, 2]
+            [outer.inner]
+            z = true
+            y = { deep = 1 }
+            [empty]
+        "#).unwrap();
+        let saved = toml_to_string_at(&data, &LayoutOptions::new());
+        assert_eq!(saved, "a = [1, 2]\nb = 1\n\n[empty]\n\n[outer.inner]\nz = true\n\n[outer.inner.y]\ndeep = 1\n");
+        assert_eq!(toml::from_str::<Table>(&saved).unwrap(), data);
+
+        let source = "b = 0\na = 0\n[outer.inner]\nz = 0\ny = 0\n[empty]\n";
+        let options = LayoutOptions::new().inline_tables(1).key_order(KeyOrder::Source(source.to_string()));
+        let saved = toml_to_string_at(&data, &options);
+        assert_eq!(saved, "b = 1\na = [1, 2]\nempty = {}\n\n[outer.inner]\nz = true\ny = { deep = 1 }\n");
+        assert_eq!(toml::from_str::<Table>(&saved).unwrap(), data);
+    }
+}
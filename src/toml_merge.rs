@@ -0,0 +1,95 @@
+use toml::{Table, Value};
+use crate::{nested, NestacError};
+use crate::options::{ArrayMerge, MergeDepth, MergeStrategy};
+
+/// Deep merges `src` into `dst`.
+///
+/// Same semantics as [crate::json_merge_into]; TOML has no `null` so
+/// [MergeStrategy::nulls] is ignored.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::{toml_merge_into, toml_read, ArrayMerge, MergeStrategy};
+///
+/// fn main() {
+///     let mut config: Value = toml::from_str("[server]\nport = 80\nhosts = [\"a\"]\n").unwrap();
+///     let overlay: Value = toml::from_str("[server]\nport = 8080\nhosts = [\"b\"]\n").unwrap();
+///     let strategy = MergeStrategy::new().arrays(ArrayMerge::Concat);
+///     toml_merge_into(&mut config, &overlay, &strategy);
+///     assert_eq!(toml_read("server.port", &config, None).unwrap().as_integer(), Some(8080));
+///     let hosts = toml_read("server.hosts", &config, None).unwrap();
+///     assert_eq!(hosts.as_array().unwrap().len(), 2);
+/// }
+/// ```
+pub fn toml_merge_into(dst: &mut Value, src: &Value, strategy: &MergeStrategy) {
+    match (dst, src) {
+        (Value::Table(dst_map), Value::Table(src_map)) => {
+            for (key, value) in src_map {
+                match dst_map.get_mut(key) {
+                    Some(existing) => toml_merge_into(existing, value, strategy),
+                    None => {
+                        dst_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(dst_items), Value::Array(src_items)) => match strategy.get_arrays() {
+            ArrayMerge::Replace => *dst_items = src_items.clone(),
+            ArrayMerge::Concat => dst_items.extend(src_items.iter().cloned()),
+            ArrayMerge::ByIndex => {
+                for (idx, value) in src_items.iter().enumerate() {
+                    match dst_items.get_mut(idx) {
+                        Some(existing) => toml_merge_into(existing, value, strategy),
+                        None => dst_items.push(value.clone()),
+                    }
+                }
+            }
+        },
+        (dst, src) => *dst = src.clone(),
+    }
+}
+
+/// Merges the keys of `table` into the table at the token-based [str] path,
+/// creating it (and any missing parent table) when absent.
+///
+/// Same semantics as [crate::json_merge_at].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_merge_at(data: &mut Value, path: &str, separator: Option<&str>, table: Table, depth: MergeDepth) -> Result<(), NestacError> {
+    nested::merge_at(data, path, separator, table, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_array_of_tables_by_index() {
+        let toml_str = "[[servers]]\nname = \"a\"\nport = 80\n";
+        let mut toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let overlay: Value = toml::from_str(
+            "[[servers]]\nport = 8080\n\n[[servers]]\nname = \"b\"\n",
+        ).unwrap();
+        let strategy = MergeStrategy::new().arrays(ArrayMerge::ByIndex);
+        toml_merge_into(toml_data.as_mut().unwrap(), &overlay, &strategy);
+        assert_eq!(
+            toml::to_string(toml_data.as_ref().unwrap()).unwrap(),
+            "[[servers]]\nname = \"a\"\nport = 8080\n\n[[servers]]\nname = \"b\"\n",
+        );
+    }
+
+    #[test]
+    fn merge_at_creates_missing_tables() {
+        let mut toml_data: Value = toml::from_str("[package]\nname = \"demo\"\n").unwrap();
+        let overlay: Table = toml::from_str("opt-level = 3\nlto = true\n").unwrap();
+        toml_merge_at(&mut toml_data, "profile.release", None, overlay, MergeDepth::Shallow).unwrap();
+        assert_eq!(
+            toml::to_string(&toml_data).unwrap(),
+            "[package]\nname = \"demo\"\n\n[profile.release]\nlto = true\nopt-level = 3\n",
+        );
+    }
+}
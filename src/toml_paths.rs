@@ -0,0 +1,317 @@
+use std::io::{self, Write};
+use toml::Value;
+use crate::nested;
+use crate::options::PathsOptions;
+
+/// Returns a [Vec] containing [String]s representing possible paths
+/// on TOML data
+///
+/// Same output format as [crate::json_get_paths].
+///
+/// Enabled by the `toml` feature.
+///
+/// Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::toml_get_paths;
+///
+/// fn main() {
+///     let toml_str = r#"
+///         [foo]
+///         bar = "bingo!"
+///
+///         [hello]
+///         world = ["!"]
+///     "#;
+///     let toml_data: Value = toml::from_str(toml_str).unwrap();
+///     let paths: Vec<String> = toml_get_paths(&toml_data, None);
+///     assert_eq!(paths, vec![
+///         "$",
+///         "$.foo",
+///         "$.foo.bar",
+///         "$.hello",
+///         "$.hello.world",
+///         "$.hello.world.0",
+///     ]);
+/// }
+/// ```
+pub fn toml_get_paths(data: &Value, symbol: Option<String>) -> Vec<String> {
+    nested::get_paths(data, symbol)
+}
+
+/// Same as [toml_get_paths] with the symbol and [crate::Order] taken from
+/// `options`.
+///
+/// Same semantics as [crate::json_get_paths_with].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_get_paths_with(data: &Value, options: &PathsOptions) -> Vec<String> {
+    nested::get_paths_with(data, options)
+}
+
+/// Returns a lazy iterator over the paths [toml_get_paths_with] would
+/// return.
+///
+/// Same semantics as [crate::json_iter_paths].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_iter_paths<'a>(data: &'a Value, options: &'a PathsOptions) -> nested::Paths<'a, Value> {
+    nested::iter_paths(data, options)
+}
+
+/// Returns a lazy iterator over every path along with the [Value] it
+/// addresses.
+///
+/// Same semantics as [crate::json_entries].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_entries<'a>(data: &'a Value, options: &'a PathsOptions) -> nested::Entries<'a, Value> {
+    nested::entries(data, options)
+}
+
+/// Returns the path of every [Value] for which `predicate` holds.
+///
+/// Same semantics as [crate::json_find_paths_where].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_find_paths_where<F: Fn(&Value) -> bool>(data: &Value, predicate: F) -> Vec<String> {
+    nested::find_paths_where(data, predicate)
+}
+
+/// Returns the paths of the nodes matched by the glob-like `pattern`.
+///
+/// Same semantics as [crate::json_get_paths_matching].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_get_paths_matching(data: &Value, pattern: &str, separator: Option<&str>) -> Vec<String> {
+    nested::get_paths_matching(data, pattern, separator)
+}
+
+/// Streams the paths [toml_get_paths_with] would return to `writer`, one per
+/// line.
+///
+/// Same semantics as [crate::json_write_paths].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_write_paths<W: Write>(data: &Value, writer: &mut W, options: &PathsOptions) -> io::Result<()> {
+    nested::write_paths(data, writer, options)
+}
+
+/// How a TOML node was written on the source document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TomlStyle {
+    /// A table with its own `[table]` header (or the document root).
+    Header,
+    /// A table only created by the header of a sub-table (`a` on `[a.b]`).
+    Implicit,
+    /// A table created by dotted keys (`a` on `a.b = 1`).
+    Dotted,
+    /// An inline table (`a = { b = 1 }`).
+    Inline,
+    /// An array of tables (`[[a]]`).
+    ArrayOfTables,
+    /// An array value (`a = [1, 2]`).
+    Array,
+    /// Any other value.
+    Value,
+}
+
+/// Same as [toml_get_paths] but parses the TOML source itself, keeping the
+/// key order of the document and reporting how each node was written, so
+/// idiomatic TOML can be reconstructed from the paths.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use nestac::toml_paths::{toml_get_styled_paths, TomlStyle};
+///
+/// fn main() {
+///     let toml_str = r#"
+///         name = "web"
+///         log.level = "debug"
+///
+///         [server.http]
+///         tls = { enabled = true }
+///     "#;
+///     let paths = toml_get_styled_paths(toml_str, None).unwrap();
+///     assert_eq!(paths, vec![
+///         ("$".to_string(), TomlStyle::Header),
+///         ("$.name".to_string(), TomlStyle::Value),
+///         ("$.log".to_string(), TomlStyle::Dotted),
+///         ("$.log.level".to_string(), TomlStyle::Value),
+///         ("$.server".to_string(), TomlStyle::Implicit),
+///         ("$.server.http".to_string(), TomlStyle::Header),
+///         ("$.server.http.tls".to_string(), TomlStyle::Inline),
+///         ("$.server.http.tls.enabled".to_string(), TomlStyle::Value),
+///     ]);
+/// }
+/// ```
+pub fn toml_get_styled_paths(source: &str, symbol: Option<String>) -> Result<Vec<(String, TomlStyle)>, toml_edit::TomlError> {
+    let doc: toml_edit::DocumentMut = source.parse()?;
+    let mut ret = vec![];
+    styled_item(doc.as_item(), symbol.unwrap_or("$".to_string()), &mut ret);
+    Ok(ret)
+}
+
+fn styled_item(item: &toml_edit::Item, path: String, ret: &mut Vec<(String, TomlStyle)>) {
+    match item {
+        toml_edit::Item::Table(table) => {
+            let style = if table.is_dotted() {
+                TomlStyle::Dotted
+            } else if table.is_implicit() {
+                TomlStyle::Implicit
+            } else {
+                TomlStyle::Header
+            };
+            ret.push((path.clone(), style));
+            for (key, child) in table.iter() {
+                styled_item(child, [path.as_str(), key].join("."), ret);
+            }
+        }
+        toml_edit::Item::ArrayOfTables(tables) => {
+            ret.push((path.clone(), TomlStyle::ArrayOfTables));
+            for (i, table) in tables.iter().enumerate() {
+                let path = [path.clone(), i.to_string()].join(".");
+                ret.push((path.clone(), TomlStyle::Header));
+                for (key, child) in table.iter() {
+                    styled_item(child, [path.as_str(), key].join("."), ret);
+                }
+            }
+        }
+        toml_edit::Item::Value(value) => styled_value(value, path, ret),
+        toml_edit::Item::None => {}
+    }
+}
+
+fn styled_value(value: &toml_edit::Value, path: String, ret: &mut Vec<(String, TomlStyle)>) {
+    match value {
+        toml_edit::Value::InlineTable(table) => {
+            let style = if table.is_dotted() {
+                TomlStyle::Dotted
+            } else {
+                TomlStyle::Inline
+            };
+            ret.push((path.clone(), style));
+            for (key, child) in table.iter() {
+                styled_value(child, [path.as_str(), key].join("."), ret);
+            }
+        }
+        toml_edit::Value::Array(items) => {
+            ret.push((path.clone(), TomlStyle::Array));
+            for (i, child) in items.iter().enumerate() {
+                styled_value(child, [path.clone(), i.to_string()].join("."), ret);
+            }
+        }
+        _ => ret.push((path, TomlStyle::Value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_paths_as_json() {
+        let toml_str = r#"
+            [foo]
+            bar = ["bingo!"]
+        "#;
+        let json_str = r#"{"foo": {"bar": ["bingo!"]}}"#;
+        let toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let json_data: serde_json::Value = serde_json::from_str(json_str).unwrap();
+        assert_eq!(
+            toml_get_paths(toml_data.as_ref().unwrap(), Some("#".to_string())),
+            crate::json_get_paths(&json_data, Some("#".to_string())),
+        );
+    }
+
+    #[test]
+    fn styled_paths_keep_document_order() {
+        let toml_str = r#"
+            zeta = [1, { a.b = 2 }]
+
+            [[alpha]]
+            x = 1
+
+            [beta]
+            y = 2
+        "#;
+        let paths = toml_get_styled_paths(toml_str, None).unwrap();
+        let styles: Vec<(&str, TomlStyle)> = paths.iter().map(|(p, s)| (p.as_str(), *s)).collect();
+        assert_eq!(styles, vec![
+            ("$", TomlStyle::Header),
+            ("$.zeta", TomlStyle::Array),
+            ("$.zeta.0", TomlStyle::Value),
+            ("$.zeta.1", TomlStyle::Inline),
+            ("$.zeta.1.a", TomlStyle::Dotted),
+            ("$.zeta.1.a.b", TomlStyle::Value),
+            ("$.alpha", TomlStyle::ArrayOfTables),
+            ("$.alpha.0", TomlStyle::Header),
+            ("$.alpha.0.x", TomlStyle::Value),
+            ("$.beta", TomlStyle::Header),
+            ("$.beta.y", TomlStyle::Value),
+        ]);
+        assert!(toml_get_styled_paths("a = ", None).is_err());
+    }
+
+    #[test]
+    fn matching_paths_round_trip() {
+        let toml_str = r#"
+            [[servers]]
+            name = "a"
+            tags = ["x"]
+
+            [[servers]]
+            name = "b"
+        "#;
+        let toml_data: Value = toml::from_str(toml_str).unwrap();
+        let paths = toml_get_paths_matching(&toml_data, "servers/[*]/name", Some("/"));
+        assert_eq!(paths, vec!["servers/[0]/name", "servers/[1]/name"]);
+        let paths = toml_get_paths_matching(&toml_data, "servers.[0].**", None);
+        assert_eq!(paths, vec!["servers.[0].name", "servers.[0].tags", "servers.[0].tags.[0]"]);
+        assert!(paths.iter().all(|path| crate::toml_read(path, &toml_data, None).is_some()));
+    }
+
+    #[test]
+    fn leaves_and_depth_limit() {
+        let toml_str = r#"
+            name = "web"
+            tags = []
+
+            [server.http]
+            port = 80
+        "#;
+        let toml_data: Value = toml::from_str(toml_str).unwrap();
+        let options = PathsOptions::new().leaves_only(true);
+        assert_eq!(
+            toml_get_paths_with(&toml_data, &options),
+            vec!["$.name", "$.server.http.port", "$.tags"],
+        );
+        let options = PathsOptions::new().max_depth(2);
+        assert_eq!(
+            toml_get_paths_with(&toml_data, &options),
+            vec!["$", "$.name", "$.server", "$.server.http", "$.tags"],
+        );
+        let options = options.leaves_only(true).symbol("");
+        assert_eq!(toml_get_paths_with(&toml_data, &options), vec!["name", "tags"]);
+    }
+
+    #[test]
+    fn find_paths_by_value() {
+        let toml_str = r#"
+            debug = false
+
+            [[servers]]
+            tls = true
+            ports = [80, 443]
+        "#;
+        let toml_data: Value = toml::from_str(toml_str).unwrap();
+        let paths = toml_find_paths_where(&toml_data, |value| value.is_bool());
+        assert_eq!(paths, vec!["debug", "servers.[0].tls"]);
+        let paths = toml_find_paths_where(&toml_data, |value| value.as_integer().is_some_and(|port| port > 100));
+        assert_eq!(paths, vec!["servers.[0].ports.[1]"]);
+        assert!(toml_find_paths_where(&toml_data, |value| value.is_float()).is_empty());
+    }
+}
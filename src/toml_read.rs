@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use toml::Value;
+use crate::{nested, NestacError};
+
+/// Return a [Value] based off the token-based [str] path.
+///
+/// Same path format and signature as [crate::json_read]: a parsed TOML
+/// document is a [Value::Table] (e.g. from `toml::from_str::<Value>`).
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::toml_read;
+///
+/// fn main() {
+///     let toml_str = r#"
+///         [foo]
+///         bar = "bingo!"
+///         ports = [80, 443]
+///     "#;
+///     let toml_data: Value = toml::from_str(toml_str).unwrap();
+///     let val: Option<&Value> = toml_read("foo.bar", &toml_data, None);
+///     assert_eq!(val.unwrap().as_str(), Some("bingo!"));
+///     let val: Option<&Value> = toml_read("foo@ports@[1]", &toml_data, Some("@"));
+///     assert_eq!(val.unwrap().as_integer(), Some(443));
+/// }
+/// ```
+pub fn toml_read<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Option<&'a Value> {
+    nested::read(path, data, separator)
+}
+
+/// Return a [Value] based off the token-based [str] path or a [NestacError]
+/// describing which token could not be resolved.
+///
+/// Same semantics as [crate::json_try_read]; type mismatches report TOML
+/// type names (`table`, `integer`, ...).
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::{toml_try_read, NestacError};
+///
+/// fn main() {
+///     let toml_data: Value = toml::from_str("port = 80").unwrap();
+///     match toml_try_read("port.[0]", &toml_data, None) {
+///         Err(NestacError::TypeMismatch { expected, found, .. }) => {
+///             assert_eq!(expected, "array");
+///             assert_eq!(found, "integer");
+///         }
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub fn toml_try_read<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Result<&'a Value, NestacError> {
+    nested::try_read(path, data, separator)
+}
+
+/// Deserializes the [Value] found at the token-based [str] path into `T`.
+///
+/// Same semantics as [crate::json_read_as].
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use serde::Deserialize;
+/// use toml::Value;
+/// use nestac::toml_read_as;
+///
+/// #[derive(Deserialize)]
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// fn main() {
+///     let toml_str = r#"
+///         [server]
+///         host = "localhost"
+///         port = 8080
+///     "#;
+///     let toml_data: Value = toml::from_str(toml_str).unwrap();
+///     let server: Server = toml_read_as("server", &toml_data, None).unwrap();
+///     assert_eq!(server.host, "localhost");
+///     assert_eq!(server.port, 8080);
+/// }
+/// ```
+pub fn toml_read_as<T: DeserializeOwned>(path: &str, data: &Value, separator: Option<&str>) -> Result<T, NestacError> {
+    let value = toml_try_read(path, data, separator)?;
+    nested::deserialize_at(path, separator, value.clone())
+}
+
+/// Consumes the [Value] returning the one found at the token-based [str]
+/// path, or gives it back along with a [NestacError].
+///
+/// Same semantics as [crate::json_extract].
+///
+/// Enabled by the `toml` feature.
+#[allow(clippy::result_large_err)]
+pub fn toml_extract(data: Value, path: &str, separator: Option<&str>) -> Result<Value, (Value, NestacError)> {
+    nested::extract(data, path, separator)
+}
+
+/// Returns a copy of the elements addressed by a path ending with a
+/// `[start:end]` token, as a new array [Value].
+///
+/// Same semantics as [crate::json_read_slice].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_read_slice(path: &str, data: &Value, separator: Option<&str>) -> Option<Value> {
+    nested::read_slice(path, data, separator)
+}
+
+/// Return a mutable reference to the [Value] at the token-based [str] path.
+///
+/// Same semantics as [crate::json_read_mut].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_read_mut<'a>(path: &str, data: &'a mut Value, separator: Option<&str>) -> Option<&'a mut Value> {
+    nested::read_mut(path, data, separator)
+}
+
+/// Return every [Value] matched by the token-based [str] path, along with
+/// its concrete path.
+///
+/// Same semantics as [crate::json_read_all].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_read_all<'a>(path: &str, data: &'a Value, separator: Option<&str>) -> Vec<(String, &'a Value)> {
+    nested::read_all(path, data, separator)
+}
+
+/// Returns the [Value] addressed by each path, walking `data` once.
+///
+/// Same semantics as [crate::json_read_many].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_read_many<'a>(paths: &[&str], data: &'a Value, separator: Option<&str>) -> HashMap<String, Option<&'a Value>> {
+    nested::read_many(paths, data, separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_array_of_tables() {
+        let toml_str = r#"
+            [[servers]]
+            name = "alpha"
+
+            [[servers]]
+            name = "beta"
+        "#;
+        let toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let val = toml_read("servers.[1].name", toml_data.as_ref().unwrap(), None);
+        assert_eq!(val.unwrap().as_str(), Some("beta"));
+        assert!(toml_read("servers.[2].name", toml_data.as_ref().unwrap(), None).is_none());
+    }
+
+    #[test]
+    fn mutate_array_in_place() {
+        let mut toml_data: Value = toml::from_str("[package]\nauthors = [\"a\"]\n").unwrap();
+        if let Some(Value::Array(authors)) = toml_read_mut("package.authors", &mut toml_data, None) {
+            authors.push(Value::String("b".to_string()));
+        }
+        assert_eq!(toml::to_string(&toml_data).unwrap(), "[package]\nauthors = [\"a\", \"b\"]\n");
+        assert!(toml_read_mut("package.name", &mut toml_data, None).is_none());
+    }
+
+    #[test]
+    fn read_as_reports_nested_field_path() {
+        #[derive(serde::Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Server {
+            port: u16,
+        }
+
+        let toml_str = "[[servers]]\nport = 80\n\n[[servers]]\nport = \"80\"\n";
+        let toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let err = toml_read_as::<Vec<Server>>("servers", toml_data.as_ref().unwrap(), None);
+        match err {
+            Err(NestacError::Deserialize { path, .. }) => assert_eq!(path, "servers.[1].port"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_and_replace_slices() {
+        let mut toml_data: Value = toml::from_str("ports = [80, 443, 8080]").unwrap();
+        assert_eq!(toml_read_slice("ports.[1:]", &toml_data, None), Some(Value::Array(vec![443.into(), 8080.into()])));
+        let old_val = crate::toml_update(&mut toml_data, "ports.[-2:]", None, Value::Array(vec![8443.into()]));
+        assert_eq!(old_val, Some(Value::Array(vec![443.into(), 8080.into()])));
+        assert_eq!(toml_read_slice("ports.[:]", &toml_data, None), Some(Value::Array(vec![80.into(), 8443.into()])));
+    }
+}
@@ -0,0 +1,300 @@
+use std::ops::ControlFlow;
+use serde::Serialize;
+use toml::Value;
+use crate::{nested, NestacError};
+
+/// Update a [Value] based off the token-based [str] path and returns the old
+/// [Value].
+///
+/// Same path format and signature as [crate::json_update]. Returns [None]
+/// when the path does not exist or the last key did not exist before the
+/// update.
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::{toml_read, toml_update};
+///
+/// fn main() {
+///     let toml_str = r#"
+///         [foo]
+///         bar = "bingo!"
+///     "#;
+///     let mut toml_data: Value = toml::from_str(toml_str).unwrap();
+///     let old_val = toml_update(
+///         &mut toml_data,
+///         "foo.bar",
+///         None,
+///         Value::String("updated!".into()),
+///     );
+///     assert_eq!(old_val.unwrap().as_str(), Some("bingo!"));
+///     let new_val = toml_read("foo.bar", &toml_data, None);
+///     assert_eq!(new_val.unwrap().as_str(), Some("updated!"));
+/// }
+/// ```
+pub fn toml_update(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Option<Value> {
+    nested::update(data, path, separator, new_value)
+}
+
+/// Same as [toml_update] but returns a [NestacError] describing which token
+/// could not be resolved instead of [None].
+///
+/// Same semantics as [crate::json_try_update].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_try_update(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, NestacError> {
+    nested::try_update(data, path, separator, new_value)
+}
+
+/// Update a [Value] based off the token-based [str] path, creating missing
+/// intermediate tables, and returns the old [Value].
+///
+/// Same semantics as [crate::json_upsert].
+///
+/// Enabled by the `toml` feature.
+///
+/// # Examples:
+/// ```rust
+/// use toml::Value;
+/// use nestac::toml_upsert;
+///
+/// fn main() {
+///     let mut toml_data: Value = toml::from_str("").unwrap();
+///     toml_upsert(&mut toml_data, "server.http.port", None, Value::Integer(80)).unwrap();
+///     assert_eq!(toml::to_string(&toml_data).unwrap(), "[server.http]\nport = 80\n");
+/// }
+/// ```
+pub fn toml_upsert(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Result<Option<Value>, NestacError> {
+    nested::upsert(data, path, separator, new_value)
+}
+
+/// Update every [Value] matched by the token-based [str] path and returns
+/// the concrete path and old [Value] of each of them.
+///
+/// Same semantics as [crate::json_update_all].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_update_all(data: &mut Value, path: &str, separator: Option<&str>, new_value: Value) -> Vec<(String, Option<Value>)> {
+    nested::update_all(data, path, separator, new_value)
+}
+
+/// Applies several updates walking the [Value] once, and returns the old
+/// [Value] of each of them.
+///
+/// Same semantics as [crate::json_update_many].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_update_many(data: &mut Value, updates: Vec<(&str, Value)>, separator: Option<&str>) -> Vec<Option<Value>> {
+    nested::update_many(data, updates, separator)
+}
+
+/// Replaces the [Value] at the token-based [str] path with the one derived
+/// by `transform` from it, and returns the old [Value].
+///
+/// Same semantics as [crate::json_transform].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_transform<F: FnOnce(&Value) -> Value>(data: &mut Value, path: &str, separator: Option<&str>, transform: F) -> Option<Value> {
+    nested::transform(data, path, separator, transform)
+}
+
+/// Creates the missing parent tables and arrays of every path in `paths`,
+/// and returns how many were created.
+///
+/// Same semantics as [crate::json_ensure].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_ensure(data: &mut Value, paths: &[&str], separator: Option<&str>) -> Result<usize, NestacError> {
+    nested::ensure(data, paths, separator)
+}
+
+/// Calls `visit` on every [Value] with its path, stopping at the first
+/// [ControlFlow::Break].
+///
+/// Same semantics as [crate::json_walk_mut].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_walk_mut<B, F: FnMut(&str, &mut Value) -> ControlFlow<B>>(data: &mut Value, visit: F) -> ControlFlow<B> {
+    nested::walk_mut(data, visit)
+}
+
+/// Flips the boolean at the token-based [str] path and returns its previous
+/// state.
+///
+/// Same semantics as [crate::json_toggle].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_toggle(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |flag| !flag)
+}
+
+/// Sets the boolean at the token-based [str] path to `true` and returns its
+/// previous state.
+///
+/// Same semantics as [crate::json_enable].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_enable(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |_| true)
+}
+
+/// Sets the boolean at the token-based [str] path to `false` and returns
+/// its previous state.
+///
+/// Same semantics as [crate::json_disable].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_disable(data: &mut Value, path: &str, separator: Option<&str>) -> Result<bool, NestacError> {
+    nested::set_flag(data, path, separator, |_| false)
+}
+
+/// Serializes `value` into a [Value] and writes it at the token-based [str]
+/// path, returning the old [Value].
+///
+/// Same semantics as [crate::json_update_with_serializable]; values TOML
+/// cannot represent (e.g. `None` or unit) are reported as
+/// [NestacError::Serialize].
+///
+/// Enabled by the `toml` feature.
+pub fn toml_update_with_serializable<T: Serialize>(data: &mut Value, path: &str, separator: Option<&str>, value: &T) -> Result<Option<Value>, NestacError> {
+    let new_value = Value::try_from(value).map_err(|err| NestacError::Serialize {
+        path: path.to_string(),
+        message: err.to_string(),
+    })?;
+    toml_try_update(data, path, separator, new_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml_read;
+
+    #[test]
+    fn update_array_element_with_custom_separator() {
+        let toml_str = r#"
+            [foo]
+            ports = [80, 443]
+        "#;
+        let mut toml_data: Result<Value, _> = toml::from_str(toml_str);
+        assert!(toml_data.is_ok());
+        let old_val = toml_update(
+            toml_data.as_mut().unwrap(),
+            "foo@ports@[0]",
+            Some("@"),
+            Value::Integer(8080),
+        );
+        assert_eq!(old_val.unwrap().as_integer(), Some(80));
+        let new_val = toml_read("foo.ports", toml_data.as_ref().unwrap(), None);
+        assert_eq!(new_val.unwrap(), &Value::Array(vec![Value::Integer(8080), Value::Integer(443)]));
+    }
+
+    #[test]
+    fn try_update_out_of_bounds() {
+        let mut toml_data: Result<Value, _> = toml::from_str("ports = [80]");
+        assert!(toml_data.is_ok());
+        let err = toml_try_update(toml_data.as_mut().unwrap(), "ports.[1]", None, Value::Integer(1));
+        assert_eq!(err, Err(NestacError::IndexOutOfBounds {
+            path: "ports.[1]".to_string(),
+            segment: "[1]".to_string(),
+            index: 1,
+            offset: 6,
+            len: 1,
+        }));
+    }
+
+    #[test]
+    fn append_token_pushes_onto_arrays() {
+        let mut toml_data: Value = toml::from_str("[foo]\ntags = [\"a\"]\nname = \"x\"\n").unwrap();
+        assert_eq!(toml_update(&mut toml_data, "foo.tags.[+]", None, Value::from("b")), None);
+        assert_eq!(toml_upsert(&mut toml_data, "foo.tags.[+]", None, Value::from("c")), Ok(None));
+        assert!(matches!(
+            toml_try_update(&mut toml_data, "foo.name.[+]", None, Value::from("d")),
+            Err(NestacError::TypeMismatch { expected, found, .. }) if expected == "array" && found == "string",
+        ));
+        assert_eq!(toml_data["foo"]["tags"], Value::Array(vec!["a".into(), "b".into(), "c".into()]));
+        assert_eq!(toml_data["foo"]["name"], Value::from("x"));
+    }
+
+    #[test]
+    fn update_with_serializable_struct_and_none() {
+        #[derive(serde::Serialize)]
+        struct Dependency {
+            version: String,
+            features: Vec<String>,
+        }
+
+        let mut toml_data: Value = toml::from_str("[dependencies]\nserde = \"1\"\n").unwrap();
+        let dependency = Dependency {
+            version: "1".to_string(),
+            features: vec!["derive".to_string()],
+        };
+        let old_val = toml_update_with_serializable(&mut toml_data, "dependencies.serde", None, &dependency);
+        assert_eq!(old_val.unwrap().unwrap().as_str(), Some("1"));
+        let features = toml_read("dependencies.serde.features.[0]", &toml_data, None);
+        assert_eq!(features.unwrap().as_str(), Some("derive"));
+
+        let err = toml_update_with_serializable(&mut toml_data, "dependencies.serde", None, &None::<u8>);
+        assert!(matches!(err, Err(NestacError::Serialize { .. })));
+        assert!(toml_read("dependencies.serde.version", &toml_data, None).is_some());
+    }
+
+    #[test]
+    fn transform_appends_to_array() {
+        let mut toml_data: Value = toml::from_str("[server]\nports = [80]\n").unwrap();
+        let old_val = toml_transform(&mut toml_data, "server.ports", None, |old| {
+            let mut ports = old.as_array().cloned().unwrap_or_default();
+            ports.push(Value::Integer(443));
+            Value::Array(ports)
+        });
+        assert_eq!(old_val, Some(Value::Array(vec![Value::Integer(80)])));
+        assert_eq!(toml::to_string(&toml_data).unwrap(), "[server]\nports = [80, 443]\n");
+        assert_eq!(toml_transform(&mut toml_data, "server.host", None, |old| old.clone()), None);
+    }
+
+    #[test]
+    fn flags_only_flip_booleans() {
+        let mut toml_data: Value = toml::from_str("[features]\nbeta = true\nname = \"x\"\n").unwrap();
+        assert_eq!(toml_disable(&mut toml_data, "features.beta", None), Ok(true));
+        assert_eq!(toml_toggle(&mut toml_data, "features.beta", None), Ok(false));
+        assert_eq!(toml_read("features.beta", &toml_data, None), Some(&Value::Boolean(true)));
+
+        let err = toml_enable(&mut toml_data, "features.name", None).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { ref found, .. } if found == "string"));
+        let err = toml_enable(&mut toml_data, "features", None).unwrap_err();
+        assert!(matches!(err, NestacError::TypeMismatch { index: 0, .. }));
+        assert!(matches!(toml_enable(&mut toml_data, "features.gamma", None), Err(NestacError::MissingKey { .. })));
+    }
+
+    #[test]
+    fn walk_mut_redacts_and_stops() {
+        let toml_str = r#"
+            name = "app"
+
+            [[users]]
+            name = "ana"
+            token = "abc"
+
+            [[users]]
+            name = "bo"
+            token = "def"
+        "#;
+        let mut toml_data: Value = toml::from_str(toml_str).unwrap();
+        let mut visited = vec![];
+        let flow = toml_walk_mut(&mut toml_data, |path, value| {
+            visited.push(path.to_string());
+            if path.ends_with(".token") {
+                *value = Value::from("***");
+            }
+            match path {
+                "users.[1]" => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        });
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(visited, vec!["", "name", "users", "users.[0]", "users.[0].name", "users.[0].token", "users.[1]"]);
+        assert_eq!(toml_data["users"][0]["token"].as_str(), Some("***"));
+        assert_eq!(toml_data["users"][1]["token"].as_str(), Some("def"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Writing files in place or atomically (temp file + rename).
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Path usable to open `path` on Windows even past the `MAX_PATH` limit.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    let display = path.to_string_lossy();
+    if !path.is_absolute() || display.starts_with(r"\\?\") || display.len() < 260 {
+        return path.to_path_buf();
+    }
+    match display.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", display.replace('/', "\\"))),
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Temporary file next to `file`, so renaming it over `file` stays on the
+/// same filesystem.
+fn tmp_path(file: &Path) -> PathBuf {
+    let name = file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    file.with_file_name(format!(
+        ".{}.nestac-{}-{}.tmp",
+        name,
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ))
+}
+
+#[cfg(unix)]
+fn set_mode(file: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(file, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_file: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Flushes the directory holding `file`, making a rename into it durable.
+#[cfg(unix)]
+pub(crate) fn sync_parent(file: &Path) -> io::Result<()> {
+    match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories cannot be opened for flushing on other platforms, where
+/// renames are left to the filesystem journal.
+#[cfg(not(unix))]
+pub(crate) fn sync_parent(_file: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Gives `file` the permissions and, where the process is allowed to, the
+/// ownership described by `original`.
+fn copy_metadata(file: &Path, original: &fs::Metadata) -> io::Result<()> {
+    fs::set_permissions(file, original.permissions())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // changing ownership requires privileges; keep the defaults otherwise
+        let _ = std::os::unix::fs::chown(file, Some(original.uid()), Some(original.gid()));
+    }
+    Ok(())
+}
+
+/// Writes `contents` to `file`.
+///
+/// - `atomic`: write to a temporary file first and rename it over `file`,
+///   copying the permissions and ownership of the file being replaced; a
+///   symlinked `file` has its target replaced, the link being kept;
+/// - `mode`: explicit unix permissions for the written file, taking
+///   precedence over the original ones (ignored on other platforms);
+/// - `fsync`: flush the file before returning, and its directory after an
+///   atomic rename.
+pub(crate) fn write_file(file: &Path, contents: &str, atomic: bool, mode: Option<u32>, fsync: bool) -> io::Result<()> {
+    let file = &long_path(file);
+    let resolved;
+    let file = match fs::symlink_metadata(file) {
+        Ok(meta) if atomic && meta.file_type().is_symlink() => {
+            // renaming over the link would replace it with a regular file
+            resolved = fs::canonicalize(file)?;
+            &resolved
+        }
+        _ => file,
+    };
+    let target = if atomic {
+        tmp_path(file)
+    } else {
+        file.to_path_buf()
+    };
+    let result = (|| {
+        let mut handle = if atomic {
+            let handle = File::options().write(true).create_new(true).open(&target)?;
+            if let Ok(original) = fs::metadata(file) {
+                copy_metadata(&target, &original)?;
+            }
+            handle
+        } else {
+            File::create(&target)?
+        };
+        if let Some(mode) = mode {
+            set_mode(&target, mode)?;
+        }
+        handle.write_all(contents.as_bytes())?;
+        if fsync {
+            handle.sync_all()?;
+        }
+        if atomic {
+            fs::rename(&target, file)?;
+            if fsync {
+                sync_parent(file)?;
+            }
+        }
+        Ok(())
+    })();
+    if result.is_err() && atomic {
+        let _ = fs::remove_file(&target);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_write_keeps_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("shared.json");
+        let link = dir.path().join("link.json");
+        fs::write(&target, "{}").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write_file(&link, r#"{"foo":1}"#, true, None, true).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), r#"{"foo":1}"#);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn atomic_write_replaces_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("atomic.json");
+        fs::write(&file, "{}").unwrap();
+        write_file(&file, r#"{"foo":1}"#, true, None, true).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), r#"{"foo":1}"#);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_write_preserves_or_sets_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("secret.json");
+        fs::write(&file, "{}").unwrap();
+        set_mode(&file, 0o600).unwrap();
+        write_file(&file, r#"{"foo":1}"#, true, None, false).unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        write_file(&file, r#"{"foo":2}"#, true, Some(0o640), false).unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        write_file(&file, r#"{"foo":3}"#, false, Some(0o600), false).unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}